@@ -0,0 +1,112 @@
+# Messages printed by the command line interface, see src/i18n.rs for the syntax
+error = Error: { $message }
+
+## Tree commands
+duplicates-merged = merged { $count } chunk(s) into { $path }
+hook-errors = { $count } error(s) found in png files
+manifest-changes = { $count } file(s) changed since the manifest was made
+sparse-summary = { $scanned } file(s) scanned, { $flagged } flagged
+corpus-written = { $count } file(s) written to { $dir }
+
+## Reading
+png-path-required = This command needs a target png file, use -p <FILE>
+stdin-needs-stdout = The png was read from stdin, use --stdout to get the edited png
+file-too-large = { $path } is { $size } bytes, above --max-file-size of { $limit } bytes. Use --force-large to operate on it anyway
+repaired-transfer = Repaired transfer corruption: { $corruption }
+strict-rejected = Strict mode rejected the file with { $count } problem(s)
+validate-summary = { $chunks } chunks, { $bytes } bytes, SHA-256 { $sha256 }
+validate-invalid = Png is not valid
+crc-report-summary = { $chunks } chunks read, { $failures } wrong CRC or length fields
+survey-summary = { $files } file(s): { $ok } ok, { $warnings } with warnings, { $errors } with errors, { $bytes } bytes scanned
+survey-top-errors = Most frequent errors:
+survey-error-kind = { $count } file(s): { $kind }
+survey-unreadable = { $path }: can't be read: { $message }
+survey-failed = { $count } file(s) with errors
+
+## Decode
+decode-found-in = Message found in { $carrier }
+decode-no-carrier = No { $chunk_type } message in any known carrier
+decode-lang-itxt-only = --lang only applies to iTXt chunks
+decode-no-keyword = No iTXt chunk with keyword { $keyword }
+decode-no-text = No { $chunk_type } chunk with keyword { $keyword }
+decode-written = Message of { $bytes } bytes written to { $path }
+decode-reference = Message is a reference to payload { $hash }, use --store to resolve it
+decode-stored-reference = Message is a reference to a stored payload, use --store to resolve it
+decode-pointer = Message points to { $path } with hash { $hash }, use --follow to read it
+decode-stored-pointer = Message points to another file, use --follow to read it
+decode-attachment = Message is the file { $name } of { $bytes } bytes, use extract-file to save it
+decode-stored-attachment = Message is an attached file, use extract-file to save it
+decode-opened = Message opened from { $path }
+
+## Encode, remove and restore
+extract-none = No { $chunk_type } chunk holds a file
+extract-exists = { $path } already exists, use --output to choose where to write the file
+extract-written = Extracted { $name } of { $bytes } bytes to { $path }
+encode-truncated = Png does not end with IEND, it may be truncated. Use --repair-trailer to add IEND after the message
+encode-message-file-itxt = --message-file can only be stored as iTXt chunks
+encode-no-split = Message of { $bytes } bytes does not fit a single chunk of at most { $limit } bytes
+encode-overwrite = Trying to overwrite original file: { $path }
+remove-saved = Removed chunk(s) saved to { $path }
+remove-none = There is no { $chunk_type } chunk
+remove-none-keyword = There is no { $chunk_type } chunk with keyword { $keyword }
+remove-all-done = { $count } chunk(s) of type { $chunk_type } removed
+remove-done = One message of type { $chunk_type } has been removed from chunk index { $index }
+rename-done = { $count } chunk(s) of type { $old } renamed to { $new }
+rename-none = There is no { $old } chunk
+rename-file = { $path }: { $count } chunk(s) renamed
+rename-file-failed = { $path }: { $message }
+rename-tree-done = { $count } chunk(s) renamed in { $files } file(s)
+rename-tree-failed = { $count } file(s) could not be renamed
+restore-done = Restored { $chunk_type } at chunk index { $index }
+cgbi-converted = Converted from Apple CgBI to a standard png
+
+## Metadata
+color-removed = Removed redundant chunks at index { $indices }
+color-problems = { $count } color chunk problem(s) found
+color-consistent = Color chunks are consistent
+info-size = Size:        { $width }x{ $height } pixels
+info-color-type = Color type:  { $name }, { $depth } bit
+info-interlaced = Interlaced:  { $value }
+info-chunks = Chunks:      { $count }
+info-file-size = File size:   { $bytes } bytes
+info-dpi = DPI:         { $dpi }
+info-modified = Modified:    { $time }
+yes = yes
+no = no
+set-dpi-done = Pixel density set to { $dpi }
+inspect-graph-json = --graph draws a diagram, it can't be combined with --format json
+dump-differs = Chunk layout differs from stored dump in { $count } line(s)
+dump-matches = Chunk layout matches stored dump
+snapshot-written = Structural digest written to { $path }
+snapshot-differs = Chunk layout differs from { $path }: expected digest { $expected }, found { $actual }
+snapshot-matches = Chunk layout matches stored digest
+convert-done = Converted { $from } at chunk index { $index } to { $to }
+convert-skipped = Skipped chunk index { $index }: { $reason }
+convert-summary = { $converted } of { $total } textual chunk(s) converted
+xmp-missing = No XMP packet found
+xmp-no-property = XMP packet has no property { $property }
+
+## Encode wizard
+wizard-input-ended = Input ended before the wizard was done
+wizard-yes-or-no = Please answer y or n
+wizard-type-intro = Messages go in chunks of a custom type, four ASCII letters like ruSt.
+    Lowercase first and second letters keep decoders from choking on the chunk and from mistaking it for a standard one.
+wizard-type-prompt = Chunk type [{ $suggested }]:
+wizard-type-reserved = The third letter must be uppercase, lowercase is reserved by the spec
+wizard-type-critical = The first letter must be lowercase, decoders refuse images with unknown critical chunks
+wizard-type-public = The second letter must be lowercase, uppercase is kept for types defined by the spec
+wizard-type-unsafe-to-copy = The fourth letter is uppercase, editors will drop the chunk when they modify the image
+wizard-type-collision = The png already has { $count } { $chunk_type } chunk(s), the message will be added next to them
+wizard-type-registered = { $chunk_type } is registered as: { $description }
+wizard-use-anyway = Use it anyway?
+wizard-message-prompt = Message, finish with an empty line:
+wizard-message-empty = The message must not be empty
+wizard-output-prompt = Output file, leave empty to overwrite { $path }:
+wizard-overwrite = { $path } exists, overwrite it?
+wizard-summary = { $bytes } byte(s) will be written as { $chunk_type } to { $path }
+wizard-continue = Continue?
+wizard-cancelled = Cancelled, nothing was written
+
+## Integration
+integration-installed = Installed { $path }
+integration-removed = Removed { $path }
@@ -0,0 +1,112 @@
+# 命令行界面输出的消息，语法见 src/i18n.rs
+error = 错误：{ $message }
+
+## Tree commands
+duplicates-merged = 已将 { $count } 个数据块合并到 { $path }
+hook-errors = 在 png 文件中发现 { $count } 个错误
+manifest-changes = 自清单生成以来有 { $count } 个文件发生变化
+sparse-summary = 已扫描 { $scanned } 个文件，标记 { $flagged } 个
+corpus-written = 已向 { $dir } 写入 { $count } 个文件
+
+## Reading
+png-path-required = 此命令需要一个目标 png 文件，请使用 -p <FILE>
+stdin-needs-stdout = png 从标准输入读取，请使用 --stdout 获取编辑后的 png
+file-too-large = { $path } 大小为 { $size } 字节，超过 --max-file-size 限制的 { $limit } 字节。使用 --force-large 强制处理
+repaired-transfer = 已修复传输损坏：{ $corruption }
+strict-rejected = 严格模式拒绝了该文件，发现 { $count } 个问题
+validate-summary = { $chunks } 个数据块，{ $bytes } 字节，SHA-256 { $sha256 }
+validate-invalid = Png 文件无效
+crc-report-summary = 读取了 { $chunks } 个数据块，{ $failures } 个 CRC 或长度字段错误
+survey-summary = { $files } 个文件：{ $ok } 个正常，{ $warnings } 个有警告，{ $errors } 个有错误，共扫描 { $bytes } 字节
+survey-top-errors = 最常见的错误：
+survey-error-kind = { $count } 个文件：{ $kind }
+survey-unreadable = { $path }：无法读取：{ $message }
+survey-failed = { $count } 个文件有错误
+
+## Decode
+decode-found-in = 在 { $carrier } 中找到消息
+decode-no-carrier = 所有已知载体中都没有 { $chunk_type } 消息
+decode-lang-itxt-only = --lang 只适用于 iTXt 数据块
+decode-no-keyword = 没有关键字为 { $keyword } 的 iTXt 数据块
+decode-no-text = 没有关键字为 { $keyword } 的 { $chunk_type } 数据块
+decode-written = 已将 { $bytes } 字节的消息写入 { $path }
+decode-reference = 消息是对负载 { $hash } 的引用，请使用 --store 解析
+decode-stored-reference = 消息是对已存储负载的引用，请使用 --store 解析
+decode-pointer = 消息指向 { $path }，哈希为 { $hash }，使用 --follow 读取
+decode-stored-pointer = 消息指向另一个文件，使用 --follow 读取
+decode-attachment = 消息是文件 { $name }，共 { $bytes } 字节，使用 extract-file 保存
+decode-stored-attachment = 消息是附加文件，使用 extract-file 保存
+decode-opened = 已从 { $path } 打开消息
+
+## Encode, remove and restore
+extract-none = 没有保存文件的 { $chunk_type } 数据块
+extract-exists = { $path } 已存在，使用 --output 指定写入位置
+extract-written = 已将 { $name }（{ $bytes } 字节）提取到 { $path }
+encode-truncated = Png 文件没有以 IEND 结尾，可能已被截断。使用 --repair-trailer 在消息后补上 IEND
+encode-message-file-itxt = --message-file 只能存储为 iTXt 数据块
+encode-no-split = { $bytes } 字节的消息无法放入单个最多 { $limit } 字节的数据块
+encode-overwrite = 正在覆盖原文件：{ $path }
+remove-saved = 已将移除的数据块保存到 { $path }
+remove-none = 没有类型为 { $chunk_type } 的数据块
+remove-none-keyword = 没有关键字为 { $keyword } 的 { $chunk_type } 数据块
+remove-all-done = 已移除 { $count } 个类型为 { $chunk_type } 的数据块
+remove-done = 已从数据块索引 { $index } 移除一条类型为 { $chunk_type } 的消息
+rename-done = 已将 { $count } 个类型为 { $old } 的数据块重命名为 { $new }
+rename-none = 没有类型为 { $old } 的数据块
+rename-file = { $path }：已重命名 { $count } 个数据块
+rename-file-failed = { $path }：{ $message }
+rename-tree-done = 已在 { $files } 个文件中重命名 { $count } 个数据块
+rename-tree-failed = { $count } 个文件无法重命名
+restore-done = 已在数据块索引 { $index } 恢复 { $chunk_type }
+cgbi-converted = 已从 Apple CgBI 转换为标准 png
+
+## Metadata
+color-removed = 已移除索引 { $indices } 处的冗余数据块
+color-problems = 发现 { $count } 个颜色数据块问题
+color-consistent = 颜色数据块一致
+info-size = 尺寸：      { $width }x{ $height } 像素
+info-color-type = 颜色类型：  { $name }，{ $depth } 位
+info-interlaced = 隔行扫描：  { $value }
+info-chunks = 数据块：    { $count }
+info-file-size = 文件大小：  { $bytes } 字节
+info-dpi = DPI：        { $dpi }
+info-modified = 修改时间：  { $time }
+yes = 是
+no = 否
+set-dpi-done = 像素密度已设置为 { $dpi }
+inspect-graph-json = --graph 输出图表，不能与 --format json 同时使用
+dump-differs = 数据块布局与保存的转储有 { $count } 行不同
+dump-matches = 数据块布局与保存的转储一致
+snapshot-written = 结构摘要已写入 { $path }
+snapshot-differs = 数据块布局与 { $path } 不同：应为摘要 { $expected }，实际为 { $actual }
+snapshot-matches = 数据块布局与保存的摘要一致
+convert-done = 已将数据块索引 { $index } 处的 { $from } 转换为 { $to }
+convert-skipped = 跳过数据块索引 { $index }：{ $reason }
+convert-summary = 已转换 { $total } 个文本数据块中的 { $converted } 个
+xmp-missing = 未找到 XMP 数据包
+xmp-no-property = XMP 数据包没有属性 { $property }
+
+## 编码向导
+wizard-input-ended = 向导完成前输入已结束
+wizard-yes-or-no = 请回答 y 或 n
+wizard-type-intro = 消息存放在自定义类型的数据块中，类型为四个 ASCII 字母，例如 ruSt。
+    第一、二个字母小写可以避免解码器出错，也不会被误认为标准数据块。
+wizard-type-prompt = 数据块类型 [{ $suggested }]：
+wizard-type-reserved = 第三个字母必须大写，小写是规范保留的
+wizard-type-critical = 第一个字母必须小写，解码器会拒绝含有未知关键数据块的图片
+wizard-type-public = 第二个字母必须小写，大写留给规范定义的类型
+wizard-type-unsafe-to-copy = 第四个字母是大写，编辑器修改图片时会丢弃该数据块
+wizard-type-collision = 该 png 已有 { $count } 个 { $chunk_type } 数据块，消息会添加在它们旁边
+wizard-type-registered = { $chunk_type } 已注册为：{ $description }
+wizard-use-anyway = 仍然使用？
+wizard-message-prompt = 消息，以空行结束：
+wizard-message-empty = 消息不能为空
+wizard-output-prompt = 输出文件，留空则覆盖 { $path }：
+wizard-overwrite = { $path } 已存在，是否覆盖？
+wizard-summary = 将以 { $chunk_type } 写入 { $bytes } 字节到 { $path }
+wizard-continue = 继续？
+wizard-cancelled = 已取消，未写入任何内容
+
+## 集成
+integration-installed = 已安装 { $path }
+integration-removed = 已删除 { $path }
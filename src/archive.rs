@@ -0,0 +1,338 @@
+#![allow(dead_code)]
+//! # Archive
+//! Unwrap a png stored inside a gzip file, a single entry zip archive or an ICO file, and wrap it
+//! back after editing. Only what is needed to round trip such containers is supported: no
+//! multi-member gzip, no zip64, no encrypted entries.
+use crate::deflate;
+use crate::ico::Ico;
+use anyhow::{anyhow, Result};
+use crc32fast::Hasher;
+
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const ZIP_LOCAL_HEADER: u32 = 0x04034B50;
+const ZIP_CENTRAL_HEADER: u32 = 0x02014B50;
+const ZIP_END_OF_CENTRAL_DIRECTORY: u32 = 0x06054B50;
+const ZIP_STORED: u16 = 0;
+const ZIP_DEFLATED: u16 = 8;
+/// General purpose flag telling sizes and CRC follow the data instead of being in the header
+const ZIP_DATA_DESCRIPTOR: u16 = 0x08;
+
+/// The container a file was found in, with what is needed to rebuild it around new content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Container {
+    Gzip {
+        /// Original file name recorded in the header, if any
+        name: Option<Vec<u8>>,
+        mtime: u32,
+    },
+    Zip {
+        /// Name of the single entry
+        name: Vec<u8>,
+        /// Compression method of the entry, stored or deflated
+        method: u16,
+        /// General purpose flags of the entry, like UTF-8 names, without the data descriptor bit
+        flags: u16,
+        /// Modification time and date in MS-DOS format
+        time: u16,
+        date: u16,
+        /// Extra fields of the local and central headers, each kept as it was
+        local_extra: Vec<u8>,
+        extra: Vec<u8>,
+    },
+    Ico {
+        /// The whole file, the image at `index` is replaced when wrapping
+        ico: Ico,
+        index: usize,
+    },
+}
+
+impl Container {
+    /// Detect the container from its magic bytes and extract the single file inside it
+    pub fn open(bytes: &[u8]) -> Result<(Container, Vec<u8>)> {
+        Self::open_limited(bytes, usize::MAX)
+    }
+
+    /// Like [open](Container::open), failing when the file inside is larger than `max_content`
+    /// bytes. Decompression stops as soon as the limit is reached.
+    pub fn open_limited(bytes: &[u8], max_content: usize) -> Result<(Container, Vec<u8>)> {
+        if bytes.starts_with(&GZIP_MAGIC) {
+            open_gzip(bytes, max_content)
+        } else if bytes.len() >= 4 && read_u32(bytes, 0)? == ZIP_LOCAL_HEADER {
+            open_zip(bytes, max_content)
+        } else {
+            Err(anyhow!("File is neither a gzip file nor a zip archive"))
+        }
+    }
+
+    /// Extract the png image at entry `index` of an ICO or CUR file. ICO files are only opened
+    /// on request, their signature is too short to be told apart reliably.
+    pub fn open_ico(bytes: &[u8], index: usize) -> Result<(Container, Vec<u8>)> {
+        let ico = Ico::parse(bytes)?;
+        let content = ico.png(index)?.to_vec();
+        Ok((Container::Ico { ico, index }, content))
+    }
+
+    /// Short name of the container format
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Container::Gzip { .. } => "gzip",
+            Container::Zip { .. } => "zip",
+            Container::Ico { .. } => "ico",
+        }
+    }
+
+    /// Build a container of the same kind holding `content`
+    pub fn wrap(&self, content: &[u8]) -> Vec<u8> {
+        match self {
+            Container::Gzip { name, mtime } => wrap_gzip(name.as_deref(), *mtime, content),
+            Container::Zip { .. } => wrap_zip(self, content),
+            Container::Ico { ico, index } => {
+                let mut ico = ico.clone();
+                // The index was checked when opening
+                ico.set_image(*index, content.to_vec()).unwrap();
+                ico.to_bytes()
+            }
+        }
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut hasher = Hasher::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| anyhow!("Unexpected end of archive"))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| anyhow!("Unexpected end of archive"))
+}
+
+fn open_gzip(bytes: &[u8], max_content: usize) -> Result<(Container, Vec<u8>)> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+    if bytes.len() < 18 {
+        return Err(anyhow!("Gzip file is too short"));
+    }
+    if bytes[2] != 8 {
+        return Err(anyhow!("Unsupported gzip compression method {}", bytes[2]));
+    }
+    let flags = bytes[3];
+    let mtime = read_u32(bytes, 4)?;
+    let mut offset = 10;
+    if flags & FEXTRA != 0 {
+        offset += 2 + read_u16(bytes, offset)? as usize;
+    }
+    let zero_terminated = |offset: &mut usize| -> Result<Vec<u8>> {
+        let rest = bytes
+            .get(*offset..)
+            .ok_or_else(|| anyhow!("Unexpected end of archive"))?;
+        let end = rest
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or_else(|| anyhow!("Unterminated string in gzip header"))?;
+        *offset += end + 1;
+        Ok(rest[..end].to_vec())
+    };
+    let name = if flags & FNAME != 0 {
+        Some(zero_terminated(&mut offset)?)
+    } else {
+        None
+    };
+    if flags & FCOMMENT != 0 {
+        zero_terminated(&mut offset)?;
+    }
+    if flags & FHCRC != 0 {
+        offset += 2;
+    }
+    let body = bytes
+        .get(offset..)
+        .ok_or_else(|| anyhow!("Unexpected end of archive"))?;
+    let (content, consumed) = deflate::inflate_limited(body, max_content)?;
+    let trailer = offset + consumed;
+    if read_u32(bytes, trailer)? != crc32(&content) {
+        return Err(anyhow!("Gzip checksum mismatch"));
+    }
+    if read_u32(bytes, trailer + 4)? != content.len() as u32 {
+        return Err(anyhow!("Gzip size mismatch"));
+    }
+    if bytes.len() > trailer + 8 {
+        return Err(anyhow!(
+            "Gzip files with more than one member are not supported"
+        ));
+    }
+    Ok((Container::Gzip { name, mtime }, content))
+}
+
+fn wrap_gzip(name: Option<&[u8]>, mtime: u32, content: &[u8]) -> Vec<u8> {
+    let mut bytes = GZIP_MAGIC.to_vec();
+    bytes.push(8);
+    bytes.push(if name.is_some() { 0x08 } else { 0 });
+    bytes.extend_from_slice(&mtime.to_le_bytes());
+    // No extra flags, unknown operating system
+    bytes.extend_from_slice(&[0, 0xFF]);
+    if let Some(name) = name {
+        bytes.extend_from_slice(name);
+        bytes.push(0);
+    }
+    bytes.extend(deflate::deflate(content));
+    bytes.extend_from_slice(&crc32(content).to_le_bytes());
+    bytes.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    bytes
+}
+
+fn open_zip(bytes: &[u8], max_content: usize) -> Result<(Container, Vec<u8>)> {
+    // End of central directory record is at least 22 bytes, followed by a comment of up to 64K
+    let search_from = bytes.len().saturating_sub(22 + u16::MAX as usize);
+    let end = (search_from..=bytes.len().saturating_sub(22))
+        .rev()
+        .find(|offset| read_u32(bytes, *offset).ok() == Some(ZIP_END_OF_CENTRAL_DIRECTORY))
+        .ok_or_else(|| anyhow!("Zip end of central directory not found"))?;
+    let entries = read_u16(bytes, end + 10)?;
+    if entries != 1 {
+        return Err(anyhow!(
+            "Zip archive holds {} entries, only single file archives are supported",
+            entries
+        ));
+    }
+    let central = read_u32(bytes, end + 16)? as usize;
+    if read_u32(bytes, central)? != ZIP_CENTRAL_HEADER {
+        return Err(anyhow!("Corrupted zip central directory"));
+    }
+    let flags = read_u16(bytes, central + 8)?;
+    if flags & 0x01 != 0 {
+        return Err(anyhow!("Encrypted zip entries are not supported"));
+    }
+    // Sizes go in the headers when wrapping again, no data descriptor follows the data
+    let flags = flags & !ZIP_DATA_DESCRIPTOR;
+    let method = read_u16(bytes, central + 10)?;
+    let time = read_u16(bytes, central + 12)?;
+    let date = read_u16(bytes, central + 14)?;
+    let crc = read_u32(bytes, central + 16)?;
+    let compressed_size = read_u32(bytes, central + 20)? as usize;
+    let name_length = read_u16(bytes, central + 28)? as usize;
+    let extra_length = read_u16(bytes, central + 30)? as usize;
+    let local = read_u32(bytes, central + 42)? as usize;
+    let name = bytes
+        .get(central + 46..central + 46 + name_length)
+        .ok_or_else(|| anyhow!("Unexpected end of archive"))?
+        .to_vec();
+    let extra = bytes
+        .get(central + 46 + name_length..central + 46 + name_length + extra_length)
+        .ok_or_else(|| anyhow!("Unexpected end of archive"))?
+        .to_vec();
+    if read_u32(bytes, local)? != ZIP_LOCAL_HEADER {
+        return Err(anyhow!("Corrupted zip local file header"));
+    }
+    // Sizes in the local header may be zero when a data descriptor follows, trust the central one
+    let local_extra_start = local + 30 + read_u16(bytes, local + 26)? as usize;
+    let data_start = local_extra_start + read_u16(bytes, local + 28)? as usize;
+    let local_extra = bytes
+        .get(local_extra_start..data_start)
+        .ok_or_else(|| anyhow!("Unexpected end of archive"))?
+        .to_vec();
+    let data = bytes
+        .get(data_start..data_start + compressed_size)
+        .ok_or_else(|| anyhow!("Unexpected end of archive"))?;
+    let content = match method {
+        ZIP_STORED if data.len() > max_content => {
+            return Err(anyhow!(
+                "Zip entry is larger than the limit of {} bytes",
+                max_content
+            ))
+        }
+        ZIP_STORED => data.to_vec(),
+        ZIP_DEFLATED => deflate::inflate_limited(data, max_content)?.0,
+        _ => return Err(anyhow!("Unsupported zip compression method {}", method)),
+    };
+    if crc32(&content) != crc {
+        return Err(anyhow!("Zip entry checksum mismatch"));
+    }
+    Ok((
+        Container::Zip {
+            name,
+            method,
+            flags,
+            time,
+            date,
+            local_extra,
+            extra,
+        },
+        content,
+    ))
+}
+
+fn wrap_zip(container: &Container, content: &[u8]) -> Vec<u8> {
+    let (name, method, flags, time, date, local_extra, extra) = match container {
+        Container::Zip {
+            name,
+            method,
+            flags,
+            time,
+            date,
+            local_extra,
+            extra,
+        } => (name, *method, *flags, *time, *date, local_extra, extra),
+        _ => unreachable!("only zip containers are wrapped as zip"),
+    };
+    let data = if method == ZIP_DEFLATED {
+        deflate::deflate(content)
+    } else {
+        content.to_vec()
+    };
+    let crc = crc32(content);
+    // Fields shared by local and central headers, from "version needed to extract" to the name
+    // length
+    let mut common = Vec::new();
+    common.extend_from_slice(&20u16.to_le_bytes());
+    common.extend_from_slice(&flags.to_le_bytes());
+    common.extend_from_slice(&method.to_le_bytes());
+    common.extend_from_slice(&time.to_le_bytes());
+    common.extend_from_slice(&date.to_le_bytes());
+    common.extend_from_slice(&crc.to_le_bytes());
+    common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    common.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+
+    let mut bytes = ZIP_LOCAL_HEADER.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&common);
+    bytes.extend_from_slice(&(local_extra.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(name);
+    bytes.extend_from_slice(local_extra);
+    bytes.extend_from_slice(&data);
+
+    let central = bytes.len();
+    bytes.extend_from_slice(&ZIP_CENTRAL_HEADER.to_le_bytes());
+    // Version made by
+    bytes.extend_from_slice(&20u16.to_le_bytes());
+    bytes.extend_from_slice(&common);
+    bytes.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+    // Comment length, disk number, internal and external attributes
+    bytes.extend_from_slice(&[0; 10]);
+    // Local header offset
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(name);
+    bytes.extend_from_slice(extra);
+    let central_size = bytes.len() - central;
+
+    bytes.extend_from_slice(&ZIP_END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+    // This disk and the disk holding the central directory
+    bytes.extend_from_slice(&[0; 4]);
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&(central_size as u32).to_le_bytes());
+    bytes.extend_from_slice(&(central as u32).to_le_bytes());
+    // Comment length
+    bytes.extend_from_slice(&0u16.to_le_bytes());
+    bytes
+}
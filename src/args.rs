@@ -1,5 +1,11 @@
-use clap::{AppSettings, Parser, Subcommand};
-use std::path::PathBuf;
+use crate::digest::HashAlgorithm;
+use crate::merge::{Resolution, TypeResolution};
+use crate::metadata::Dpi;
+use crate::mime::MimeType;
+use crate::text::{Keyword, TextForm};
+use clap::{AppSettings, ArgEnum, Parser, Subcommand};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Debug, Parser)]
 #[clap(author, version, about)]
@@ -8,24 +14,681 @@ pub struct Cli {
     /// Optional name to operate on
     #[clap(subcommand)]
     pub command: Command,
-    /// Path to target png file
+    /// Path to target png file, required by commands working on a single file. `-` reads it
+    /// from stdin, commands editing it then need `--stdout`
     #[clap(short, parse(from_os_str), value_name = "FILE")]
-    pub png: PathBuf,
+    pub png: Option<PathBuf>,
+    #[clap(flatten)]
+    pub options: GlobalOptions,
+}
+
+/// Options accepted by every command
+#[derive(Debug, Clone, clap::Args)]
+pub struct GlobalOptions {
+    /// Treat the file as a bare sequence of chunks without png signature, for chunk surgery
+    #[clap(long, global = true)]
+    pub fragment: bool,
+    /// Format of command output, json for scripts. Binary payloads are base64 in json
+    #[clap(long, arg_enum, global = true, default_value = "text")]
+    pub format: OutputFormat,
+    /// Refuse files that break spec recommendations, not only the hard rules checked on parse
+    #[clap(long, global = true)]
+    pub strict: bool,
+    /// Undo damage done to the file in transfer (added BOM, LF to CRLF conversion) when possible.
+    /// Commands writing the file back save the repaired version
+    #[clap(long, global = true)]
+    pub fix_transfer_corruption: bool,
+    /// Largest ancillary chunk accepted in strict mode, in bytes
+    #[clap(long, global = true, default_value_t = crate::verify::STRICT_MAX_ANCILLARY_LENGTH)]
+    pub max_ancillary_size: u32,
+    /// The file is a gzip file or a single file zip archive holding the png. It is unwrapped
+    /// before parsing and commands writing the file back rewrap it the same way
+    #[clap(long, global = true)]
+    pub in_archive: bool,
+    /// The file is an ICO or CUR file, operate on the png image of this entry. The file is
+    /// rewritten with updated directory sizes and offsets
+    #[clap(long, global = true, value_name = "N", conflicts_with = "in-archive")]
+    pub ico_index: Option<usize>,
+    /// TOML registry describing custom chunk types, registered chunks are checked against it
+    /// by `hook` and in strict mode, and annotated with their description and problems by `list`
+    #[clap(long, global = true, parse(from_os_str), value_name = "TOML_FILE")]
+    pub registry: Option<PathBuf>,
+    /// Sign the ordering tokens of split payload parts with the key in this file, and require
+    /// valid signatures when joining them
+    #[clap(long, global = true, parse(from_os_str), value_name = "KEY_FILE")]
+    pub split_key: Option<PathBuf>,
+    /// Hash algorithm for payload references and file pointers made by `encode`: sha256,
+    /// sha512 or blake3. The algorithm is recorded with the hash, reading always uses the
+    /// recorded one
+    #[clap(
+        long,
+        global = true,
+        default_value = "blake3",
+        value_name = "ALGORITHM"
+    )]
+    pub hash: HashAlgorithm,
+    /// Refuse to operate on paths that are symbolic links, files met while walking a directory
+    /// tree included
+    #[clap(long, global = true)]
+    pub no_follow_symlinks: bool,
+    /// Only read and write files resolving under this directory. Can be repeated
+    #[clap(
+        long = "allow-root",
+        global = true,
+        parse(from_os_str),
+        value_name = "DIR"
+    )]
+    pub allow_roots: Vec<PathBuf>,
+    /// Octal mode of files created by the command, like 0644 or 0600, whatever the umask.
+    /// Without it the umask applies. Files edited in place keep their mode
+    #[clap(long, global = true, value_name = "MODE", parse(try_from_str = parse_mode))]
+    pub output_mode: Option<u32>,
+    /// Write the edited png to stdout instead of back to the file. Other output goes to stderr
+    #[clap(long, global = true)]
+    pub stdout: bool,
+    /// Set when the png was read from stdin with `-p -`
+    #[clap(skip)]
+    pub from_stdin: bool,
+    /// Leave the tIME chunk alone when writing a file. By default it is set to the current time
+    #[clap(long, global = true)]
+    pub no_touch_time: bool,
+    /// Refuse files larger than this, in bytes or with a K, M, G or T suffix. `validate` streams
+    /// files and is not limited
+    #[clap(long, global = true, value_name = "SIZE", default_value = "2G", parse(try_from_str = parse_size))]
+    pub max_file_size: u64,
+    /// Operate on files above `--max-file-size`
+    #[clap(long, global = true)]
+    pub force_large: bool,
+    /// Abort a single file command taking longer than this, in seconds or with a ms, s, m or h
+    /// suffix. A command that started writing always finishes
+    #[clap(long, global = true, value_name = "DURATION", parse(try_from_str = parse_duration))]
+    pub timeout: Option<Duration>,
+    /// Abort a single file command when the file content it loads, unwrapped archives included,
+    /// is larger than this. Same units as `--max-file-size`
+    #[clap(long, global = true, value_name = "SIZE", parse(try_from_str = parse_size))]
+    pub memory_limit: Option<u64>,
+    /// Language of messages, like `en` or `zh_CN`. Defaults to `LC_ALL`, `LC_MESSAGES` or `LANG`
+    #[clap(long, global = true, value_name = "LOCALE")]
+    pub locale: Option<String>,
+}
+
+/// Parse a duration in seconds, or with a unit suffix: `500ms`, `30s`, `5m`, `1h`
+pub fn parse_duration(s: &str) -> anyhow::Result<Duration> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let value: u64 = s[..split].parse()?;
+    let millis = match &s[split..] {
+        "ms" => Some(value),
+        "" | "s" => value.checked_mul(1000),
+        "m" => value.checked_mul(60 * 1000),
+        "h" => value.checked_mul(60 * 60 * 1000),
+        unit => return Err(anyhow::anyhow!("unknown duration unit {:?}", unit)),
+    };
+    millis
+        .map(Duration::from_millis)
+        .ok_or_else(|| anyhow::anyhow!("duration {} is too large", s))
+}
+
+/// Parse an octal file mode, `0644`, `600` or `0o640`
+pub fn parse_mode(s: &str) -> anyhow::Result<u32> {
+    let digits = s.strip_prefix("0o").unwrap_or(s);
+    if digits.is_empty() || !digits.bytes().all(|digit| matches!(digit, b'0'..=b'7')) {
+        return Err(anyhow::anyhow!("{:?} is not an octal file mode", s));
+    }
+    match u32::from_str_radix(digits, 8) {
+        Ok(mode) if mode <= 0o7777 => Ok(mode),
+        _ => Err(anyhow::anyhow!("file mode {} is out of range", s)),
+    }
+}
+
+/// Parse a byte count with an optional binary unit suffix, `512`, `64K`, `2G`
+pub fn parse_size(s: &str) -> anyhow::Result<u64> {
+    let (digits, shift) = match s.char_indices().last() {
+        Some((index, unit)) if unit.is_ascii_alphabetic() => {
+            let shift = match unit.to_ascii_uppercase() {
+                'K' => 10,
+                'M' => 20,
+                'G' => 30,
+                'T' => 40,
+                _ => return Err(anyhow::anyhow!("unknown size unit {:?}", unit)),
+            };
+            (&s[..index], shift)
+        }
+        _ => (s, 0),
+    };
+    let value: u64 = digits.parse()?;
+    value
+        .checked_mul(1 << shift)
+        .ok_or_else(|| anyhow::anyhow!("size {} is too large", s))
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatsSort {
+    Size,
+    Count,
+    Type,
+    Order,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlanMethod {
+    Chunk,
+    Store,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
 }
 
 #[derive(Subcommand, Debug)]
 pub enum Command {
     /// Add message into a png file
     Encode {
-        chunk_type: String,
-        message: String,
+        #[clap(required_unless_present_any = &["interactive", "text", "ztxt", "itxt"])]
+        chunk_type: Option<String>,
+        #[clap(required_unless_present_any = &["message-file", "interactive", "pointer", "text", "ztxt", "itxt"])]
+        message: Option<String>,
+        /// Embed translations from a TOML file mapping language tags to strings, one iTXt chunk
+        /// per language. Chunk type must be iTXt
+        #[clap(long, parse(from_os_str), value_name = "TOML_FILE", conflicts_with_all = &["message", "store"])]
+        message_file: Option<PathBuf>,
+        /// Keyword of the iTXt chunks written from `--message-file`
+        #[clap(long, default_value = "Message", requires = "message-file")]
+        keyword: Keyword,
         #[clap(short, long, parse(from_os_str))]
         output: Option<PathBuf>,
+        /// Keep the message in this content addressed store and only embed a reference to it
+        #[clap(long, parse(from_os_str), value_name = "DIR")]
+        store: Option<PathBuf>,
+        /// Record where the stored message can be downloaded from, requires `--store`
+        #[clap(long, requires = "store")]
+        store_url: Option<String>,
+        /// Add the missing IEND chunk after the message when the png is truncated
+        #[clap(long)]
+        repair_trailer: bool,
+        /// Largest chunk data written, longer messages are split across several chunks.
+        /// Capped to the spec limit of 2^31-1 bytes
+        #[clap(long, value_name = "BYTES", default_value_t = crate::chunk::MAX_LENGTH)]
+        max_chunk_size: usize,
+        /// Fail instead of splitting a message that does not fit a single chunk
+        #[clap(long)]
+        no_split: bool,
+        /// Record the MIME type of the message, like `application/json`, so decode can show it
+        /// accordingly
+        #[clap(long, value_name = "TYPE", conflicts_with = "message-file")]
+        mime: Option<MimeType>,
+        /// Choose the chunk type, message and output file step by step
+        #[clap(long, conflicts_with_all = &["chunk-type", "message", "message-file", "output", "pointer"])]
+        interactive: bool,
+        /// Embed a pointer to this file instead of a message: its path relative to the png and
+        /// its hash, computed with the --hash algorithm. The file must be in the directory of the
+        /// png or below
+        #[clap(long, parse(from_os_str), value_name = "FILE", conflicts_with_all = &["message", "message-file", "store", "mime"])]
+        pointer: Option<PathBuf>,
+        /// Add a spec compliant tEXt chunk instead of a message: keyword, null byte and Latin-1
+        /// text, as image viewers and exiftool show it. The text is never split
+        #[clap(
+            long,
+            number_of_values = 2,
+            value_names = &["KEYWORD", "VALUE"],
+            conflicts_with_all = &["chunk-type", "message", "message-file", "store", "mime", "interactive", "pointer", "no-split", "max-chunk-size", "ztxt", "itxt"]
+        )]
+        text: Option<Vec<String>>,
+        /// Like `--text` but in a zTXt chunk, its text zlib compressed. Worth it for long texts
+        #[clap(
+            long,
+            number_of_values = 2,
+            value_names = &["KEYWORD", "VALUE"],
+            conflicts_with_all = &["chunk-type", "message", "message-file", "store", "mime", "interactive", "pointer", "no-split", "max-chunk-size", "itxt"]
+        )]
+        ztxt: Option<Vec<String>>,
+        /// Like `--text` but in an iTXt chunk, for UTF-8 text. See `--lang`,
+        /// `--translated-keyword` and `--compress`
+        #[clap(
+            long,
+            number_of_values = 2,
+            value_names = &["KEYWORD", "VALUE"],
+            conflicts_with_all = &["chunk-type", "message", "message-file", "store", "mime", "interactive", "pointer", "no-split", "max-chunk-size"]
+        )]
+        itxt: Option<Vec<String>>,
+        /// Language tag of the `--itxt` text, like `en` or `zh-CN`
+        #[clap(long, value_name = "LANGUAGE_TAG", requires = "itxt")]
+        lang: Option<String>,
+        /// The `--itxt` keyword translated into the language of its text
+        #[clap(long, value_name = "KEYWORD", requires = "itxt")]
+        translated_keyword: Option<String>,
+        /// Store the `--itxt` text zlib compressed
+        #[clap(long, requires = "itxt")]
+        compress: bool,
+    },
+    /// Tell whether a payload of given size fits and what encoding it would do, without
+    /// touching the file
+    Plan {
+        chunk_type: String,
+        /// Payload size in bytes or with a K, M, G or T suffix
+        #[clap(parse(try_from_str = parse_size), value_name = "SIZE")]
+        payload_size: u64,
+        /// Embed the payload itself, or keep it in a store and embed a reference
+        #[clap(long, arg_enum, default_value = "chunk")]
+        method: PlanMethod,
+        /// Store the `store` method would use
+        #[clap(
+            long,
+            parse(from_os_str),
+            value_name = "DIR",
+            required_if_eq("method", "store")
+        )]
+        store: Option<PathBuf>,
+        /// Download location recorded in the reference, requires `--store`
+        #[clap(long, requires = "store")]
+        store_url: Option<String>,
+        #[clap(long, value_name = "BYTES", default_value_t = crate::chunk::MAX_LENGTH)]
+        max_chunk_size: usize,
+        #[clap(long)]
+        no_split: bool,
     },
     /// Get a message from a png file
-    Decode { chunk_type: String },
+    Decode {
+        #[clap(required_unless_present_any = &["text", "ztxt", "itxt"])]
+        chunk_type: Option<String>,
+        /// Resolve message references against this content addressed store
+        #[clap(long, parse(from_os_str), value_name = "DIR")]
+        store: Option<PathBuf>,
+        /// Pick the best translation for this language from iTXt chunks, chunk type must be iTXt
+        #[clap(long, value_name = "LANGUAGE_TAG")]
+        lang: Option<String>,
+        /// Keyword of the iTXt chunks to pick from with `--lang`
+        #[clap(long, default_value = "Message", requires = "lang")]
+        keyword: Keyword,
+        /// Try every carrier this tool can read (png, png in a gzip or zip file, png entry of
+        /// an ICO file, bare chunk fragment) and report on stderr which one held the message
+        #[clap(long, conflicts_with = "lang")]
+        any: bool,
+        /// Write the raw message to this file instead of printing it. The message is streamed
+        /// from the png without being held in memory unless an option needs the whole file
+        #[clap(long, parse(from_os_str), value_name = "FILE")]
+        output_file: Option<PathBuf>,
+        /// Open the message with the default application for its MIME type
+        #[clap(long, conflicts_with = "output-file")]
+        open: bool,
+        /// When the message points to another file, read that file and check its hash
+        #[clap(long)]
+        follow: bool,
+        /// Print every message of this type, each prefixed with its index among them
+        #[clap(long, conflicts_with_all = &["lang", "any", "output-file", "open"])]
+        all: bool,
+        /// Get the message at this zero based index among messages of this type, as numbered by
+        /// `--all`. A message split over several chunks counts once
+        #[clap(long, value_name = "N", conflicts_with_all = &["lang", "any", "all"])]
+        nth: Option<usize>,
+        /// Print the text of the first tEXt chunk with this keyword
+        #[clap(
+            long,
+            value_name = "KEYWORD",
+            conflicts_with_all = &["chunk-type", "store", "lang", "any", "open", "follow", "all", "nth", "ztxt", "itxt"]
+        )]
+        text: Option<Keyword>,
+        /// Print the inflated text of the first zTXt chunk with this keyword
+        #[clap(
+            long,
+            value_name = "KEYWORD",
+            conflicts_with_all = &["chunk-type", "store", "lang", "any", "open", "follow", "all", "nth", "itxt"]
+        )]
+        ztxt: Option<Keyword>,
+        /// Print the text of the first iTXt chunk with this keyword, whatever its language and
+        /// compression. `--lang` picks among translations instead
+        #[clap(
+            long,
+            value_name = "KEYWORD",
+            conflicts_with_all = &["chunk-type", "store", "lang", "any", "open", "follow", "all", "nth"]
+        )]
+        itxt: Option<Keyword>,
+    },
+    /// Add a whole file to a png, keeping its name and size so it can be extracted as it was
+    EncodeFile {
+        #[clap(parse(from_os_str))]
+        path: PathBuf,
+        #[clap(long, default_value = crate::attachment::DEFAULT_CHUNK_TYPE)]
+        chunk_type: String,
+        /// Write the png here instead of replacing it
+        #[clap(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+        /// Largest chunk data written, larger files are split across several chunks
+        #[clap(long, value_name = "BYTES", default_value_t = crate::chunk::MAX_LENGTH)]
+        max_chunk_size: usize,
+    },
+    /// Save a file added with `encode-file`
+    ExtractFile {
+        #[clap(default_value = crate::attachment::DEFAULT_CHUNK_TYPE)]
+        chunk_type: String,
+        /// Where to write the file, by default its original name in the current directory
+        #[clap(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
     /// Remove a message from a png file
-    Remove { chunk_type: String },
+    Remove {
+        chunk_type: String,
+        /// Also write the removed chunks to this fragment file, `restore` puts them back
+        #[clap(long, parse(from_os_str), value_name = "FRAGMENT_FILE")]
+        save_removed: Option<PathBuf>,
+        /// Remove every chunk of this type instead of the first one
+        #[clap(long)]
+        all: bool,
+        /// Remove the chunk at this zero based index among chunks of this type
+        #[clap(long, value_name = "N", conflicts_with = "all")]
+        nth: Option<usize>,
+        /// Only count textual chunks with this keyword, chunk type must be tEXt, zTXt or iTXt
+        #[clap(long)]
+        keyword: Option<Keyword>,
+    },
+    /// Change the type of every chunk of type OLD to NEW, keeping data and recomputing CRC. Both
+    /// types must be ancillary
+    RenameType {
+        old: String,
+        new: String,
+        /// Rename in every png file under this directory instead of the file given with -p
+        #[clap(long, parse(from_os_str), value_name = "DIR")]
+        dir: Option<PathBuf>,
+    },
+    /// Put chunks saved with `remove --save-removed` back at their original indices
+    Restore {
+        #[clap(parse(from_os_str))]
+        fragment_file: PathBuf,
+    },
     /// Print given png file
     Print,
+    /// Find conflicting sRGB, iCCP, gAMA and cHRM chunks
+    ColorCheck {
+        /// Remove the chunks that lose against another color chunk
+        #[clap(long)]
+        fix: bool,
+    },
+    /// Print image size, color type and physical pixel density
+    Info {
+        /// Only print the pixel density
+        #[clap(long)]
+        dpi: bool,
+    },
+    /// Store the physical pixel density in a pHYs chunk
+    SetDpi {
+        /// Dots per inch, like 300, or 300x150 for different horizontal and vertical values
+        dpi: Dpi,
+    },
+    /// Print the content id: SHA-256 of IHDR, PLTE and IDAT, the same for copies of an image
+    /// that only differ in metadata or messages
+    Id,
+    /// Show the chunk layout, consecutive chunks of the same type are grouped
+    Inspect {
+        /// Emit the layout as a diagram instead of text
+        #[clap(long, arg_enum, value_name = "FORMAT")]
+        graph: Option<GraphFormat>,
+    },
+    /// Bring metadata and message chunks over from another version of the same image
+    Merge {
+        /// The other version, its critical chunks are ignored
+        #[clap(parse(from_os_str))]
+        other: PathBuf,
+        /// What to keep when both files have different chunks of a type: ours, theirs or both
+        #[clap(long, default_value = "ours", value_name = "RESOLUTION")]
+        prefer: Resolution,
+        /// Resolution for a single chunk type, like tEXt=both. Can be repeated
+        #[clap(long = "type", value_name = "TYPE=RESOLUTION")]
+        per_type: Vec<TypeResolution>,
+    },
+    /// Show how file size splits between image data, metadata and messages
+    Stats {
+        /// Break sizes down per chunk type
+        #[clap(long)]
+        by_type: bool,
+        /// Order of the per type rows
+        #[clap(long, arg_enum, default_value = "size", requires = "by-type")]
+        sort: StatsSort,
+    },
+    /// Print a diff-friendly line per chunk with its type, length, crc and data sha256
+    Dump {
+        /// Compare against a previously stored dump instead of printing
+        #[clap(long, parse(from_os_str), value_name = "DUMP_FILE")]
+        check: Option<PathBuf>,
+    },
+    /// Print the structural digest: SHA-256 of the chunk layout, ignoring the modification time.
+    /// Stored next to an asset, it lets tests assert a pipeline leaves the file unchanged
+    Snapshot {
+        /// Write the digest to this file instead of printing it
+        #[clap(short, long, parse(from_os_str), value_name = "DIGEST_FILE")]
+        output: Option<PathBuf>,
+        /// Compare against a digest file written before, failing when they differ
+        #[clap(
+            long,
+            parse(from_os_str),
+            value_name = "DIGEST_FILE",
+            conflicts_with = "output"
+        )]
+        check: Option<PathBuf>,
+    },
+    /// List every chunk with its index, type, length, crc, type properties and the keyword of
+    /// textual chunks. Chunks of types in `--registry` also get their description and problems
+    List,
+    /// Pre-commit check: verify structure and required chunks of given or staged png files
+    Hook {
+        /// Files to check, staged png files are checked when none is given
+        #[clap(parse(from_os_str))]
+        files: Vec<PathBuf>,
+        /// Chunk type every file must carry, can be repeated
+        #[clap(long = "require", value_name = "CHUNK_TYPE")]
+        required: Vec<String>,
+    },
+    /// Check structure and CRCs while streaming the file, chunk data is never held in memory.
+    /// Suited to very large or untrusted files, honors `--strict` and `--max-ancillary-size`
+    #[clap(visible_alias = "verify")]
+    Validate {
+        /// Check every png file under this directory instead and print a summary of the corpus
+        #[clap(long, parse(from_os_str), value_name = "DIR")]
+        recursive: Option<PathBuf>,
+        /// Also write a JUnit XML report with a test case per file, for CI dashboards
+        #[clap(
+            long,
+            parse(from_os_str),
+            value_name = "XML_FILE",
+            requires = "recursive"
+        )]
+        junit: Option<PathBuf>,
+        /// Load the whole file and list every chunk with a wrong CRC or length field, with its
+        /// offset and the right value, instead of stopping at the first one
+        #[clap(long, conflicts_with = "recursive")]
+        crc_report: bool,
+    },
+    /// Convert tEXt, zTXt and iTXt chunks to another of these types, keeping their keywords
+    ConvertText {
+        /// Target chunk type: tEXt, zTXt or iTXt
+        #[clap(long, value_name = "CHUNK_TYPE")]
+        to: TextForm,
+        /// Only convert chunks with this keyword
+        #[clap(long)]
+        keyword: Option<Keyword>,
+        /// Language tag given to converted iTXt chunks that have none
+        #[clap(long, value_name = "LANGUAGE_TAG")]
+        lang: Option<String>,
+        /// Compress the text of converted iTXt chunks
+        #[clap(long)]
+        compress: bool,
+    },
+    /// Read and edit the XMP packet stored in the standard iTXt chunk
+    Xmp {
+        #[clap(subcommand)]
+        action: XmpAction,
+    },
+    /// Copy message chunks into identically-pathed files of another tree with the same pixels
+    SyncMeta {
+        #[clap(parse(from_os_str))]
+        source: PathBuf,
+        #[clap(parse(from_os_str))]
+        destination: PathBuf,
+        /// Only report what would be synced
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// List png files of a directory tree sharing the same pixel content
+    FindDuplicates {
+        #[clap(parse(from_os_str))]
+        dir: PathBuf,
+        /// Copy message chunks of every duplicate into the first file of its group
+        #[clap(long)]
+        merge: bool,
+    },
+    /// List the content id, message ids and verification status of every png of a directory
+    /// tree, or check the tree against such a manifest
+    Manifest {
+        #[clap(parse(from_os_str))]
+        dir: PathBuf,
+        /// Write the manifest to this file instead of stdout
+        #[clap(short, long, parse(from_os_str), conflicts_with = "check")]
+        output: Option<PathBuf>,
+        /// Report files modified, missing or missing messages since this manifest was made
+        #[clap(long, parse(from_os_str), value_name = "MANIFEST")]
+        check: Option<PathBuf>,
+        /// Sign the manifest with HMAC-SHA256 using the contents of this file as key
+        #[clap(long, parse(from_os_str), value_name = "FILE")]
+        key: Option<PathBuf>,
+    },
+    /// Flag png files of a directory tree with data after IEND, reading only the head and tail
+    /// of each file. Prints one JSON object per flagged file.
+    ScanTrailing {
+        #[clap(parse(from_os_str))]
+        dir: PathBuf,
+        /// Bytes read at each end of a file
+        #[clap(long, default_value = "65536", value_name = "BYTES")]
+        window: u64,
+        /// Print every file, not only flagged ones
+        #[clap(long)]
+        all: bool,
+    },
+    /// Write a set of unusual png files to a directory, for testing programs that read png:
+    /// interlaced, 16 bit, palette, APNG, zero length chunks, huge chunks and a corrupt CRC
+    GenCorpus {
+        #[clap(parse(from_os_str))]
+        dir: PathBuf,
+        /// Size of the private chunk in large-chunk.png
+        #[clap(long, default_value_t = crate::corpus::DEFAULT_LARGE_CHUNK_SIZE, value_name = "BYTES")]
+        large_chunk_size: usize,
+    },
+    /// Turn an Apple CgBI png, as extracted from iOS apps, into a standard png
+    ConvertCgbi {
+        /// Write the standard png here instead of replacing the file
+        #[clap(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Copy a png from stdin to stdout unchanged, reporting messages found on the way to stderr
+    Tee {
+        /// Chunk types to report, every private ancillary chunk when none is given
+        chunk_types: Vec<String>,
+        /// Write the report to this file descriptor instead of stderr, like 3
+        #[clap(long, value_name = "FD")]
+        report_fd: Option<u32>,
+    },
+    /// Add "Decode message" and "Add message" to the context menu of png files in the file
+    /// manager
+    InstallIntegration {
+        /// Chunk type "Decode message" looks for
+        #[clap(long, default_value = crate::wizard::SUGGESTED_CHUNK_TYPE)]
+        chunk_type: String,
+        /// Terminal running the encode wizard on Linux, followed by the command to run
+        #[clap(long, default_value = crate::integration::DEFAULT_TERMINAL)]
+        terminal: String,
+        /// Remove the entries instead
+        #[clap(long)]
+        uninstall: bool,
+        /// Print the files that would be written without writing them
+        #[clap(long, conflicts_with = "uninstall")]
+        dry_run: bool,
+    },
+}
+
+impl Command {
+    /// Every file or directory given on the command line for this command
+    pub fn paths(&self) -> Vec<&Path> {
+        let mut paths: Vec<&PathBuf> = Vec::new();
+        match self {
+            Command::Encode {
+                message_file,
+                output,
+                store,
+                pointer,
+                ..
+            } => paths.extend([message_file, output, store, pointer].into_iter().flatten()),
+            Command::Plan { store, .. } => paths.extend(store),
+            Command::Decode {
+                store, output_file, ..
+            } => paths.extend([store, output_file].into_iter().flatten()),
+            Command::EncodeFile { path, output, .. } => {
+                paths.push(path);
+                paths.extend(output);
+            }
+            Command::ExtractFile { output, .. } | Command::ConvertCgbi { output } => {
+                paths.extend(output)
+            }
+            Command::Snapshot { output, check } => {
+                paths.extend([output, check].into_iter().flatten())
+            }
+            Command::Remove { save_removed, .. } => paths.extend(save_removed),
+            Command::Restore { fragment_file } => paths.push(fragment_file),
+            Command::RenameType { dir, .. } => paths.extend(dir),
+            Command::Validate {
+                recursive, junit, ..
+            } => paths.extend([recursive, junit].into_iter().flatten()),
+            Command::Merge { other, .. } => paths.push(other),
+            Command::Dump { check } => paths.extend(check),
+            Command::Hook { files, .. } => paths.extend(files),
+            Command::SyncMeta {
+                source,
+                destination,
+                ..
+            } => paths.extend([source, destination]),
+            Command::FindDuplicates { dir, .. }
+            | Command::ScanTrailing { dir, .. }
+            | Command::GenCorpus { dir, .. } => paths.push(dir),
+            Command::Manifest {
+                dir,
+                output,
+                check,
+                key,
+            } => {
+                paths.push(dir);
+                paths.extend([output, check, key].into_iter().flatten());
+            }
+            Command::Xmp {
+                action: XmpAction::Embed { packet },
+            } => paths.push(packet),
+            _ => {}
+        }
+        paths.into_iter().map(PathBuf::as_path).collect()
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum XmpAction {
+    /// Print the whole XMP packet
+    Show,
+    /// Store an XMP packet read from a file, replacing the current one
+    Embed {
+        #[clap(parse(from_os_str), value_name = "XMP_FILE")]
+        packet: PathBuf,
+    },
+    /// Print a property, array items are printed one per line
+    Get {
+        /// Prefixed property name like dc:creator
+        property: String,
+    },
+    /// Set a property, creating the XMP packet when there is none
+    Set {
+        /// Prefixed property name like dc:creator
+        property: String,
+        value: String,
+    },
 }
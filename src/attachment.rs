@@ -0,0 +1,97 @@
+#![allow(dead_code)]
+//! # Attachment
+//! Whole files carried as messages, with their original name and size so they can be extracted
+//! as they were.
+//!
+//! The message starts with a header line, `msgfile:<size> <name>`, followed by the file bytes.
+//! Only the file name is kept, never the directories it was in, so extracting can't write
+//! outside of where it is asked to.
+use anyhow::{anyhow, Result};
+use std::path::Path;
+
+pub const PREFIX: &str = "msgfile:";
+
+/// Chunk type files go to unless told otherwise. Ancillary, private and safe to copy.
+pub const DEFAULT_CHUNK_TYPE: &str = "fiLe";
+
+/// Longest header accepted, prefix and line break included
+pub const MAX_HEADER_LEN: usize = 512;
+
+/// What is known about an attached file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    pub name: String,
+    pub size: u64,
+}
+
+impl Attachment {
+    /// Describe the file at `path` holding `content`
+    pub fn of(path: &Path, content: &[u8]) -> Result<Self> {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow!("{} has no UTF-8 file name", path.display()))?;
+        check_name(name)?;
+        Ok(Attachment {
+            name: name.to_string(),
+            size: content.len() as u64,
+        })
+    }
+}
+
+fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\', '\n', '\0']) {
+        return Err(anyhow!("{:?} is not a plain file name", name));
+    }
+    Ok(())
+}
+
+/// Header followed by the file bytes
+pub fn wrap(attachment: &Attachment, content: &[u8]) -> Vec<u8> {
+    let header = format!("{}{} {}\n", PREFIX, attachment.size, attachment.name);
+    let mut data = Vec::with_capacity(header.len() + content.len());
+    data.extend_from_slice(header.as_bytes());
+    data.extend_from_slice(content);
+    data
+}
+
+/// Tells whether given message data is an attached file
+pub fn is_attachment(data: &[u8]) -> bool {
+    data.starts_with(PREFIX.as_bytes())
+}
+
+/// Split message data into the file description and its bytes, checking the size
+pub fn unwrap(data: &[u8]) -> Result<(Attachment, &[u8])> {
+    if !is_attachment(data) {
+        return Err(anyhow!("Message is not an attached file"));
+    }
+    let header_end = data
+        .iter()
+        .take(MAX_HEADER_LEN)
+        .position(|byte| *byte == b'\n')
+        .ok_or_else(|| anyhow!("Attached file header is not terminated"))?;
+    let header = std::str::from_utf8(&data[PREFIX.len()..header_end])?;
+    let (size, name) = header
+        .split_once(' ')
+        .ok_or_else(|| anyhow!("Attached file header has no name"))?;
+    let size: u64 = size
+        .parse()
+        .map_err(|_| anyhow!("Malformed attached file size {:?}", size))?;
+    check_name(name)?;
+    let content = &data[header_end + 1..];
+    if content.len() as u64 != size {
+        return Err(anyhow!(
+            "Attached file {} should be {} bytes, found {}",
+            name,
+            size,
+            content.len()
+        ));
+    }
+    Ok((
+        Attachment {
+            name: name.to_string(),
+            size,
+        },
+        content,
+    ))
+}
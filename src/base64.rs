@@ -0,0 +1,56 @@
+#![allow(dead_code)]
+//! # Base64
+//! Standard base64 with padding, RFC 4648, for binary payloads in JSON output.
+use anyhow::{anyhow, Result};
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn encode(data: &[u8]) -> String {
+    let mut text = String::with_capacity(data.len().div_ceil(3) * 4);
+    for group in data.chunks(3) {
+        let bytes = [
+            group[0],
+            group.get(1).copied().unwrap_or(0),
+            group.get(2).copied().unwrap_or(0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for position in 0..4 {
+            if position <= group.len() {
+                let index = (bits >> (18 - position * 6)) & 0x3f;
+                text.push(ALPHABET[index as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+pub fn decode(text: &str) -> Result<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return Err(anyhow!(
+            "Base64 length {} is not a multiple of 4",
+            text.len()
+        ));
+    }
+    let mut data = Vec::with_capacity(text.len() / 4 * 3);
+    let groups = text.len() / 4;
+    for (group_index, group) in text.chunks(4).enumerate() {
+        let padding = group.iter().rev().take_while(|byte| **byte == b'=').count();
+        if padding > 2 || (padding > 0 && group_index + 1 != groups) {
+            return Err(anyhow!("Misplaced base64 padding"));
+        }
+        let mut bits = 0u32;
+        for byte in group[..4 - padding].iter() {
+            let value = ALPHABET
+                .iter()
+                .position(|letter| letter == byte)
+                .ok_or_else(|| anyhow!("{:?} is not a base64 character", *byte as char))?;
+            bits = bits << 6 | value as u32;
+        }
+        bits <<= 6 * padding;
+        data.extend_from_slice(&bits.to_be_bytes()[1..4 - padding]);
+    }
+    Ok(data)
+}
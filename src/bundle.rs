@@ -0,0 +1,127 @@
+#![allow(dead_code)]
+//! # Bundle
+//! A message with translations, stored as one `iTXt` chunk per language sharing the same keyword.
+//!
+//! Bundles are written by hand as a flat TOML file mapping language tags to strings:
+//! ```toml
+//! # Comments and blank lines are ignored
+//! en = "Hello"
+//! de = "Hallo"
+//! "zh-CN" = "你好"
+//! ```
+//! The first entry is the default used when no better translation is found, and an entry with an
+//! empty key (`"" = "..."`) is the text of unknown language.
+use crate::chunk::Chunk;
+use crate::png::PNG;
+use crate::text::{self, InternationalText, Keyword};
+use crate::toml::{self, Line, Value};
+use anyhow::{anyhow, Result};
+
+/// Keyword used for bundle entries unless another one is given
+pub const DEFAULT_KEYWORD: &str = "Message";
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MessageBundle {
+    /// `(language tag, text)` pairs in the order they were defined
+    pub entries: Vec<(String, String)>,
+}
+
+impl MessageBundle {
+    /// Parse the flat TOML subset described in the module documentation.
+    pub fn from_toml(source: &str) -> Result<Self> {
+        let mut entries: Vec<(String, String)> = Vec::new();
+        for (line_number, line) in source.lines().enumerate() {
+            let (key, value) = match toml::parse_line(line)
+                .map_err(|e| anyhow!("line {}: {}", line_number + 1, e))?
+            {
+                None => continue,
+                Some(Line::KeyValue(key, Value::String(value))) => (key, value),
+                Some(Line::KeyValue(key, _)) => {
+                    return Err(anyhow!(
+                        "line {}: value of {} must be a quoted string",
+                        line_number + 1,
+                        key
+                    ))
+                }
+                Some(Line::Table(name)) => {
+                    return Err(anyhow!(
+                        "line {}: tables are not allowed in message bundles, found [{}]",
+                        line_number + 1,
+                        name
+                    ))
+                }
+            };
+            if entries.iter().any(|(existing, _)| existing == &key) {
+                return Err(anyhow!(
+                    "line {}: duplicate language {}",
+                    line_number + 1,
+                    key
+                ));
+            }
+            entries.push((key, value));
+        }
+        if entries.is_empty() {
+            return Err(anyhow!("Message bundle has no entries"));
+        }
+        Ok(Self { entries })
+    }
+
+    /// One `iTXt` chunk per entry, all with given keyword
+    pub fn to_chunks(&self, keyword: &Keyword) -> Vec<Chunk> {
+        self.entries
+            .iter()
+            .map(|(language, text)| InternationalText::new(keyword, language, text).to_chunk())
+            .collect()
+    }
+
+    /// Collect every `iTXt` entry of given keyword from a png, in file order. Chunks of other
+    /// keywords are skipped without inflating their text. Fails when an entry can not be parsed
+    /// or its compressed text inflates to more than `max_text` bytes.
+    pub fn from_png(png: &PNG, keyword: &Keyword, max_text: usize) -> Result<Self> {
+        let entries = png
+            .chunks()
+            .iter()
+            .filter(|chunk| chunk.chunk_type().bytes() == InternationalText::CHUNK_TYPE)
+            .filter(|chunk| {
+                text::keyword_of(chunk.chunk_type(), chunk.data()).as_deref()
+                    == Some(keyword.as_str())
+            })
+            .map(|chunk| {
+                InternationalText::from_chunk_limited(chunk, max_text)
+                    .map(|text| (text.language_tag, text.text))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { entries })
+    }
+
+    /// Bytes of text held by the bundle
+    pub fn text_len(&self) -> usize {
+        self.entries.iter().map(|(_, text)| text.len()).sum()
+    }
+
+    /// Pick the best text for requested language. Tags are compared case insensitively and
+    /// the first rule that matches wins:
+    /// 1. the exact language tag, `de-AT` for `de-AT`
+    /// 2. the primary language, `de` for `de-AT`
+    /// 3. another region of the primary language, `de-CH` for `de-AT`
+    /// 4. an entry without language tag
+    /// 5. the first entry of the bundle
+    pub fn select(&self, language: &str) -> Option<&str> {
+        let primary = primary_subtag(language);
+        let found = self
+            .find(|tag| tag.eq_ignore_ascii_case(language))
+            .or_else(|| self.find(|tag| tag.eq_ignore_ascii_case(primary)))
+            .or_else(|| self.find(|tag| primary_subtag(tag).eq_ignore_ascii_case(primary)))
+            .or_else(|| self.find(|tag| tag.is_empty()))
+            .or_else(|| self.entries.first());
+        found.map(|(_, text)| text.as_str())
+    }
+
+    fn find<F: Fn(&str) -> bool>(&self, predicate: F) -> Option<&(String, String)> {
+        self.entries.iter().find(|(tag, _)| predicate(tag))
+    }
+}
+
+fn primary_subtag(language: &str) -> &str {
+    language.split(['-', '_']).next().unwrap_or(language)
+}
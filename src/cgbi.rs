@@ -0,0 +1,176 @@
+#![allow(dead_code)]
+//! # CgBI
+//! Apple's iOS optimized png variant, as found in images extracted from app bundles and asset
+//! catalogs.
+//!
+//! Such a file starts with a `CgBI` chunk before IHDR, and its image data differs from the spec:
+//! IDAT holds a raw DEFLATE stream without the zlib header and checksum, pixels are stored in BGR
+//! or BGRA order, and color is premultiplied by alpha. Other chunks are standard, so messages can
+//! be read and written as usual, but only Apple's decoders display the image. [to_standard]
+//! turns it into a png every decoder reads.
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::deflate;
+use crate::metadata::{COLOR_TYPE_TRUECOLOR, COLOR_TYPE_TRUECOLOR_ALPHA};
+use crate::png::PNG;
+use anyhow::{anyhow, Result};
+
+pub const CHUNK_TYPE: [u8; 4] = *b"CgBI";
+
+/// Apple's private chunk locating IDAT pieces for parallel decoding, stale once IDAT is rewritten
+pub const IDOT_CHUNK_TYPE: [u8; 4] = *b"iDOT";
+
+/// Largest IDAT written by [to_standard], the image data is spread over as many as needed
+pub const IDAT_PIECE_SIZE: usize = 1 << 18;
+
+/// Tells whether the png is in Apple's CgBI variant
+pub fn is_cgbi(png: &PNG) -> bool {
+    png.chunks()
+        .first()
+        .is_some_and(|chunk| chunk.chunk_type().bytes() == CHUNK_TYPE)
+}
+
+/// Undo the scanline filters, returning rows without their filter type byte
+pub fn unfilter(data: &[u8], row_bytes: usize, rows: usize, pixel_bytes: usize) -> Result<Vec<u8>> {
+    let expected = rows * (row_bytes + 1);
+    if data.len() < expected {
+        return Err(anyhow!(
+            "Image data holds {} bytes, {} rows of {} bytes need {}",
+            data.len(),
+            rows,
+            row_bytes,
+            expected
+        ));
+    }
+    let mut pixels = vec![0u8; rows * row_bytes];
+    for row in 0..rows {
+        let filter = data[row * (row_bytes + 1)];
+        let line = &data[row * (row_bytes + 1) + 1..(row + 1) * (row_bytes + 1)];
+        let (done, current) = pixels.split_at_mut(row * row_bytes);
+        let previous = match row {
+            0 => None,
+            _ => Some(&done[(row - 1) * row_bytes..]),
+        };
+        let current = &mut current[..row_bytes];
+        for x in 0..row_bytes {
+            let a = if x >= pixel_bytes {
+                current[x - pixel_bytes]
+            } else {
+                0
+            };
+            let b = previous.map_or(0, |previous| previous[x]);
+            let c = match previous {
+                Some(previous) if x >= pixel_bytes => previous[x - pixel_bytes],
+                _ => 0,
+            };
+            let predicted = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err(anyhow!("Unknown filter type {} in row {}", filter, row)),
+            };
+            current[x] = line[x].wrapping_add(predicted);
+        }
+    }
+    Ok(pixels)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Swap blue and red, and undo the alpha premultiplication when there is alpha
+fn to_rgb(pixels: &mut [u8], pixel_bytes: usize) {
+    for pixel in pixels.chunks_exact_mut(pixel_bytes) {
+        pixel.swap(0, 2);
+        if pixel_bytes == 4 {
+            let alpha = pixel[3] as u32;
+            if alpha != 0 && alpha != 255 {
+                for channel in pixel[..3].iter_mut() {
+                    *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+                }
+            }
+        }
+    }
+}
+
+/// Standard png with the same image and every chunk other than `CgBI`, `iDOT` and IDAT kept
+/// in place. Only 8 bit, non interlaced truecolor images are handled, which is what Apple's
+/// tools write.
+pub fn to_standard(png: &PNG) -> Result<PNG> {
+    if !is_cgbi(png) {
+        return Err(anyhow!("Png is not in Apple's CgBI format"));
+    }
+    let header = png.image_header()?;
+    let pixel_bytes = match (header.color_type, header.bit_depth) {
+        (COLOR_TYPE_TRUECOLOR, 8) => 3,
+        (COLOR_TYPE_TRUECOLOR_ALPHA, 8) => 4,
+        _ => {
+            return Err(anyhow!(
+                "CgBI images of color type {} at {} bit are not supported",
+                header.color_type,
+                header.bit_depth
+            ))
+        }
+    };
+    if header.interlace_method != 0 {
+        return Err(anyhow!("Interlaced CgBI images are not supported"));
+    }
+    if header.width == 0 || header.height == 0 {
+        return Err(anyhow!("Image has no pixels"));
+    }
+    let row_bytes = header.width as usize * pixel_bytes;
+    let rows = header.height as usize;
+    let expected = rows
+        .checked_mul(row_bytes + 1)
+        .ok_or_else(|| anyhow!("Image of {}x{} is too large", header.width, rows))?;
+    let compressed: Vec<u8> = png
+        .chunks()
+        .iter()
+        .filter(|chunk| chunk.chunk_type().bytes() == *b"IDAT")
+        .flat_map(|chunk| chunk.data().iter().copied())
+        .collect();
+    let (filtered, _) = deflate::inflate_limited(&compressed, expected)?;
+    let mut pixels = unfilter(&filtered, row_bytes, rows, pixel_bytes)?;
+    to_rgb(&mut pixels, pixel_bytes);
+    let mut raw = Vec::with_capacity(expected);
+    for row in pixels.chunks_exact(row_bytes) {
+        // Filter type None
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    let image_data = deflate::zlib_compress(&raw);
+    let idat = ChunkType { inner: *b"IDAT" };
+    let mut chunks = Vec::with_capacity(png.chunks().len());
+    let mut idat_written = false;
+    for chunk in png.chunks() {
+        match chunk.chunk_type().bytes() {
+            CHUNK_TYPE | IDOT_CHUNK_TYPE => {}
+            [b'I', b'D', b'A', b'T'] if idat_written => {}
+            [b'I', b'D', b'A', b'T'] => {
+                for piece in image_data.chunks(IDAT_PIECE_SIZE) {
+                    chunks.push(Chunk::new(idat.clone(), piece.to_vec()));
+                }
+                idat_written = true;
+            }
+            _ => chunks.push(chunk.clone()),
+        }
+    }
+    let mut standard = PNG::from_chunks(chunks);
+    standard.set_ordering_rules(png.ordering_rules().clone());
+    Ok(standard)
+}
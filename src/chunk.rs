@@ -12,12 +12,13 @@
 //! the PNG file.
 
 use crate::chunk_type::ChunkType;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use crc32fast::Hasher;
 use std::fmt::{Display, Formatter};
-use std::io::{BufReader, Read};
+use std::io::{BufReader, Read, Write};
+use std::sync::Arc;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Chunk {
     /// A 4-byte unsigned integer giving the number of bytes in the chunk's data field.
     /// The `length` counts only the `data` field, not itself, the `chunk_type` code, or the `crc`.
@@ -25,29 +26,139 @@ pub struct Chunk {
     length: u32,
     /// A 4-byte chunk type code. Only ASCII A-Z(65-90) and a-z(97-122) are acceptable.
     chunk_type: ChunkType,
-    /// 0..N Bytes depending on what type this chunk is
-    data: Vec<u8>,
+    /// 0..N Bytes depending on what type this chunk is.
+    /// Shared between clones until one of them is modified, so cloning a chunk is cheap.
+    data: Arc<Vec<u8>>,
     /// ISO-3309 Cyclic Redundancy Check  
     /// The 32-bit CRC register is initialized to all 1's, and then the data from each byte
     /// is processed from the least significant bit (1) to the most significant bit (128).
     crc: u32,
+    /// Partial CRC after the chunk type and every [CRC_CHECKPOINT_INTERVAL] bytes of data.
+    /// When data is mutated, hashing resumes from the last checkpoint before the first changed
+    /// byte instead of starting over. Empty for chunks shorter than one interval.
+    crc_checkpoints: Vec<u32>,
+}
+
+/// Number of data bytes between two stored partial CRC values
+pub const CRC_CHECKPOINT_INTERVAL: usize = 1 << 20;
+
+/// Largest data length the spec allows, 2^31-1 bytes
+pub const MAX_LENGTH: usize = (1 << 31) - 1;
+
+/// Data length as stored in the length field, or an error when it exceeds [MAX_LENGTH]
+pub(crate) fn checked_length(length: usize) -> Result<u32> {
+    if length > MAX_LENGTH {
+        return Err(anyhow!(
+            "Chunk data of {} bytes exceeds the limit of {} bytes",
+            length,
+            MAX_LENGTH
+        ));
+    }
+    Ok(length as u32)
 }
 
 impl Chunk {
     /// Construct a `Chunk` from given chunk type and data.
     /// Notice that this method does not check if `data` is valid for given `chunk_type`.
     /// Chunk length and crc are calculate automatically.
+    ///
+    /// # Panics
+    /// When `data` is longer than [MAX_LENGTH], use [try_new](Chunk::try_new) for data of
+    /// unknown size.
     pub fn new(chunk_type: ChunkType, data: Vec<u8>) -> Self {
-        let mut crc_hasher = Hasher::new();
-        crc_hasher.update(chunk_type.inner.as_slice());
-        crc_hasher.update(data.as_slice());
-        let crc = crc_hasher.finalize();
-        Self {
-            length: data.len() as u32,
+        match Self::try_new(chunk_type, data) {
+            Ok(chunk) => chunk,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Like [new](Chunk::new), but returns an error when `data` is longer than [MAX_LENGTH].
+    pub fn try_new(chunk_type: ChunkType, data: Vec<u8>) -> Result<Self> {
+        let mut chunk = Self {
+            length: checked_length(data.len())?,
             chunk_type,
-            data,
-            crc,
+            data: Arc::new(data),
+            crc: 0,
+            crc_checkpoints: Vec::new(),
+        };
+        chunk.update_crc_from(0);
+        Ok(chunk)
+    }
+
+    /// Replace the whole data of this chunk.
+    /// Only bytes after the common prefix of old and new data are hashed again, rounded down to
+    /// the closest CRC checkpoint.
+    ///
+    /// # Panics
+    /// When `data` is longer than [MAX_LENGTH].
+    pub fn set_data(&mut self, data: Vec<u8>) {
+        let length = match checked_length(data.len()) {
+            Ok(length) => length,
+            Err(e) => panic!("{}", e),
+        };
+        let unchanged = self
+            .data
+            .iter()
+            .zip(data.iter())
+            .take_while(|(old, new)| old == new)
+            .count();
+        self.data = Arc::new(data);
+        self.length = length;
+        self.update_crc_from(unchanged);
+    }
+
+    /// Change the type of this chunk, data is kept as it is and CRC is computed again since it
+    /// covers the type
+    pub fn set_type(&mut self, chunk_type: ChunkType) {
+        self.chunk_type = chunk_type;
+        self.crc_checkpoints.clear();
+        self.update_crc_from(0);
+    }
+
+    /// Keep the first `offset` bytes of data and replace everything after them with `suffix`.
+    /// This is the cheap way to update a large chunk, bytes before `offset` are never rehashed
+    /// beyond the closest CRC checkpoint.
+    pub fn replace_suffix(&mut self, offset: usize, suffix: &[u8]) -> Result<()> {
+        if offset > self.data.len() {
+            return Err(anyhow!(
+                "Offset {} is beyond chunk data of {} bytes",
+                offset,
+                self.data.len()
+            ));
+        }
+        let length = checked_length(offset + suffix.len())?;
+        // Copies the data first when it is shared with a clone
+        let data = Arc::make_mut(&mut self.data);
+        data.truncate(offset);
+        data.extend_from_slice(suffix);
+        self.length = length;
+        self.update_crc_from(offset);
+        Ok(())
+    }
+
+    /// Recompute CRC assuming the first `unchanged` bytes of data are the same as when the
+    /// checkpoints were taken.
+    fn update_crc_from(&mut self, unchanged: usize) {
+        let resume_at = (unchanged / CRC_CHECKPOINT_INTERVAL).min(self.crc_checkpoints.len());
+        self.crc_checkpoints.truncate(resume_at);
+        let mut crc_hasher = match self.crc_checkpoints.last() {
+            Some(partial_crc) => Hasher::new_with_initial(*partial_crc),
+            None => {
+                let mut crc_hasher = Hasher::new();
+                crc_hasher.update(self.chunk_type.inner.as_slice());
+                crc_hasher
+            }
+        };
+        let mut pieces = self.data[resume_at * CRC_CHECKPOINT_INTERVAL..]
+            .chunks(CRC_CHECKPOINT_INTERVAL)
+            .peekable();
+        while let Some(piece) = pieces.next() {
+            crc_hasher.update(piece);
+            if piece.len() == CRC_CHECKPOINT_INTERVAL && pieces.peek().is_some() {
+                self.crc_checkpoints.push(crc_hasher.clone().finalize());
+            }
         }
+        self.crc = crc_hasher.finalize();
     }
 
     pub fn length(&self) -> u32 {
@@ -66,24 +177,34 @@ impl Chunk {
         self.crc
     }
 
+    /// Tells whether both chunks hold the same data buffer, meaning one is an unmodified clone of
+    /// the other
+    pub fn shares_data(&self, other: &Chunk) -> bool {
+        Arc::ptr_eq(&self.data, &other.data)
+    }
+
     /// Convert data into UTF-8 string, if given data can not be represented as valid UTF-8 string,
     /// error will be returned instead
     pub fn data_as_string(&self) -> Result<String> {
         std::str::from_utf8(self.data.as_slice())
-            .map_err(|e| anyhow::Error::from(e))
-            .map(|slice| String::from(slice))
+            .map_err(anyhow::Error::from)
+            .map(String::from)
     }
 
     /// Returns the entire chunk as a sequence of bytes in the order required by the PNG spec.
     pub fn as_bytes(&self) -> Vec<u8> {
-        self.length
-            .to_be_bytes()
-            .iter()
-            .chain(self.chunk_type.inner.iter())
-            .chain(self.data.iter())
-            .chain(self.crc.to_be_bytes().iter())
-            .copied()
-            .collect()
+        let mut bytes = Vec::with_capacity(self.data.len() + 12);
+        // Writing to a Vec can't fail
+        self.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Write the chunk as [as_bytes](Chunk::as_bytes) would return it, without copying its data
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&self.length.to_be_bytes())?;
+        writer.write_all(&self.chunk_type.inner)?;
+        writer.write_all(&self.data)?;
+        writer.write_all(&self.crc.to_be_bytes())
     }
 }
 
@@ -96,6 +217,7 @@ impl TryFrom<&[u8]> for Chunk {
 
         reader.read_exact(&mut four_bytes)?;
         let length = u32::from_be_bytes(four_bytes);
+        checked_length(length as usize)?;
 
         reader.read_exact(&mut four_bytes)?;
         let chunk_type = ChunkType::try_from(four_bytes)?;
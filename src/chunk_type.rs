@@ -3,11 +3,104 @@
 //! PNG files are essentially just a list of "chunks", each containing their own data.
 //! Each chunk has a type that can be represented as a 4 character string.
 //! See more on [PNG spec](http://www.libpng.org/pub/png/spec/1.2/PNG-Contents.html)
+//!
+//! Parsing fails with a [ChunkTypeError] telling what is wrong and at which bytes, so a stray
+//! space or a look-alike letter from another script is easy to spot.
 
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
-#[derive(Debug)]
+/// Chunk types defined by the PNG spec or registered as public extensions
+pub const REGISTERED_TYPES: [&[u8; 4]; 34] = [
+    b"IHDR", b"PLTE", b"IDAT", b"IEND", b"acTL", b"bKGD", b"cHRM", b"cICP", b"cLLI", b"dSIG",
+    b"eXIf", b"fcTL", b"fdAT", b"fRAc", b"gAMA", b"gIFg", b"gIFt", b"gIFx", b"hIST", b"iCCP",
+    b"iTXt", b"mDCV", b"oFFs", b"pCAL", b"pHYs", b"sBIT", b"sCAL", b"sPLT", b"sRGB", b"sTER",
+    b"tEXt", b"tIME", b"tRNS", b"zTXt",
+];
+
+/// Error returned when bytes or a string are not a chunk type, can be downcast from
+/// `anyhow::Error`. Positions are zero based byte offsets in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChunkTypeError {
+    Empty,
+    Whitespace(Vec<usize>),
+    /// Bytes of characters outside of ASCII, like an accented or Cyrillic letter
+    NonAscii(Vec<usize>),
+    /// ASCII characters other than letters, like digits or punctuation
+    NotAlphabetic(Vec<usize>),
+    /// Input made of letters only, but not four of them
+    Length(usize),
+    /// Third letter in lowercase, only rejected for raw bytes
+    ReservedBit,
+}
+
+impl Display for ChunkTypeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChunkTypeError::Empty => write!(f, "Chunk type is empty, expected 4 ASCII letters"),
+            ChunkTypeError::Whitespace(positions) => {
+                write!(f, "Chunk type has whitespace at {}", at(positions))
+            }
+            ChunkTypeError::NonAscii(positions) => {
+                write!(f, "Chunk type has non ASCII bytes at {}", at(positions))
+            }
+            ChunkTypeError::NotAlphabetic(positions) => write!(
+                f,
+                "Chunk type has characters other than ASCII letters at {}",
+                at(positions)
+            ),
+            ChunkTypeError::Length(length) => {
+                write!(f, "Chunk type must be 4 bytes, not {}", length)
+            }
+            ChunkTypeError::ReservedBit => {
+                write!(
+                    f,
+                    "Chunk type has its reserved bit set, third letter must be uppercase"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChunkTypeError {}
+
+fn at(positions: &[usize]) -> String {
+    let list: Vec<String> = positions.iter().map(|p| p.to_string()).collect();
+    match positions.len() {
+        1 => format!("byte {}", list[0]),
+        _ => format!("bytes {}", list.join(", ")),
+    }
+}
+
+/// Positions of the bytes matching `predicate`
+fn positions(bytes: &[u8], predicate: impl Fn(u8) -> bool) -> Vec<usize> {
+    (0..bytes.len()).filter(|&i| predicate(bytes[i])).collect()
+}
+
+/// Check that `bytes` are four ASCII letters, the first problem found is reported
+fn check(bytes: &[u8]) -> Result<(), ChunkTypeError> {
+    if bytes.is_empty() {
+        return Err(ChunkTypeError::Empty);
+    }
+    let whitespace = positions(bytes, |b| b.is_ascii_whitespace());
+    if !whitespace.is_empty() {
+        return Err(ChunkTypeError::Whitespace(whitespace));
+    }
+    let non_ascii = positions(bytes, |b| !b.is_ascii());
+    if !non_ascii.is_empty() {
+        return Err(ChunkTypeError::NonAscii(non_ascii));
+    }
+    let not_alphabetic = positions(bytes, |b| !b.is_ascii_alphabetic());
+    if !not_alphabetic.is_empty() {
+        return Err(ChunkTypeError::NotAlphabetic(not_alphabetic));
+    }
+    if bytes.len() != 4 {
+        return Err(ChunkTypeError::Length(bytes.len()));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
 pub struct ChunkType {
     /// Stores four bytes in the order of `critical byte`, `public/private byte`, `reserved byte` and
     /// `state-of-copy byte`.
@@ -17,7 +110,7 @@ pub struct ChunkType {
 impl ChunkType {
     /// Get bytes from this type, bytes are copied.
     pub fn bytes(&self) -> [u8; 4] {
-        return self.inner;
+        self.inner
     }
 
     /// Determine whether this chunk type is valid according to PNG spec.
@@ -30,7 +123,7 @@ impl ChunkType {
                 return false;
             }
         }
-        return self.is_reserved_bit_valid();
+        self.is_reserved_bit_valid()
     }
 
     pub fn is_critical(&self) -> bool {
@@ -48,18 +141,28 @@ impl ChunkType {
     pub fn is_safe_to_copy(&self) -> bool {
         self.inner[3].is_ascii_lowercase()
     }
+
+    /// Messages are stored in ancillary private chunks, so any chunk of such type is treated as one.
+    pub fn is_message(&self) -> bool {
+        !self.is_critical() && !self.is_public()
+    }
+
+    /// Whether this type is one of the [REGISTERED_TYPES], matched case sensitively
+    pub fn is_registered(&self) -> bool {
+        REGISTERED_TYPES.contains(&&self.inner)
+    }
 }
 
 impl TryFrom<[u8; 4]> for ChunkType {
     type Error = anyhow::Error;
 
     fn try_from(value: [u8; 4]) -> Result<Self, Self::Error> {
+        check(&value)?;
         let chunk_type = ChunkType { inner: value };
-        if chunk_type.is_valid() {
-            Ok(chunk_type)
-        } else {
-            Err(Self::Error::msg("Not a valid chunk type value"))
+        if !chunk_type.is_reserved_bit_valid() {
+            return Err(ChunkTypeError::ReservedBit.into());
         }
+        Ok(chunk_type)
     }
 }
 
@@ -68,31 +171,25 @@ impl FromStr for ChunkType {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let source_bytes = s.as_bytes();
-        if source_bytes.len() != 4 {
-            return Err(Self::Err::msg("Chunk type str must be 4 bytes"));
-        }
+        check(source_bytes)?;
         let mut chunk_bytes: [u8; 4] = [0; 4];
-        for (index, byte) in source_bytes[0..4].iter().enumerate() {
-            if !byte.is_ascii_alphabetic() {
-                let err_msg = format!(
-                    "Source string contains non ASCII alphabetic letter {}",
-                    byte
-                );
-                return Err(Self::Err::msg(err_msg));
-            }
-            chunk_bytes[index] = *byte;
-        }
+        chunk_bytes.copy_from_slice(source_bytes);
         // This chunk type could be invalid, but we still construct an instance for it
         Ok(ChunkType { inner: chunk_bytes })
     }
 }
 
 impl Display for ChunkType {
+    /// Printable ASCII as is, other bytes as `\xNN`, so types read from damaged files still show
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        // UTF-8 has the same code page as ASCII in first 128 characters
-        let chunk_type_string =
-            String::from_utf8(self.inner.to_vec()).unwrap_or(String::from("Unknown"));
-        write!(f, "{}", chunk_type_string)
+        for byte in self.inner {
+            if byte.is_ascii_graphic() {
+                write!(f, "{}", byte as char)?;
+            } else {
+                write!(f, "\\x{:02x}", byte)?;
+            }
+        }
+        Ok(())
     }
 }
 
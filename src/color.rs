@@ -0,0 +1,171 @@
+#![allow(dead_code)]
+//! # Color
+//! Consistency checks on the chunks describing color space: `sRGB`, `iCCP`, `gAMA` and `cHRM`.
+//!
+//! Viewers resolve conflicting color chunks differently, so the same file can render with
+//! shifted colors depending on where it is opened. Precedence follows the spec: `iCCP` wins over
+//! `sRGB`, which wins over `gAMA` and `cHRM`. A chunk that loses against another is redundant
+//! and can be removed without changing how a conforming decoder displays the image.
+use crate::png::PNG;
+use crate::verify::Issue;
+
+/// gAMA value matching sRGB, 1/2.2 scaled by 100000
+pub const SRGB_GAMMA: u32 = 45455;
+
+/// cHRM values matching sRGB: white point, red, green and blue x/y, scaled by 100000
+pub const SRGB_CHROMATICITIES: [u32; 8] = [31270, 32900, 64000, 33000, 30000, 60000, 15000, 6000];
+
+/// Difference tolerated between a gAMA or cHRM value and its sRGB counterpart, rounding in
+/// encoders makes exact matches rare
+const TOLERANCE: u32 = 1000;
+
+const COLOR_TYPES: [&[u8; 4]; 4] = [b"sRGB", b"iCCP", b"gAMA", b"cHRM"];
+
+/// A problem with the color chunks, with the chunk that can be removed to solve it if any
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorConflict {
+    pub issue: Issue,
+    pub redundant: Option<usize>,
+}
+
+impl ColorConflict {
+    fn new(index: usize, message: String, redundant: bool) -> Self {
+        Self {
+            issue: Issue::error(Some(index), message),
+            redundant: redundant.then_some(index),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"issue\":{},\"redundant\":{}}}",
+            self.issue.to_json(),
+            self.redundant
+                .map_or("null".to_string(), |index| index.to_string())
+        )
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Find conflicting, duplicated and malformed color chunks
+pub fn check(png: &PNG) -> Vec<ColorConflict> {
+    let mut conflicts = Vec::new();
+    let mut first: [Option<usize>; 4] = [None; 4];
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        let chunk_type = chunk.chunk_type().bytes();
+        let kind = match COLOR_TYPES.iter().position(|t| **t == chunk_type) {
+            Some(kind) => kind,
+            None => continue,
+        };
+        match first[kind] {
+            Some(earlier) => conflicts.push(ColorConflict::new(
+                index,
+                format!(
+                    "{} already appears at chunk {}, only one is allowed",
+                    chunk.chunk_type(),
+                    earlier
+                ),
+                true,
+            )),
+            None => first[kind] = Some(index),
+        }
+    }
+    let [srgb, iccp, gama, chrm] = first;
+    let chunks = png.chunks();
+
+    if let Some(srgb) = srgb {
+        match chunks[srgb].data() {
+            [intent] if *intent <= 3 => {}
+            _ => conflicts.push(ColorConflict::new(
+                srgb,
+                "sRGB must hold a single rendering intent from 0 to 3".to_string(),
+                false,
+            )),
+        }
+        if let Some(iccp) = iccp {
+            conflicts.push(ColorConflict::new(
+                srgb,
+                format!(
+                    "sRGB and iCCP (chunk {}) must not both appear, iCCP takes precedence",
+                    iccp
+                ),
+                true,
+            ));
+        }
+    }
+
+    if let Some(gama) = gama {
+        match read_u32(chunks[gama].data(), 0) {
+            Some(0) | None => conflicts.push(ColorConflict::new(
+                gama,
+                "gAMA must hold a non-zero 4 byte gamma".to_string(),
+                false,
+            )),
+            Some(gamma) => {
+                if let (Some(srgb), None) = (srgb, iccp) {
+                    if gamma.abs_diff(SRGB_GAMMA) > TOLERANCE {
+                        conflicts.push(ColorConflict::new(
+                            gama,
+                            format!(
+                                "gAMA of {} does not match sRGB (chunk {}), which implies {}",
+                                gamma, srgb, SRGB_GAMMA
+                            ),
+                            true,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(chrm) = chrm {
+        let values = (0..8)
+            .map(|field| read_u32(chunks[chrm].data(), field * 4))
+            .collect::<Option<Vec<_>>>();
+        match values {
+            Some(values) if chunks[chrm].data().len() == 32 => {
+                if let (Some(srgb), None) = (srgb, iccp) {
+                    let matches = values
+                        .iter()
+                        .zip(SRGB_CHROMATICITIES.iter())
+                        .all(|(value, expected)| value.abs_diff(*expected) <= TOLERANCE);
+                    if !matches {
+                        conflicts.push(ColorConflict::new(
+                            chrm,
+                            format!("cHRM chromaticities do not match sRGB (chunk {})", srgb),
+                            true,
+                        ));
+                    }
+                }
+            }
+            _ => conflicts.push(ColorConflict::new(
+                chrm,
+                "cHRM must hold eight 4 byte values".to_string(),
+                false,
+            )),
+        }
+    }
+    conflicts.sort_by_key(|conflict| conflict.issue.chunk_index);
+    conflicts
+}
+
+/// Remove every chunk marked redundant by given conflicts.
+/// Returns the indices of removed chunks, as they were before removal.
+pub fn remove_redundant(png: &mut PNG, conflicts: &[ColorConflict]) -> Vec<usize> {
+    let mut redundant = conflicts
+        .iter()
+        .filter_map(|conflict| conflict.redundant)
+        .collect::<Vec<_>>();
+    redundant.sort_unstable();
+    redundant.dedup();
+    // Remove from the back so earlier indices stay valid
+    for index in redundant.iter().rev() {
+        // Indices come from the chunks of this png
+        png.remove_chunk_at(*index).unwrap();
+    }
+    redundant
+}
@@ -0,0 +1,99 @@
+#![allow(dead_code)]
+//! # Confine
+//! Keep a run inside the directories it was meant for.
+//!
+//! A [PathPolicy] refuses symbolic links when asked to, and paths resolving outside a set of
+//! allowed roots. Paths are resolved the way the operating system would open them, symbolic
+//! links and `..` included, so a crafted name or a link planted in the tree can't lead a batch
+//! run to read or write elsewhere. A path that does not exist yet, like an output file, is
+//! resolved through its parent directory.
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The default policy allows every path
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathPolicy {
+    /// Refuse paths that are symbolic links themselves
+    pub no_follow_symlinks: bool,
+    /// Canonical directories every path must resolve under, any path when empty
+    pub roots: Vec<PathBuf>,
+}
+
+impl PathPolicy {
+    /// Policy allowing paths under `roots` only, which must exist
+    pub fn new(no_follow_symlinks: bool, roots: &[PathBuf]) -> Result<Self> {
+        let roots = roots
+            .iter()
+            .map(|root| {
+                root.canonicalize()
+                    .map_err(|e| anyhow!("Allowed root {}: {}", root.display(), e))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(PathPolicy {
+            no_follow_symlinks,
+            roots,
+        })
+    }
+
+    /// Tells whether this policy refuses anything at all
+    pub fn is_unrestricted(&self) -> bool {
+        !self.no_follow_symlinks && self.roots.is_empty()
+    }
+
+    /// Error telling why `path` is refused, if it is
+    pub fn check(&self, path: &Path) -> Result<()> {
+        if self.is_unrestricted() {
+            return Ok(());
+        }
+        if self.no_follow_symlinks && is_symlink(path) {
+            return Err(anyhow!(
+                "{} is a symbolic link, refused with --no-follow-symlinks",
+                path.display()
+            ));
+        }
+        if self.roots.is_empty() {
+            return Ok(());
+        }
+        let resolved = resolve(path)?;
+        if self.roots.iter().any(|root| resolved.starts_with(root)) {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "{} resolves to {}, outside of the allowed roots",
+                path.display(),
+                resolved.display()
+            ))
+        }
+    }
+
+    /// Like [check], but as a filter for files found while walking a tree
+    pub fn allows(&self, path: &Path) -> bool {
+        self.check(path).is_ok()
+    }
+}
+
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path)
+        .map(|metadata| metadata.file_type().is_symlink())
+        .unwrap_or(false)
+}
+
+/// Absolute path with every symbolic link resolved, through the parent directory for a path
+/// that does not exist yet
+pub fn resolve(path: &Path) -> Result<PathBuf> {
+    if let Ok(resolved) = path.canonicalize() {
+        return Ok(resolved);
+    }
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Can't resolve {}", path.display()))?;
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let parent = parent
+        .canonicalize()
+        .map_err(|e| anyhow!("Can't resolve {}: {}", path.display(), e))?;
+    Ok(parent.join(name))
+}
@@ -0,0 +1,295 @@
+#![allow(dead_code)]
+//! # Corpus
+//! Generators for unusual png files, to test programs reading what this crate writes.
+//!
+//! Pixel data follows a fixed pattern, so the same arguments always give the same bytes. Every
+//! valid combination of color type and bit depth can be generated, interlaced or not, which
+//! suits property tests iterating over all of them. [samples] gathers the cases worth keeping
+//! on disk: sub-byte and 16 bit depths, palettes, Adam7, APNG, zero length chunks, a very large
+//! chunk, a length field at the spec maximum and a corrupt CRC.
+use crate::chunk::{Chunk, MAX_LENGTH};
+use crate::chunk_type::ChunkType;
+use crate::deflate::zlib_compress;
+use crate::metadata::{
+    ImageHeader, COLOR_TYPE_GRAYSCALE, COLOR_TYPE_GRAYSCALE_ALPHA, COLOR_TYPE_INDEXED,
+    COLOR_TYPE_TRUECOLOR, COLOR_TYPE_TRUECOLOR_ALPHA,
+};
+use crate::png::PNG;
+use anyhow::{anyhow, Result};
+use std::str::FromStr;
+
+/// Size of the large chunk in [samples] unless told otherwise
+pub const DEFAULT_LARGE_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Adam7 passes: first column, first row, column step and row step
+const ADAM7: [(u32, u32, u32, u32); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// One generated file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sample {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+/// Bit depths allowed for each color type
+pub fn bit_depths(color_type: u8) -> &'static [u8] {
+    match color_type {
+        COLOR_TYPE_GRAYSCALE => &[1, 2, 4, 8, 16],
+        COLOR_TYPE_INDEXED => &[1, 2, 4, 8],
+        COLOR_TYPE_TRUECOLOR | COLOR_TYPE_GRAYSCALE_ALPHA | COLOR_TYPE_TRUECOLOR_ALPHA => &[8, 16],
+        _ => &[],
+    }
+}
+
+/// Header of a `width` by `height` image, compression and filter methods left at 0
+pub fn header(
+    width: u32,
+    height: u32,
+    color_type: u8,
+    bit_depth: u8,
+    interlaced: bool,
+) -> ImageHeader {
+    ImageHeader {
+        width,
+        height,
+        bit_depth,
+        color_type,
+        compression_method: 0,
+        filter_method: 0,
+        interlace_method: interlaced as u8,
+    }
+}
+
+/// Size in pixels of each Adam7 pass, some of them empty for small images
+pub fn adam7_passes(width: u32, height: u32) -> Vec<(u32, u32)> {
+    let count = |size: u32, start: u32, step: u32| {
+        if size > start {
+            (size - start).div_ceil(step)
+        } else {
+            0
+        }
+    };
+    ADAM7
+        .iter()
+        .map(|(x, y, x_step, y_step)| (count(width, *x, *x_step), count(height, *y, *y_step)))
+        .collect()
+}
+
+/// Uncompressed image data: every scanline of every pass, each starting with filter type 0
+pub fn scanlines(header: &ImageHeader) -> Result<Vec<u8>> {
+    let channels = header
+        .channels()
+        .filter(|_| bit_depths(header.color_type).contains(&header.bit_depth))
+        .ok_or_else(|| {
+            anyhow!(
+                "Bit depth {} is not allowed for color type {}",
+                header.bit_depth,
+                header.color_type
+            )
+        })?;
+    let bits_per_pixel = channels as u64 * header.bit_depth as u64;
+    let passes = match header.interlace_method {
+        0 => vec![(header.width, header.height)],
+        1 => adam7_passes(header.width, header.height),
+        method => return Err(anyhow!("Unknown interlace method {}", method)),
+    };
+    let mut data = Vec::new();
+    for (pass, (width, height)) in passes.into_iter().enumerate() {
+        if width == 0 {
+            continue;
+        }
+        let row_len = (width as u64 * bits_per_pixel).div_ceil(8) as usize;
+        for row in 0..height as usize {
+            data.push(0);
+            data.extend((0..row_len).map(|column| (column * 31 + row * 17 + pass * 7) as u8));
+        }
+    }
+    Ok(data)
+}
+
+/// Palette with every index a pixel of given bit depth can take
+fn palette(bit_depth: u8) -> Chunk {
+    let entries = 1usize << bit_depth.min(8);
+    let data = (0..entries)
+        .flat_map(|index| [index as u8, (index * 3) as u8, 255 - index as u8])
+        .collect();
+    chunk("PLTE", data)
+}
+
+fn chunk(chunk_type: &str, data: Vec<u8>) -> Chunk {
+    Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+}
+
+/// A complete image for given header, with a palette when it is indexed
+pub fn image(header: ImageHeader) -> Result<PNG> {
+    let mut chunks = vec![header.to_chunk()];
+    if header.color_type == COLOR_TYPE_INDEXED {
+        chunks.push(palette(header.bit_depth));
+    }
+    chunks.push(chunk("IDAT", zlib_compress(&scanlines(&header)?)));
+    chunks.push(chunk("IEND", Vec::new()));
+    Ok(PNG::from_chunks(chunks))
+}
+
+/// An APNG of `frames` frames the size of the image, the first one being the default image
+pub fn animated(header: ImageHeader, frames: u32) -> Result<PNG> {
+    if frames == 0 {
+        return Err(anyhow!("An animation needs at least one frame"));
+    }
+    let mut png = image(header)?;
+    let pixels = zlib_compress(&scanlines(&header)?);
+    let mut control = Vec::with_capacity(8);
+    control.extend_from_slice(&frames.to_be_bytes());
+    // Loop forever
+    control.extend_from_slice(&0u32.to_be_bytes());
+    png.insert_chunk(1, chunk("acTL", control))?;
+    let mut sequence = 0u32;
+    let frame_control = |sequence: u32| {
+        let mut data = Vec::with_capacity(26);
+        data.extend_from_slice(&sequence.to_be_bytes());
+        data.extend_from_slice(&header.width.to_be_bytes());
+        data.extend_from_slice(&header.height.to_be_bytes());
+        // No offset, 1/10 second delay, no disposal, no blending
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(&1u16.to_be_bytes());
+        data.extend_from_slice(&10u16.to_be_bytes());
+        data.extend_from_slice(&[0, 0]);
+        chunk("fcTL", data)
+    };
+    let idat = png.position_of("IDAT", 0)?.unwrap();
+    png.insert_chunk(idat, frame_control(sequence))?;
+    for _ in 1..frames {
+        sequence += 1;
+        png.append_chunk(frame_control(sequence));
+        sequence += 1;
+        let mut data = sequence.to_be_bytes().to_vec();
+        data.extend_from_slice(&pixels);
+        png.append_chunk(chunk("fdAT", data));
+    }
+    Ok(png)
+}
+
+/// Image data spread over several IDAT chunks, empty ones among them, plus an empty private
+/// chunk
+pub fn with_zero_length_chunks(header: ImageHeader) -> Result<PNG> {
+    let pixels = zlib_compress(&scanlines(&header)?);
+    let (first, second) = pixels.split_at(pixels.len() / 2);
+    Ok(PNG::from_chunks(vec![
+        header.to_chunk(),
+        chunk("zeRo", Vec::new()),
+        chunk("IDAT", Vec::new()),
+        chunk("IDAT", first.to_vec()),
+        chunk("IDAT", Vec::new()),
+        chunk("IDAT", second.to_vec()),
+        chunk("IEND", Vec::new()),
+    ]))
+}
+
+/// Bytes of given png with the CRC of the chunk at `index` flipped
+pub fn with_corrupt_crc(png: &PNG, index: usize) -> Result<Vec<u8>> {
+    let chunk = png
+        .chunks()
+        .get(index)
+        .ok_or_else(|| anyhow!("There is no chunk {}", index))?;
+    let crc_offset = png.chunk_offsets()[index] + 8 + chunk.data().len();
+    let mut bytes = png.as_bytes();
+    for byte in bytes[crc_offset..crc_offset + 4].iter_mut() {
+        *byte = !*byte;
+    }
+    Ok(bytes)
+}
+
+/// An image followed by a chunk whose length field says [MAX_LENGTH] but whose data stops
+/// right away. Readers must fail without trying to allocate 2 GiB.
+pub fn truncated_max_length(header: ImageHeader) -> Result<Vec<u8>> {
+    let png = image(header)?;
+    let iend = png.chunk_offsets()[png.chunks().len() - 1];
+    let mut bytes = png.as_bytes();
+    bytes.truncate(iend);
+    bytes.extend_from_slice(&(MAX_LENGTH as u32).to_be_bytes());
+    bytes.extend_from_slice(b"laRg");
+    Ok(bytes)
+}
+
+/// Every case worth testing against, with a `large_chunk_size` byte private chunk in one of them
+pub fn samples(large_chunk_size: usize) -> Result<Vec<Sample>> {
+    let small = header(16, 16, COLOR_TYPE_TRUECOLOR, 8, false);
+    let sample = |name, description, png: PNG| Sample {
+        name,
+        description,
+        bytes: png.as_bytes(),
+    };
+    let mut large = image(small)?;
+    large.append_chunk(Chunk::try_new(
+        ChunkType::from_str("laRg")?,
+        (0..large_chunk_size).map(|index| index as u8).collect(),
+    )?);
+    Ok(vec![
+        sample("basic.png", "16x16 truecolor, 8 bit", image(small)?),
+        sample(
+            "interlaced.png",
+            "13x7 truecolor, Adam7 interlaced",
+            image(header(13, 7, COLOR_TYPE_TRUECOLOR, 8, true))?,
+        ),
+        sample(
+            "interlaced-tiny.png",
+            "3x2 grayscale, Adam7 interlaced with empty passes",
+            image(header(3, 2, COLOR_TYPE_GRAYSCALE, 8, true))?,
+        ),
+        sample(
+            "16-bit.png",
+            "16x16 truecolor with alpha, 16 bit",
+            image(header(16, 16, COLOR_TYPE_TRUECOLOR_ALPHA, 16, false))?,
+        ),
+        sample(
+            "gray-1-bit.png",
+            "13x5 grayscale, 1 bit, rows ending mid byte",
+            image(header(13, 5, COLOR_TYPE_GRAYSCALE, 1, false))?,
+        ),
+        sample(
+            "palette.png",
+            "16x16 indexed, 8 bit, 256 entry palette",
+            image(header(16, 16, COLOR_TYPE_INDEXED, 8, false))?,
+        ),
+        sample(
+            "palette-2-bit.png",
+            "7x7 indexed, 2 bit, interlaced",
+            image(header(7, 7, COLOR_TYPE_INDEXED, 2, true))?,
+        ),
+        sample(
+            "gray-alpha.png",
+            "8x8 grayscale with alpha, 16 bit",
+            image(header(8, 8, COLOR_TYPE_GRAYSCALE_ALPHA, 16, false))?,
+        ),
+        sample("apng.png", "16x16 APNG, 3 frames", animated(small, 3)?),
+        sample(
+            "zero-length-chunks.png",
+            "Empty IDAT chunks between image data and an empty private chunk",
+            with_zero_length_chunks(small)?,
+        ),
+        sample(
+            "large-chunk.png",
+            "Private chunk of the requested size before IEND",
+            large,
+        ),
+        Sample {
+            name: "max-length.png",
+            description: "Truncated chunk whose length field is the spec maximum",
+            bytes: truncated_max_length(small)?,
+        },
+        Sample {
+            name: "corrupt-crc.png",
+            description: "IDAT with a wrong CRC",
+            bytes: with_corrupt_crc(&image(small)?, 1)?,
+        },
+    ])
+}
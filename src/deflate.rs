@@ -0,0 +1,525 @@
+#![allow(dead_code)]
+//! # Deflate
+//! A small, dependency free implementation of DEFLATE (RFC 1951) and its zlib wrapper (RFC 1950).
+//!
+//! PNG uses zlib streams for IDAT, iCCP, zTXt and compressed iTXt chunks. The decoder handles every
+//! block type, the encoder does greedy LZ77 matching with the fixed Huffman codes, which is far from
+//! what zlib achieves on images but works well enough for text. Data that does not compress, like
+//! an already compressed png, is stored as it is instead of growing.
+use anyhow::{anyhow, Result};
+
+const MAX_BITS: usize = 15;
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// How many earlier positions the encoder tries before settling for the best match found
+const MAX_CHAIN: usize = 64;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// Order in which code length code lengths are stored in a dynamic block header
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Decompress a zlib stream, checking its header and Adler-32 trailer.
+pub fn zlib_decompress(data: &[u8]) -> Result<Vec<u8>> {
+    zlib_decompress_limited(data, usize::MAX)
+}
+
+/// Like [zlib_decompress], failing as soon as the output grows beyond `max_output` bytes
+pub fn zlib_decompress_limited(data: &[u8], max_output: usize) -> Result<Vec<u8>> {
+    if data.len() < 6 {
+        return Err(anyhow!("Zlib stream is too short"));
+    }
+    let (cmf, flg) = (data[0], data[1]);
+    if cmf & 0x0F != 8 {
+        return Err(anyhow!(
+            "Unsupported zlib compression method {}",
+            cmf & 0x0F
+        ));
+    }
+    if (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 {
+        return Err(anyhow!("Corrupted zlib header"));
+    }
+    if flg & 0x20 != 0 {
+        return Err(anyhow!("Zlib preset dictionaries are not supported"));
+    }
+    let (output, consumed) = inflate_limited(&data[2..], max_output)?;
+    let trailer = data
+        .get(2 + consumed..2 + consumed + 4)
+        .ok_or_else(|| anyhow!("Zlib stream is missing its checksum"))?;
+    let expected = u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    if adler32(&output) != expected {
+        return Err(anyhow!("Zlib checksum mismatch"));
+    }
+    Ok(output)
+}
+
+/// Compress data into a zlib stream
+pub fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    // Deflate with a 32K window, default compression level
+    let mut output = vec![0x78, 0x9C];
+    output.extend(deflate(data));
+    output.extend_from_slice(&adler32(data).to_be_bytes());
+    output
+}
+
+/// Adler-32 checksum used by zlib
+pub fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the largest run that can't overflow before taking the modulo
+    for piece in data.chunks(5552) {
+        for byte in piece {
+            a += u32::from(*byte);
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
+/// Decompress a raw DEFLATE stream
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>> {
+    inflate_with_length(data).map(|(output, _)| output)
+}
+
+/// Decompress a raw DEFLATE stream, also returning how many input bytes it took up
+pub fn inflate_with_length(data: &[u8]) -> Result<(Vec<u8>, usize)> {
+    inflate_limited(data, usize::MAX)
+}
+
+/// Like [inflate_with_length], failing as soon as the output grows beyond `max_output` bytes
+/// instead of decompressing a bomb to the end.
+pub fn inflate_limited(data: &[u8], max_output: usize) -> Result<(Vec<u8>, usize)> {
+    let mut reader = BitReader::new(data);
+    let mut output = Vec::new();
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let length = reader.bits(16)? as u16;
+                let complement = reader.bits(16)? as u16;
+                if length != !complement {
+                    return Err(anyhow!("Corrupted stored block length"));
+                }
+                output.extend_from_slice(reader.bytes(length as usize)?);
+                check_output(&output, max_output)?;
+            }
+            1 => {
+                let (literals, distances) = fixed_tables();
+                inflate_block(&mut reader, &mut output, &literals, &distances, max_output)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_tables(&mut reader)?;
+                inflate_block(&mut reader, &mut output, &literals, &distances, max_output)?;
+            }
+            _ => return Err(anyhow!("Invalid deflate block type")),
+        }
+        if last {
+            break;
+        }
+    }
+    reader.align();
+    Ok((output, reader.position))
+}
+
+/// Reads bits least significant first, as DEFLATE packs them
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    bit_buffer: u32,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+            bit_buffer: 0,
+            bit_count: 0,
+        }
+    }
+
+    fn bits(&mut self, count: u32) -> Result<u32> {
+        while self.bit_count < count {
+            let byte = *self
+                .data
+                .get(self.position)
+                .ok_or_else(|| anyhow!("Unexpected end of deflate stream"))?;
+            self.position += 1;
+            self.bit_buffer |= u32::from(byte) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let value = self.bit_buffer & ((1u64 << count) - 1) as u32;
+        self.bit_buffer >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+
+    /// Drop bits left in the current byte
+    fn align(&mut self) {
+        self.bit_buffer = 0;
+        self.bit_count = 0;
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + count)
+            .ok_or_else(|| anyhow!("Unexpected end of deflate stream"))?;
+        self.position += count;
+        Ok(bytes)
+    }
+}
+
+/// Canonical Huffman decoding table, symbols sorted by code
+struct Huffman {
+    /// Number of codes of each length
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut left: i32 = 1;
+        for count in counts.iter().skip(1) {
+            left = (left << 1) - i32::from(*count);
+            if left < 0 {
+                return Err(anyhow!("Over-subscribed Huffman code"));
+            }
+        }
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for length in 1..=MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                symbols[offsets[*length as usize] as usize] = symbol as u16;
+                offsets[*length as usize] += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..=MAX_BITS {
+            code |= reader.bits(1)? as i32;
+            let count = i32::from(self.counts[length]);
+            if code - count < first {
+                return Ok(self.symbols[(index + (code - first)) as usize]);
+            }
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+        Err(anyhow!("Invalid Huffman code"))
+    }
+}
+
+fn fixed_literal_lengths() -> [u8; 288] {
+    let mut lengths = [0u8; 288];
+    for (symbol, length) in lengths.iter_mut().enumerate() {
+        *length = match symbol {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+    lengths
+}
+
+fn fixed_tables() -> (Huffman, Huffman) {
+    // Fixed codes are always well formed
+    let literals = Huffman::new(&fixed_literal_lengths()).unwrap();
+    let distances = Huffman::new(&[5u8; 30]).unwrap();
+    (literals, distances)
+}
+
+fn dynamic_tables(reader: &mut BitReader) -> Result<(Huffman, Huffman)> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return Err(anyhow!("Too many codes in dynamic block"));
+    }
+    let mut code_lengths = [0u8; 19];
+    for index in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_lengths[*index] = reader.bits(3)? as u8;
+    }
+    let code_length_table = Huffman::new(&code_lengths)?;
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_length_table.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .ok_or_else(|| anyhow!("Repeat without previous code length"))?;
+                (previous, 3 + reader.bits(2)? as usize)
+            }
+            17 => (0, 3 + reader.bits(3)? as usize),
+            18 => (0, 11 + reader.bits(7)? as usize),
+            _ => return Err(anyhow!("Invalid code length symbol")),
+        };
+        if lengths.len() + repeat > literal_count + distance_count {
+            return Err(anyhow!("Code lengths overflow dynamic block header"));
+        }
+        lengths.extend(std::iter::repeat_n(value, repeat));
+    }
+    if lengths[256] == 0 {
+        return Err(anyhow!("Dynamic block has no end of block code"));
+    }
+    let literals = Huffman::new(&lengths[..literal_count])?;
+    let distances = Huffman::new(&lengths[literal_count..])?;
+    Ok((literals, distances))
+}
+
+fn check_output(output: &[u8], max_output: usize) -> Result<()> {
+    if output.len() > max_output {
+        return Err(anyhow!(
+            "Decompressed data is larger than the limit of {} bytes",
+            max_output
+        ));
+    }
+    Ok(())
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+    max_output: usize,
+) -> Result<()> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 => {
+                output.push(symbol as u8);
+                check_output(output, max_output)?;
+            }
+            256 => return Ok(()),
+            257..=285 => {
+                let index = symbol - 257;
+                let length = LENGTH_BASE[index] as usize
+                    + reader.bits(u32::from(LENGTH_EXTRA[index]))? as usize;
+                let distance_symbol = distances.decode(reader)? as usize;
+                if distance_symbol >= 30 {
+                    return Err(anyhow!("Invalid distance symbol"));
+                }
+                let distance = DISTANCE_BASE[distance_symbol] as usize
+                    + reader.bits(u32::from(DISTANCE_EXTRA[distance_symbol]))? as usize;
+                if distance > output.len() {
+                    return Err(anyhow!("Distance reaches before start of output"));
+                }
+                let start = output.len() - distance;
+                for offset in 0..length {
+                    output.push(output[start + offset]);
+                }
+                check_output(output, max_output)?;
+            }
+            _ => return Err(anyhow!("Invalid literal/length symbol")),
+        }
+    }
+}
+
+/// Writes bits least significant first
+struct BitWriter {
+    output: Vec<u8>,
+    bit_buffer: u64,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            output: Vec::new(),
+            bit_buffer: 0,
+            bit_count: 0,
+        }
+    }
+
+    fn bits(&mut self, value: u32, count: u32) {
+        self.bit_buffer |= u64::from(value) << self.bit_count;
+        self.bit_count += count;
+        while self.bit_count >= 8 {
+            self.output.push(self.bit_buffer as u8);
+            self.bit_buffer >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    /// Huffman codes are defined most significant bit first
+    fn code(&mut self, code: u32, length: u32) {
+        let reversed = code.reverse_bits() >> (32 - length);
+        self.bits(reversed, length);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.output.push(self.bit_buffer as u8);
+        }
+        self.output
+    }
+}
+
+fn write_fixed_literal(writer: &mut BitWriter, symbol: u16) {
+    match symbol {
+        0..=143 => writer.code(0x30 + u32::from(symbol), 8),
+        144..=255 => writer.code(0x190 + u32::from(symbol) - 144, 9),
+        256..=279 => writer.code(u32::from(symbol) - 256, 7),
+        _ => writer.code(0xC0 + u32::from(symbol) - 280, 8),
+    }
+}
+
+fn write_match(writer: &mut BitWriter, length: usize, distance: usize) {
+    let length_index = LENGTH_BASE
+        .iter()
+        .rposition(|base| *base as usize <= length)
+        .unwrap_or(0);
+    write_fixed_literal(writer, 257 + length_index as u16);
+    writer.bits(
+        (length - LENGTH_BASE[length_index] as usize) as u32,
+        u32::from(LENGTH_EXTRA[length_index]),
+    );
+    let distance_index = DISTANCE_BASE
+        .iter()
+        .rposition(|base| *base as usize <= distance)
+        .unwrap_or(0);
+    writer.code(distance_index as u32, 5);
+    writer.bits(
+        (distance - DISTANCE_BASE[distance_index] as usize) as u32,
+        u32::from(DISTANCE_EXTRA[distance_index]),
+    );
+}
+
+fn hash3(data: &[u8]) -> usize {
+    ((usize::from(data[0]) << 10) ^ (usize::from(data[1]) << 5) ^ usize::from(data[2])) & 0x7FFF
+}
+
+/// Largest data of a stored block
+const MAX_STORED_BLOCK: usize = 65535;
+
+/// Compress data into a raw DEFLATE stream: a single fixed Huffman block, or stored blocks when
+/// that would be smaller
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let compressed = deflate_fixed(data);
+    // Each stored block takes a header byte, length and its complement
+    let stored_len = data.len() + 5 * data.len().div_ceil(MAX_STORED_BLOCK).max(1);
+    if stored_len < compressed.len() {
+        deflate_stored(data)
+    } else {
+        compressed
+    }
+}
+
+/// Raw DEFLATE stream of stored blocks, the data is not compressed
+fn deflate_stored(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len() + 5);
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        // BFINAL set, BTYPE 00 and an empty block
+        output.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let length = block.len() as u16;
+        // BFINAL on the last block, BTYPE 00, the rest of the byte is padding
+        output.push(u8::from(blocks.peek().is_none()));
+        output.extend_from_slice(&length.to_le_bytes());
+        output.extend_from_slice(&(!length).to_le_bytes());
+        output.extend_from_slice(block);
+    }
+    output
+}
+
+/// Raw DEFLATE stream made of a single fixed Huffman block
+fn deflate_fixed(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    // BFINAL set, BTYPE 01 (fixed Huffman codes)
+    writer.bits(1, 1);
+    writer.bits(1, 2);
+    let mut head = vec![usize::MAX; 1 << 15];
+    let mut previous = vec![usize::MAX; WINDOW_SIZE];
+    let mut position = 0;
+    let insert = |head: &mut Vec<usize>, previous: &mut Vec<usize>, at: usize| {
+        if at + MIN_MATCH <= data.len() {
+            let hash = hash3(&data[at..]);
+            previous[at % WINDOW_SIZE] = head[hash];
+            head[hash] = at;
+        }
+    };
+    while position < data.len() {
+        let mut best_length = 0;
+        let mut best_distance = 0;
+        if position + MIN_MATCH <= data.len() {
+            let mut candidate = head[hash3(&data[position..])];
+            let max_length = MAX_MATCH.min(data.len() - position);
+            let mut chain = 0;
+            while candidate != usize::MAX
+                && position - candidate <= WINDOW_SIZE
+                && chain < MAX_CHAIN
+            {
+                let length = data[candidate..]
+                    .iter()
+                    .zip(&data[position..position + max_length])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if length > best_length {
+                    best_length = length;
+                    best_distance = position - candidate;
+                    if length == max_length {
+                        break;
+                    }
+                }
+                let next = previous[candidate % WINDOW_SIZE];
+                // Entries of the ring buffer get overwritten, stop once links go forward
+                if next == usize::MAX || next >= candidate {
+                    break;
+                }
+                candidate = next;
+                chain += 1;
+            }
+        }
+        if best_length >= MIN_MATCH {
+            write_match(&mut writer, best_length, best_distance);
+            for at in position..position + best_length {
+                insert(&mut head, &mut previous, at);
+            }
+            position += best_length;
+        } else {
+            write_fixed_literal(&mut writer, u16::from(data[position]));
+            insert(&mut head, &mut previous, position);
+            position += 1;
+        }
+    }
+    write_fixed_literal(&mut writer, 256);
+    writer.finish()
+}
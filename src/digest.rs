@@ -0,0 +1,153 @@
+#![allow(dead_code)]
+//! # Digest
+//! Payload hashes that carry the name of the algorithm which made them.
+//!
+//! References and pointers record their hash as `<algorithm>:<hex>`, so checking a payload
+//! always uses the algorithm it was hashed with, whatever is picked for new ones. BLAKE3 is the
+//! default, it is the fastest of the three on large payloads. SHA-256 is what references and
+//! pointers were made with before the algorithm was recorded, their serialized form is
+//! unchanged.
+use crate::blake3::blake3;
+use crate::sha256::{sha256, to_hex};
+use crate::sha512::sha512;
+use anyhow::{anyhow, Result};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha512,
+    #[default]
+    Blake3,
+}
+
+impl HashAlgorithm {
+    pub const ALL: [HashAlgorithm; 3] = [
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Sha512,
+        HashAlgorithm::Blake3,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    /// Length of a digest in bytes
+    pub fn digest_len(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 | HashAlgorithm::Blake3 => 32,
+            HashAlgorithm::Sha512 => 64,
+        }
+    }
+
+    /// Length of a serialized digest, `<algorithm>:<hex>`
+    pub fn text_len(&self) -> usize {
+        self.name().len() + 1 + self.digest_len() * 2
+    }
+
+    pub fn hash(&self, data: &[u8]) -> Digest {
+        let bytes = match self {
+            HashAlgorithm::Sha256 => sha256(data).to_vec(),
+            HashAlgorithm::Sha512 => sha512(data).to_vec(),
+            HashAlgorithm::Blake3 => blake3(data).to_vec(),
+        };
+        Digest {
+            algorithm: *self,
+            bytes,
+        }
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        HashAlgorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name() == s.to_ascii_lowercase())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown hash algorithm {}, expected one of sha256, sha512, blake3",
+                    s
+                )
+            })
+    }
+}
+
+impl Display for HashAlgorithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A hash and the algorithm it was made with
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Digest {
+    pub algorithm: HashAlgorithm,
+    /// Always [digest_len](HashAlgorithm::digest_len) bytes long
+    bytes: Vec<u8>,
+}
+
+impl Digest {
+    /// Digest from raw bytes, which must have the length the algorithm gives
+    pub fn new(algorithm: HashAlgorithm, bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != algorithm.digest_len() {
+            return Err(anyhow!(
+                "A {} digest is {} bytes long, not {}",
+                algorithm,
+                algorithm.digest_len(),
+                bytes.len()
+            ));
+        }
+        Ok(Digest {
+            algorithm,
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn hex(&self) -> String {
+        to_hex(&self.bytes)
+    }
+
+    /// Tells whether `data` hashes to this digest, with the algorithm recorded in it
+    pub fn matches(&self, data: &[u8]) -> bool {
+        self.algorithm.hash(data) == *self
+    }
+}
+
+impl FromStr for Digest {
+    type Err = anyhow::Error;
+
+    /// Parse `<algorithm>:<hex>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (algorithm, hex) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Malformed digest {}, expected <algorithm>:<hex>", s))?;
+        let algorithm: HashAlgorithm = algorithm.parse()?;
+        // from_str_radix alone would take a `+` sign in front of a digit
+        if hex.len() != algorithm.digest_len() * 2 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(anyhow!("Malformed {} digest {}", algorithm, hex));
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&hex[index..index + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| anyhow!("Malformed {} digest {}", algorithm, hex))?;
+        Ok(Digest { algorithm, bytes })
+    }
+}
+
+impl Display for Digest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.hex())
+    }
+}
@@ -0,0 +1,231 @@
+#![allow(dead_code)]
+//! # Dump
+//! Deterministic text representation of a png chunk layout.
+//!
+//! Each chunk is written on its own line as `<type> length=<n> crc=<hex> sha256=<hex>`, in file
+//! order, so storing the dump next to an asset makes structural changes show up in plain diffs.
+//! [table] shows the same chunks for reading rather than diffing, with the property bits of
+//! their types spelled out, and the keyword of every textual chunk. Given a [Registry], chunks of
+//! registered types get their description and whatever breaks their spec on lines of their own.
+use crate::chunk_type::ChunkType;
+use crate::lazy::ChunkEntry;
+use crate::plan::json_string;
+use crate::png::PNG;
+use crate::registry::Registry;
+use crate::sha256::{sha256, to_hex};
+use crate::text;
+use std::fmt::Write;
+
+/// First line of every dump, bump the version when the line format changes
+pub const DUMP_HEADER: &str = "# msg-in-png dump v1";
+
+/// Render the chunk layout of given png
+pub fn dump(png: &PNG) -> String {
+    let mut text = String::from(DUMP_HEADER);
+    text.push('\n');
+    for chunk in png.chunks() {
+        text.push_str(&format!(
+            "{} length={} crc={:08x} sha256={}\n",
+            chunk.chunk_type(),
+            chunk.length(),
+            chunk.crc(),
+            to_hex(&sha256(chunk.data()))
+        ));
+    }
+    text
+}
+
+/// [dump] as a JSON array, one object per chunk
+pub fn dump_json(png: &PNG) -> String {
+    let rows: Vec<String> = png
+        .chunks()
+        .iter()
+        .map(|chunk| {
+            format!(
+                "{{\"type\":{},\"length\":{},\"crc\":\"{:08x}\",\"sha256\":\"{}\"}}",
+                json_string(&chunk.chunk_type().to_string()),
+                chunk.length(),
+                chunk.crc(),
+                to_hex(&sha256(chunk.data()))
+            )
+        })
+        .collect();
+    format!("[{}]", rows.join(","))
+}
+
+/// What [table] shows of a chunk beyond its header
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Annotation {
+    /// Keyword of a tEXt, zTXt or iTXt chunk
+    pub keyword: Option<String>,
+    /// Description of the chunk type in the registry
+    pub description: Option<String>,
+    /// How the chunk breaks its registered spec
+    pub problems: Vec<String>,
+}
+
+impl Annotation {
+    /// Annotation of a chunk from its data. The first
+    /// [KEYWORD_PREFIX_LEN](text::KEYWORD_PREFIX_LEN) bytes are enough.
+    pub fn from_data(chunk_type: &ChunkType, data: &[u8]) -> Self {
+        Annotation {
+            keyword: text::keyword_of(chunk_type, data),
+            ..Annotation::default()
+        }
+    }
+
+    /// Annotations of every chunk of a png already in memory, checked against `registry` if any
+    pub fn list(png: &PNG, registry: Option<&Registry>) -> Vec<Annotation> {
+        let mut annotations: Vec<Annotation> = png
+            .chunks()
+            .iter()
+            .map(|chunk| Annotation::from_data(chunk.chunk_type(), chunk.data()))
+            .collect();
+        if let Some(registry) = registry {
+            for (annotation, chunk) in annotations.iter_mut().zip(png.chunks()) {
+                annotation.description = registry.describe(chunk.chunk_type()).map(String::from);
+            }
+            for issue in registry.validate(png) {
+                if let Some(annotation) = issue.chunk_index.and_then(|i| annotations.get_mut(i)) {
+                    annotation.problems.push(issue.message);
+                }
+            }
+        }
+        annotations
+    }
+}
+
+/// One row per chunk: index, type, length, crc, critical or ancillary, public or private,
+/// whether editors that don't know the type may copy it and the keyword of textual chunks.
+/// Chunks of types in `registry` are followed by their description and registry errors.
+pub fn table(png: &PNG, registry: Option<&Registry>) -> String {
+    entries_table(&ChunkEntry::list(png), &Annotation::list(png, registry))
+}
+
+/// [table] from chunk headers and their annotations, as read by [LazyPng](crate::lazy::LazyPng)
+pub fn entries_table(entries: &[ChunkEntry], annotations: &[Annotation]) -> String {
+    let mut text = String::new();
+    // Writing to a String can't fail
+    writeln!(
+        text,
+        "{:>5}  {:<4} {:>10}  {:<8}  {:<9}  {:<7}  {:<6}  KEYWORD",
+        "INDEX", "TYPE", "LENGTH", "CRC", "KIND", "SCOPE", "COPY"
+    )
+    .unwrap();
+    for (index, (entry, annotation)) in entries.iter().zip(annotations).enumerate() {
+        let chunk_type = &entry.chunk_type;
+        let copy = if chunk_type.is_safe_to_copy() {
+            "safe"
+        } else {
+            "unsafe"
+        };
+        let copy = match &annotation.keyword {
+            Some(keyword) => format!("{:<6}  {}", copy, keyword),
+            None => copy.to_string(),
+        };
+        writeln!(
+            text,
+            "{:>5}  {:<4} {:>10}  {:08x}  {:<9}  {:<7}  {}",
+            index,
+            chunk_type,
+            entry.length,
+            entry.crc,
+            if chunk_type.is_critical() {
+                "critical"
+            } else {
+                "ancillary"
+            },
+            if chunk_type.is_public() {
+                "public"
+            } else {
+                "private"
+            },
+            copy
+        )
+        .unwrap();
+        // Under the type column, below the row they belong to
+        if let Some(description) = &annotation.description {
+            writeln!(text, "{:7}{}", "", description).unwrap();
+        }
+        for problem in annotation.problems.iter() {
+            writeln!(text, "{:7}error: {}", "", problem).unwrap();
+        }
+    }
+    text
+}
+
+/// [table] as a JSON array, one object per chunk, with the offset of each chunk in the file
+pub fn table_json(png: &PNG, registry: Option<&Registry>) -> String {
+    entries_table_json(&ChunkEntry::list(png), &Annotation::list(png, registry))
+}
+
+/// [table_json] from chunk headers and their annotations
+pub fn entries_table_json(entries: &[ChunkEntry], annotations: &[Annotation]) -> String {
+    let rows: Vec<String> = entries
+        .iter()
+        .zip(annotations)
+        .enumerate()
+        .map(|(index, (entry, annotation))| {
+            let chunk_type = &entry.chunk_type;
+            format!(
+                "{{\"index\":{},\"offset\":{},\"type\":{},\"length\":{},\"crc\":\"{:08x}\",\"critical\":{},\"public\":{},\"safe_to_copy\":{},\"keyword\":{},\"description\":{},\"problems\":[{}]}}",
+                index,
+                entry.offset,
+                json_string(&chunk_type.to_string()),
+                entry.length,
+                entry.crc,
+                chunk_type.is_critical(),
+                chunk_type.is_public(),
+                chunk_type.is_safe_to_copy(),
+                annotation
+                    .keyword
+                    .as_deref()
+                    .map_or("null".to_string(), json_string),
+                annotation
+                    .description
+                    .as_deref()
+                    .map_or("null".to_string(), json_string),
+                annotation
+                    .problems
+                    .iter()
+                    .map(|problem| json_string(problem))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            )
+        })
+        .collect();
+    format!("[{}]", rows.join(","))
+}
+
+/// Compare the layout of given png against a stored dump.
+/// Returns a human readable line for every difference, an empty list means the layout matches.
+pub fn check(png: &PNG, expected: &str) -> Vec<String> {
+    let actual = dump(png);
+    let actual_lines: Vec<&str> = actual.lines().collect();
+    let expected_lines: Vec<&str> = expected.lines().collect();
+    let mut differences = Vec::new();
+    for line_number in 0..actual_lines.len().max(expected_lines.len()) {
+        match (
+            expected_lines.get(line_number),
+            actual_lines.get(line_number),
+        ) {
+            (Some(expected), Some(actual)) if expected == actual => {}
+            (Some(expected), Some(actual)) => differences.push(format!(
+                "line {}: expected `{}`, found `{}`",
+                line_number + 1,
+                expected,
+                actual
+            )),
+            (Some(expected), None) => differences.push(format!(
+                "line {}: expected `{}`, found nothing",
+                line_number + 1,
+                expected
+            )),
+            (None, Some(actual)) => {
+                differences.push(format!("line {}: unexpected `{}`", line_number + 1, actual))
+            }
+            (None, None) => {}
+        }
+    }
+    differences
+}
@@ -0,0 +1,185 @@
+#![allow(dead_code)]
+//! # Duplicates
+//! Find png files of a directory tree showing the same image, and optionally gather their
+//! message chunks into one canonical copy.
+//!
+//! Two files are duplicates when their pixel content is the same, see
+//! [PNG::content_id](crate::png::PNG::content_id). Metadata and messages are ignored, so a
+//! re-export carrying different chunks still counts as a copy.
+use crate::confine::PathPolicy;
+use crate::merge::{MergeOutcome, MergePolicy, Resolution};
+use crate::plan::json_string;
+use crate::png::PNG;
+use crate::sha256::to_hex;
+use crate::tree::png_files;
+use anyhow::{anyhow, Result};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Files sharing one content id. The first path is the canonical copy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    pub content_id: [u8; 32],
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    pub fn canonical(&self) -> &Path {
+        &self.paths[0]
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DuplicateReport {
+    /// Groups of two files or more, ordered by their canonical path
+    pub groups: Vec<DuplicateGroup>,
+    /// Files that could not be read or parsed as png, with the reason
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+impl DuplicateReport {
+    /// Groups with their content id and paths, canonical first, and the files skipped
+    pub fn to_json(&self) -> String {
+        let path_json = |path: &PathBuf| json_string(&path.display().to_string());
+        let groups: Vec<String> = self
+            .groups
+            .iter()
+            .map(|group| {
+                format!(
+                    "{{\"content_id\":\"{}\",\"paths\":[{}]}}",
+                    to_hex(&group.content_id),
+                    group
+                        .paths
+                        .iter()
+                        .map(path_json)
+                        .collect::<Vec<_>>()
+                        .join(",")
+                )
+            })
+            .collect();
+        let skipped: Vec<String> = self
+            .skipped
+            .iter()
+            .map(|(path, reason)| {
+                format!(
+                    "{{\"path\":{},\"reason\":{}}}",
+                    path_json(path),
+                    json_string(reason)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"groups\":[{}],\"skipped\":[{}]}}",
+            groups.join(","),
+            skipped.join(",")
+        )
+    }
+}
+
+impl Display for DuplicateReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for group in self.groups.iter() {
+            writeln!(f, "{}", to_hex(&group.content_id))?;
+            for path in group.paths.iter() {
+                writeln!(f, "  {}", path.display())?;
+            }
+        }
+        for (path, reason) in self.skipped.iter() {
+            writeln!(f, "skipped {}: {}", path.display(), reason)?;
+        }
+        writeln!(
+            f,
+            "{} group(s), {} duplicate file(s)",
+            self.groups.len(),
+            self.groups
+                .iter()
+                .map(|group| group.paths.len() - 1)
+                .sum::<usize>()
+        )
+    }
+}
+
+/// Group every png under `root` by content id. Files larger than `max_file_size` are skipped
+/// without being read.
+pub fn find_duplicates(
+    root: &Path,
+    max_file_size: Option<u64>,
+    policy: &PathPolicy,
+) -> Result<DuplicateReport> {
+    let mut report = DuplicateReport::default();
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    for path in png_files(root, policy)? {
+        match content_id_of(&path, max_file_size) {
+            Ok(content_id) => match groups
+                .iter_mut()
+                .find(|group| group.content_id == content_id)
+            {
+                Some(group) => group.paths.push(path),
+                None => groups.push(DuplicateGroup {
+                    content_id,
+                    paths: vec![path],
+                }),
+            },
+            Err(e) => report.skipped.push((path, e.to_string())),
+        }
+    }
+    // Files come sorted, so groups already are in canonical path order
+    report.groups = groups
+        .into_iter()
+        .filter(|group| group.paths.len() > 1)
+        .collect();
+    Ok(report)
+}
+
+fn content_id_of(path: &Path, max_file_size: Option<u64>) -> Result<[u8; 32]> {
+    if let Some(max_file_size) = max_file_size {
+        let size = fs::metadata(path)?.len();
+        if size > max_file_size {
+            return Err(anyhow!(
+                "{} bytes, above the size limit of {} bytes",
+                size,
+                max_file_size
+            ));
+        }
+    }
+    Ok(PNG::try_from(fs::read(path)?.as_slice())?.content_id())
+}
+
+/// Add the message chunks of `other` that `canonical` lacks, returns how many were added.
+/// Messages already in `canonical` are never removed or rewritten.
+pub fn merge_messages(canonical: &mut PNG, other: &PNG) -> Result<usize> {
+    let messages = PNG::from_chunks(
+        other
+            .chunks()
+            .iter()
+            .filter(|chunk| chunk.chunk_type().is_critical() || chunk.chunk_type().is_message())
+            .cloned()
+            .collect(),
+    );
+    Ok(canonical
+        .merge_ancillary(&messages, &MergePolicy::new(Resolution::Both))?
+        .outcomes
+        .iter()
+        .map(|(_, outcome)| match outcome {
+            MergeOutcome::Added(count) | MergeOutcome::KeptBoth(count) => *count,
+            _ => 0,
+        })
+        .sum())
+}
+
+/// Gather the message chunks of every file of the group into its canonical copy, which is
+/// written back when anything was added. Other copies are left untouched.
+/// Returns the number of chunks added.
+pub fn merge_group(group: &DuplicateGroup) -> Result<usize> {
+    let mut canonical = PNG::try_from(fs::read(group.canonical())?.as_slice())?;
+    let mut added = 0;
+    for path in group.paths.iter().skip(1) {
+        let other = PNG::try_from(fs::read(path)?.as_slice())?;
+        added += merge_messages(&mut canonical, &other)?;
+    }
+    if added > 0 {
+        fs::write(group.canonical(), canonical.as_bytes())?;
+    }
+    Ok(added)
+}
@@ -0,0 +1,98 @@
+#![allow(dead_code)]
+//! # Encode
+//! Embedding message chunks into a png and describing what changed.
+use crate::chunk::Chunk;
+use crate::png::PNG;
+use anyhow::Result;
+use std::fmt::{Display, Formatter};
+
+/// Summary of an [embed] call
+#[derive(Debug, PartialEq)]
+pub struct EncodeReport {
+    /// Size of the message as given by the user
+    pub message_bytes: usize,
+    /// Bytes the png grew by, chunk headers and CRCs included
+    pub bytes_added: usize,
+    /// Chunk data bytes divided by message bytes. Below 1 when the message was stored in a
+    /// smaller form (like a store reference), above 1 when framing like iTXt fields was added.
+    pub compression_ratio: f64,
+    /// Index in [chunks](PNG::chunks) of the first chunk written
+    pub chunk_index: usize,
+    /// Size of the resulting png
+    pub file_size: usize,
+    /// Chunks of the encoded type present after encoding, the new ones included
+    pub message_chunks: usize,
+}
+
+impl EncodeReport {
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"message_bytes\":{},\"bytes_added\":{},\"compression_ratio\":{:.4},\
+             \"chunk_index\":{},\"file_size\":{},\"message_chunks\":{}}}",
+            self.message_bytes,
+            self.bytes_added,
+            self.compression_ratio,
+            self.chunk_index,
+            self.file_size,
+            self.message_chunks
+        )
+    }
+}
+
+impl Display for EncodeReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Message size:      {} bytes", self.message_bytes)?;
+        writeln!(f, "Bytes added:       {}", self.bytes_added)?;
+        writeln!(f, "Compression ratio: {:.2}", self.compression_ratio)?;
+        writeln!(f, "Chunk index:       {}", self.chunk_index)?;
+        writeln!(f, "New file size:     {} bytes", self.file_size)?;
+        writeln!(f, "Message chunks:    {}", self.message_chunks)
+    }
+}
+
+/// Add message chunks to png, before IEND when there is one, at the end otherwise.
+/// All chunks are expected to share the same type. `message_bytes` is the size of the original
+/// message, used to compute the compression ratio.
+/// Fails when a chunk breaks an [ordering rule](PNG::set_ordering_rules) of the png.
+pub fn embed(png: &mut PNG, chunks: Vec<Chunk>, message_bytes: usize) -> Result<EncodeReport> {
+    let chunk_type = chunks.first().map(|chunk| chunk.chunk_type().clone());
+    let end_index = if png.ends_with_iend() {
+        png.chunks().len() - 1
+    } else {
+        png.chunks().len()
+    };
+    let mut chunk_index = None;
+    let data_bytes: usize = chunks.iter().map(|chunk| chunk.data().len()).sum();
+    let bytes_added: usize = chunks.iter().map(|chunk| chunk.data().len() + 12).sum();
+    for chunk in chunks {
+        let index = if png.ends_with_iend() {
+            png.try_append_chunk(chunk)?
+        } else {
+            let index = png.chunks().len();
+            png.insert_chunk(index, chunk)?;
+            index
+        };
+        chunk_index.get_or_insert(index);
+    }
+    let chunk_index = chunk_index.unwrap_or(end_index);
+    let message_chunks = match chunk_type {
+        Some(chunk_type) => png
+            .chunks()
+            .iter()
+            .filter(|chunk| chunk.chunk_type() == &chunk_type)
+            .count(),
+        None => 0,
+    };
+    Ok(EncodeReport {
+        message_bytes,
+        bytes_added,
+        compression_ratio: if message_bytes == 0 {
+            1.0
+        } else {
+            data_bytes as f64 / message_bytes as f64
+        },
+        chunk_index,
+        file_size: png.byte_len(),
+        message_chunks,
+    })
+}
@@ -0,0 +1,156 @@
+#![allow(dead_code)]
+//! # Graph
+//! Chunk layout as a diagram, for documentation and bug reports about file structure.
+//!
+//! Consecutive chunks of the same type, typically long IDAT runs, are grouped into one node.
+use crate::chunk_type::ChunkType;
+use crate::plan::json_string;
+use crate::png::PNG;
+use std::fmt::Write;
+
+/// A run of consecutive chunks sharing a type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkGroup {
+    pub chunk_type: ChunkType,
+    /// Index of the first chunk of the run in [chunks](PNG::chunks)
+    pub first_index: usize,
+    pub count: usize,
+    /// Sum of data lengths
+    pub data_bytes: u64,
+}
+
+impl ChunkGroup {
+    fn label(&self, line_break: &str) -> String {
+        let name = if self.count > 1 {
+            format!("{} x{}", self.chunk_type, self.count)
+        } else {
+            self.chunk_type.to_string()
+        };
+        format!("{}{}{}", name, line_break, human_size(self.data_bytes))
+    }
+}
+
+/// Group consecutive chunks of the same type
+pub fn group_chunks(png: &PNG) -> Vec<ChunkGroup> {
+    let mut groups: Vec<ChunkGroup> = Vec::new();
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        match groups.last_mut() {
+            Some(group) if &group.chunk_type == chunk.chunk_type() => {
+                group.count += 1;
+                group.data_bytes += chunk.length() as u64;
+            }
+            _ => groups.push(ChunkGroup {
+                chunk_type: chunk.chunk_type().clone(),
+                first_index: index,
+                count: 1,
+                data_bytes: chunk.length() as u64,
+            }),
+        }
+    }
+    groups
+}
+
+/// One line per group with the chunk indices it covers
+pub fn to_text(png: &PNG) -> String {
+    let mut text = String::new();
+    for group in group_chunks(png) {
+        let indices = if group.count > 1 {
+            format!(
+                "{}-{}",
+                group.first_index,
+                group.first_index + group.count - 1
+            )
+        } else {
+            group.first_index.to_string()
+        };
+        // Writing to a String can't fail
+        writeln!(text, "{:>7} {}", indices, group.label(", ")).unwrap();
+    }
+    text
+}
+
+/// [to_text] as a JSON array, one object per group
+pub fn to_json(png: &PNG) -> String {
+    let groups: Vec<String> = group_chunks(png)
+        .iter()
+        .map(|group| {
+            format!(
+                "{{\"type\":{},\"first_index\":{},\"count\":{},\"data_bytes\":{}}}",
+                json_string(&group.chunk_type.to_string()),
+                group.first_index,
+                group.count,
+                group.data_bytes
+            )
+        })
+        .collect();
+    format!("[{}]", groups.join(","))
+}
+
+/// Graphviz DOT digraph, critical chunks are drawn bold and message chunks dashed
+pub fn to_dot(png: &PNG) -> String {
+    let groups = group_chunks(png);
+    let mut dot = String::from("digraph png {\n    rankdir=LR;\n    node [shape=box];\n");
+    for (index, group) in groups.iter().enumerate() {
+        let style = if group.chunk_type.is_critical() {
+            ", style=bold"
+        } else if group.chunk_type.is_message() {
+            ", style=dashed"
+        } else {
+            ""
+        };
+        writeln!(
+            dot,
+            "    n{} [label=\"{}\"{}];",
+            index,
+            group.label("\\n"),
+            style
+        )
+        .unwrap();
+    }
+    for index in 1..groups.len() {
+        writeln!(dot, "    n{} -> n{};", index - 1, index).unwrap();
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Mermaid flowchart, critical chunks get square nodes and ancillary chunks rounded ones
+pub fn to_mermaid(png: &PNG) -> String {
+    let groups = group_chunks(png);
+    let mut mermaid = String::from("flowchart LR\n");
+    for (index, group) in groups.iter().enumerate() {
+        let (open, close) = if group.chunk_type.is_critical() {
+            ("[", "]")
+        } else {
+            ("(", ")")
+        };
+        writeln!(
+            mermaid,
+            "    n{}{}\"{}\"{}",
+            index,
+            open,
+            group.label("<br/>"),
+            close
+        )
+        .unwrap();
+    }
+    for index in 1..groups.len() {
+        writeln!(mermaid, "    n{} --> n{}", index - 1, index).unwrap();
+    }
+    mermaid
+}
+
+/// Byte count with a binary unit, `1.5 KiB`
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
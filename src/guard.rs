@@ -0,0 +1,146 @@
+#![allow(dead_code)]
+//! # Guard
+//! Time and memory limits for an operation on a single file, so one pathological file can't
+//! stall a batch run over untrusted input.
+//!
+//! The operation runs on a worker thread watched by the caller. When time is up the caller gets
+//! a [LimitError] right away and the worker is left behind, to die with the process. An
+//! operation about to write calls [commit](Guard::commit) first: past that point it is never
+//! abandoned, so files are not left half written. Memory is checked by the operation itself,
+//! with [check_memory](Guard::check_memory), where it loads file content.
+use anyhow::{anyhow, Result};
+use std::fmt::{Display, Formatter};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub timeout: Option<Duration>,
+    /// Bytes of file content an operation may hold in memory
+    pub memory: Option<u64>,
+}
+
+/// Error returned when an operation goes over its limits, can be downcast from `anyhow::Error`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitError {
+    Timeout(Duration),
+    Memory {
+        /// What was being loaded
+        what: String,
+        bytes: u64,
+        limit: u64,
+    },
+}
+
+impl Display for LimitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitError::Timeout(timeout) => {
+                write!(f, "Operation aborted after {:?}, above --timeout", timeout)
+            }
+            LimitError::Memory { what, bytes, limit } => write!(
+                f,
+                "Operation aborted: {} takes {} bytes, above --memory-limit of {} bytes",
+                what, bytes, limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Running,
+    Committing,
+    Abandoned,
+}
+
+/// Handle given to a guarded operation
+#[derive(Debug, Clone)]
+pub struct Guard {
+    state: Arc<Mutex<State>>,
+    memory: Option<u64>,
+}
+
+impl Guard {
+    /// A guard that never stops anything, for running guarded code without limits
+    pub fn unlimited() -> Self {
+        Self::new(None)
+    }
+
+    fn new(memory: Option<u64>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State::Running)),
+            memory,
+        }
+    }
+
+    /// Fail when holding `bytes` of `what` goes over the memory limit
+    pub fn check_memory(&self, what: &str, bytes: u64) -> Result<()> {
+        match self.memory {
+            Some(limit) if bytes > limit => Err(LimitError::Memory {
+                what: what.to_string(),
+                bytes,
+                limit,
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Bytes left for more content when `used` are already held, `None` without a limit
+    pub fn memory_left(&self, used: u64) -> Option<u64> {
+        self.memory.map(|limit| limit.saturating_sub(used))
+    }
+
+    /// Declare that the operation is about to write. Fails when it was already abandoned for
+    /// taking too long, after that it runs to its end whatever the time.
+    pub fn commit(&self) -> Result<()> {
+        // A poisoned lock means the watcher panicked, nothing is left to abandon the operation
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match *state {
+            State::Abandoned => Err(anyhow!("Operation was abandoned, nothing written")),
+            _ => {
+                *state = State::Committing;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Run `operation` within `limits`. Without a timeout it runs on the current thread.
+pub fn run<T, F>(limits: Limits, operation: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&Guard) -> Result<T> + Send + 'static,
+{
+    let guard = Guard::new(limits.memory);
+    let timeout = match limits.timeout {
+        Some(timeout) => timeout,
+        None => return operation(&guard),
+    };
+    let (sender, receiver) = mpsc::channel();
+    let worker_guard = guard.clone();
+    thread::spawn(move || {
+        // The receiver is gone once the operation was abandoned, the result is of no use then
+        let _ = sender.send(operation(&worker_guard));
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => {
+            let mut state = guard.state.lock().unwrap_or_else(|e| e.into_inner());
+            if *state == State::Committing {
+                drop(state);
+                return receiver
+                    .recv()
+                    .unwrap_or_else(|_| Err(anyhow!("Operation panicked")));
+            }
+            *state = State::Abandoned;
+            Err(LimitError::Timeout(timeout).into())
+        }
+        Err(RecvTimeoutError::Disconnected) => Err(anyhow!("Operation panicked")),
+    }
+}
@@ -0,0 +1,136 @@
+#![allow(dead_code)]
+//! # Hook
+//! Pre-commit checks on png files.
+//!
+//! Every file must parse, pass [verify](crate::verify::verify) and carry each of the required
+//! stamp chunk types. Chunks of types listed in a [Registry] are checked against it. Problems
+//! are reported as `path:chunk: severity: message` lines, which editors and CI log viewers pick
+//! up the same way as compiler diagnostics.
+use crate::chunk_type::ChunkType;
+use crate::plan::json_string;
+use crate::png::PNG;
+use crate::registry::Registry;
+use crate::tree::is_png_path;
+use crate::verify::{verify, Issue};
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Diagnostic for a single file
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub issue: Issue,
+}
+
+impl Diagnostic {
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"path\":{},\"severity\":\"{}\",\"chunk_index\":{},\"message\":{}}}",
+            json_string(&self.path.display().to_string()),
+            self.issue.severity,
+            self.issue
+                .chunk_index
+                .map_or("null".to_string(), |index| index.to_string()),
+            json_string(&self.issue.message)
+        )
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.issue.chunk_index {
+            Some(index) => write!(
+                f,
+                "{}:{}: {}: {}",
+                self.path.display(),
+                index,
+                self.issue.severity,
+                self.issue.message
+            ),
+            None => write!(f, "{}: {}", self.path.display(), self.issue),
+        }
+    }
+}
+
+/// Png files staged in the git index, as paths relative to the repository root
+pub fn staged_png_files() -> Result<Vec<PathBuf>> {
+    let output = Command::new("git")
+        .args([
+            "diff",
+            "--cached",
+            "--name-only",
+            "--diff-filter=ACMR",
+            "-z",
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to list staged files: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output
+        .stdout
+        .split(|byte| *byte == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| PathBuf::from(String::from_utf8_lossy(name).into_owned()))
+        .filter(|path| is_png_path(path))
+        .collect())
+}
+
+/// Content of a file as staged in the git index, which may differ from the working tree
+pub fn staged_content(path: &Path) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("show")
+        .arg(format!(":{}", path.display()))
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to read staged {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Check a single file content, returns every problem found
+pub fn check_file(
+    path: &Path,
+    content: &[u8],
+    required: &[ChunkType],
+    registry: Option<&Registry>,
+) -> Vec<Diagnostic> {
+    let png = match PNG::try_from(content) {
+        Ok(png) => png,
+        Err(e) => {
+            return vec![Diagnostic {
+                path: path.to_path_buf(),
+                issue: Issue::error(None, e.to_string()),
+            }]
+        }
+    };
+    let mut issues = verify(&png);
+    if let Some(registry) = registry {
+        issues.extend(registry.validate(&png));
+    }
+    for chunk_type in required {
+        if !png
+            .chunks()
+            .iter()
+            .any(|chunk| chunk.chunk_type() == chunk_type)
+        {
+            issues.push(Issue::error(
+                None,
+                format!("missing required {} chunk", chunk_type),
+            ));
+        }
+    }
+    issues
+        .into_iter()
+        .map(|issue| Diagnostic {
+            path: path.to_path_buf(),
+            issue,
+        })
+        .collect()
+}
@@ -0,0 +1,184 @@
+#![allow(dead_code)]
+//! # I18n
+//! Translations of the messages printed by the command line interface.
+//!
+//! Every locale has a catalog under `locales/`, written in the subset of the Fluent syntax this
+//! tool needs: `#` comments and `id = text` messages, where `{ $name }` stands for an argument.
+//! Lines indented under a message continue it on a new line. Messages missing from a catalog
+//! fall back to English. Errors raised by library code are not translated.
+use anyhow::{anyhow, Result};
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+const EN: &str = include_str!("../locales/en.ftl");
+const ZH: &str = include_str!("../locales/zh.ftl");
+
+static MESSAGES: OnceLock<Messages> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    Zh,
+}
+
+impl Locale {
+    /// Locale of a tag like `zh`, `zh-Hans` or a POSIX locale like `zh_CN.UTF-8`.
+    /// `C` and `POSIX` are English. Returns `None` for languages without a catalog.
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let language = tag
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match language.as_str() {
+            "en" | "c" | "posix" => Some(Locale::En),
+            "zh" => Some(Locale::Zh),
+            _ => None,
+        }
+    }
+
+    /// Locale given with `--locale`, otherwise the first of `LC_ALL`, `LC_MESSAGES` and `LANG`
+    /// that is set, like POSIX tools do. Falls back to English.
+    pub fn detect(explicit: Option<&str>) -> Locale {
+        let from_env = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty());
+        explicit
+            .map(str::to_string)
+            .or(from_env)
+            .and_then(|tag| Locale::from_tag(&tag))
+            .unwrap_or(Locale::En)
+    }
+
+    fn source(&self) -> &'static str {
+        match self {
+            Locale::En => EN,
+            Locale::Zh => ZH,
+        }
+    }
+}
+
+/// Messages of one locale
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Catalog {
+    messages: Vec<(String, String)>,
+}
+
+impl Catalog {
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut messages: Vec<(String, String)> = Vec::new();
+        for (line_number, line) in source.lines().enumerate() {
+            let context = |message: &str| anyhow!("line {}: {}", line_number + 1, message);
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            if line.starts_with(char::is_whitespace) {
+                let (_, text) = messages
+                    .last_mut()
+                    .ok_or_else(|| context("continuation line outside of a message"))?;
+                text.push('\n');
+                text.push_str(line.trim());
+                continue;
+            }
+            let (id, text) = line
+                .split_once('=')
+                .ok_or_else(|| context("expected `id = text`"))?;
+            let id = id.trim();
+            if id.is_empty()
+                || !id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(context(&format!("invalid message id {:?}", id)));
+            }
+            if messages.iter().any(|(existing, _)| existing == id) {
+                return Err(context(&format!("duplicate message id {}", id)));
+            }
+            messages.push((id.to_string(), text.trim().to_string()));
+        }
+        Ok(Self { messages })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.messages
+            .iter()
+            .find(|(existing, _)| existing == id)
+            .map(|(_, text)| text.as_str())
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.messages.iter().map(|(id, _)| id.as_str())
+    }
+}
+
+/// Replace every `{ $name }` of `pattern` with the matching argument. Placeables without an
+/// argument are left as they are.
+pub fn format(pattern: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut text = String::with_capacity(pattern.len());
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let placeable = &rest[start..];
+        let end = match placeable.find('}') {
+            Some(end) => end,
+            None => {
+                rest = placeable;
+                break;
+            }
+        };
+        let name = placeable[1..end].trim().strip_prefix('$');
+        match name.and_then(|name| args.iter().find(|(arg, _)| *arg == name)) {
+            Some((_, value)) => text.push_str(&value.to_string()),
+            None => text.push_str(&placeable[..=end]),
+        }
+        rest = &placeable[end + 1..];
+    }
+    text.push_str(rest);
+    text
+}
+
+/// A locale catalog along with the English one to fall back to
+#[derive(Debug, Clone)]
+pub struct Messages {
+    locale: Locale,
+    catalog: Catalog,
+    fallback: Catalog,
+}
+
+impl Messages {
+    pub fn new(locale: Locale) -> Self {
+        // Catalogs are part of the binary, tests make sure they parse
+        Self {
+            locale,
+            catalog: Catalog::parse(locale.source()).unwrap(),
+            fallback: Catalog::parse(EN).unwrap(),
+        }
+    }
+
+    pub fn locale(&self) -> Locale {
+        self.locale
+    }
+
+    /// Message `id` with its arguments filled in. Unknown ids are returned as they are.
+    pub fn get(&self, id: &str, args: &[(&str, &dyn Display)]) -> String {
+        let pattern = self
+            .catalog
+            .get(id)
+            .or_else(|| self.fallback.get(id))
+            .unwrap_or(id);
+        format(pattern, args)
+    }
+}
+
+/// Select the locale used by [tr] for the rest of the process. Only the first call counts.
+pub fn init(locale: Locale) {
+    let _ = MESSAGES.set(Messages::new(locale));
+}
+
+/// Message `id` in the locale given to [init], English before that
+pub fn tr(id: &str, args: &[(&str, &dyn Display)]) -> String {
+    MESSAGES
+        .get_or_init(|| Messages::new(Locale::En))
+        .get(id, args)
+}
@@ -0,0 +1,161 @@
+#![allow(dead_code)]
+//! # ICO
+//! Windows icon and cursor files, whose images may be stored as whole png files since Windows
+//! Vista.
+//!
+//! An ICO file is a 6 byte header, one 16 byte directory entry per image giving its size and
+//! offset, then the images themselves. Only png images can carry messages, BMP images are kept
+//! as they are. [Ico::to_bytes] lays the images out again after the directory, so a png growing
+//! or shrinking gets correct sizes and offsets everywhere.
+use crate::png::PNG;
+use anyhow::{anyhow, Result};
+
+const HEADER_LEN: usize = 6;
+const ENTRY_LEN: usize = 16;
+
+pub const KIND_ICON: u16 = 1;
+pub const KIND_CURSOR: u16 = 2;
+
+/// One image of the file, directory fields kept as read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IcoEntry {
+    /// Pixels, 0 meaning 256
+    pub width: u8,
+    pub height: u8,
+    pub colors: u8,
+    pub reserved: u8,
+    /// Color planes for icons, hotspot x for cursors
+    pub planes: u16,
+    /// Bits per pixel for icons, hotspot y for cursors
+    pub bit_count: u16,
+    pub data: Vec<u8>,
+}
+
+impl IcoEntry {
+    pub fn is_png(&self) -> bool {
+        self.data.starts_with(&PNG::STANDARD_HEADER)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ico {
+    /// [KIND_ICON] or [KIND_CURSOR]
+    pub kind: u16,
+    pub entries: Vec<IcoEntry>,
+}
+
+impl Ico {
+    /// Tells whether given bytes start like an icon or cursor file
+    pub fn is_ico(bytes: &[u8]) -> bool {
+        bytes.len() >= HEADER_LEN
+            && read_u16(bytes, 0) == Some(0)
+            && matches!(read_u16(bytes, 2), Some(KIND_ICON | KIND_CURSOR))
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Ico> {
+        if !Self::is_ico(bytes) {
+            return Err(anyhow!("Not an ICO or CUR file"));
+        }
+        let truncated = || anyhow!("Unexpected end of ICO file");
+        let kind = read_u16(bytes, 2).ok_or_else(truncated)?;
+        let count = read_u16(bytes, 4).ok_or_else(truncated)? as usize;
+        let mut entries = Vec::with_capacity(count);
+        for index in 0..count {
+            let at = HEADER_LEN + index * ENTRY_LEN;
+            let entry = bytes.get(at..at + ENTRY_LEN).ok_or_else(truncated)?;
+            let size = read_u32(entry, 8).ok_or_else(truncated)? as usize;
+            let offset = read_u32(entry, 12).ok_or_else(truncated)? as usize;
+            let data = offset
+                .checked_add(size)
+                .and_then(|end| bytes.get(offset..end))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "ICO entry {} of {} bytes at offset {} is outside the file",
+                        index,
+                        size,
+                        offset
+                    )
+                })?;
+            entries.push(IcoEntry {
+                width: entry[0],
+                height: entry[1],
+                colors: entry[2],
+                reserved: entry[3],
+                planes: read_u16(entry, 4).ok_or_else(truncated)?,
+                bit_count: read_u16(entry, 6).ok_or_else(truncated)?,
+                data: data.to_vec(),
+            });
+        }
+        Ok(Ico { kind, entries })
+    }
+
+    /// Indices of the entries stored as png
+    pub fn png_entries(&self) -> Vec<usize> {
+        (0..self.entries.len())
+            .filter(|index| self.entries[*index].is_png())
+            .collect()
+    }
+
+    /// Png bytes of the entry at `index`
+    pub fn png(&self, index: usize) -> Result<&[u8]> {
+        let entry = self.entries.get(index).ok_or_else(|| {
+            anyhow!(
+                "ICO has {} entries, there is no entry {}",
+                self.entries.len(),
+                index
+            )
+        })?;
+        if !entry.is_png() {
+            return Err(anyhow!(
+                "ICO entry {} is a BMP image, png entries are {:?}",
+                index,
+                self.png_entries()
+            ));
+        }
+        Ok(&entry.data)
+    }
+
+    /// Replace the image of the entry at `index`, directory fields are kept
+    pub fn set_image(&mut self, index: usize, data: Vec<u8>) -> Result<()> {
+        let entries = self.entries.len();
+        let entry = self
+            .entries
+            .get_mut(index)
+            .ok_or_else(|| anyhow!("ICO has {} entries, there is no entry {}", entries, index))?;
+        entry.data = data;
+        Ok(())
+    }
+
+    /// Serialize with the images following the directory in entry order
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.extend_from_slice(&self.kind.to_le_bytes());
+        bytes.extend_from_slice(&(self.entries.len() as u16).to_le_bytes());
+        let mut offset = HEADER_LEN + self.entries.len() * ENTRY_LEN;
+        for entry in self.entries.iter() {
+            bytes.extend_from_slice(&[entry.width, entry.height, entry.colors, entry.reserved]);
+            bytes.extend_from_slice(&entry.planes.to_le_bytes());
+            bytes.extend_from_slice(&entry.bit_count.to_le_bytes());
+            bytes.extend_from_slice(&(entry.data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&(offset as u32).to_le_bytes());
+            offset += entry.data.len();
+        }
+        for entry in self.entries.iter() {
+            bytes.extend_from_slice(&entry.data);
+        }
+        bytes
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
@@ -0,0 +1,429 @@
+#![allow(dead_code)]
+//! # Integration
+//! "Decode message" and "Add message" entries in the context menu of png files, so the tool can
+//! be used from a file manager.
+//!
+//! Both entries run this executable: decoding opens the message with `decode --open`, adding one
+//! starts `encode --interactive` in a terminal. What gets installed depends on the platform:
+//! - Linux: a KDE service menu, a `.desktop` file with one action per entry
+//! - macOS: one Quick Action per entry in `~/Library/Services`
+//! - Windows: `HKCU\Software\Classes\SystemFileAssociations\.png\shell` keys, imported from a
+//!   `.reg` file with `reg import`
+use crate::chunk_type::ChunkType;
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Terminal used on Linux to run the encode wizard, followed by the command to run
+pub const DEFAULT_TERMINAL: &str = "x-terminal-emulator -e";
+
+const DECODE_LABEL: &str = "Decode message";
+const ENCODE_LABEL: &str = "Add message";
+const REGISTRY_KEY: &str = r"HKEY_CURRENT_USER\Software\Classes\SystemFileAssociations\.png\shell";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Linux,
+    MacOs,
+    Windows,
+}
+
+impl Platform {
+    pub fn current() -> Self {
+        if cfg!(target_os = "windows") {
+            Platform::Windows
+        } else if cfg!(target_os = "macos") {
+            Platform::MacOs
+        } else {
+            Platform::Linux
+        }
+    }
+}
+
+/// A file written by [install]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrationFile {
+    pub path: PathBuf,
+    pub contents: String,
+    /// KDE only runs service menus marked executable
+    pub executable: bool,
+}
+
+/// What the context menu entries run
+#[derive(Debug, Clone)]
+pub struct Integration {
+    pub platform: Platform,
+    /// Absolute path of this executable
+    pub executable: PathBuf,
+    /// Message type decoded by the "Decode message" entry
+    pub chunk_type: ChunkType,
+    /// See [DEFAULT_TERMINAL], Linux only
+    pub terminal: String,
+    /// Home directory on Linux and macOS, `%APPDATA%` on Windows
+    pub base_dir: PathBuf,
+}
+
+impl Integration {
+    /// Integration for the current platform and executable
+    pub fn current(chunk_type: ChunkType, terminal: String) -> Result<Self> {
+        let platform = Platform::current();
+        let base_dir = match platform {
+            Platform::Windows => std::env::var_os("APPDATA"),
+            _ => std::env::var_os("HOME"),
+        }
+        .ok_or_else(|| anyhow!("Can't tell where to install, HOME or APPDATA is not set"))?;
+        Ok(Self {
+            platform,
+            executable: std::env::current_exe()?,
+            chunk_type,
+            terminal,
+            base_dir: PathBuf::from(base_dir),
+        })
+    }
+
+    /// Files making up the integration
+    pub fn files(&self) -> Vec<IntegrationFile> {
+        match self.platform {
+            Platform::Linux => vec![IntegrationFile {
+                path: self
+                    .base_dir
+                    .join(".local/share/kio/servicemenus/msg-in-png.desktop"),
+                contents: self.service_menu(),
+                executable: true,
+            }],
+            Platform::MacOs => [
+                (DECODE_LABEL, self.decode_script()),
+                (ENCODE_LABEL, self.encode_script()),
+            ]
+            .into_iter()
+            .flat_map(|(label, script)| {
+                let bundle = self
+                    .base_dir
+                    .join("Library/Services")
+                    .join(format!("{}.workflow", label))
+                    .join("Contents");
+                [
+                    IntegrationFile {
+                        path: bundle.join("Info.plist"),
+                        contents: quick_action_info(label),
+                        executable: false,
+                    },
+                    IntegrationFile {
+                        path: bundle.join("document.wflow"),
+                        contents: quick_action_workflow(&script),
+                        executable: false,
+                    },
+                ]
+            })
+            .collect(),
+            Platform::Windows => vec![IntegrationFile {
+                path: self.registry_file(),
+                contents: self.registry_entries(),
+                executable: false,
+            }],
+        }
+    }
+
+    fn registry_file(&self) -> PathBuf {
+        self.base_dir.join("msg-in-png").join("integration.reg")
+    }
+
+    /// Files written by [install] that [uninstall] removes. On Windows, a `.reg` file deleting
+    /// the keys is imported instead.
+    fn installed_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self.files().into_iter().map(|file| file.path).collect();
+        if self.platform == Platform::MacOs {
+            // Remove the whole bundles, not only their content
+            paths = paths
+                .iter()
+                .filter_map(|path| path.parent()?.parent().map(Path::to_path_buf))
+                .collect();
+            paths.dedup();
+        }
+        paths
+    }
+
+    fn service_menu(&self) -> String {
+        let executable = desktop_quote(&self.executable.to_string_lossy());
+        format!(
+            "[Desktop Entry]\n\
+             Type=Service\n\
+             MimeType=image/png;\n\
+             Actions=decode;encode;\n\
+             X-KDE-ServiceTypes=KonqPopupMenu/Plugin\n\
+             \n\
+             [Desktop Action decode]\n\
+             Name={}\n\
+             Icon=document-open\n\
+             Exec={} -p %f decode --open {}\n\
+             \n\
+             [Desktop Action encode]\n\
+             Name={}\n\
+             Icon=document-edit\n\
+             Exec={} {} -p %f encode --interactive\n",
+            DECODE_LABEL, executable, self.chunk_type, ENCODE_LABEL, self.terminal, executable
+        )
+    }
+
+    fn decode_script(&self) -> String {
+        format!(
+            "for f in \"$@\"; do {} -p \"$f\" decode --open {}; done",
+            shell_quote(&self.executable.to_string_lossy()),
+            self.chunk_type
+        )
+    }
+
+    /// Terminal.app is told to run the wizard, Quick Actions have no terminal of their own
+    fn encode_script(&self) -> String {
+        format!(
+            "for f in \"$@\"; do osascript -e 'on run argv' \
+             -e 'tell application \"Terminal\" to do script (quoted form of item 1 of argv & \" -p \" & quoted form of item 2 of argv & \" encode --interactive\")' \
+             -e 'end run' {} \"$f\"; done",
+            shell_quote(&self.executable.to_string_lossy())
+        )
+    }
+
+    fn registry_entries(&self) -> String {
+        let executable = self.executable.to_string_lossy();
+        let decode = format!(
+            "\"{}\" -p \"%1\" decode --open {}",
+            executable, self.chunk_type
+        );
+        let encode = format!(
+            "cmd.exe /K \"\"{}\" -p \"%1\" encode --interactive\"",
+            executable
+        );
+        let mut entries = String::from("Windows Registry Editor Version 5.00\r\n");
+        for (key, label, command) in [
+            ("msg-in-png.decode", DECODE_LABEL, decode),
+            ("msg-in-png.encode", ENCODE_LABEL, encode),
+        ] {
+            entries.push_str(&format!(
+                "\r\n[{}\\{}]\r\n@={}\r\n\r\n[{}\\{}\\command]\r\n@={}\r\n",
+                REGISTRY_KEY,
+                key,
+                reg_string(label),
+                REGISTRY_KEY,
+                key,
+                reg_string(&command)
+            ));
+        }
+        entries
+    }
+
+    fn registry_removal(&self) -> String {
+        format!(
+            "Windows Registry Editor Version 5.00\r\n\r\n[-{0}\\msg-in-png.decode]\r\n\r\n[-{0}\\msg-in-png.encode]\r\n",
+            REGISTRY_KEY
+        )
+    }
+}
+
+/// Quote an argument for the `Exec` key of a `.desktop` file
+fn desktop_quote(arg: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// String value of a `.reg` file
+fn reg_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn quick_action_info(label: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>NSServices</key>
+	<array>
+		<dict>
+			<key>NSMenuItem</key>
+			<dict>
+				<key>default</key>
+				<string>{}</string>
+			</dict>
+			<key>NSMessage</key>
+			<string>runWorkflowAsService</string>
+			<key>NSRequiredContext</key>
+			<dict>
+				<key>NSApplicationIdentifier</key>
+				<string>com.apple.finder</string>
+			</dict>
+			<key>NSSendFileTypes</key>
+			<array>
+				<string>public.png</string>
+			</array>
+		</dict>
+	</array>
+</dict>
+</plist>
+"#,
+        xml_escape(label)
+    )
+}
+
+/// Workflow with a single "Run Shell Script" action receiving the selected files as arguments
+fn quick_action_workflow(script: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>AMApplicationBuild</key>
+	<string>523</string>
+	<key>AMApplicationVersion</key>
+	<string>2.10</string>
+	<key>AMDocumentVersion</key>
+	<string>2</string>
+	<key>actions</key>
+	<array>
+		<dict>
+			<key>action</key>
+			<dict>
+				<key>AMAccepts</key>
+				<dict>
+					<key>Container</key>
+					<string>List</string>
+					<key>Optional</key>
+					<true/>
+					<key>Types</key>
+					<array>
+						<string>com.apple.cocoa.path</string>
+					</array>
+				</dict>
+				<key>AMActionVersion</key>
+				<string>2.0.3</string>
+				<key>AMParameterProperties</key>
+				<dict>
+					<key>COMMAND_STRING</key>
+					<dict/>
+					<key>inputMethod</key>
+					<dict/>
+					<key>shell</key>
+					<dict/>
+				</dict>
+				<key>ActionBundlePath</key>
+				<string>/System/Library/Automator/Run Shell Script.action</string>
+				<key>ActionName</key>
+				<string>Run Shell Script</string>
+				<key>ActionParameters</key>
+				<dict>
+					<key>COMMAND_STRING</key>
+					<string>{}</string>
+					<key>CheckedForUserDefaultShell</key>
+					<true/>
+					<key>inputMethod</key>
+					<integer>1</integer>
+					<key>shell</key>
+					<string>/bin/sh</string>
+				</dict>
+				<key>BundleIdentifier</key>
+				<string>com.apple.RunShellScript</string>
+				<key>CFBundleVersion</key>
+				<string>2.0.3</string>
+				<key>Class Name</key>
+				<string>RunShellScriptAction</string>
+			</dict>
+		</dict>
+	</array>
+	<key>workflowMetaData</key>
+	<dict>
+		<key>serviceInputTypeIdentifier</key>
+		<string>com.apple.Automator.fileSystemObject</string>
+		<key>serviceOutputTypeIdentifier</key>
+		<string>com.apple.Automator.nothing</string>
+		<key>workflowTypeIdentifier</key>
+		<string>com.apple.Automator.servicesMenu</string>
+	</dict>
+</dict>
+</plist>
+"#,
+        xml_escape(script)
+    )
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o111);
+    fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+fn import_registry_file(path: &Path) -> Result<()> {
+    let status = Command::new("reg").arg("import").arg(path).status()?;
+    if !status.success() {
+        return Err(anyhow!("reg import {:?} failed with {}", path, status));
+    }
+    Ok(())
+}
+
+/// Write the integration files, replacing earlier ones. Returns the files written.
+pub fn install(integration: &Integration) -> Result<Vec<PathBuf>> {
+    let files = integration.files();
+    for file in files.iter() {
+        if let Some(parent) = file.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&file.path, &file.contents)?;
+        if file.executable {
+            make_executable(&file.path)?;
+        }
+    }
+    if integration.platform == Platform::Windows {
+        import_registry_file(&integration.registry_file())?;
+    }
+    Ok(files.into_iter().map(|file| file.path).collect())
+}
+
+/// Remove what [install] added. Returns the files and directories removed.
+pub fn uninstall(integration: &Integration) -> Result<Vec<PathBuf>> {
+    if integration.platform == Platform::Windows {
+        let path = integration.registry_file();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, integration.registry_removal())?;
+        import_registry_file(&path)?;
+        fs::remove_file(&path)?;
+        return Ok(vec![path]);
+    }
+    let mut removed = Vec::new();
+    for path in integration.installed_paths() {
+        if path.is_dir() {
+            fs::remove_dir_all(&path)?;
+        } else if path.exists() {
+            fs::remove_file(&path)?;
+        } else {
+            continue;
+        }
+        removed.push(path);
+    }
+    Ok(removed)
+}
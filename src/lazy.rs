@@ -0,0 +1,311 @@
+#![allow(dead_code)]
+//! # Lazy
+//! Png files whose chunk bodies stay on disk until asked for.
+//!
+//! [LazyPng::open] reads the signature and, for every chunk, its length, type and CRC, seeking
+//! over the data in between. Listing chunks or decoding one message then costs a few reads no
+//! matter how large the IDAT chunks are. Bodies are read and CRC checked by
+//! [data](LazyPng::data), a chunk that is never read is never checked.
+//! [check_crcs](LazyPng::check_crcs) checks every chunk the way parsing the whole file does,
+//! reading bodies in blocks without keeping them.
+//!
+//! On a file opened for writing, [splice](LazyPng::splice) edits in place: everything before the
+//! first affected chunk is left as it is on disk, only the rest of the file is written again,
+//! moved in blocks so memory use does not depend on how much follows the edit.
+use crate::chunk::{checked_length, Chunk};
+use crate::chunk_type::ChunkType;
+use crate::png::{signature_error, Format, PNG};
+use anyhow::{anyhow, Result};
+use crc32fast::Hasher;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// Size of the blocks chunk bodies are read in when they are not kept, and the end of the file is
+/// moved in by [splice](LazyPng::splice)
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// Where a chunk is and what its header says, without its data
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkEntry {
+    /// Offset of the length field from the start of the file
+    pub offset: u64,
+    pub length: u32,
+    pub chunk_type: ChunkType,
+    /// As stored in the file, not checked against the data yet
+    pub crc: u32,
+}
+
+impl ChunkEntry {
+    /// Entries of a png already in memory, offsets as in [as_bytes](PNG::as_bytes)
+    pub fn list(png: &PNG) -> Vec<ChunkEntry> {
+        png.chunks()
+            .iter()
+            .zip(png.chunk_offsets())
+            .map(|(chunk, offset)| ChunkEntry {
+                offset: offset as u64,
+                length: chunk.length(),
+                chunk_type: chunk.chunk_type().clone(),
+                crc: chunk.crc(),
+            })
+            .collect()
+    }
+
+    /// Offset right after the CRC of this chunk
+    pub fn end(&self) -> u64 {
+        self.offset + self.length as u64 + 12
+    }
+}
+
+pub struct LazyPng<R> {
+    reader: R,
+    signature: [u8; 8],
+    entries: Vec<ChunkEntry>,
+}
+
+impl<R: Read + Seek> LazyPng<R> {
+    /// Read the signature and every chunk header, skipping chunk data
+    pub fn open(mut reader: R) -> Result<Self> {
+        reader.rewind()?;
+        let mut start = Vec::with_capacity(8);
+        reader.by_ref().take(8).read_to_end(&mut start)?;
+        let signature: [u8; 8] = match start.as_slice().try_into() {
+            Ok(signature) if Format::from_signature(&start).is_some() => signature,
+            _ => return Err(signature_error(&start)),
+        };
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        let mut offset = reader.seek(SeekFrom::Start(8))?;
+        let mut entries = Vec::new();
+        let mut header: [u8; 8] = [0; 8];
+        let mut crc_four_bytes: [u8; 4] = [0; 4];
+        while offset < file_len {
+            reader.read_exact(&mut header)?;
+            let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+            checked_length(length as usize)?;
+            let chunk_type = ChunkType::try_from([header[4], header[5], header[6], header[7]])?;
+            let entry_end = offset + length as u64 + 12;
+            if entry_end > file_len {
+                return Err(anyhow!("Unexpected end of file inside a chunk"));
+            }
+            reader.seek(SeekFrom::Start(entry_end - 4))?;
+            reader.read_exact(&mut crc_four_bytes)?;
+            entries.push(ChunkEntry {
+                offset,
+                length,
+                chunk_type,
+                crc: u32::from_be_bytes(crc_four_bytes),
+            });
+            offset = entry_end;
+        }
+        Ok(LazyPng {
+            reader,
+            signature,
+            entries,
+        })
+    }
+
+    pub fn signature(&self) -> &[u8; 8] {
+        &self.signature
+    }
+
+    pub fn entries(&self) -> &[ChunkEntry] {
+        &self.entries
+    }
+
+    /// Index of the `n`th (zero based) chunk of given type
+    pub fn position_of(&self, chunk_type: &str, n: usize) -> Result<Option<usize>> {
+        let chunk_type: ChunkType = chunk_type.parse()?;
+        Ok(self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.chunk_type == chunk_type)
+            .nth(n)
+            .map(|(index, _)| index))
+    }
+
+    /// Read the data of the chunk at `index`, checking it against the stored CRC
+    pub fn data(&mut self, index: usize) -> Result<Vec<u8>> {
+        Ok(self.chunk(index)?.data().to_vec())
+    }
+
+    /// Read the chunk at `index`, checking its CRC
+    pub fn chunk(&mut self, index: usize) -> Result<Chunk> {
+        let entry = self.entries.get(index).ok_or_else(|| {
+            anyhow!(
+                "Index {} is out of range for {} chunks",
+                index,
+                self.entries.len()
+            )
+        })?;
+        self.reader.seek(SeekFrom::Start(entry.offset + 8))?;
+        let mut data = Vec::new();
+        self.reader
+            .by_ref()
+            .take(entry.length as u64)
+            .read_to_end(&mut data)?;
+        if data.len() != entry.length as usize {
+            return Err(anyhow!("Unexpected end of file inside a chunk"));
+        }
+        let chunk = Chunk::try_new(entry.chunk_type.clone(), data)?;
+        if chunk.crc() != entry.crc {
+            return Err(crc_error(entry, index));
+        }
+        Ok(chunk)
+    }
+
+    /// Read at most `len` bytes from the start of the data of the chunk at `index`. The CRC covers
+    /// the whole body so it is not checked
+    pub fn prefix(&mut self, index: usize, len: usize) -> Result<Vec<u8>> {
+        let entry = self.entries.get(index).ok_or_else(|| {
+            anyhow!(
+                "Index {} is out of range for {} chunks",
+                index,
+                self.entries.len()
+            )
+        })?;
+        self.reader.seek(SeekFrom::Start(entry.offset + 8))?;
+        let mut data = Vec::new();
+        self.reader
+            .by_ref()
+            .take(len.min(entry.length as usize) as u64)
+            .read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Check the body of every chunk against its stored CRC, reading it in blocks
+    pub fn check_crcs(&mut self) -> Result<()> {
+        let mut block = vec![0; BLOCK_SIZE];
+        for (index, entry) in self.entries.iter().enumerate() {
+            self.reader.seek(SeekFrom::Start(entry.offset + 8))?;
+            let mut hasher = Hasher::new();
+            hasher.update(&entry.chunk_type.bytes());
+            let mut left = entry.length as usize;
+            while left > 0 {
+                let size = left.min(BLOCK_SIZE);
+                self.reader.read_exact(&mut block[..size])?;
+                hasher.update(&block[..size]);
+                left -= size;
+            }
+            if hasher.finalize() != entry.crc {
+                return Err(crc_error(entry, index));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read every chunk, giving the same png [PNG::try_from] would
+    pub fn to_png(&mut self) -> Result<PNG> {
+        let chunks = (0..self.entries.len())
+            .map(|index| self.chunk(index))
+            .collect::<Result<Vec<_>>>()?;
+        let format = Format::from_signature(&self.signature).unwrap_or(Format::Png);
+        Ok(PNG::from_chunks_with_format(chunks, format))
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+fn crc_error(entry: &ChunkEntry, index: usize) -> anyhow::Error {
+    anyhow!(
+        "CRC check failed for {} chunk at index {}",
+        entry.chunk_type,
+        index
+    )
+}
+
+impl LazyPng<File> {
+    /// Replace `remove` chunks starting at `index` with `insert`. Bytes before the chunk at
+    /// `index` are not written, bodies of chunks after the removed ones are moved in blocks
+    /// without being checked. Entries are updated to the new layout.
+    pub fn splice(&mut self, index: usize, remove: usize, insert: &[Chunk]) -> Result<()> {
+        let removed_end = index
+            .checked_add(remove)
+            .filter(|end| *end <= self.entries.len())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Can't remove {} chunk(s) at index {} of {} chunks",
+                    remove,
+                    index,
+                    self.entries.len()
+                )
+            })?;
+        let start = match self.entries.get(index) {
+            Some(entry) => entry.offset,
+            None => self.entries.last().map_or(8, |entry| entry.end()),
+        };
+        let tail_start = match self.entries.get(removed_end) {
+            Some(entry) => entry.offset,
+            None => self.entries.last().map_or(8, |entry| entry.end()),
+        };
+        let tail_len = self.reader.seek(SeekFrom::End(0))? - tail_start;
+        let mut replacement = Vec::new();
+        let mut offset = start;
+        for chunk in insert {
+            chunk.write_to(&mut replacement)?;
+        }
+        let inserted: Vec<ChunkEntry> = insert
+            .iter()
+            .map(|chunk| {
+                let entry = ChunkEntry {
+                    offset,
+                    length: chunk.length(),
+                    chunk_type: chunk.chunk_type().clone(),
+                    crc: chunk.crc(),
+                };
+                offset = entry.end();
+                entry
+            })
+            .collect();
+        // The tail goes first, the replacement may cover where it was
+        self.move_bytes(tail_start, offset, tail_len)?;
+        self.reader.seek(SeekFrom::Start(start))?;
+        self.reader.write_all(&replacement)?;
+        self.reader.set_len(offset + tail_len)?;
+        self.reader.flush()?;
+        let shift = offset as i64 - tail_start as i64;
+        let mut moved = self.entries.split_off(removed_end);
+        for entry in moved.iter_mut() {
+            entry.offset = (entry.offset as i64 + shift) as u64;
+        }
+        self.entries.truncate(index);
+        self.entries.extend(inserted);
+        self.entries.extend(moved);
+        Ok(())
+    }
+
+    /// Copy `len` bytes at `from` to `to`, one block at a time. The ranges may overlap: moving
+    /// towards the end of the file starts with the last block so nothing is overwritten before
+    /// it is read.
+    fn move_bytes(&mut self, from: u64, to: u64, len: u64) -> Result<()> {
+        if from == to {
+            return Ok(());
+        }
+        let mut block = vec![0; BLOCK_SIZE.min(len as usize)];
+        let mut moved = 0;
+        while moved < len {
+            let size = (len - moved).min(BLOCK_SIZE as u64);
+            let position = if to > from { len - moved - size } else { moved };
+            let block = &mut block[..size as usize];
+            self.reader.seek(SeekFrom::Start(from + position))?;
+            self.reader.read_exact(block)?;
+            self.reader.seek(SeekFrom::Start(to + position))?;
+            self.reader.write_all(block)?;
+            moved += size;
+        }
+        Ok(())
+    }
+
+    /// Remove the chunk at `index` from the file, returning it
+    pub fn remove(&mut self, index: usize) -> Result<Chunk> {
+        let chunk = self.chunk(index)?;
+        self.splice(index, 1, &[])?;
+        Ok(chunk)
+    }
+
+    /// Insert `chunk` at `index` in the file, shifting every chunk after it
+    pub fn insert(&mut self, index: usize, chunk: Chunk) -> Result<()> {
+        self.splice(index, 0, &[chunk])
+    }
+}
@@ -0,0 +1,101 @@
+//! # msg-in-png
+//! Add messages to png files as ancillary chunks, read them back and remove them.
+//!
+//! The command line tool is built on this library. [PNG], [Chunk] and [ChunkType] give access
+//! to the chunk layout, [encode_message] and [decode_message] cover the common case of
+//! storing a message under a chunk type and getting it back:
+//!
+//! ```
+//! use msg_in_png::{decode_message, encode_message, PNG};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! # let bytes = PNG::from_chunks(vec![
+//! #     msg_in_png::Chunk::new("IHDR".parse()?, vec![0; 13]),
+//! #     msg_in_png::Chunk::new("IEND".parse()?, Vec::new()),
+//! # ]).as_bytes();
+//! let mut png = PNG::try_from(bytes.as_slice())?;
+//! encode_message(&mut png, "ruSt", b"hidden")?;
+//! assert_eq!(decode_message(&png, "ruSt")?, Some(b"hidden".to_vec()));
+//! # Ok(())
+//! # }
+//! ```
+pub mod archive;
+pub mod args;
+pub mod attachment;
+pub mod base64;
+pub mod blake3;
+pub mod bundle;
+pub mod cgbi;
+pub mod chunk;
+pub mod chunk_type;
+pub mod color;
+mod commands;
+pub mod confine;
+pub mod corpus;
+pub mod deflate;
+pub mod digest;
+pub mod dump;
+pub mod duplicates;
+pub mod encode;
+pub mod graph;
+pub mod guard;
+pub mod hook;
+pub mod i18n;
+pub mod ico;
+pub mod integration;
+pub mod lazy;
+pub mod manifest;
+pub mod merge;
+pub mod message_store;
+pub mod metadata;
+pub mod mime;
+pub mod ordering;
+pub mod permissions;
+pub mod plan;
+pub mod png;
+pub mod pointer;
+pub mod registry;
+pub mod sha256;
+pub mod sha512;
+pub mod sidecar;
+pub mod signature;
+pub mod sparse;
+pub mod split;
+pub mod stats;
+pub mod store;
+pub mod survey;
+pub mod sync;
+pub mod tee;
+mod tests;
+pub mod text;
+pub mod toml;
+pub mod tree;
+pub mod verify;
+pub mod wizard;
+pub mod xmp;
+
+pub use crate::chunk::Chunk;
+pub use crate::chunk_type::ChunkType;
+pub use crate::encode::EncodeReport;
+pub use crate::png::PNG;
+
+use anyhow::Result;
+use std::str::FromStr;
+
+/// Add `message` to `png` before IEND, as chunks of `chunk_type`. Messages larger than a chunk
+/// can hold are split over several chunks.
+pub fn encode_message(png: &mut PNG, chunk_type: &str, message: &[u8]) -> Result<EncodeReport> {
+    let chunk_type = ChunkType::from_str(chunk_type)?;
+    let chunks = split::split_payload(&chunk_type, message, chunk::MAX_LENGTH, None)?;
+    encode::embed(png, chunks, message.len())
+}
+
+/// The first message stored under `chunk_type`, joined back together when it was split and
+/// without the MIME type header it may carry. Returns `Ok(None)` when there is no such chunk.
+pub fn decode_message(png: &PNG, chunk_type: &str) -> Result<Option<Vec<u8>>> {
+    let chunk_type = ChunkType::from_str(chunk_type)?;
+    match split::join_payload(png, &chunk_type, None)? {
+        Some(payload) => Ok(Some(mime::unwrap(&payload)?.1.to_vec())),
+        None => Ok(None),
+    }
+}
@@ -1,69 +1,2293 @@
 extern crate core;
 
-mod args;
-mod chunk;
-mod chunk_type;
-mod commands;
-mod png;
-mod tests;
-
-use crate::args::*;
-use crate::chunk::Chunk;
-use crate::chunk_type::ChunkType;
-use crate::png::PNG;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::Parser;
-use std::fs::{File, OpenOptions, Permissions};
-use std::io::{BufWriter, Read, Seek, Write};
+use msg_in_png::archive::Container;
+use msg_in_png::args::*;
+use msg_in_png::attachment::{self, Attachment};
+use msg_in_png::bundle::MessageBundle;
+use msg_in_png::chunk::{Chunk, MAX_LENGTH};
+use msg_in_png::chunk_type::ChunkType;
+use msg_in_png::confine::PathPolicy;
+use msg_in_png::guard::{Guard, Limits};
+use msg_in_png::i18n::{tr, Locale};
+use msg_in_png::ico::Ico;
+use msg_in_png::integration::Integration;
+use msg_in_png::lazy::LazyPng;
+use msg_in_png::manifest::Manifest;
+use msg_in_png::merge::MergePolicy;
+use msg_in_png::metadata::{LastModified, PhysicalDimensions};
+use msg_in_png::mime::{MimeType, StripHeader};
+use msg_in_png::plan::{Method, PlanRequest};
+use msg_in_png::png::PNG;
+use msg_in_png::pointer::FilePointer;
+use msg_in_png::registry::Registry;
+use msg_in_png::sha256::to_hex;
+use msg_in_png::sidecar::RemovedChunks;
+use msg_in_png::stats::SortKey;
+use msg_in_png::store::{PayloadRef, PayloadStore};
+use msg_in_png::survey::{Health, Survey, TOP_ERROR_KINDS};
+use msg_in_png::text::{CompressedText, InternationalText, Keyword, Text, TextForm};
+use msg_in_png::{
+    base64, cgbi, color, corpus, dump, duplicates, encode, graph, guard, hook, i18n, integration,
+    metadata, mime, permissions, plan, signature, sparse, split, stats, sync, tee, text, tree,
+    verify, wizard, xmp,
+};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-fn main() -> Result<()> {
+fn main() {
     let cli: Cli = Cli::parse();
-    println!("{:?} file: {:?}", cli.command, cli.png);
+    i18n::init(Locale::detect(cli.options.locale.as_deref()));
+    if let Err(e) = run(cli) {
+        eprintln!("{}", tr("error", &[("message", &format!("{:#}", e))]));
+        std::process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let policy = path_policy(&cli.options)?;
+    if let Some(png_path) = cli.png.as_deref().filter(|path| *path != Path::new("-")) {
+        policy.check(png_path)?;
+    }
+    for path in [&cli.options.registry, &cli.options.split_key]
+        .into_iter()
+        .flatten()
+    {
+        policy.check(path)?;
+    }
+    for path in cli.command.paths() {
+        policy.check(path)?;
+    }
+    match cli.command {
+        Command::SyncMeta {
+            source,
+            destination,
+            dry_run,
+        } => {
+            check_file_size(&source, &cli.options)?;
+            check_file_size(&destination, &cli.options)?;
+            let report = sync::sync_meta(&source, &destination, dry_run, &policy)?;
+            match cli.options.format {
+                OutputFormat::Text => print!("{}", report),
+                OutputFormat::Json => println!("{}", report.to_json()),
+            }
+            Ok(())
+        }
+        Command::FindDuplicates { dir, merge } => {
+            let max_file_size = (!cli.options.force_large).then_some(cli.options.max_file_size);
+            let report = duplicates::find_duplicates(&dir, max_file_size, &policy)?;
+            if cli.options.format == OutputFormat::Json {
+                let mut merged = Vec::new();
+                for group in report.groups.iter().filter(|_| merge) {
+                    merged.push(format!(
+                        "{{\"path\":{},\"added\":{}}}",
+                        plan::json_string(&group.canonical().display().to_string()),
+                        duplicates::merge_group(group)?
+                    ));
+                }
+                println!(
+                    "{{\"duplicates\":{},\"merged\":[{}]}}",
+                    report.to_json(),
+                    merged.join(",")
+                );
+                return Ok(());
+            }
+            print!("{}", report);
+            if merge {
+                for group in report.groups.iter() {
+                    let added = duplicates::merge_group(group)?;
+                    println!(
+                        "{}",
+                        tr(
+                            "duplicates-merged",
+                            &[("count", &added), ("path", &group.canonical().display())]
+                        )
+                    );
+                }
+            }
+            Ok(())
+        }
+        Command::Manifest {
+            dir,
+            output,
+            check,
+            key,
+        } => {
+            let key = key.map(std::fs::read).transpose()?;
+            let max_file_size = (!cli.options.force_large).then_some(cli.options.max_file_size);
+            let current = Manifest::scan(&dir, max_file_size, &policy)?;
+            let check = match check {
+                Some(check) => check,
+                None => {
+                    let text = current.to_text(key.as_deref());
+                    match (output, cli.options.format) {
+                        (Some(output), _) => {
+                            permissions::write(output, text, cli.options.output_mode)?
+                        }
+                        (None, OutputFormat::Text) => print!("{}", text),
+                        (None, OutputFormat::Json) => println!(
+                            "[{}]",
+                            current
+                                .entries
+                                .iter()
+                                .map(|entry| entry.to_json())
+                                .collect::<Vec<_>>()
+                                .join(",")
+                        ),
+                    }
+                    return Ok(());
+                }
+            };
+            let previous = Manifest::parse(&std::fs::read_to_string(check)?, key.as_deref())?;
+            let changes = previous.check(&current);
+            match cli.options.format {
+                OutputFormat::Text => {
+                    for change in changes.iter() {
+                        println!("{}", change);
+                    }
+                }
+                OutputFormat::Json => println!(
+                    "[{}]",
+                    changes
+                        .iter()
+                        .map(|change| change.to_json())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                ),
+            }
+            match changes.iter().filter(|change| change.is_problem()).count() {
+                0 => Ok(()),
+                count => Err(anyhow!(tr("manifest-changes", &[("count", &count)]))),
+            }
+        }
+        Command::RenameType {
+            old,
+            new,
+            dir: Some(dir),
+        } => {
+            let (mut renamed, mut files, mut failed) = (0usize, 0usize, 0usize);
+            let json = cli.options.format == OutputFormat::Json;
+            let mut rows = Vec::new();
+            tree::for_each_png_file(&dir, &policy, |path| {
+                let path_json = plan::json_string(&path.display().to_string());
+                match rename_in_file(path, &old, &new, &cli.options) {
+                    Ok(0) => {}
+                    Ok(count) if json => {
+                        renamed += count;
+                        files += 1;
+                        rows.push(format!("{{\"path\":{},\"renamed\":{}}}", path_json, count));
+                    }
+                    Ok(count) => {
+                        renamed += count;
+                        files += 1;
+                        println!(
+                            "{}",
+                            tr(
+                                "rename-file",
+                                &[("path", &path.display()), ("count", &count)]
+                            )
+                        );
+                    }
+                    Err(e) if json => {
+                        failed += 1;
+                        rows.push(format!(
+                            "{{\"path\":{},\"error\":{}}}",
+                            path_json,
+                            plan::json_string(&format!("{:#}", e))
+                        ));
+                    }
+                    Err(e) => {
+                        failed += 1;
+                        eprintln!(
+                            "{}",
+                            tr(
+                                "rename-file-failed",
+                                &[("path", &path.display()), ("message", &format!("{:#}", e))]
+                            )
+                        );
+                    }
+                }
+                Ok(())
+            })?;
+            match cli.options.format {
+                OutputFormat::Text => println!(
+                    "{}",
+                    tr(
+                        "rename-tree-done",
+                        &[("count", &renamed), ("files", &files)]
+                    )
+                ),
+                OutputFormat::Json => println!(
+                    "{{\"files\":[{}],\"renamed\":{},\"failed\":{}}}",
+                    rows.join(","),
+                    renamed,
+                    failed
+                ),
+            }
+            match failed {
+                0 => Ok(()),
+                count => Err(anyhow!(tr("rename-tree-failed", &[("count", &count)]))),
+            }
+        }
+        Command::Validate {
+            recursive: Some(dir),
+            junit,
+            ..
+        } => {
+            let options = verify::StreamOptions {
+                strict: cli.options.strict,
+                max_ancillary_length: cli.options.max_ancillary_size,
+            };
+            let survey = Survey::run(&dir, &policy, options)?;
+            match cli.options.format {
+                OutputFormat::Text => print_survey(&survey),
+                OutputFormat::Json => println!("{}", survey.to_json(TOP_ERROR_KINDS)),
+            }
+            if let Some(junit) = junit {
+                permissions::write(junit, survey.to_junit(), cli.options.output_mode)?;
+            }
+            match survey.count(Health::Errors) {
+                0 => Ok(()),
+                count => Err(anyhow!(tr("survey-failed", &[("count", &count)]))),
+            }
+        }
+        Command::ScanTrailing { dir, window, all } => {
+            let mut output = BufWriter::new(std::io::stdout().lock());
+            let (mut scanned, mut flagged) = (0usize, 0usize);
+            tree::for_each_png_file(&dir, &policy, |path| {
+                scanned += 1;
+                match sparse::scan_file(path, window) {
+                    Ok(report) if report.is_suspicious() => {
+                        flagged += 1;
+                        writeln!(output, "{}", report.to_json())?;
+                    }
+                    Ok(report) if all => writeln!(output, "{}", report.to_json())?,
+                    Ok(_) => {}
+                    Err(e) => {
+                        flagged += 1;
+                        writeln!(output, "{}", sparse::error_json(path, &e))?;
+                    }
+                }
+                Ok(())
+            })?;
+            output.flush()?;
+            eprintln!(
+                "{}",
+                tr(
+                    "sparse-summary",
+                    &[("scanned", &scanned), ("flagged", &flagged)]
+                )
+            );
+            Ok(())
+        }
+        Command::GenCorpus {
+            dir,
+            large_chunk_size,
+        } => {
+            std::fs::create_dir_all(&dir)?;
+            let samples = corpus::samples(large_chunk_size)?;
+            let mut rows = Vec::new();
+            for sample in samples.iter() {
+                permissions::write(
+                    dir.join(sample.name),
+                    &sample.bytes,
+                    cli.options.output_mode,
+                )?;
+                match cli.options.format {
+                    OutputFormat::Text => println!("{:<24} {}", sample.name, sample.description),
+                    OutputFormat::Json => rows.push(format!(
+                        "{{\"name\":{},\"description\":{},\"bytes\":{}}}",
+                        plan::json_string(sample.name),
+                        plan::json_string(sample.description),
+                        sample.bytes.len()
+                    )),
+                }
+            }
+            if cli.options.format == OutputFormat::Json {
+                println!("[{}]", rows.join(","));
+            }
+            eprintln!(
+                "{}",
+                tr(
+                    "corpus-written",
+                    &[("count", &samples.len()), ("dir", &dir.display())]
+                )
+            );
+            Ok(())
+        }
+        Command::Hook { files, required } => {
+            let required = required
+                .iter()
+                .map(|chunk_type| ChunkType::from_str(chunk_type))
+                .collect::<Result<Vec<_>>>()?;
+            let registry = load_registry(&cli.options)?;
+            let staged = files.is_empty();
+            let files = if staged {
+                hook::staged_png_files()?
+            } else {
+                files
+            };
+            let mut errors = 0;
+            let mut rows = Vec::new();
+            for path in files.iter() {
+                let content = if staged {
+                    policy.check(path)?;
+                    let content = hook::staged_content(path)?;
+                    check_size(path, content.len() as u64, &cli.options)?;
+                    content
+                } else {
+                    check_file_size(path, &cli.options)?;
+                    std::fs::read(path)?
+                };
+                for diagnostic in hook::check_file(path, &content, &required, registry.as_ref()) {
+                    if diagnostic.issue.is_error() {
+                        errors += 1;
+                    }
+                    match cli.options.format {
+                        OutputFormat::Text => println!("{}", diagnostic),
+                        OutputFormat::Json => rows.push(diagnostic.to_json()),
+                    }
+                }
+            }
+            if cli.options.format == OutputFormat::Json {
+                println!("[{}]", rows.join(","));
+            }
+            if errors > 0 {
+                return Err(anyhow!(tr("hook-errors", &[("count", &errors)])));
+            }
+            Ok(())
+        }
+        Command::Tee {
+            chunk_types,
+            report_fd,
+        } => {
+            let chunk_types = chunk_types
+                .iter()
+                .map(|chunk_type| ChunkType::from_str(chunk_type))
+                .collect::<Result<Vec<_>>>()?;
+            let mut report: Box<dyn Write> = match report_fd {
+                Some(fd) => Box::new(
+                    OpenOptions::new()
+                        .write(true)
+                        .open(format!("/dev/fd/{}", fd))
+                        .map_err(|e| anyhow!("Can't write to file descriptor {}: {}", fd, e))?,
+                ),
+                None => Box::new(std::io::stderr()),
+            };
+            let format = cli.options.format;
+            tee::tee(
+                std::io::stdin().lock(),
+                BufWriter::new(std::io::stdout().lock()),
+                &chunk_types,
+                |message| {
+                    match format {
+                        OutputFormat::Text => writeln!(report, "{}", message.to_text())?,
+                        OutputFormat::Json => writeln!(report, "{}", message.to_json())?,
+                    }
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+        Command::InstallIntegration {
+            chunk_type,
+            terminal,
+            uninstall,
+            dry_run,
+        } => {
+            let integration = Integration::current(ChunkType::from_str(&chunk_type)?, terminal)?;
+            let json = cli.options.format == OutputFormat::Json;
+            if dry_run {
+                let mut rows = Vec::new();
+                for file in integration.files() {
+                    if json {
+                        rows.push(format!(
+                            "{{\"path\":{},\"contents\":{}}}",
+                            plan::json_string(&file.path.display().to_string()),
+                            plan::json_string(&file.contents)
+                        ));
+                    } else {
+                        println!("==> {}", file.path.display());
+                        println!("{}", file.contents);
+                    }
+                }
+                if json {
+                    println!("[{}]", rows.join(","));
+                }
+                return Ok(());
+            }
+            let (paths, message) = if uninstall {
+                (integration::uninstall(&integration)?, "integration-removed")
+            } else {
+                (integration::install(&integration)?, "integration-installed")
+            };
+            if json {
+                println!(
+                    "{{\"{}\":[{}]}}",
+                    if uninstall { "removed" } else { "installed" },
+                    paths
+                        .iter()
+                        .map(|path| plan::json_string(&path.display().to_string()))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                );
+                return Ok(());
+            }
+            for path in paths {
+                println!("{}", tr(message, &[("path", &path.display())]));
+            }
+            Ok(())
+        }
+        command => {
+            let mut png_path = required_png_path(cli.png)?;
+            let mut options = cli.options.clone();
+            let spooled = if png_path == Path::new("-") {
+                png_path = spool_stdin()?;
+                options.from_stdin = true;
+                Some(png_path.clone())
+            } else {
+                None
+            };
+            let limits = Limits {
+                timeout: options.timeout,
+                memory: options.memory_limit,
+            };
+            let result = guard::run(limits, move |guard| {
+                run_single_file(png_path, command, &options, guard)
+            });
+            if let Some(spooled) = spooled {
+                let _ = std::fs::remove_file(spooled);
+            }
+            result
+        }
+    }
+}
+
+/// Issues of every file of a `validate --recursive` survey, then the summary
+fn print_survey(survey: &Survey) {
+    for file in survey.files.iter() {
+        match &file.report {
+            Ok(report) => {
+                for issue in report.issues.iter() {
+                    println!("{}: {}", file.path.display(), issue);
+                }
+            }
+            Err(message) => println!(
+                "{}",
+                tr(
+                    "survey-unreadable",
+                    &[("path", &file.path.display()), ("message", message)]
+                )
+            ),
+        }
+    }
+    println!(
+        "{}",
+        tr(
+            "survey-summary",
+            &[
+                ("files", &survey.files.len()),
+                ("ok", &survey.count(Health::Ok)),
+                ("warnings", &survey.count(Health::Warnings)),
+                ("errors", &survey.count(Health::Errors)),
+                ("bytes", &survey.bytes_scanned()),
+            ]
+        )
+    );
+    let kinds = survey.top_error_kinds(TOP_ERROR_KINDS);
+    if !kinds.is_empty() {
+        println!("{}", tr("survey-top-errors", &[]));
+        for (kind, count) in kinds.iter() {
+            println!(
+                "  {}",
+                tr("survey-error-kind", &[("count", count), ("kind", kind)])
+            );
+        }
+    }
+}
+
+/// Run a command operating on a single png file, within the limits of `guard`
+fn run_single_file(
+    png_path: PathBuf,
+    command: Command,
+    options: &GlobalOptions,
+    guard: &Guard,
+) -> Result<()> {
+    match command {
+        Command::Validate {
+            crc_report: true, ..
+        } => {
+            check_file_size(&png_path, options)?;
+            let (png, failures) = PNG::parse_lenient(&read_file(&png_path, guard)?)?;
+            match options.format {
+                OutputFormat::Text => {
+                    for failure in failures.iter() {
+                        println!("{}", failure);
+                    }
+                    println!(
+                        "{}",
+                        tr(
+                            "crc-report-summary",
+                            &[
+                                ("chunks", &png.chunks().len()),
+                                ("failures", &failures.len())
+                            ]
+                        )
+                    );
+                }
+                OutputFormat::Json => println!(
+                    "{{\"chunks\":{},\"failures\":[{}]}}",
+                    png.chunks().len(),
+                    failures
+                        .iter()
+                        .map(|failure| failure.to_json())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                ),
+            }
+            if !failures.is_empty() {
+                return Err(anyhow!(tr("validate-invalid", &[])));
+            }
+            Ok(())
+        }
+        Command::Validate { .. } => {
+            let format = options.format;
+            let options = verify::StreamOptions {
+                strict: options.strict,
+                max_ancillary_length: options.max_ancillary_size,
+            };
+            let report = verify::validate_stream(BufReader::new(File::open(png_path)?), options)?;
+            if format == OutputFormat::Json {
+                println!("{}", report.to_json());
+            } else {
+                for issue in report.issues.iter() {
+                    println!("{}", issue);
+                }
+                println!(
+                    "{}",
+                    tr(
+                        "validate-summary",
+                        &[
+                            ("chunks", &report.chunks),
+                            ("bytes", &report.bytes),
+                            ("sha256", &to_hex(&report.sha256)),
+                        ]
+                    )
+                );
+            }
+            if !report.is_valid() {
+                return Err(anyhow!(tr("validate-invalid", &[])));
+            }
+            Ok(())
+        }
+        Command::Decode {
+            chunk_type,
+            store,
+            lang,
+            keyword,
+            output_file,
+            any,
+            open,
+            follow,
+            all,
+            nth,
+            text,
+            ztxt,
+            itxt,
+        } => {
+            check_file_size(&png_path, options)?;
+            let textual = match (text, ztxt, itxt) {
+                (Some(keyword), _, _) => Some((keyword, TextForm::Text)),
+                (_, Some(keyword), _) => Some((keyword, TextForm::CompressedText)),
+                (_, _, Some(keyword)) => Some((keyword, TextForm::InternationalText)),
+                (None, None, None) => None,
+            };
+            if let Some((keyword, form)) = textual {
+                let (data, _) = unwrap_archive(read_file(&png_path, guard)?, options, guard)?;
+                let png = load_png(&data, options)?;
+                let found = text::find(&png, &keyword, form).ok_or_else(|| {
+                    anyhow!(tr(
+                        "decode-no-text",
+                        &[("chunk_type", &form), ("keyword", &keyword.as_str())]
+                    ))
+                })?;
+                return print_text(found.text(), &form.to_string(), output_file, options, guard);
+            }
+            // Clap makes sure a chunk type is given without --text, --ztxt or --itxt
+            let chunk_type = chunk_type.unwrap_or_default();
+            let needs_whole_file =
+                nth.is_some() || options.strict || lang.is_some() || needs_whole_file(options);
+            // References are resolved in memory, they only stand for payloads from the store
+            if let (Some(output_path), false) = (
+                &output_file,
+                needs_whole_file || any || store.is_some() || follow,
+            ) {
+                guard.commit()?;
+                return decode_to_file(
+                    &png_path,
+                    &ChunkType::from_str(&chunk_type)?,
+                    output_path,
+                    split_key(options)?.as_deref(),
+                    options,
+                );
+            }
+            if all {
+                let (data, _) = unwrap_archive(read_file(&png_path, guard)?, options, guard)?;
+                let png = load_png(&data, options)?;
+                let payloads = split::join_all(
+                    &png,
+                    &ChunkType::from_str(&chunk_type)?,
+                    split_key(options)?.as_deref(),
+                )?;
+                let mut objects = Vec::new();
+                for (index, msg_data) in payloads.into_iter().enumerate() {
+                    let msg_data =
+                        resolve_message(msg_data, store.as_deref(), follow, &png_path, options)?;
+                    let (mime, msg_data) = mime::unwrap(&msg_data)?;
+                    match (options.format, mime) {
+                        (OutputFormat::Json, mime) => objects.push(payload_json(
+                            Some(index),
+                            &chunk_type,
+                            mime.as_ref(),
+                            msg_data,
+                        )),
+                        (OutputFormat::Text, Some(mime)) => print!(
+                            "[{}] {}",
+                            index,
+                            ensure_line_end(mime::render(&mime, msg_data))
+                        ),
+                        (OutputFormat::Text, None) => {
+                            println!("[{}] {}", index, std::str::from_utf8(msg_data)?)
+                        }
+                    }
+                }
+                if options.format == OutputFormat::Json {
+                    println!("[{}]", objects.join(","));
+                }
+                return Ok(());
+            }
+            let msg_data = if any {
+                let data = read_file(&png_path, guard)?;
+                let key = split_key(options)?;
+                match decode_any(
+                    &data,
+                    &ChunkType::from_str(&chunk_type)?,
+                    key.as_deref(),
+                    guard,
+                ) {
+                    Some((carrier, msg_data)) => {
+                        eprintln!("{}", tr("decode-found-in", &[("carrier", &carrier)]));
+                        Some(msg_data)
+                    }
+                    None => {
+                        return Err(anyhow!(tr(
+                            "decode-no-carrier",
+                            &[("chunk_type", &chunk_type)]
+                        )))
+                    }
+                }
+            } else if needs_whole_file {
+                let (data, _) = unwrap_archive(read_file(&png_path, guard)?, options, guard)?;
+                let png = load_png(&data, options)?;
+                if let Some(lang) = lang {
+                    if chunk_type != "iTXt" {
+                        return Err(anyhow!(tr("decode-lang-itxt-only", &[])));
+                    }
+                    let bundle =
+                        MessageBundle::from_png(&png, &keyword, content_limit(&data, guard))?;
+                    guard
+                        .check_memory("message bundle", (data.len() + bundle.text_len()) as u64)?;
+                    let text = bundle.select(&lang).ok_or_else(|| {
+                        anyhow!(tr("decode-no-keyword", &[("keyword", &keyword.as_str())]))
+                    })?;
+                    return print_text(text, &chunk_type, output_file, options, guard);
+                }
+                match nth {
+                    Some(n) => split::join_all(
+                        &png,
+                        &ChunkType::from_str(&chunk_type)?,
+                        split_key(options)?.as_deref(),
+                    )?
+                    .into_iter()
+                    .nth(n),
+                    None => split::join_payload(
+                        &png,
+                        &ChunkType::from_str(&chunk_type)?,
+                        split_key(options)?.as_deref(),
+                    )?,
+                }
+            } else {
+                match PNG::find_chunk_streaming(File::open(&png_path)?, &chunk_type)? {
+                    // Parts of a split payload are spread over the file, read all of it
+                    Some(msg_chunk) if split::is_part(msg_chunk.data()) => {
+                        let png = PNG::try_from(read_file(&png_path, guard)?.as_slice())?;
+                        split::join_payload(
+                            &png,
+                            msg_chunk.chunk_type(),
+                            split_key(options)?.as_deref(),
+                        )?
+                    }
+                    msg_chunk => msg_chunk.map(|msg_chunk| msg_chunk.data().to_vec()),
+                }
+            };
+            if let Some(msg_data) = msg_data {
+                let msg_data =
+                    resolve_message(msg_data, store.as_deref(), follow, &png_path, options)?;
+                let (mime, msg_data) = mime::unwrap(&msg_data)?;
+                match output_file {
+                    Some(output_path) => {
+                        guard.commit()?;
+                        permissions::write(&output_path, msg_data, options.output_mode)?;
+                        print_written(msg_data.len() as u64, &output_path, options.format);
+                    }
+                    None if open => {
+                        guard.commit()?;
+                        let mime = mime.unwrap_or(MimeType::from_str(MimeType::OCTET_STREAM)?);
+                        let path = mime::open(&mime, msg_data)?;
+                        eprintln!("{}", tr("decode-opened", &[("path", &path.display())]));
+                    }
+                    None if options.format == OutputFormat::Json => println!(
+                        "{}",
+                        payload_json(None, &chunk_type, mime.as_ref(), msg_data)
+                    ),
+                    None => match mime {
+                        Some(mime) => print!("{}", ensure_line_end(mime::render(&mime, msg_data))),
+                        None => println!("{}", std::str::from_utf8(msg_data)?),
+                    },
+                }
+            } else if options.format == OutputFormat::Json && output_file.is_none() {
+                println!(
+                    "{{\"chunk_type\":{},\"payload\":null}}",
+                    plan::json_string(&chunk_type)
+                );
+            }
+            Ok(())
+        }
+        // Only chunk headers are needed, bodies are streamed through for their CRC and never kept
+        // Registry checks need chunk data
+        Command::List
+            if !options.strict
+                && !options.stdout
+                && options.registry.is_none()
+                && !needs_whole_file(options) =>
+        {
+            let mut lazy = LazyPng::open(BufReader::new(File::open(png_path)?))?;
+            lazy.check_crcs()?;
+            // Keywords come first in textual chunks, the rest of their data is not needed
+            let annotations = (0..lazy.entries().len())
+                .map(|index| {
+                    let chunk_type = lazy.entries()[index].chunk_type.clone();
+                    let prefix = lazy.prefix(index, text::KEYWORD_PREFIX_LEN)?;
+                    Ok(dump::Annotation::from_data(&chunk_type, &prefix))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let entries = lazy.entries();
+            match options.format {
+                OutputFormat::Text => print!("{}", dump::entries_table(entries, &annotations)),
+                OutputFormat::Json => {
+                    println!("{}", dump::entries_table_json(entries, &annotations))
+                }
+            }
+            Ok(())
+        }
+        // Editing in place needs the file itself, with chunks where they are on disk. Registry
+        // rules may place the tIME chunk elsewhere than the png would
+        command @ Command::Remove { .. }
+            if !options.strict
+                && !options.stdout
+                && !options.from_stdin
+                && options.registry.is_none()
+                && !needs_whole_file(options) =>
+        {
+            remove_in_place(png_path, command, options, guard)
+        }
+        command => run_file_command(png_path, command, options, guard),
+    }
+}
+
+/// Print text read from a `chunk_type` chunk, or write it to `output_file`
+fn print_text(
+    text: &str,
+    chunk_type: &str,
+    output_file: Option<PathBuf>,
+    options: &GlobalOptions,
+    guard: &Guard,
+) -> Result<()> {
+    match output_file {
+        Some(output_path) => {
+            guard.commit()?;
+            permissions::write(&output_path, text, options.output_mode)?;
+            print_written(text.len() as u64, &output_path, options.format);
+        }
+        None if options.format == OutputFormat::Json => {
+            println!("{}", payload_json(None, chunk_type, None, text.as_bytes()))
+        }
+        None => println!("{}", text),
+    }
+    Ok(())
+}
+
+/// Tell where a decoded message of `bytes` bytes was written
+fn print_written(bytes: u64, output_path: &Path, format: OutputFormat) {
+    match format {
+        OutputFormat::Text => println!(
+            "{}",
+            tr(
+                "decode-written",
+                &[("bytes", &bytes), ("path", &format!("{:?}", output_path))]
+            )
+        ),
+        OutputFormat::Json => println!(
+            "{{\"written\":{},\"path\":{}}}",
+            bytes,
+            plan::json_string(&output_path.display().to_string())
+        ),
+    }
+}
+
+/// Resolve what a message stands for: payload references against `store`, pointers to files
+/// next to the png when `follow` is given. Attachments are refused, they are extracted with
+/// `extract-file`.
+fn resolve_message(
+    msg_data: Vec<u8>,
+    store: Option<&Path>,
+    follow: bool,
+    png_path: &Path,
+    options: &GlobalOptions,
+) -> Result<Vec<u8>> {
+    if PayloadRef::is_ref(&msg_data) {
+        let payload_ref = PayloadRef::try_from(msg_data.as_slice())?;
+        let store_path =
+            store.ok_or_else(|| anyhow!(tr("decode-reference", &[("hash", &payload_ref.hash)])))?;
+        PayloadStore::open(store_path)?.resolve(&payload_ref)
+    } else if attachment::is_attachment(&msg_data) {
+        let (attachment, _) = attachment::unwrap(&msg_data)?;
+        Err(anyhow!(tr(
+            "decode-attachment",
+            &[("name", &attachment.name), ("bytes", &attachment.size)]
+        )))
+    } else if FilePointer::is_pointer(&msg_data) {
+        let pointer = FilePointer::try_from(msg_data.as_slice())?;
+        if !follow {
+            return Err(anyhow!(tr(
+                "decode-pointer",
+                &[("path", &pointer.path), ("hash", &pointer.hash)]
+            )));
+        }
+        path_policy(options)?.check(&pointer.target(png_directory(png_path))?)?;
+        pointer.follow(png_directory(png_path))
+    } else {
+        Ok(msg_data)
+    }
+}
+
+/// JSON object for a decoded message, `index` being its position among messages of its type
+fn payload_json(
+    index: Option<usize>,
+    chunk_type: &str,
+    mime: Option<&MimeType>,
+    data: &[u8],
+) -> String {
+    format!(
+        "{{{}\"chunk_type\":{},\"mime\":{},\"length\":{},\"payload\":\"{}\"}}",
+        index.map_or(String::new(), |index| format!("\"index\":{},", index)),
+        plan::json_string(chunk_type),
+        mime.map_or("null".to_string(), |mime| plan::json_string(
+            &mime.to_string()
+        )),
+        data.len(),
+        base64::encode(data)
+    )
+}
+
+fn ensure_line_end(mut text: String) -> String {
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text
+}
+
+/// Look for a message of given type in every carrier that can be read, in order: png, png
+/// inside a gzip or zip file, png entries of an ICO file, bare chunk fragment.
+/// Returns the name of the carrier that held it along with the message.
+fn decode_any(
+    data: &[u8],
+    chunk_type: &ChunkType,
+    key: Option<&[u8]>,
+    guard: &Guard,
+) -> Option<(String, Vec<u8>)> {
+    let mut carriers: Vec<(String, Result<PNG>)> = vec![("png".to_string(), PNG::try_from(data))];
+    if let Ok((container, content)) = Container::open_limited(data, content_limit(data, guard)) {
+        carriers.push((
+            format!("png inside a {} file", container.kind()),
+            PNG::try_from(content.as_slice()),
+        ));
+    }
+    if let Ok(ico) = Ico::parse(data) {
+        for index in ico.png_entries() {
+            carriers.push((
+                format!("png entry {} of an ico file", index),
+                PNG::try_from(ico.entries[index].data.as_slice()),
+            ));
+        }
+    }
+    carriers.push(("chunk fragment".to_string(), PNG::from_fragment(data)));
+    carriers.into_iter().find_map(|(carrier, png)| {
+        let msg_data = split::join_payload(&png.ok()?, chunk_type, key).ok()??;
+        Some((carrier, msg_data))
+    })
+}
+
+/// Stream the message of given type straight from the png file to `output_path`.
+/// The output file is removed when there is no message or decoding fails part way.
+fn decode_to_file(
+    png_path: &Path,
+    chunk_type: &ChunkType,
+    output_path: &Path,
+    key: Option<&[u8]>,
+    options: &GlobalOptions,
+) -> Result<()> {
+    let mut writer = StripHeader::new(BufWriter::new(permissions::create(
+        output_path,
+        options.output_mode,
+    )?));
+    let copied = split::copy_payload(
+        BufReader::new(File::open(png_path)?),
+        chunk_type,
+        &mut writer,
+        key,
+    )
+    .and_then(|copied| {
+        writer.finish()?;
+        Ok(copied)
+    });
+    let header_len = writer.header_len() as u64;
+    drop(writer);
+    let written = match copied {
+        Ok(Some(written)) => written - header_len,
+        Ok(None) => {
+            std::fs::remove_file(output_path)?;
+            return Ok(());
+        }
+        Err(e) => {
+            std::fs::remove_file(output_path)?;
+            return Err(e);
+        }
+    };
+    let prefix_len = PayloadRef::PREFIX
+        .len()
+        .max(FilePointer::PREFIX.len())
+        .max(attachment::PREFIX.len());
+    let mut start = Vec::with_capacity(prefix_len);
+    File::open(output_path)?
+        .take(prefix_len as u64)
+        .read_to_end(&mut start)?;
+    if PayloadRef::is_ref(&start) {
+        std::fs::remove_file(output_path)?;
+        return Err(anyhow!(tr("decode-stored-reference", &[])));
+    }
+    if FilePointer::is_pointer(&start) {
+        std::fs::remove_file(output_path)?;
+        return Err(anyhow!(tr("decode-stored-pointer", &[])));
+    }
+    if attachment::is_attachment(&start) {
+        std::fs::remove_file(output_path)?;
+        return Err(anyhow!(tr("decode-stored-attachment", &[])));
+    }
+    print_written(written, output_path, options.format);
+    Ok(())
+}
+
+/// Parse file content as png, or as a fragment with `--fragment`.
+/// With `--strict`, problems are printed to stderr and any of them fails the parse. Chunks listed
+/// in the `--registry` file are checked against it as well.
+/// Placement rules of the `--registry` file are enforced on chunks added to the png afterwards.
+/// With `--fix-transfer-corruption`, a damaged signature is repaired before parsing when possible.
+fn load_png(data: &[u8], options: &GlobalOptions) -> Result<PNG> {
+    let png = if options.fragment {
+        PNG::from_fragment(data)?
+    } else if options.fix_transfer_corruption {
+        match signature::diagnose(data) {
+            Some(corruption) => {
+                let repaired = signature::repair(data)?;
+                eprintln!(
+                    "{}",
+                    tr("repaired-transfer", &[("corruption", &corruption)])
+                );
+                PNG::try_from(repaired.as_slice())?
+            }
+            None => PNG::try_from(data)?,
+        }
+    } else {
+        PNG::try_from(data)?
+    };
+    check_png(png, options)
+}
+
+/// Rename chunk types in the png at `path` for `rename-type --dir`, leaving it untouched when
+/// there is nothing to rename. Containers and fragments are handled as by single file commands.
+/// Returns how many chunks were renamed.
+fn rename_in_file(path: &Path, old: &str, new: &str, options: &GlobalOptions) -> Result<usize> {
+    check_file_size(path, options)?;
+    let (data, container) = unwrap_archive(std::fs::read(path)?, options, &Guard::unlimited())?;
+    let mut png = load_png(&data, options)?;
+    let count = png.rename_chunk_type(old, new)?.len();
+    if count > 0 {
+        if !options.fragment && !options.no_touch_time {
+            metadata::touch_time(&mut png, LastModified::now())?;
+        }
+        replace_file(path, |writer| match &container {
+            Some(container) => {
+                let bytes = if options.fragment {
+                    png.fragment_bytes()
+                } else {
+                    png.as_bytes()
+                };
+                Ok(writer.write_all(&container.wrap(&bytes))?)
+            }
+            None if options.fragment => png.write_fragment_to(writer),
+            None => png.write_to(writer),
+        })?;
+    }
+    Ok(count)
+}
+
+/// Write a new version of the file at `path` to a temporary file in the same directory, then
+/// rename it over the original. The original is left whole if writing fails half way.
+fn replace_file(path: &Path, write: impl FnOnce(&mut BufWriter<File>) -> Result<()>) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a file", path.display()))?;
+    let temporary = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    let written = (|| -> Result<()> {
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        write(&mut writer)?;
+        let file = writer.into_inner().map_err(|e| e.into_error())?;
+        file.set_permissions(std::fs::metadata(path)?.permissions())?;
+        file.sync_all()?;
+        Ok(std::fs::rename(&temporary, path)?)
+    })();
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temporary);
+    }
+    written
+}
+
+/// Tells whether the png can't be parsed as it is read, because it must be unwrapped from a
+/// container, parsed as a fragment or repaired first
+fn needs_whole_file(options: &GlobalOptions) -> bool {
+    options.fragment
+        || options.fix_transfer_corruption
+        || options.in_archive
+        || options.ico_index.is_some()
+}
+
+/// Apply `--strict` and `--registry` to a freshly parsed png
+fn check_png(mut png: PNG, options: &GlobalOptions) -> Result<PNG> {
+    let registry = load_registry(options)?;
+    if options.strict {
+        let mut issues = verify::verify_strict(&png, options.max_ancillary_size);
+        if let Some(registry) = registry.as_ref() {
+            issues.extend(registry.validate(&png));
+        }
+        let issues = issues
+            .into_iter()
+            // Fragments are pieces of a png, they are not expected to hold the whole layout
+            .filter(|issue| !options.fragment || issue.chunk_index.is_some())
+            .collect::<Vec<_>>();
+        if !issues.is_empty() {
+            for issue in issues.iter() {
+                eprintln!("{}", issue);
+            }
+            return Err(anyhow!(tr("strict-rejected", &[("count", &issues.len())])));
+        }
+    }
+    if let Some(registry) = registry {
+        png.set_ordering_rules(registry.ordering_rules());
+    }
+    Ok(png)
+}
+
+/// Symbolic link and directory restrictions given with `--no-follow-symlinks` and `--allow-root`
+fn path_policy(options: &GlobalOptions) -> Result<PathPolicy> {
+    PathPolicy::new(options.no_follow_symlinks, &options.allow_roots)
+}
+
+/// Key split payload tokens are signed and checked with, read from the `--split-key` file
+fn split_key(options: &GlobalOptions) -> Result<Option<Vec<u8>>> {
+    options
+        .split_key
+        .as_ref()
+        .map(|path| std::fs::read(path).map_err(|e| anyhow!("Split key {}: {}", path.display(), e)))
+        .transpose()
+}
+
+/// Load the chunk registry given with `--registry`
+fn load_registry(options: &GlobalOptions) -> Result<Option<Registry>> {
+    options
+        .registry
+        .as_ref()
+        .map(|path| {
+            let source = std::fs::read_to_string(path)?;
+            Registry::from_toml(&source).map_err(|e| anyhow!("{}: {}", path.display(), e))
+        })
+        .transpose()
+}
+
+/// With `--in-archive` or `--ico-index`, extract the png from its container and return the
+/// container as well so edits can be wrapped back into it. Otherwise the data is returned
+/// untouched. The unwrapped content counts against the memory limit of `guard` along with the
+/// data.
+fn unwrap_archive(
+    data: Vec<u8>,
+    options: &GlobalOptions,
+    guard: &Guard,
+) -> Result<(Vec<u8>, Option<Container>)> {
+    if let Some(index) = options.ico_index {
+        let (container, content) = Container::open_ico(&data, index)?;
+        guard.check_memory("ico entry", (data.len() + content.len()) as u64)?;
+        Ok((content, Some(container)))
+    } else if options.in_archive {
+        let (container, content) = Container::open_limited(&data, content_limit(&data, guard))?;
+        Ok((content, Some(container)))
+    } else {
+        Ok((data, None))
+    }
+}
+
+/// Largest content that can be unwrapped from `data` within the memory limit of `guard`
+fn content_limit(data: &[u8], guard: &Guard) -> usize {
+    memory_left(data.len() as u64, guard)
+}
+
+/// Bytes that can still be held within the memory limit of `guard` when `used` are already
+fn memory_left(used: u64, guard: &Guard) -> usize {
+    guard.memory_left(used).map_or(usize::MAX, |left| {
+        usize::try_from(left).unwrap_or(usize::MAX)
+    })
+}
+
+/// Read a whole file, failing before reading when it is over the memory limit of `guard`
+fn read_file(path: &Path, guard: &Guard) -> Result<Vec<u8>> {
+    guard.check_memory(&format!("{:?}", path), std::fs::metadata(path)?.len())?;
+    Ok(std::fs::read(path)?)
+}
+
+/// Fail when the file is above `--max-file-size`, unless `--force-large` is given
+fn check_file_size(path: &Path, options: &GlobalOptions) -> Result<()> {
+    check_size(path, std::fs::metadata(path)?.len(), options)
+}
+
+fn check_size(path: &Path, size: u64, options: &GlobalOptions) -> Result<()> {
+    if !options.force_large && size > options.max_file_size {
+        return Err(anyhow!(tr(
+            "file-too-large",
+            &[
+                ("path", &format!("{:?}", path)),
+                ("size", &size),
+                ("limit", &options.max_file_size)
+            ]
+        )));
+    }
+    Ok(())
+}
+
+/// JSON object naming a chunk by type and index
+fn chunk_json(chunk_type: &str, index: usize) -> String {
+    format!(
+        "{{\"chunk_type\":{},\"index\":{}}}",
+        plan::json_string(chunk_type),
+        index
+    )
+}
+
+/// Directory pointers of the png are relative to
+fn png_directory(png_path: &Path) -> &Path {
+    match png_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Copy stdin to a temporary file, so commands can work on it like on any png file
+fn spool_stdin() -> Result<PathBuf> {
+    let path = std::env::temp_dir().join(format!("msg-in-png-stdin-{}.png", std::process::id()));
+    // Left over by an earlier process with the same id
+    let _ = std::fs::remove_file(&path);
+    let mut spooled = permissions::create_private(&path)?;
+    if let Err(e) = std::io::copy(&mut std::io::stdin().lock(), &mut spooled) {
+        drop(spooled);
+        let _ = std::fs::remove_file(&path);
+        return Err(e.into());
+    }
+    Ok(path)
+}
+
+fn required_png_path(png: Option<PathBuf>) -> Result<PathBuf> {
+    png.ok_or_else(|| anyhow!(tr("png-path-required", &[])))
+}
+
+/// `remove` editing the file in place: chunk bodies are only read to check their CRC, to match a
+/// keyword and to be saved, and only the end of the file from the first removed chunk is written
+/// again. Files that must be unwrapped or repaired first go through [run_file_command].
+fn remove_in_place(
+    png_path: PathBuf,
+    command: Command,
+    options: &GlobalOptions,
+    guard: &Guard,
+) -> Result<()> {
+    let (chunk_type, save_removed, all, nth, keyword) = match command {
+        Command::Remove {
+            chunk_type,
+            save_removed,
+            all,
+            nth,
+            keyword,
+        } => (chunk_type, save_removed, all, nth, keyword),
+        _ => unreachable!("Only called for remove"),
+    };
+    check_file_size(&png_path, options)?;
+    let file = OpenOptions::new().read(true).write(true).open(&png_path)?;
+    let mut lazy = LazyPng::open(file)?;
+    lazy.check_crcs()?;
+    let indices: Vec<usize> = match &keyword {
+        Some(keyword) => {
+            let form = TextForm::from_str(&chunk_type)?;
+            let mut positions = Vec::new();
+            for index in 0..lazy.entries().len() {
+                if lazy.entries()[index].chunk_type.to_string() == form.to_string()
+                    && text::has_keyword(&lazy.chunk(index)?, keyword, form)
+                {
+                    positions.push(index);
+                }
+            }
+            if all {
+                positions
+            } else {
+                positions
+                    .into_iter()
+                    .nth(nth.unwrap_or(0))
+                    .into_iter()
+                    .collect()
+            }
+        }
+        None if all => {
+            let chunk_type = ChunkType::from_str(&chunk_type)?;
+            (0..lazy.entries().len())
+                .filter(|index| lazy.entries()[*index].chunk_type == chunk_type)
+                .collect()
+        }
+        None => {
+            let n = nth.unwrap_or(0);
+            let index = lazy.position_of(&chunk_type, n)?.ok_or_else(|| match nth {
+                Some(_) => anyhow!(
+                    "There is no {} chunk at position {} among chunks of its type",
+                    chunk_type,
+                    n
+                ),
+                None => anyhow!("No such type"),
+            })?;
+            vec![index]
+        }
+    };
+    if indices.is_empty() {
+        return Err(anyhow!(match keyword {
+            Some(keyword) => tr(
+                "remove-none-keyword",
+                &[("chunk_type", &chunk_type), ("keyword", &keyword.as_str())]
+            ),
+            None => tr("remove-none", &[("chunk_type", &chunk_type)]),
+        }));
+    }
+    let mut out = std::io::stdout();
+    guard.commit()?;
+    if let Some(sidecar_path) = &save_removed {
+        let removed_chunks = indices
+            .iter()
+            .map(|index| Ok((*index, lazy.chunk(*index)?)))
+            .collect::<Result<Vec<_>>>()?;
+        save_removed_chunks(sidecar_path, removed_chunks, options, &mut out)?;
+    }
+    // Last first, so the indices of the others stay valid
+    for index in indices.iter().rev() {
+        lazy.splice(*index, 1, &[])?;
+    }
+    if !options.no_touch_time {
+        let time = LastModified::now().to_chunk();
+        match lazy.position_of("tIME", 0)? {
+            Some(index) => lazy.splice(index, 1, &[time])?,
+            None => {
+                let index = lazy.position_of("IEND", 0)?;
+                lazy.insert(index.unwrap_or(lazy.entries().len()), time)?;
+            }
+        }
+    }
+    print_removed(&mut out, &chunk_type, &indices, all, save_removed, options)
+}
+
+/// Write chunks taken out by `remove` to the `--save-removed` sidecar. Done before the png is
+/// written so a failure never leaves a chunk lost.
+fn save_removed_chunks(
+    sidecar_path: &Path,
+    removed_chunks: Vec<(usize, Chunk)>,
+    options: &GlobalOptions,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let mut removed = RemovedChunks::new();
+    for (index, msg_chunk) in removed_chunks {
+        removed.push(index, msg_chunk);
+    }
+    permissions::write(sidecar_path, removed.to_fragment()?, options.output_mode)?;
+    if options.format == OutputFormat::Text {
+        writeln!(
+            out,
+            "{}",
+            tr("remove-saved", &[("path", &format!("{:?}", sidecar_path))])
+        )?;
+    }
+    Ok(())
+}
+
+/// Report the chunks `remove` took out, by the indices they had
+fn print_removed(
+    out: &mut dyn Write,
+    chunk_type: &str,
+    indices: &[usize],
+    all: bool,
+    save_removed: Option<PathBuf>,
+    options: &GlobalOptions,
+) -> Result<()> {
+    match options.format {
+        OutputFormat::Text if all => writeln!(
+            out,
+            "{}",
+            tr(
+                "remove-all-done",
+                &[("chunk_type", &chunk_type), ("count", &indices.len())]
+            )
+        )?,
+        OutputFormat::Text => writeln!(
+            out,
+            "{}",
+            tr(
+                "remove-done",
+                &[("chunk_type", &chunk_type), ("index", &indices[0])]
+            )
+        )?,
+        OutputFormat::Json => {
+            let removed: Vec<String> = indices
+                .iter()
+                .map(|index| chunk_json(chunk_type, *index))
+                .collect();
+            writeln!(
+                out,
+                "{{\"removed\":[{}],\"saved_to\":{}}}",
+                removed.join(","),
+                save_removed.map_or("null".to_string(), |path| plan::json_string(
+                    &path.to_string_lossy()
+                ))
+            )?
+        }
+    }
+    Ok(())
+}
+
+/// Run a command operating on a single png file, or on a chunk fragment with `--fragment`
+fn run_file_command(
+    png_path: PathBuf,
+    command: Command,
+    options: &GlobalOptions,
+    guard: &Guard,
+) -> Result<()> {
+    let fragment = options.fragment;
+    check_file_size(&png_path, options)?;
     let mut file = OpenOptions::new()
         .read(true)
         .write(true)
         .append(false)
-        .open(cli.png.as_path())?;
-    let mut data: Vec<u8> = Vec::with_capacity(file.metadata()?.len() as usize);
-    file.read_to_end(&mut data)?;
-    file.rewind()?;
-    let mut png = PNG::try_from(data.as_slice())?;
-    match cli.command {
+        .open(png_path.as_path())?;
+    let size = file.metadata()?.len();
+    guard.check_memory(&format!("{:?}", png_path), size)?;
+    let (mut png, container) = if needs_whole_file(options) {
+        let mut data: Vec<u8> = Vec::with_capacity(size as usize);
+        file.read_to_end(&mut data)?;
+        file.rewind()?;
+        let (data, container) = unwrap_archive(data, options, guard)?;
+        (load_png(data.as_slice(), options)?, container)
+    } else {
+        // Nothing to unwrap or repair, chunks are read straight from the file
+        let png = PNG::from_reader(&mut file)?;
+        file.rewind()?;
+        (check_png(png, options)?, None)
+    };
+    // Every command writing the file back goes through here, so tIME always reflects the edit.
+    // Fragments are left alone, they are not meant to carry whole-image metadata.
+    let prepare_edited = |png: &mut PNG| -> Result<()> {
+        guard.commit()?;
+        if !fragment && !options.no_touch_time {
+            metadata::touch_time(png, LastModified::now())?;
+        }
+        Ok(())
+    };
+    let serialize = |png: &PNG| -> Vec<u8> {
+        let bytes = if fragment {
+            png.fragment_bytes()
+        } else {
+            png.as_bytes()
+        };
+        match &container {
+            Some(container) => container.wrap(&bytes),
+            None => bytes,
+        }
+    };
+    let serialize_edited = |png: &mut PNG| -> Result<Vec<u8>> {
+        prepare_edited(png)?;
+        Ok(serialize(png))
+    };
+    // With `--stdout`, stdout only carries the edited png. Outside of a container the file is
+    // written chunk by chunk, the whole edited file is never built in memory. Returns its size.
+    let mut write_back = |png: &mut PNG| -> Result<usize> {
+        prepare_edited(png)?;
+        if options.stdout {
+            let bytes = serialize(png);
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(&bytes)?;
+            stdout.flush()?;
+            Ok(bytes.len())
+        } else if options.from_stdin {
+            Err(anyhow!(tr("stdin-needs-stdout", &[])))
+        } else if container.is_some() {
+            let bytes = serialize(png);
+            file.rewind()?;
+            file.set_len(bytes.len() as u64)?;
+            file.write_all(&bytes)?;
+            Ok(bytes.len())
+        } else {
+            file.rewind()?;
+            if fragment {
+                png.write_fragment_to(BufWriter::new(&file))?;
+            } else {
+                png.write_to(BufWriter::new(&file))?;
+            }
+            let len = file.stream_position()?;
+            file.set_len(len)?;
+            Ok(len as usize)
+        }
+    };
+    let mut out: Box<dyn Write> = if options.stdout {
+        Box::new(std::io::stderr())
+    } else {
+        Box::new(std::io::stdout())
+    };
+    match command {
         Command::Encode {
             chunk_type,
             message,
+            message_file,
+            keyword,
             output,
+            store,
+            store_url,
+            repair_trailer,
+            max_chunk_size,
+            no_split,
+            interactive,
+            mime,
+            pointer,
+            text,
+            ztxt,
+            itxt,
+            lang,
+            translated_keyword,
+            compress,
         } => {
+            let text_form = match (&text, &ztxt, &itxt) {
+                (Some(_), _, _) => Some(TextForm::Text),
+                (_, Some(_), _) => Some(TextForm::CompressedText),
+                (_, _, Some(_)) => Some(TextForm::InternationalText),
+                (None, None, None) => None,
+            };
+            let text = text.or(ztxt).or(itxt);
+            let (chunk_type, message, output) = if interactive {
+                let registry = load_registry(options)?;
+                let answers = wizard::run(
+                    &png,
+                    registry.as_ref(),
+                    &png_path,
+                    std::io::stdin().lock(),
+                    std::io::stderr(),
+                )?;
+                (
+                    answers.chunk_type.to_string(),
+                    Some(answers.message),
+                    answers.output,
+                )
+            } else if let Some(form) = text_form {
+                (form.to_string(), None, output)
+            } else {
+                // Clap makes sure a chunk type is given outside of the wizard and textual chunks
+                (chunk_type.unwrap_or_default(), message, output)
+            };
+            if !png.ends_with_iend() && !fragment {
+                if !repair_trailer {
+                    return Err(anyhow!(tr("encode-truncated", &[])));
+                }
+                png.repair_trailer();
+            }
             let msg_chunk_type = ChunkType::from_str(&chunk_type)?;
-            let msg_chunk = Chunk::new(msg_chunk_type, message.into_bytes());
-            png.append_chunk(msg_chunk);
+            let (msg_chunks, message_bytes) = match message_file {
+                Some(bundle_path) => {
+                    if msg_chunk_type.bytes() != InternationalText::CHUNK_TYPE {
+                        return Err(anyhow!(tr("encode-message-file-itxt", &[])));
+                    }
+                    let bundle = MessageBundle::from_toml(&std::fs::read_to_string(bundle_path)?)?;
+                    let message_bytes = bundle.entries.iter().map(|(_, text)| text.len()).sum();
+                    (bundle.to_chunks(&keyword), message_bytes)
+                }
+                None if text.is_some() => {
+                    // Clap makes sure --text, --ztxt and --itxt come with a keyword and a value
+                    let (keyword, value) = text.as_deref().unwrap_or_default().split_at(1);
+                    let keyword = Keyword::from_str(&keyword[0])?;
+                    let chunk = match text_form {
+                        Some(TextForm::CompressedText) => {
+                            CompressedText::new(&keyword, &value[0])?.to_chunk()
+                        }
+                        Some(TextForm::InternationalText) => InternationalText::try_new(
+                            &keyword,
+                            lang.as_deref().unwrap_or_default(),
+                            translated_keyword.as_deref().unwrap_or_default(),
+                            &value[0],
+                            compress,
+                        )?
+                        .to_chunk(),
+                        _ => Text::new(&keyword, &value[0])?.to_chunk(),
+                    };
+                    (vec![chunk], value[0].len())
+                }
+                None if pointer.is_some() => {
+                    let target = pointer.unwrap_or_default();
+                    let pointer =
+                        FilePointer::to_file(png_directory(&png_path), &target, options.hash)?;
+                    let msg_data = pointer.as_bytes();
+                    let limit = max_chunk_size.min(MAX_LENGTH);
+                    let msg_chunks = split::split_payload(
+                        &msg_chunk_type,
+                        &msg_data,
+                        limit,
+                        split_key(options)?.as_deref(),
+                    )?;
+                    (msg_chunks, msg_data.len())
+                }
+                None => {
+                    // Clap makes sure a message is given when there is no message file
+                    let message = message.unwrap_or_default();
+                    let message_bytes = message.len();
+                    let message = match &mime {
+                        Some(mime) => mime::wrap(mime, message.as_bytes()),
+                        None => message.into_bytes(),
+                    };
+                    let msg_data = match store {
+                        Some(store_path) => {
+                            let store = PayloadStore::open(&store_path)?;
+                            let hash = store.put(&message, options.hash)?;
+                            PayloadRef::new(hash, store_url).as_bytes()
+                        }
+                        None => message,
+                    };
+                    let limit = max_chunk_size.min(MAX_LENGTH);
+                    if no_split && msg_data.len() > limit {
+                        return Err(anyhow!(tr(
+                            "encode-no-split",
+                            &[("bytes", &msg_data.len()), ("limit", &limit)]
+                        )));
+                    }
+                    let msg_chunks = split::split_payload(
+                        &msg_chunk_type,
+                        &msg_data,
+                        limit,
+                        split_key(options)?.as_deref(),
+                    )?;
+                    (msg_chunks, message_bytes)
+                }
+            };
+            let mut report = encode::embed(&mut png, msg_chunks, message_bytes)?;
             if let Some(output_path) = output {
-                let output_file = File::create(output_path)?;
+                let bytes = serialize_edited(&mut png)?;
+                report.file_size = bytes.len();
+                let output_file = permissions::create(output_path, options.output_mode)?;
                 let mut writer = BufWriter::new(output_file);
-                writer.write_all(png.as_bytes().as_slice())?;
+                writer.write_all(bytes.as_slice())?;
             } else {
-                println!(
-                    "Trying to overwrite original file: {:?}",
-                    cli.png.as_path().canonicalize()?
-                );
-                file.write_all(png.as_bytes().as_slice())?;
+                if !options.stdout && !options.from_stdin {
+                    eprintln!(
+                        "{}",
+                        tr(
+                            "encode-overwrite",
+                            &[("path", &format!("{:?}", png_path.as_path().canonicalize()?))]
+                        )
+                    );
+                }
+                report.file_size = write_back(&mut png)?;
+            }
+            match options.format {
+                OutputFormat::Text => write!(out, "{}", report)?,
+                OutputFormat::Json => writeln!(out, "{}", report.to_json())?,
             }
         }
-        Command::Decode { chunk_type } => {
-            if let Some(msg_chunk) = png.chunk_by_type(&chunk_type) {
-                println!("{}", String::from_utf8(msg_chunk.data().into())?);
+        Command::EncodeFile {
+            path,
+            chunk_type,
+            output,
+            max_chunk_size,
+        } => {
+            if !png.ends_with_iend() && !fragment {
+                return Err(anyhow!(tr("encode-truncated", &[])));
+            }
+            let content = read_file(&path, guard)?;
+            let attachment = Attachment::of(&path, &content)?;
+            let msg_data = attachment::wrap(&attachment, &content);
+            let msg_chunks = split::split_payload(
+                &ChunkType::from_str(&chunk_type)?,
+                &msg_data,
+                max_chunk_size.min(MAX_LENGTH),
+                split_key(options)?.as_deref(),
+            )?;
+            let mut report = encode::embed(&mut png, msg_chunks, content.len())?;
+            report.file_size = match output {
+                Some(output_path) => {
+                    let bytes = serialize_edited(&mut png)?;
+                    permissions::write(output_path, &bytes, options.output_mode)?;
+                    bytes.len()
+                }
+                None => write_back(&mut png)?,
+            };
+            match options.format {
+                OutputFormat::Text => write!(out, "{}", report)?,
+                OutputFormat::Json => writeln!(out, "{}", report.to_json())?,
             }
         }
-        Command::Remove { chunk_type } => {
-            let _msg_chunk = png.remove_chunk(&chunk_type)?;
-            let bytes = png.as_bytes();
-            file.set_len(bytes.len() as u64)?;
-            file.write_all(bytes.as_slice())?;
-            println!("One message of type {} has been removed", chunk_type);
+        Command::ExtractFile { chunk_type, output } => {
+            let msg_data = split::join_payload(
+                &png,
+                &ChunkType::from_str(&chunk_type)?,
+                split_key(options)?.as_deref(),
+            )?
+            .ok_or_else(|| anyhow!(tr("extract-none", &[("chunk_type", &chunk_type)])))?;
+            let (attachment, content) = attachment::unwrap(&msg_data)?;
+            let output_path = match output {
+                Some(output_path) => output_path,
+                None => {
+                    let output_path = PathBuf::from(&attachment.name);
+                    if output_path.exists() {
+                        return Err(anyhow!(tr(
+                            "extract-exists",
+                            &[("path", &output_path.display())]
+                        )));
+                    }
+                    output_path
+                }
+            };
+            path_policy(options)?.check(&output_path)?;
+            guard.commit()?;
+            permissions::write(&output_path, content, options.output_mode)?;
+            match options.format {
+                OutputFormat::Text => writeln!(
+                    out,
+                    "{}",
+                    tr(
+                        "extract-written",
+                        &[
+                            ("name", &attachment.name),
+                            ("bytes", &attachment.size),
+                            ("path", &output_path.display())
+                        ]
+                    )
+                )?,
+                OutputFormat::Json => writeln!(
+                    out,
+                    "{{\"name\":{},\"bytes\":{},\"path\":{}}}",
+                    plan::json_string(&attachment.name),
+                    attachment.size,
+                    plan::json_string(&output_path.display().to_string())
+                )?,
+            }
+        }
+        Command::Remove {
+            chunk_type,
+            save_removed,
+            all,
+            nth,
+            keyword,
+        } => {
+            let removed_chunks = match (&keyword, all, nth) {
+                (Some(keyword), _, _) => {
+                    let form = TextForm::from_str(&chunk_type)?;
+                    let positions = text::positions(&png, keyword, form);
+                    let selected: Vec<usize> = if all {
+                        positions
+                    } else {
+                        positions
+                            .into_iter()
+                            .nth(nth.unwrap_or(0))
+                            .into_iter()
+                            .collect()
+                    };
+                    // Last first, so the indices of the others stay valid
+                    let mut removed = Vec::with_capacity(selected.len());
+                    for index in selected.into_iter().rev() {
+                        removed.push((index, png.remove_chunk_at(index)?));
+                    }
+                    removed.reverse();
+                    removed
+                }
+                (None, true, _) => png.remove_chunks_by_type(&chunk_type)?,
+                (None, false, Some(n)) => vec![png.remove_nth(&chunk_type, n)?],
+                (None, false, None) => vec![png.remove_chunk(&chunk_type)?],
+            };
+            if removed_chunks.is_empty() {
+                return Err(anyhow!(match keyword {
+                    Some(keyword) => tr(
+                        "remove-none-keyword",
+                        &[("chunk_type", &chunk_type), ("keyword", &keyword.as_str())]
+                    ),
+                    None => tr("remove-none", &[("chunk_type", &chunk_type)]),
+                }));
+            }
+            let indices: Vec<usize> = removed_chunks.iter().map(|(index, _)| *index).collect();
+            if let Some(sidecar_path) = &save_removed {
+                guard.commit()?;
+                save_removed_chunks(sidecar_path, removed_chunks, options, &mut out)?;
+            }
+            write_back(&mut png)?;
+            print_removed(&mut out, &chunk_type, &indices, all, save_removed, options)?;
+        }
+        Command::Restore { fragment_file } => {
+            let removed = RemovedChunks::from_fragment(&std::fs::read(fragment_file)?)?;
+            let restored = removed.restore(&mut png)?;
+            write_back(&mut png)?;
+            if options.format == OutputFormat::Json {
+                let restored: Vec<String> = restored
+                    .iter()
+                    .map(|index| chunk_json(&png.chunks()[*index].chunk_type().to_string(), *index))
+                    .collect();
+                writeln!(out, "{{\"restored\":[{}]}}", restored.join(","))?;
+                return Ok(());
+            }
+            for index in restored {
+                writeln!(
+                    out,
+                    "{}",
+                    tr(
+                        "restore-done",
+                        &[
+                            ("chunk_type", png.chunks()[index].chunk_type()),
+                            ("index", &index)
+                        ]
+                    )
+                )?;
+            }
+        }
+        Command::RenameType { old, new, .. } => {
+            let indices = png.rename_chunk_type(&old, &new)?;
+            if indices.is_empty() {
+                return Err(anyhow!(tr("rename-none", &[("old", &old)])));
+            }
+            write_back(&mut png)?;
+            match options.format {
+                OutputFormat::Text => writeln!(
+                    out,
+                    "{}",
+                    tr(
+                        "rename-done",
+                        &[("count", &indices.len()), ("old", &old), ("new", &new)]
+                    )
+                )?,
+                OutputFormat::Json => {
+                    let renamed: Vec<String> = indices
+                        .iter()
+                        .map(|index| chunk_json(&new, *index))
+                        .collect();
+                    writeln!(out, "{{\"renamed\":[{}]}}", renamed.join(","))?
+                }
+            }
         }
         Command::Print => {
-            println!("{:?}", data);
+            let bytes = if fragment {
+                png.fragment_bytes()
+            } else {
+                png.as_bytes()
+            };
+            writeln!(out, "{:?}", bytes)?;
+        }
+        Command::ColorCheck { fix } => {
+            let json = options.format == OutputFormat::Json;
+            let conflicts = color::check(&png);
+            for conflict in conflicts.iter().filter(|_| !json) {
+                writeln!(out, "{}", conflict.issue)?;
+            }
+            let mut remaining = conflicts.len();
+            let mut removed = Vec::new();
+            if fix {
+                removed = color::remove_redundant(&mut png, &conflicts);
+                if !removed.is_empty() {
+                    write_back(&mut png)?;
+                    if !json {
+                        writeln!(
+                            out,
+                            "{}",
+                            tr("color-removed", &[("indices", &format!("{:?}", removed))])
+                        )?;
+                    }
+                }
+                remaining = conflicts
+                    .iter()
+                    .filter(|conflict| conflict.redundant.is_none())
+                    .count();
+            }
+            if json {
+                writeln!(
+                    out,
+                    "{{\"conflicts\":[{}],\"removed\":{:?},\"remaining\":{}}}",
+                    conflicts
+                        .iter()
+                        .map(|conflict| conflict.to_json())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                    removed,
+                    remaining
+                )?;
+            }
+            if remaining > 0 {
+                return Err(anyhow!(tr("color-problems", &[("count", &remaining)])));
+            }
+            if conflicts.is_empty() && !json {
+                writeln!(out, "{}", tr("color-consistent", &[]))?;
+            }
+        }
+        Command::Info { dpi } => {
+            let physical = png
+                .chunk_by_type("pHYs")
+                .map(PhysicalDimensions::try_from)
+                .transpose()?;
+            let known_dpi = physical.as_ref().and_then(PhysicalDimensions::dpi);
+            let density = match physical {
+                Some(physical) => match physical.dpi() {
+                    Some(dpi) => dpi.to_string(),
+                    None => format!(
+                        "unknown, aspect ratio {}:{}",
+                        physical.pixels_per_unit_x, physical.pixels_per_unit_y
+                    ),
+                },
+                None => "not set".to_string(),
+            };
+            if options.format == OutputFormat::Json {
+                let header = png.image_header()?;
+                let modified = png
+                    .chunk_by_type("tIME")
+                    .map(LastModified::try_from)
+                    .transpose()?;
+                writeln!(out,
+                    "{{\"width\":{},\"height\":{},\"color_type\":{},\"bit_depth\":{},\"interlaced\":{},\"chunks\":{},\"file_size\":{},\"dpi\":{},\"modified\":{}}}",
+                    header.width,
+                    header.height,
+                    plan::json_string(header.color_type_name()),
+                    header.bit_depth,
+                    header.interlace_method == 1,
+                    png.chunks().len(),
+                    png.byte_len(),
+                    known_dpi.map_or("null".to_string(), |known| format!(
+                        "{{\"x\":{:.0},\"y\":{:.0}}}",
+                        known.x, known.y
+                    )),
+                    modified.map_or("null".to_string(), |time| plan::json_string(&time.to_string()))
+                )?;
+            } else if dpi {
+                writeln!(out, "{}", density)?;
+            } else {
+                let header = png.image_header()?;
+                writeln!(
+                    out,
+                    "{}",
+                    tr(
+                        "info-size",
+                        &[("width", &header.width), ("height", &header.height)]
+                    )
+                )?;
+                writeln!(
+                    out,
+                    "{}",
+                    tr(
+                        "info-color-type",
+                        &[
+                            ("name", &header.color_type_name()),
+                            ("depth", &header.bit_depth)
+                        ]
+                    )
+                )?;
+                let interlaced = tr(
+                    if header.interlace_method == 1 {
+                        "yes"
+                    } else {
+                        "no"
+                    },
+                    &[],
+                );
+                writeln!(out, "{}", tr("info-interlaced", &[("value", &interlaced)]))?;
+                writeln!(
+                    out,
+                    "{}",
+                    tr("info-chunks", &[("count", &png.chunks().len())])
+                )?;
+                writeln!(
+                    out,
+                    "{}",
+                    tr("info-file-size", &[("bytes", &png.byte_len())])
+                )?;
+                writeln!(out, "{}", tr("info-dpi", &[("dpi", &density)]))?;
+                if let Some(time) = png.chunk_by_type("tIME") {
+                    let time = LastModified::try_from(time)?;
+                    writeln!(out, "{}", tr("info-modified", &[("time", &time)]))?;
+                }
+            }
+        }
+        Command::SetDpi { dpi } => {
+            png.set_chunk_before_idat(PhysicalDimensions::from_dpi(dpi).to_chunk())?;
+            write_back(&mut png)?;
+            match options.format {
+                OutputFormat::Text => writeln!(out, "{}", tr("set-dpi-done", &[("dpi", &dpi)]))?,
+                OutputFormat::Json => {
+                    writeln!(out, "{{\"dpi\":{{\"x\":{:.0},\"y\":{:.0}}}}}", dpi.x, dpi.y)?
+                }
+            }
+        }
+        Command::Inspect { graph } => match (graph, options.format) {
+            (None, OutputFormat::Text) => write!(out, "{}", graph::to_text(&png))?,
+            (None, OutputFormat::Json) => writeln!(out, "{}", graph::to_json(&png))?,
+            (Some(_), OutputFormat::Json) => {
+                return Err(anyhow!(tr("inspect-graph-json", &[])));
+            }
+            (Some(GraphFormat::Dot), _) => write!(out, "{}", graph::to_dot(&png))?,
+            (Some(GraphFormat::Mermaid), _) => write!(out, "{}", graph::to_mermaid(&png))?,
+        },
+        Command::Id => {
+            let content_id = to_hex(&png.content_id());
+            match options.format {
+                OutputFormat::Text => writeln!(out, "{}", content_id)?,
+                OutputFormat::Json => writeln!(out, "{{\"content_id\":\"{}\"}}", content_id)?,
+            }
+        }
+        Command::Merge {
+            other,
+            prefer,
+            per_type,
+        } => {
+            check_file_size(&other, options)?;
+            let theirs = PNG::try_from(read_file(&other, guard)?.as_slice())?;
+            let mut policy = MergePolicy::new(prefer);
+            for type_resolution in per_type {
+                policy.set(type_resolution.chunk_type, type_resolution.resolution);
+            }
+            let report = png.merge_ancillary(&theirs, &policy)?;
+            match options.format {
+                OutputFormat::Text => write!(out, "{}", report)?,
+                OutputFormat::Json => writeln!(out, "{}", report.to_json())?,
+            }
+            if report.changed() {
+                write_back(&mut png)?;
+            }
+        }
+        Command::Plan {
+            chunk_type,
+            payload_size,
+            method,
+            store,
+            store_url,
+            max_chunk_size,
+            no_split,
+        } => {
+            let method = match (method, store) {
+                (PlanMethod::Store, Some(store)) => Method::Store(store),
+                // Clap makes sure the store method comes with a store
+                (PlanMethod::Store, None) => unreachable!(),
+                (PlanMethod::Chunk, _) => Method::Chunk,
+            };
+            let request = PlanRequest {
+                png_path: png_path.clone(),
+                chunk_type: ChunkType::from_str(&chunk_type)?,
+                payload_bytes: usize::try_from(payload_size)?,
+                method,
+                max_chunk_size,
+                no_split,
+                store_url,
+                hash: options.hash,
+                touch_time: !fragment && !options.no_touch_time,
+                max_file_size: (!options.force_large).then_some(options.max_file_size),
+            };
+            let plan = plan::plan(&png, &request);
+            match options.format {
+                OutputFormat::Text => write!(out, "{}", plan)?,
+                OutputFormat::Json => writeln!(out, "{}", plan.to_json())?,
+            }
+        }
+        Command::Stats { by_type, sort } => {
+            let sort = match sort {
+                StatsSort::Size => SortKey::Size,
+                StatsSort::Count => SortKey::Count,
+                StatsSort::Type => SortKey::Type,
+                StatsSort::Order => SortKey::Order,
+            };
+            if by_type && options.format == OutputFormat::Text {
+                writeln!(out, "{}", stats::by_type_to_text(&png, sort))?;
+            }
+            match options.format {
+                OutputFormat::Text => write!(out, "{}", stats::to_text(&png))?,
+                OutputFormat::Json => writeln!(out, "{}", stats::to_json(&png, sort))?,
+            }
+        }
+        Command::Dump { check } => match check {
+            Some(dump_path) => {
+                let expected = std::fs::read_to_string(dump_path)?;
+                let differences = dump::check(&png, &expected);
+                match options.format {
+                    OutputFormat::Text => {
+                        for difference in differences.iter() {
+                            writeln!(out, "{}", difference)?;
+                        }
+                    }
+                    OutputFormat::Json => writeln!(
+                        out,
+                        "{{\"matches\":{},\"differences\":[{}]}}",
+                        differences.is_empty(),
+                        differences
+                            .iter()
+                            .map(|difference| plan::json_string(difference))
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    )?,
+                }
+                if !differences.is_empty() {
+                    return Err(anyhow!(tr(
+                        "dump-differs",
+                        &[("count", &differences.len())]
+                    )));
+                }
+                if options.format == OutputFormat::Text {
+                    writeln!(out, "{}", tr("dump-matches", &[]))?;
+                }
+            }
+            None => match options.format {
+                OutputFormat::Text => write!(out, "{}", dump::dump(&png))?,
+                OutputFormat::Json => writeln!(out, "{}", dump::dump_json(&png))?,
+            },
+        },
+        Command::Snapshot { output, check } => {
+            let digest = to_hex(&png.structural_digest());
+            match (output, check) {
+                (Some(output_path), _) => {
+                    permissions::write(&output_path, format!("{}\n", digest), options.output_mode)?;
+                    match options.format {
+                        OutputFormat::Text => writeln!(
+                            out,
+                            "{}",
+                            tr("snapshot-written", &[("path", &output_path.display())])
+                        )?,
+                        OutputFormat::Json => writeln!(
+                            out,
+                            "{{\"structural_digest\":\"{}\",\"path\":{}}}",
+                            digest,
+                            plan::json_string(&output_path.display().to_string())
+                        )?,
+                    }
+                }
+                (None, Some(digest_path)) => {
+                    let expected = std::fs::read_to_string(&digest_path)?;
+                    if expected.trim() != digest {
+                        return Err(anyhow!(tr(
+                            "snapshot-differs",
+                            &[
+                                ("path", &digest_path.display()),
+                                ("expected", &expected.trim()),
+                                ("actual", &digest)
+                            ]
+                        )));
+                    }
+                    match options.format {
+                        OutputFormat::Text => writeln!(out, "{}", tr("snapshot-matches", &[]))?,
+                        OutputFormat::Json => writeln!(
+                            out,
+                            "{{\"structural_digest\":\"{}\",\"matches\":true}}",
+                            digest
+                        )?,
+                    }
+                }
+                (None, None) => match options.format {
+                    OutputFormat::Text => writeln!(out, "{}", digest)?,
+                    OutputFormat::Json => {
+                        writeln!(out, "{{\"structural_digest\":\"{}\"}}", digest)?
+                    }
+                },
+            }
+        }
+        Command::List => {
+            let registry = load_registry(options)?;
+            match options.format {
+                OutputFormat::Text => write!(out, "{}", dump::table(&png, registry.as_ref()))?,
+                OutputFormat::Json => {
+                    writeln!(out, "{}", dump::table_json(&png, registry.as_ref()))?
+                }
+            }
+        }
+        Command::ConvertText {
+            to,
+            keyword,
+            lang,
+            compress,
+        } => {
+            let outcomes = text::convert_chunks(
+                &mut png,
+                keyword.as_ref(),
+                to,
+                compress,
+                lang.as_deref(),
+                memory_left(size, guard),
+            );
+            // Uncompressed text stays in the png until it is written back
+            guard.check_memory("converted png", png.byte_len() as u64)?;
+            let json = options.format == OutputFormat::Json;
+            let mut converted = 0;
+            let mut rows = Vec::new();
+            for (index, outcome) in outcomes.iter() {
+                match outcome {
+                    Ok(from) if json => {
+                        converted += 1;
+                        rows.push(format!(
+                            "{{\"index\":{},\"from\":\"{}\",\"to\":\"{}\"}}",
+                            index, from, to
+                        ));
+                    }
+                    Err(e) if json => rows.push(format!(
+                        "{{\"index\":{},\"skipped\":{}}}",
+                        index,
+                        plan::json_string(&e.to_string())
+                    )),
+                    Ok(from) => {
+                        converted += 1;
+                        writeln!(
+                            out,
+                            "{}",
+                            tr(
+                                "convert-done",
+                                &[("from", &from), ("index", &index), ("to", &to)]
+                            )
+                        )?;
+                    }
+                    Err(e) => writeln!(
+                        out,
+                        "{}",
+                        tr("convert-skipped", &[("index", &index), ("reason", &e)])
+                    )?,
+                }
+            }
+            if converted > 0 {
+                write_back(&mut png)?;
+            }
+            match options.format {
+                OutputFormat::Text => writeln!(
+                    out,
+                    "{}",
+                    tr(
+                        "convert-summary",
+                        &[("converted", &converted), ("total", &outcomes.len())]
+                    )
+                )?,
+                OutputFormat::Json => writeln!(
+                    out,
+                    "{{\"chunks\":[{}],\"converted\":{},\"total\":{}}}",
+                    rows.join(","),
+                    converted,
+                    outcomes.len()
+                )?,
+            }
+        }
+        Command::Xmp { action } => {
+            let packet = xmp::from_png(&png).map(|(_, packet)| packet);
+            let json = options.format == OutputFormat::Json;
+            let updated = match action {
+                XmpAction::Show => {
+                    let packet = packet.ok_or_else(|| anyhow!(tr("xmp-missing", &[])))?;
+                    if json {
+                        writeln!(out, "{{\"packet\":{}}}", plan::json_string(&packet))?;
+                    } else {
+                        writeln!(out, "{}", packet)?;
+                    }
+                    None
+                }
+                XmpAction::Get { property } => {
+                    let packet = packet.ok_or_else(|| anyhow!(tr("xmp-missing", &[])))?;
+                    let value = xmp::get_property(&packet, &property)?.ok_or_else(|| {
+                        anyhow!(tr("xmp-no-property", &[("property", &property)]))
+                    })?;
+                    if json {
+                        writeln!(
+                            out,
+                            "{{\"property\":{},\"value\":{}}}",
+                            plan::json_string(&property),
+                            plan::json_string(&value)
+                        )?;
+                    } else {
+                        writeln!(out, "{}", value)?;
+                    }
+                    None
+                }
+                XmpAction::Embed { packet } => Some(std::fs::read_to_string(packet)?),
+                XmpAction::Set { property, value } => {
+                    let packet = packet.unwrap_or_else(|| xmp::EMPTY_PACKET.to_string());
+                    Some(xmp::set_property(&packet, &property, &value)?)
+                }
+            };
+            if let Some(updated) = updated {
+                xmp::store(&mut png, &updated)?;
+                write_back(&mut png)?;
+                if json {
+                    writeln!(out, "{{\"packet\":{}}}", plan::json_string(&updated))?;
+                }
+            }
+        }
+        Command::ConvertCgbi { output } => {
+            let mut standard = cgbi::to_standard(&png)?;
+            let file_size = match output {
+                Some(output_path) => {
+                    let bytes = serialize_edited(&mut standard)?;
+                    permissions::write(output_path, &bytes, options.output_mode)?;
+                    bytes.len()
+                }
+                None => write_back(&mut standard)?,
+            };
+            match options.format {
+                OutputFormat::Text => writeln!(out, "{}", tr("cgbi-converted", &[]))?,
+                OutputFormat::Json => {
+                    writeln!(out, "{{\"converted\":true,\"file_size\":{}}}", file_size)?
+                }
+            }
+        }
+        Command::SyncMeta { .. }
+        | Command::FindDuplicates { .. }
+        | Command::Hook { .. }
+        | Command::Manifest { .. }
+        | Command::ScanTrailing { .. }
+        | Command::GenCorpus { .. }
+        | Command::InstallIntegration { .. }
+        | Command::Tee { .. }
+        | Command::Decode { .. }
+        | Command::Validate { .. } => {
+            unreachable!("Handled in main without loading the whole file")
         }
     }
     Ok(())
@@ -0,0 +1,374 @@
+#![allow(dead_code)]
+//! # Manifest
+//! Record of every png of a directory tree, to find out later which images were modified or lost
+//! their messages.
+//!
+//! Each file is listed with its content id (see [PNG::content_id]), an id per message chunk and
+//! whether it passes [verify]. The manifest is plain text:
+//!
+//! ```text
+//! msg-in-png manifest 1
+//! <content id> <status> <message ids> <path>
+//! hmac-sha256 <hex>
+//! ```
+//!
+//! The last line authenticates everything above it. With a key it is an HMAC-SHA256, so the
+//! manifest cannot be rewritten without the key. Without a key it is a plain SHA-256 that only
+//! catches accidental edits.
+use crate::confine::PathPolicy;
+use crate::plan::json_string;
+use crate::png::PNG;
+use crate::sha256::{from_hex, hmac_sha256, sha256, to_hex, Sha256};
+use crate::tree::png_files;
+use crate::verify::verify;
+use anyhow::{anyhow, Result};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+pub const HEADER: &str = "msg-in-png manifest 1";
+
+/// Whether a file passed [verify] when the manifest was made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    Valid,
+    /// Number of errors found
+    Invalid(usize),
+    /// Not a png, or above the size limit
+    Unreadable,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Status::Valid => write!(f, "valid"),
+            Status::Invalid(errors) => write!(f, "invalid:{}", errors),
+            Status::Unreadable => write!(f, "unreadable"),
+        }
+    }
+}
+
+impl std::str::FromStr for Status {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "valid" => Ok(Status::Valid),
+            "unreadable" => Ok(Status::Unreadable),
+            _ => s
+                .strip_prefix("invalid:")
+                .and_then(|errors| errors.parse().ok())
+                .map(Status::Invalid)
+                .ok_or_else(|| anyhow!("Unknown status {}", s)),
+        }
+    }
+}
+
+/// One png of the tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Relative to the root of the tree, with `/` separators
+    pub path: String,
+    /// `None` for unreadable files
+    pub content_id: Option<[u8; 32]>,
+    /// `type:sha256` of every message chunk, in file order
+    pub message_ids: Vec<String>,
+    pub status: Status,
+}
+
+impl Entry {
+    fn of(path: String, png: &PNG) -> Self {
+        let errors = verify(png).iter().filter(|issue| issue.is_error()).count();
+        Entry {
+            path,
+            content_id: Some(png.content_id()),
+            message_ids: png
+                .chunks()
+                .iter()
+                .filter(|chunk| chunk.chunk_type().is_message())
+                .map(|chunk| {
+                    let mut hasher = Sha256::new();
+                    hasher.update(&chunk.chunk_type().bytes());
+                    hasher.update(chunk.data());
+                    format!("{}:{}", chunk.chunk_type(), to_hex(&hasher.finalize()))
+                })
+                .collect(),
+            status: match errors {
+                0 => Status::Valid,
+                errors => Status::Invalid(errors),
+            },
+        }
+    }
+
+    fn unreadable(path: String) -> Self {
+        Entry {
+            path,
+            content_id: None,
+            message_ids: Vec::new(),
+            status: Status::Unreadable,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"path\":{},\"content_id\":{},\"status\":\"{}\",\"message_ids\":[{}]}}",
+            json_string(&self.path),
+            self.content_id
+                .map_or("null".to_string(), |content_id| format!(
+                    "\"{}\"",
+                    to_hex(&content_id)
+                )),
+            self.status,
+            self.message_ids
+                .iter()
+                .map(|id| json_string(id))
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let content_id = self
+            .content_id
+            .map_or("-".to_string(), |content_id| to_hex(&content_id));
+        let message_ids = match self.message_ids.is_empty() {
+            true => "-".to_string(),
+            false => self.message_ids.join(","),
+        };
+        write!(
+            f,
+            "{} {} {} {}",
+            content_id, self.status, message_ids, self.path
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    /// Sorted by path
+    pub entries: Vec<Entry>,
+}
+
+impl Manifest {
+    /// Read every png under `root`. Files larger than `max_file_size` are listed as unreadable
+    /// without being read. Files `policy` refuses are left out.
+    pub fn scan(root: &Path, max_file_size: Option<u64>, policy: &PathPolicy) -> Result<Self> {
+        let mut entries = Vec::new();
+        for path in png_files(root, policy)? {
+            let relative = relative_path(root, &path)?;
+            entries.push(match read_png(&path, max_file_size) {
+                Ok(png) => Entry::of(relative, &png),
+                Err(_) => Entry::unreadable(relative),
+            });
+        }
+        Ok(Manifest { entries })
+    }
+
+    /// Manifest text, signed with `key` when given
+    pub fn to_text(&self, key: Option<&[u8]>) -> String {
+        let mut body = format!("{}\n", HEADER);
+        for entry in self.entries.iter() {
+            body.push_str(&format!("{}\n", entry));
+        }
+        let signature = signature_line(&body, key);
+        body + &signature + "\n"
+    }
+
+    /// Parse a manifest, checking its last line against everything above it.
+    /// A manifest signed with a key can only be read with that key.
+    pub fn parse(text: &str, key: Option<&[u8]>) -> Result<Self> {
+        let body_end = text
+            .trim_end_matches('\n')
+            .rfind('\n')
+            .ok_or_else(|| anyhow!("Manifest is empty or has no signature"))?
+            + 1;
+        let (body, signature) = (&text[..body_end], text[body_end..].trim_end());
+        match (signature.split_once(' '), key) {
+            (Some(("hmac-sha256", _)), None) => {
+                return Err(anyhow!("Manifest is signed, its key is needed to check it"))
+            }
+            (Some(("sha256", _)), Some(_)) => {
+                return Err(anyhow!("Manifest is not signed with a key"))
+            }
+            (Some(("hmac-sha256" | "sha256", _)), _) => {}
+            _ => return Err(anyhow!("Manifest has no signature line")),
+        }
+        if signature != signature_line(body, key) {
+            return Err(anyhow!(
+                "Manifest signature does not match, it was modified or the key is wrong"
+            ));
+        }
+        let mut lines = body.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(anyhow!("Not a manifest, or of an unsupported version"));
+        }
+        let entries = lines
+            .enumerate()
+            .map(|(number, line)| {
+                parse_entry(line).ok_or_else(|| anyhow!("Malformed manifest line {}", number + 2))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Manifest { entries })
+    }
+
+    pub fn get(&self, path: &str) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.path == path)
+    }
+
+    /// What happened to the files of `self` in `current`
+    pub fn check(&self, current: &Manifest) -> Vec<Change> {
+        let mut changes = Vec::new();
+        for old in self.entries.iter() {
+            let path = old.path.clone();
+            let new = match current.get(&old.path) {
+                Some(new) => new,
+                None => {
+                    changes.push(Change::Missing(path));
+                    continue;
+                }
+            };
+            if old.content_id != new.content_id {
+                changes.push(Change::Modified(path.clone()));
+            }
+            let lost: Vec<String> = old
+                .message_ids
+                .iter()
+                .filter(|id| !new.message_ids.contains(id))
+                .cloned()
+                .collect();
+            if !lost.is_empty() {
+                changes.push(Change::MessagesLost(path.clone(), lost));
+            }
+            if old.status == Status::Valid && new.status != Status::Valid {
+                changes.push(Change::NoLongerValid(path, new.status.clone()));
+            }
+        }
+        for new in current.entries.iter() {
+            if self.get(&new.path).is_none() {
+                changes.push(Change::Added(new.path.clone()));
+            }
+        }
+        changes
+    }
+}
+
+/// Difference between a manifest and the tree as it is now
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// The file is gone
+    Missing(String),
+    /// Pixel content differs
+    Modified(String),
+    /// Message chunks no longer in the file, by message id. Chunks that were rewritten count
+    /// as lost too.
+    MessagesLost(String, Vec<String>),
+    /// The file used to pass verification
+    NoLongerValid(String, Status),
+    /// The file is not in the manifest, not a problem on its own
+    Added(String),
+}
+
+impl Change {
+    pub fn is_problem(&self) -> bool {
+        !matches!(self, Change::Added(_))
+    }
+
+    /// `messages` lists lost message ids and `status` the new status, when they apply
+    pub fn to_json(&self) -> String {
+        let (change, path, messages, status) = match self {
+            Change::Missing(path) => ("missing", path, &[][..], None),
+            Change::Modified(path) => ("modified", path, &[][..], None),
+            Change::MessagesLost(path, ids) => ("messages_lost", path, &ids[..], None),
+            Change::NoLongerValid(path, status) => ("no_longer_valid", path, &[][..], Some(status)),
+            Change::Added(path) => ("added", path, &[][..], None),
+        };
+        format!(
+            "{{\"change\":\"{}\",\"path\":{},\"problem\":{},\"messages\":[{}],\"status\":{}}}",
+            change,
+            json_string(path),
+            self.is_problem(),
+            messages
+                .iter()
+                .map(|id| json_string(id))
+                .collect::<Vec<_>>()
+                .join(","),
+            status.map_or("null".to_string(), |status| format!("\"{}\"", status))
+        )
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Missing(path) => write!(f, "missing {}", path),
+            Change::Modified(path) => write!(f, "modified {}", path),
+            Change::MessagesLost(path, ids) => {
+                write!(
+                    f,
+                    "lost {} message(s) {}: {}",
+                    ids.len(),
+                    path,
+                    ids.join(",")
+                )
+            }
+            Change::NoLongerValid(path, status) => write!(f, "now {} {}", status, path),
+            Change::Added(path) => write!(f, "added {}", path),
+        }
+    }
+}
+
+fn signature_line(body: &str, key: Option<&[u8]>) -> String {
+    match key {
+        Some(key) => format!("hmac-sha256 {}", to_hex(&hmac_sha256(key, body.as_bytes()))),
+        None => format!("sha256 {}", to_hex(&sha256(body.as_bytes()))),
+    }
+}
+
+fn parse_entry(line: &str) -> Option<Entry> {
+    let mut fields = line.splitn(4, ' ');
+    let content_id = match fields.next()? {
+        "-" => None,
+        hex => Some(from_hex(hex)?),
+    };
+    let status = fields.next()?.parse().ok()?;
+    let message_ids = match fields.next()? {
+        "-" => Vec::new(),
+        ids => ids.split(',').map(str::to_string).collect(),
+    };
+    let path = fields.next().filter(|path| !path.is_empty())?.to_string();
+    Some(Entry {
+        path,
+        content_id,
+        message_ids,
+        status,
+    })
+}
+
+fn relative_path(root: &Path, path: &Path) -> Result<String> {
+    let relative = path.strip_prefix(root)?;
+    let parts = relative
+        .components()
+        .map(|part| {
+            part.as_os_str()
+                .to_str()
+                .ok_or_else(|| anyhow!("Path is not valid UTF-8: {}", path.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let relative = parts.join("/");
+    if relative.contains('\n') {
+        return Err(anyhow!("Path contains a line break: {}", path.display()));
+    }
+    Ok(relative)
+}
+
+fn read_png(path: &Path, max_file_size: Option<u64>) -> Result<PNG> {
+    if let Some(max_file_size) = max_file_size {
+        let size = fs::metadata(path)?.len();
+        if size > max_file_size {
+            return Err(anyhow!("{} bytes, above the size limit", size));
+        }
+    }
+    PNG::try_from(fs::read(path)?.as_slice())
+}
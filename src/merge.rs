@@ -0,0 +1,281 @@
+#![allow(dead_code)]
+//! # Merge
+//! Combine the ancillary chunks of two versions of the same image, like an original and a
+//! re-export that dropped or rewrote some metadata.
+//!
+//! Critical chunks are never touched, the image data of the png merged into is kept as is. For
+//! every ancillary type found in the other png, chunks missing from ours are copied over, and a
+//! [Resolution] decides what happens when both have chunks of that type with different data.
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::plan::json_string;
+use crate::png::PNG;
+use anyhow::{anyhow, Result};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// What to keep when both pngs have different chunks of a type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// Keep our chunks, ignore theirs
+    Ours,
+    /// Replace our chunks with theirs
+    Theirs,
+    /// Keep ours and add those of theirs we don't already have
+    Both,
+}
+
+impl FromStr for Resolution {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ours" => Ok(Resolution::Ours),
+            "theirs" => Ok(Resolution::Theirs),
+            "both" => Ok(Resolution::Both),
+            _ => Err(anyhow!(
+                "unknown resolution {:?}, use \"ours\", \"theirs\" or \"both\"",
+                s
+            )),
+        }
+    }
+}
+
+/// Resolution for a single chunk type, parsed from `TYPE=RESOLUTION` like `tEXt=both`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeResolution {
+    pub chunk_type: ChunkType,
+    pub resolution: Resolution,
+}
+
+impl FromStr for TypeResolution {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (chunk_type, resolution) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected TYPE=RESOLUTION, found {:?}", s))?;
+        Ok(Self {
+            chunk_type: ChunkType::from_str(chunk_type)?,
+            resolution: Resolution::from_str(resolution)?,
+        })
+    }
+}
+
+/// A default resolution with overrides per chunk type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergePolicy {
+    default: Resolution,
+    overrides: Vec<TypeResolution>,
+}
+
+impl MergePolicy {
+    pub fn new(default: Resolution) -> Self {
+        Self {
+            default,
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Use `resolution` for chunks of given type, replacing an earlier override
+    pub fn set(&mut self, chunk_type: ChunkType, resolution: Resolution) {
+        self.overrides
+            .retain(|existing| existing.chunk_type != chunk_type);
+        self.overrides.push(TypeResolution {
+            chunk_type,
+            resolution,
+        });
+    }
+
+    pub fn resolution(&self, chunk_type: &ChunkType) -> Resolution {
+        self.overrides
+            .iter()
+            .find(|existing| &existing.chunk_type == chunk_type)
+            .map(|existing| existing.resolution)
+            .unwrap_or(self.default)
+    }
+}
+
+/// What happened to one chunk type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeOutcome {
+    /// Only they had this type, their chunks were copied
+    Added(usize),
+    /// Both had the same chunks
+    Identical,
+    /// Chunks differed, ours were kept
+    KeptOurs,
+    /// Chunks differed, ours were replaced by this many of theirs
+    TookTheirs(usize),
+    /// Chunks differed, this many of theirs were added next to ours
+    KeptBoth(usize),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    pub outcomes: Vec<(ChunkType, MergeOutcome)>,
+}
+
+impl MergeReport {
+    /// Tells whether the png merged into was modified
+    pub fn changed(&self) -> bool {
+        self.outcomes.iter().any(|(_, outcome)| {
+            !matches!(
+                outcome,
+                MergeOutcome::Identical | MergeOutcome::KeptOurs | MergeOutcome::KeptBoth(0)
+            )
+        })
+    }
+
+    /// One object per chunk type with the outcome and the number of their chunks it brought in
+    pub fn to_json(&self) -> String {
+        let types: Vec<String> = self
+            .outcomes
+            .iter()
+            .map(|(chunk_type, outcome)| {
+                let (name, count) = match outcome {
+                    MergeOutcome::Added(count) => ("added", *count),
+                    MergeOutcome::Identical => ("identical", 0),
+                    MergeOutcome::KeptOurs => ("kept_ours", 0),
+                    MergeOutcome::TookTheirs(count) => ("took_theirs", *count),
+                    MergeOutcome::KeptBoth(count) => ("kept_both", *count),
+                };
+                format!(
+                    "{{\"type\":{},\"outcome\":\"{}\",\"chunks\":{}}}",
+                    json_string(&chunk_type.to_string()),
+                    name,
+                    count
+                )
+            })
+            .collect();
+        format!(
+            "{{\"changed\":{},\"types\":[{}]}}",
+            self.changed(),
+            types.join(",")
+        )
+    }
+}
+
+impl Display for MergeReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (chunk_type, outcome) in self.outcomes.iter() {
+            match outcome {
+                MergeOutcome::Added(count) => writeln!(f, "{}: added {}", chunk_type, count)?,
+                MergeOutcome::Identical => writeln!(f, "{}: identical", chunk_type)?,
+                MergeOutcome::KeptOurs => writeln!(f, "{}: conflict, kept ours", chunk_type)?,
+                MergeOutcome::TookTheirs(count) => writeln!(
+                    f,
+                    "{}: conflict, replaced by {} of theirs",
+                    chunk_type, count
+                )?,
+                MergeOutcome::KeptBoth(count) => writeln!(
+                    f,
+                    "{}: conflict, kept ours and added {} of theirs",
+                    chunk_type, count
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn same_data(a: &Chunk, b: &Chunk) -> bool {
+    a.data() == b.data()
+}
+
+fn first_idat(png: &PNG) -> Option<usize> {
+    png.position_of("IDAT", 0).ok().flatten()
+}
+
+/// Insert chunks one after another starting at `index`, fails when a chunk breaks an ordering
+/// rule of the png
+fn insert_run(png: &mut PNG, index: usize, chunks: &[&Chunk]) -> Result<()> {
+    for (offset, chunk) in chunks.iter().enumerate() {
+        png.insert_chunk(index + offset, (*chunk).clone())?;
+    }
+    Ok(())
+}
+
+/// See [merge_ancillary](PNG::merge_ancillary)
+pub fn merge_ancillary(ours: &mut PNG, theirs: &PNG, policy: &MergePolicy) -> Result<MergeReport> {
+    let snapshot = ours.snapshot();
+    let report = merge_types(ours, theirs, policy);
+    if report.is_err() {
+        ours.revert(&snapshot);
+    }
+    report
+}
+
+fn merge_types(ours: &mut PNG, theirs: &PNG, policy: &MergePolicy) -> Result<MergeReport> {
+    let mut types: Vec<&ChunkType> = Vec::new();
+    for chunk in theirs.chunks() {
+        let chunk_type = chunk.chunk_type();
+        if !chunk_type.is_critical() && !types.contains(&chunk_type) {
+            types.push(chunk_type);
+        }
+    }
+    let their_idat = first_idat(theirs);
+    let mut report = MergeReport::default();
+    for chunk_type in types {
+        let their_chunks: Vec<&Chunk> = theirs
+            .chunks()
+            .iter()
+            .filter(|chunk| chunk.chunk_type() == chunk_type)
+            .collect();
+        let our_indices: Vec<usize> = ours
+            .chunks()
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| chunk.chunk_type() == chunk_type)
+            .map(|(index, _)| index)
+            .collect();
+        let outcome = if our_indices.is_empty() {
+            let their_first = theirs
+                .chunks()
+                .iter()
+                .position(|chunk| chunk.chunk_type() == chunk_type);
+            let before_idat =
+                matches!((their_first, their_idat), (Some(first), Some(idat)) if first < idat);
+            let index = match (before_idat, first_idat(ours)) {
+                (true, Some(idat)) => idat,
+                _ if ours.ends_with_iend() => ours.chunks().len() - 1,
+                _ => ours.chunks().len(),
+            };
+            insert_run(ours, index, &their_chunks)?;
+            MergeOutcome::Added(their_chunks.len())
+        } else if our_indices.len() == their_chunks.len()
+            && our_indices
+                .iter()
+                .zip(their_chunks.iter())
+                .all(|(index, theirs)| same_data(&ours.chunks()[*index], theirs))
+        {
+            MergeOutcome::Identical
+        } else {
+            match policy.resolution(chunk_type) {
+                Resolution::Ours => MergeOutcome::KeptOurs,
+                Resolution::Theirs => {
+                    for index in our_indices.iter().rev() {
+                        ours.remove_chunk_at(*index)?;
+                    }
+                    insert_run(ours, our_indices[0], &their_chunks)?;
+                    MergeOutcome::TookTheirs(their_chunks.len())
+                }
+                Resolution::Both => {
+                    let missing: Vec<&Chunk> = their_chunks
+                        .iter()
+                        .filter(|theirs| {
+                            !our_indices
+                                .iter()
+                                .any(|index| same_data(&ours.chunks()[*index], theirs))
+                        })
+                        .copied()
+                        .collect();
+                    insert_run(ours, our_indices[our_indices.len() - 1] + 1, &missing)?;
+                    MergeOutcome::KeptBoth(missing.len())
+                }
+            }
+        };
+        report.outcomes.push((chunk_type.clone(), outcome));
+    }
+    Ok(report)
+}
@@ -0,0 +1,154 @@
+#![allow(dead_code)]
+//! # Message Store
+//! Key value access to messages in a png, for library users who want to store values without
+//! dealing with chunks.
+//!
+//! Every value lives in chunks of a single private type (`msKv` unless told otherwise). The data
+//! of each chunk is the key, a null separator, then the value. Values too large for one chunk
+//! are split with the [split](crate::split) framing, every part carrying the key.
+use crate::chunk::{Chunk, MAX_LENGTH};
+use crate::chunk_type::ChunkType;
+use crate::png::PNG;
+use crate::split;
+use anyhow::{anyhow, Result};
+
+pub struct MessageStore {
+    png: PNG,
+    chunk_type: ChunkType,
+    max_chunk_size: usize,
+}
+
+impl MessageStore {
+    /// Ancillary, private and safe to copy
+    pub const DEFAULT_CHUNK_TYPE: [u8; 4] = *b"msKv";
+
+    pub fn new(png: PNG) -> Self {
+        Self::with_chunk_type(
+            png,
+            ChunkType {
+                inner: Self::DEFAULT_CHUNK_TYPE,
+            },
+        )
+    }
+
+    pub fn with_chunk_type(png: PNG, chunk_type: ChunkType) -> Self {
+        Self {
+            png,
+            chunk_type,
+            max_chunk_size: MAX_LENGTH,
+        }
+    }
+
+    /// Largest chunk data written by [put](MessageStore::put), key included. Capped to the spec
+    /// limit.
+    pub fn set_max_chunk_size(&mut self, max_chunk_size: usize) {
+        self.max_chunk_size = max_chunk_size.min(MAX_LENGTH);
+    }
+
+    pub fn png(&self) -> &PNG {
+        &self.png
+    }
+
+    pub fn into_png(self) -> PNG {
+        self.png
+    }
+
+    /// Index and value bytes of every chunk holding given key, in file order
+    fn parts<'a>(&'a self, key: &'a str) -> impl Iterator<Item = (usize, &'a [u8])> + 'a {
+        self.png
+            .chunks()
+            .iter()
+            .enumerate()
+            .filter(move |(_, chunk)| chunk.chunk_type() == &self.chunk_type)
+            .filter_map(move |(index, chunk)| {
+                let (chunk_key, value) = split_key(chunk.data())?;
+                (chunk_key == key.as_bytes()).then_some((index, value))
+            })
+    }
+
+    /// Store `value` under `key`, replacing the previous value in place. New keys go before IEND.
+    pub fn put(&mut self, key: &str, value: &[u8]) -> Result<()> {
+        check_key(key)?;
+        let prefix_len = key.len() + 1;
+        let room = self.max_chunk_size.checked_sub(prefix_len).ok_or_else(|| {
+            anyhow!(
+                "Key {:?} leaves no room for a value in chunks of {} bytes",
+                key,
+                self.max_chunk_size
+            )
+        })?;
+        let chunks = split::split_payload(&self.chunk_type, value, room, None)?
+            .into_iter()
+            .map(|chunk| {
+                let mut data = Vec::with_capacity(prefix_len + chunk.data().len());
+                data.extend_from_slice(key.as_bytes());
+                data.push(0);
+                data.extend_from_slice(chunk.data());
+                Chunk::try_new(self.chunk_type.clone(), data)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let index = match self.remove_key(key) {
+            Some(index) => index,
+            None if self.png.ends_with_iend() => self.png.chunks().len() - 1,
+            None => self.png.chunks().len(),
+        };
+        for (offset, chunk) in chunks.into_iter().enumerate() {
+            self.png.insert_chunk(index + offset, chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Value stored under `key`, `Ok(None)` when there is none
+    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        split::join_parts(self.parts(key).map(|(_, value)| value), None)
+            .map_err(|e| anyhow!("Value of {:?}: {}", key, e))
+    }
+
+    /// Remove the value stored under `key`. Returns whether there was one.
+    pub fn delete(&mut self, key: &str) -> bool {
+        self.remove_key(key).is_some()
+    }
+
+    /// Remove every chunk of a key, returns the index of the first one
+    fn remove_key(&mut self, key: &str) -> Option<usize> {
+        let indices: Vec<usize> = self.parts(key).map(|(index, _)| index).collect();
+        for index in indices.iter().rev() {
+            // Indices were just taken from the png
+            self.png.remove_chunk_at(*index).unwrap();
+        }
+        indices.first().copied()
+    }
+
+    /// Every key, in the order they first appear
+    pub fn list(&self) -> Vec<String> {
+        let mut keys: Vec<String> = Vec::new();
+        for chunk in self.png.chunks() {
+            if chunk.chunk_type() != &self.chunk_type {
+                continue;
+            }
+            let key =
+                split_key(chunk.data()).and_then(|(key, _)| String::from_utf8(key.to_vec()).ok());
+            if let Some(key) = key {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+        keys
+    }
+}
+
+fn split_key(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let separator = data.iter().position(|byte| *byte == 0)?;
+    Some((&data[..separator], &data[separator + 1..]))
+}
+
+fn check_key(key: &str) -> Result<()> {
+    if key.is_empty() {
+        return Err(anyhow!("Key must not be empty"));
+    }
+    if key.contains('\0') {
+        return Err(anyhow!("Key {:?} must not contain a null character", key));
+    }
+    Ok(())
+}
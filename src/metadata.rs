@@ -0,0 +1,519 @@
+#![allow(dead_code)]
+//! # Metadata
+//! Typed representation of the standard chunks describing the image itself, as opposed to the
+//! textual chunks in [text](crate::text).
+//!
+//! See [Chunk specifications](http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html)
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::PNG;
+use anyhow::{anyhow, Result};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Metres per inch, pHYs counts pixels per metre
+const METRES_PER_INCH: f64 = 0.0254;
+
+/// Values of [ImageHeader::color_type]
+pub const COLOR_TYPE_GRAYSCALE: u8 = 0;
+pub const COLOR_TYPE_TRUECOLOR: u8 = 2;
+pub const COLOR_TYPE_INDEXED: u8 = 3;
+pub const COLOR_TYPE_GRAYSCALE_ALPHA: u8 = 4;
+pub const COLOR_TYPE_TRUECOLOR_ALPHA: u8 = 6;
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn check_chunk(chunk: &Chunk, chunk_type: &[u8; 4], length: usize) -> Result<()> {
+    if &chunk.chunk_type().bytes() != chunk_type {
+        return Err(anyhow!(
+            "{} is not a {} chunk",
+            chunk.chunk_type(),
+            String::from_utf8_lossy(chunk_type)
+        ));
+    }
+    if chunk.data().len() != length {
+        return Err(anyhow!(
+            "{} chunk must be {} bytes long, found {}",
+            chunk.chunk_type(),
+            length,
+            chunk.data().len()
+        ));
+    }
+    Ok(())
+}
+
+/// The `IHDR` chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageHeader {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u8,
+    pub color_type: u8,
+    pub compression_method: u8,
+    pub filter_method: u8,
+    pub interlace_method: u8,
+}
+
+impl ImageHeader {
+    pub const CHUNK_TYPE: [u8; 4] = *b"IHDR";
+
+    /// Human readable name of the color type
+    pub fn color_type_name(&self) -> &'static str {
+        match self.color_type {
+            COLOR_TYPE_GRAYSCALE => "grayscale",
+            COLOR_TYPE_TRUECOLOR => "truecolor",
+            COLOR_TYPE_INDEXED => "indexed",
+            COLOR_TYPE_GRAYSCALE_ALPHA => "grayscale with alpha",
+            COLOR_TYPE_TRUECOLOR_ALPHA => "truecolor with alpha",
+            _ => "unknown",
+        }
+    }
+
+    /// Samples per pixel, `None` for an unknown color type
+    pub fn channels(&self) -> Option<u8> {
+        match self.color_type {
+            COLOR_TYPE_GRAYSCALE | COLOR_TYPE_INDEXED => Some(1),
+            COLOR_TYPE_GRAYSCALE_ALPHA => Some(2),
+            COLOR_TYPE_TRUECOLOR => Some(3),
+            COLOR_TYPE_TRUECOLOR_ALPHA => Some(4),
+            _ => None,
+        }
+    }
+
+    pub fn to_chunk(self) -> Chunk {
+        let mut data = Vec::with_capacity(13);
+        data.extend_from_slice(&self.width.to_be_bytes());
+        data.extend_from_slice(&self.height.to_be_bytes());
+        data.extend_from_slice(&[
+            self.bit_depth,
+            self.color_type,
+            self.compression_method,
+            self.filter_method,
+            self.interlace_method,
+        ]);
+        Chunk::new(
+            ChunkType {
+                inner: Self::CHUNK_TYPE,
+            },
+            data,
+        )
+    }
+}
+
+impl TryFrom<&Chunk> for ImageHeader {
+    type Error = anyhow::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        check_chunk(chunk, &Self::CHUNK_TYPE, 13)?;
+        let data = chunk.data();
+        Ok(Self {
+            width: read_u32(data, 0),
+            height: read_u32(data, 4),
+            bit_depth: data[8],
+            color_type: data[9],
+            compression_method: data[10],
+            filter_method: data[11],
+            interlace_method: data[12],
+        })
+    }
+}
+
+/// Unit of [PhysicalDimensions]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhysicalUnit {
+    /// Only the aspect ratio is known
+    Unknown,
+    Metre,
+}
+
+/// The `pHYs` chunk, intended pixel size or aspect ratio
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhysicalDimensions {
+    pub pixels_per_unit_x: u32,
+    pub pixels_per_unit_y: u32,
+    pub unit: PhysicalUnit,
+}
+
+impl PhysicalDimensions {
+    pub const CHUNK_TYPE: [u8; 4] = *b"pHYs";
+
+    /// Dimensions for given horizontal and vertical dots per inch
+    pub fn from_dpi(dpi: Dpi) -> Self {
+        let to_metre = |dpi: f64| (dpi / METRES_PER_INCH).round() as u32;
+        Self {
+            pixels_per_unit_x: to_metre(dpi.x),
+            pixels_per_unit_y: to_metre(dpi.y),
+            unit: PhysicalUnit::Metre,
+        }
+    }
+
+    /// Dots per inch, `None` when the unit is unknown
+    pub fn dpi(&self) -> Option<Dpi> {
+        match self.unit {
+            PhysicalUnit::Unknown => None,
+            PhysicalUnit::Metre => Some(Dpi {
+                x: self.pixels_per_unit_x as f64 * METRES_PER_INCH,
+                y: self.pixels_per_unit_y as f64 * METRES_PER_INCH,
+            }),
+        }
+    }
+
+    pub fn to_chunk(self) -> Chunk {
+        let mut data = Vec::with_capacity(9);
+        data.extend_from_slice(&self.pixels_per_unit_x.to_be_bytes());
+        data.extend_from_slice(&self.pixels_per_unit_y.to_be_bytes());
+        data.push(match self.unit {
+            PhysicalUnit::Unknown => 0,
+            PhysicalUnit::Metre => 1,
+        });
+        Chunk::new(
+            ChunkType {
+                inner: Self::CHUNK_TYPE,
+            },
+            data,
+        )
+    }
+}
+
+impl TryFrom<&Chunk> for PhysicalDimensions {
+    type Error = anyhow::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        check_chunk(chunk, &Self::CHUNK_TYPE, 9)?;
+        let data = chunk.data();
+        let unit = match data[8] {
+            0 => PhysicalUnit::Unknown,
+            1 => PhysicalUnit::Metre,
+            other => return Err(anyhow!("Unknown pHYs unit {}", other)),
+        };
+        Ok(Self {
+            pixels_per_unit_x: read_u32(data, 0),
+            pixels_per_unit_y: read_u32(data, 4),
+            unit,
+        })
+    }
+}
+
+/// Horizontal and vertical dots per inch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dpi {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl FromStr for Dpi {
+    type Err = anyhow::Error;
+
+    /// Parse `300` for square pixels or `300x150` for different horizontal and vertical values
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |value: &str| -> Result<f64> {
+            let dpi = value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| anyhow!("{:?} is not a number", value))?;
+            if !(dpi > 0.0 && dpi.is_finite()) {
+                return Err(anyhow!("DPI must be a positive number, found {}", value));
+            }
+            Ok(dpi)
+        };
+        match s.split_once(['x', 'X']) {
+            Some((x, y)) => Ok(Self {
+                x: parse(x)?,
+                y: parse(y)?,
+            }),
+            None => {
+                let dpi = parse(s)?;
+                Ok(Self { x: dpi, y: dpi })
+            }
+        }
+    }
+}
+
+impl Display for Dpi {
+    /// Rounded to whole dots, pHYs can't store most DPI values exactly
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.x.round() == self.y.round() {
+            write!(f, "{:.0} dpi", self.x)
+        } else {
+            write!(f, "{:.0}x{:.0} dpi", self.x, self.y)
+        }
+    }
+}
+
+/// The `tIME` chunk, time of the last image modification in UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LastModified {
+    pub year: u16,
+    /// 1 to 12
+    pub month: u8,
+    /// 1 to 31
+    pub day: u8,
+    /// 0 to 23
+    pub hour: u8,
+    /// 0 to 59
+    pub minute: u8,
+    /// 0 to 60, to allow for leap seconds
+    pub second: u8,
+}
+
+impl LastModified {
+    pub const CHUNK_TYPE: [u8; 4] = *b"tIME";
+
+    /// Current system time
+    pub fn now() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        Self::from_unix_seconds(seconds)
+    }
+
+    pub fn from_unix_seconds(seconds: u64) -> Self {
+        let days = (seconds / 86400) as i64;
+        let seconds_of_day = seconds % 86400;
+        // Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let day_of_era = z.rem_euclid(146097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (seconds_of_day / 3600) as u8,
+            minute: (seconds_of_day / 60 % 60) as u8,
+            second: (seconds_of_day % 60) as u8,
+        }
+    }
+
+    pub fn to_chunk(self) -> Chunk {
+        let mut data = self.year.to_be_bytes().to_vec();
+        data.extend_from_slice(&[self.month, self.day, self.hour, self.minute, self.second]);
+        Chunk::new(
+            ChunkType {
+                inner: Self::CHUNK_TYPE,
+            },
+            data,
+        )
+    }
+}
+
+impl TryFrom<&Chunk> for LastModified {
+    type Error = anyhow::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        check_chunk(chunk, &Self::CHUNK_TYPE, 7)?;
+        let data = chunk.data();
+        let time = Self {
+            year: u16::from_be_bytes([data[0], data[1]]),
+            month: data[2],
+            day: data[3],
+            hour: data[4],
+            minute: data[5],
+            second: data[6],
+        };
+        if !(1..=12).contains(&time.month)
+            || !(1..=31).contains(&time.day)
+            || time.hour > 23
+            || time.minute > 59
+            || time.second > 60
+        {
+            return Err(anyhow!("tIME holds an invalid date {}", time));
+        }
+        Ok(time)
+    }
+}
+
+impl Display for LastModified {
+    /// ISO 8601 in UTC
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// Record given modification time, replacing the existing tIME chunk in place or adding one
+/// before IEND. The spec puts no constraint on where tIME goes.
+pub fn touch_time(png: &mut PNG, time: LastModified) -> Result<()> {
+    match png.position_of("tIME", 0)? {
+        Some(index) => {
+            png.replace_chunk(index, time.to_chunk())?;
+        }
+        None => png.append_chunk(time.to_chunk()),
+    }
+    Ok(())
+}
+
+fn rgb_bytes(red: u16, green: u16, blue: u16) -> Vec<u8> {
+    [red, green, blue]
+        .iter()
+        .flat_map(|sample| sample.to_be_bytes())
+        .collect()
+}
+
+/// The `bKGD` chunk, default background color. Its layout depends on the image color type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Background {
+    /// Index into the palette, for indexed images
+    PaletteIndex(u8),
+    /// Gray level, for grayscale images with or without alpha
+    Gray(u16),
+    /// Red, green and blue samples, for truecolor images with or without alpha
+    Rgb(u16, u16, u16),
+}
+
+impl Background {
+    pub const CHUNK_TYPE: [u8; 4] = *b"bKGD";
+
+    /// Parse a bKGD chunk of an image with given color type
+    pub fn from_chunk(chunk: &Chunk, color_type: u8) -> Result<Self> {
+        match color_type {
+            COLOR_TYPE_INDEXED => {
+                check_chunk(chunk, &Self::CHUNK_TYPE, 1)?;
+                Ok(Background::PaletteIndex(chunk.data()[0]))
+            }
+            COLOR_TYPE_GRAYSCALE | COLOR_TYPE_GRAYSCALE_ALPHA => {
+                check_chunk(chunk, &Self::CHUNK_TYPE, 2)?;
+                Ok(Background::Gray(read_u16(chunk.data(), 0)))
+            }
+            COLOR_TYPE_TRUECOLOR | COLOR_TYPE_TRUECOLOR_ALPHA => {
+                check_chunk(chunk, &Self::CHUNK_TYPE, 6)?;
+                let data = chunk.data();
+                Ok(Background::Rgb(
+                    read_u16(data, 0),
+                    read_u16(data, 2),
+                    read_u16(data, 4),
+                ))
+            }
+            other => Err(anyhow!("Unknown color type {}", other)),
+        }
+    }
+
+    /// Tells whether this background can be stored in an image of given color type
+    pub fn fits(&self, color_type: u8) -> bool {
+        matches!(
+            (self, color_type),
+            (Background::PaletteIndex(_), COLOR_TYPE_INDEXED)
+                | (
+                    Background::Gray(_),
+                    COLOR_TYPE_GRAYSCALE | COLOR_TYPE_GRAYSCALE_ALPHA
+                )
+                | (
+                    Background::Rgb(..),
+                    COLOR_TYPE_TRUECOLOR | COLOR_TYPE_TRUECOLOR_ALPHA
+                )
+        )
+    }
+
+    pub fn to_chunk(self) -> Chunk {
+        let data = match self {
+            Background::PaletteIndex(index) => vec![index],
+            Background::Gray(gray) => gray.to_be_bytes().to_vec(),
+            Background::Rgb(red, green, blue) => rgb_bytes(red, green, blue),
+        };
+        Chunk::new(
+            ChunkType {
+                inner: Self::CHUNK_TYPE,
+            },
+            data,
+        )
+    }
+}
+
+/// The `tRNS` chunk, simple transparency for images without an alpha channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transparency {
+    /// Alpha of each palette entry in order, entries past the end are opaque
+    PaletteAlpha(Vec<u8>),
+    /// Gray level drawn fully transparent
+    Gray(u16),
+    /// Color drawn fully transparent
+    Rgb(u16, u16, u16),
+}
+
+impl Transparency {
+    pub const CHUNK_TYPE: [u8; 4] = *b"tRNS";
+
+    /// Parse a tRNS chunk of an image with given color type. Images with an alpha channel must
+    /// not have one.
+    pub fn from_chunk(chunk: &Chunk, color_type: u8) -> Result<Self> {
+        match color_type {
+            COLOR_TYPE_INDEXED => {
+                check_chunk(chunk, &Self::CHUNK_TYPE, chunk.data().len())?;
+                if chunk.data().len() > 256 {
+                    return Err(anyhow!(
+                        "tRNS holds {} alpha values, a palette has at most 256 entries",
+                        chunk.data().len()
+                    ));
+                }
+                Ok(Transparency::PaletteAlpha(chunk.data().to_vec()))
+            }
+            COLOR_TYPE_GRAYSCALE => {
+                check_chunk(chunk, &Self::CHUNK_TYPE, 2)?;
+                Ok(Transparency::Gray(read_u16(chunk.data(), 0)))
+            }
+            COLOR_TYPE_TRUECOLOR => {
+                check_chunk(chunk, &Self::CHUNK_TYPE, 6)?;
+                let data = chunk.data();
+                Ok(Transparency::Rgb(
+                    read_u16(data, 0),
+                    read_u16(data, 2),
+                    read_u16(data, 4),
+                ))
+            }
+            COLOR_TYPE_GRAYSCALE_ALPHA | COLOR_TYPE_TRUECOLOR_ALPHA => Err(anyhow!(
+                "tRNS is not allowed in images with an alpha channel"
+            )),
+            other => Err(anyhow!("Unknown color type {}", other)),
+        }
+    }
+
+    /// Tells whether this transparency can be stored in an image of given color type
+    pub fn fits(&self, color_type: u8) -> bool {
+        matches!(
+            (self, color_type),
+            (Transparency::PaletteAlpha(_), COLOR_TYPE_INDEXED)
+                | (Transparency::Gray(_), COLOR_TYPE_GRAYSCALE)
+                | (Transparency::Rgb(..), COLOR_TYPE_TRUECOLOR)
+        )
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = match self {
+            Transparency::PaletteAlpha(alpha) => alpha.clone(),
+            Transparency::Gray(gray) => gray.to_be_bytes().to_vec(),
+            Transparency::Rgb(red, green, blue) => rgb_bytes(*red, *green, *blue),
+        };
+        Chunk::new(
+            ChunkType {
+                inner: Self::CHUNK_TYPE,
+            },
+            data,
+        )
+    }
+}
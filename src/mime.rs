@@ -0,0 +1,477 @@
+#![allow(dead_code)]
+//! # Mime
+//! Messages tagged with a MIME type, so decode knows how to show them.
+//!
+//! A typed message starts with a `msgmime:<type>\n` header followed by the message itself. The
+//! header goes around the message before it is stored or split, so it is found again once a
+//! payload is resolved and joined. Messages without a header are shown as UTF-8 text like before.
+use anyhow::{anyhow, Result};
+use std::fmt::{Display, Formatter, Write as _};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+
+/// Every typed message starts with this prefix
+pub const PREFIX: &str = "msgmime:";
+
+/// Longest header accepted, prefix and line break included
+pub const MAX_HEADER_LEN: usize = 256;
+
+/// Nesting deeper than this is not pretty-printed, it would only take stack
+const MAX_JSON_DEPTH: usize = 256;
+
+/// Bytes shown per line of a hex dump
+const HEX_DUMP_WIDTH: usize = 16;
+
+/// A MIME type like `application/json` or `text/plain; charset=utf-8`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MimeType(String);
+
+impl MimeType {
+    pub const OCTET_STREAM: &'static str = "application/octet-stream";
+
+    /// Type and subtype in lowercase, without parameters
+    pub fn essence(&self) -> String {
+        let essence = self.0.split(';').next().unwrap_or_default();
+        essence.trim().to_ascii_lowercase()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+fn is_token(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c))
+}
+
+impl FromStr for MimeType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let essence = s.split(';').next().unwrap_or_default().trim();
+        let valid = match essence.split_once('/') {
+            Some((kind, subtype)) => is_token(kind) && is_token(subtype),
+            None => false,
+        };
+        if !valid {
+            return Err(anyhow!(
+                "{:?} is not a MIME type like \"application/json\"",
+                s
+            ));
+        }
+        if s.chars().any(|c| c.is_control() || !c.is_ascii()) {
+            return Err(anyhow!("MIME type {:?} must be printable ASCII", s));
+        }
+        if PREFIX.len() + s.len() + 1 > MAX_HEADER_LEN {
+            return Err(anyhow!("MIME type {:?} is too long", s));
+        }
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl Display for MimeType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Prefix `message` with a header recording its type
+pub fn wrap(mime: &MimeType, message: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(PREFIX.len() + mime.0.len() + 1 + message.len());
+    data.extend_from_slice(PREFIX.as_bytes());
+    data.extend_from_slice(mime.0.as_bytes());
+    data.push(b'\n');
+    data.extend_from_slice(message);
+    data
+}
+
+/// Tells whether given message data starts with a type header
+pub fn is_typed(data: &[u8]) -> bool {
+    data.starts_with(PREFIX.as_bytes())
+}
+
+/// Split message data into its type and the message itself. Untyped data is returned as is.
+pub fn unwrap(data: &[u8]) -> Result<(Option<MimeType>, &[u8])> {
+    if !is_typed(data) {
+        return Ok((None, data));
+    }
+    let header_end = data
+        .iter()
+        .take(MAX_HEADER_LEN)
+        .position(|byte| *byte == b'\n')
+        .ok_or_else(|| anyhow!("MIME type header is not terminated"))?;
+    let mime = std::str::from_utf8(&data[PREFIX.len()..header_end])?.parse()?;
+    Ok((Some(mime), &data[header_end + 1..]))
+}
+
+/// How a message is shown on the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rendering {
+    /// Re-indented, 2 spaces per level
+    Json,
+    /// Printed as it is, YAML is already laid out for reading
+    Yaml,
+    Text,
+    /// Offsets, bytes in hex and their ASCII form
+    HexDump,
+}
+
+impl Rendering {
+    pub fn for_type(mime: &MimeType) -> Self {
+        let essence = mime.essence();
+        let (kind, subtype) = essence.split_once('/').unwrap_or_default();
+        match (kind, subtype) {
+            (_, "json") => Rendering::Json,
+            (_, subtype) if subtype.ends_with("+json") => Rendering::Json,
+            (_, "yaml" | "x-yaml") => Rendering::Yaml,
+            (_, subtype) if subtype.ends_with("+yaml") => Rendering::Yaml,
+            ("text", _) => Rendering::Text,
+            (_, "xml") => Rendering::Text,
+            (_, subtype) if subtype.ends_with("+xml") => Rendering::Text,
+            _ => Rendering::HexDump,
+        }
+    }
+}
+
+/// Message as shown by decode. JSON that does not parse is shown as plain text, text that is not
+/// UTF-8 as a hex dump.
+pub fn render(mime: &MimeType, message: &[u8]) -> String {
+    let rendering = Rendering::for_type(mime);
+    let text = match std::str::from_utf8(message) {
+        Ok(text) if rendering != Rendering::HexDump => text,
+        _ => return hex_dump(message),
+    };
+    match rendering {
+        Rendering::Json => pretty_json(text).unwrap_or_else(|_| text.to_string()),
+        _ => text.to_string(),
+    }
+}
+
+/// `xxd` like dump: offset, up to 16 bytes in hex, then the printable ones
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let mut dump = String::new();
+    for (line, row) in bytes.chunks(HEX_DUMP_WIDTH).enumerate() {
+        // Writing to a String can't fail
+        write!(dump, "{:08x} ", line * HEX_DUMP_WIDTH).unwrap();
+        for column in 0..HEX_DUMP_WIDTH {
+            if column % 8 == 0 {
+                dump.push(' ');
+            }
+            match row.get(column) {
+                Some(byte) => write!(dump, "{:02x} ", byte).unwrap(),
+                None => dump.push_str("   "),
+            }
+        }
+        dump.push_str(" |");
+        dump.extend(row.iter().map(|byte| match byte {
+            0x20..=0x7e => *byte as char,
+            _ => '.',
+        }));
+        dump.push_str("|\n");
+    }
+    dump
+}
+
+struct JsonPrinter<'a> {
+    text: &'a str,
+    position: usize,
+    pretty: String,
+}
+
+impl<'a> JsonPrinter<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<()> {
+        self.skip_whitespace();
+        if self.peek() != Some(byte) {
+            return Err(anyhow!(
+                "expected {:?} at byte {}",
+                byte as char,
+                self.position
+            ));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn new_line(&mut self, depth: usize) {
+        self.pretty.push('\n');
+        self.pretty.push_str(&"  ".repeat(depth));
+    }
+
+    fn value(&mut self, depth: usize) -> Result<()> {
+        if depth > MAX_JSON_DEPTH {
+            return Err(anyhow!("JSON nested deeper than {} levels", MAX_JSON_DEPTH));
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.container(depth, b'}', true),
+            Some(b'[') => self.container(depth, b']', false),
+            Some(b'"') => self.string(),
+            Some(b't') => self.literal("true"),
+            Some(b'f') => self.literal("false"),
+            Some(b'n') => self.literal("null"),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(anyhow!("expected a JSON value at byte {}", self.position)),
+        }
+    }
+
+    /// Object when `keyed`, array otherwise
+    fn container(&mut self, depth: usize, close: u8, keyed: bool) -> Result<()> {
+        self.pretty
+            .push(self.text.as_bytes()[self.position] as char);
+        self.position += 1;
+        self.skip_whitespace();
+        if self.peek() == Some(close) {
+            self.position += 1;
+            self.pretty.push(close as char);
+            return Ok(());
+        }
+        loop {
+            self.new_line(depth + 1);
+            if keyed {
+                self.skip_whitespace();
+                if self.peek() != Some(b'"') {
+                    return Err(anyhow!("expected a key at byte {}", self.position));
+                }
+                self.string()?;
+                self.expect(b':')?;
+                self.pretty.push_str(": ");
+            }
+            self.value(depth + 1)?;
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => {
+                    self.position += 1;
+                    self.pretty.push(',');
+                }
+                Some(byte) if byte == close => {
+                    self.position += 1;
+                    break;
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "expected ',' or {:?} at byte {}",
+                        close as char,
+                        self.position
+                    ))
+                }
+            }
+        }
+        self.new_line(depth);
+        self.pretty.push(close as char);
+        Ok(())
+    }
+
+    fn string(&mut self) -> Result<()> {
+        let start = self.position;
+        self.position += 1;
+        loop {
+            match self.peek() {
+                None => return Err(anyhow!("string starting at byte {} is not closed", start)),
+                Some(b'"') => break,
+                Some(b'\\') => self.position += 2,
+                Some(0..=0x1f) => {
+                    return Err(anyhow!(
+                        "control character in string at byte {}",
+                        self.position
+                    ))
+                }
+                Some(_) => self.position += 1,
+            }
+        }
+        self.position += 1;
+        self.pretty.push_str(&self.text[start..self.position]);
+        Ok(())
+    }
+
+    fn literal(&mut self, literal: &str) -> Result<()> {
+        if !self.text[self.position..].starts_with(literal) {
+            return Err(anyhow!("expected {} at byte {}", literal, self.position));
+        }
+        self.position += literal.len();
+        self.pretty.push_str(literal);
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<()> {
+        let start = self.position;
+        while matches!(
+            self.peek(),
+            Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+        ) {
+            self.position += 1;
+        }
+        let number = &self.text[start..self.position];
+        number
+            .parse::<f64>()
+            .map_err(|_| anyhow!("malformed number {:?} at byte {}", number, start))?;
+        self.pretty.push_str(number);
+        Ok(())
+    }
+}
+
+/// Re-indent a JSON document with 2 spaces per level. Strings and numbers are kept as written.
+pub fn pretty_json(text: &str) -> Result<String> {
+    let mut printer = JsonPrinter {
+        text,
+        position: 0,
+        pretty: String::with_capacity(text.len()),
+    };
+    printer.value(0)?;
+    printer.skip_whitespace();
+    if printer.position != text.len() {
+        return Err(anyhow!("trailing data at byte {}", printer.position));
+    }
+    Ok(printer.pretty)
+}
+
+/// File name extension the OS uses to pick an application for given type
+pub fn extension(mime: &MimeType) -> &'static str {
+    match mime.essence().as_str() {
+        "application/json" => "json",
+        "application/yaml" | "application/x-yaml" | "text/yaml" => "yaml",
+        "application/xml" | "text/xml" => "xml",
+        "application/pdf" => "pdf",
+        "application/zip" => "zip",
+        "application/gzip" => "gz",
+        "text/plain" => "txt",
+        "text/html" => "html",
+        "text/csv" => "csv",
+        "text/markdown" => "md",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/svg+xml" => "svg",
+        "audio/mpeg" => "mp3",
+        "video/mp4" => "mp4",
+        _ => "bin",
+    }
+}
+
+/// Command opening a file with the default application of the OS
+fn opener(path: &Path) -> Command {
+    if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]).arg(path);
+        command
+    } else {
+        let program = if cfg!(target_os = "macos") {
+            "open"
+        } else {
+            "xdg-open"
+        };
+        let mut command = Command::new(program);
+        command.arg(path);
+        command
+    }
+}
+
+/// Write `message` to a temporary file named after its type and open it with the default
+/// application. Returns the temporary file, left in place for the application to read. It is
+/// removed when the application can't be started.
+pub fn open(mime: &MimeType, message: &[u8]) -> Result<PathBuf> {
+    let digest = crate::sha256::to_hex(&crate::sha256::sha256(message));
+    let path =
+        std::env::temp_dir().join(format!("msg-in-png-{}.{}", &digest[..16], extension(mime)));
+    // Only the user running the command may read the message, an earlier copy is replaced
+    let _ = std::fs::remove_file(&path);
+    crate::permissions::create_private(&path)?.write_all(message)?;
+    let opened = match opener(&path).status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(anyhow!("Opening {:?} failed with {}", path, status)),
+        Err(e) => Err(anyhow!(
+            "Could not start {:?}: {}",
+            opener(&path).get_program(),
+            e
+        )),
+    };
+    if let Err(e) = opened {
+        std::fs::remove_file(&path)?;
+        return Err(e);
+    }
+    Ok(path)
+}
+
+/// Writer dropping the type header from the start of a message, for streaming a typed message to
+/// a file. Untyped messages pass through unchanged.
+pub struct StripHeader<W: Write> {
+    inner: W,
+    /// Start of the message while it may still be a header
+    pending: Option<Vec<u8>>,
+    mime: Option<MimeType>,
+    header_len: usize,
+}
+
+impl<W: Write> StripHeader<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            pending: Some(Vec::new()),
+            mime: None,
+            header_len: 0,
+        }
+    }
+
+    /// Type found in the header, once it was written through
+    pub fn mime(&self) -> Option<&MimeType> {
+        self.mime.as_ref()
+    }
+
+    /// Bytes dropped from the start of the message
+    pub fn header_len(&self) -> usize {
+        self.header_len
+    }
+
+    /// Write out what was held back while looking for a header and flush
+    pub fn finish(&mut self) -> std::io::Result<()> {
+        if let Some(pending) = self.pending.take() {
+            self.inner.write_all(&pending)?;
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Write for StripHeader<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let pending = match self.pending.as_mut() {
+            Some(pending) => pending,
+            None => return self.inner.write(buf),
+        };
+        pending.extend_from_slice(buf);
+        let prefix = PREFIX.as_bytes();
+        let compared = pending.len().min(prefix.len());
+        if pending[..compared] != prefix[..compared] {
+            let pending = self.pending.take().unwrap_or_default();
+            self.inner.write_all(&pending)?;
+            return Ok(buf.len());
+        }
+        if pending.len() < MAX_HEADER_LEN && !pending.contains(&b'\n') {
+            return Ok(buf.len());
+        }
+        let pending = self.pending.take().unwrap_or_default();
+        let (mime, message) = unwrap(&pending)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+        self.header_len = pending.len() - message.len();
+        self.mime = mime;
+        self.inner.write_all(message)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,217 @@
+#![allow(dead_code)]
+//! # Ordering
+//! Placement rules for custom chunk types, like "must precede IDAT" or "must be unique".
+//!
+//! Rules attached to a png with [set_ordering_rules](PNG::set_ordering_rules) are enforced by
+//! [insert_chunk](PNG::insert_chunk) and [try_append_chunk](PNG::try_append_chunk), and reported
+//! by [verify](crate::verify::verify). Appending a chunk that must precede PLTE or IDAT puts it
+//! right before them instead of before IEND.
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::verify::Issue;
+use anyhow::{anyhow, Result};
+use std::str::FromStr;
+
+/// Where chunks of a type must be relative to the image data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    /// Before PLTE and every IDAT, like sBIT or gAMA
+    BeforePlte,
+    /// Before every IDAT, like pHYs or bKGD
+    BeforeIdat,
+    /// After every IDAT
+    AfterIdat,
+}
+
+impl FromStr for Position {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "before_plte" => Ok(Position::BeforePlte),
+            "before_idat" => Ok(Position::BeforeIdat),
+            "after_idat" => Ok(Position::AfterIdat),
+            _ => Err(anyhow!(
+                "unknown position {:?}, use \"before_plte\", \"before_idat\" or \"after_idat\"",
+                s
+            )),
+        }
+    }
+}
+
+impl Position {
+    /// Tells whether the chunk at `index` of a sequence of chunk types is where it must be
+    pub fn holds(&self, types: &[&ChunkType], index: usize) -> bool {
+        let is_idat = |chunk_type: &&&ChunkType| &chunk_type.bytes() == b"IDAT";
+        match self {
+            Position::BeforePlte => !types[..index]
+                .iter()
+                .any(|earlier| is_idat(&earlier) || &earlier.bytes() == b"PLTE"),
+            Position::BeforeIdat => !types[..index].iter().any(|earlier| is_idat(&earlier)),
+            Position::AfterIdat => !types[index + 1..].iter().any(|later| is_idat(&later)),
+        }
+    }
+
+    /// Where chunks must be, completes "must come ..."
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Position::BeforePlte => "before PLTE and IDAT",
+            Position::BeforeIdat => "before IDAT",
+            Position::AfterIdat => "after IDAT",
+        }
+    }
+}
+
+/// Constraints on the chunks of one type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderingConstraint {
+    pub chunk_type: ChunkType,
+    pub position: Option<Position>,
+    /// At most one chunk of this type per png
+    pub unique: bool,
+}
+
+/// Every registered constraint, at most one per chunk type
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OrderingRules {
+    constraints: Vec<OrderingConstraint>,
+}
+
+impl OrderingRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.constraints.is_empty()
+    }
+
+    pub fn get(&self, chunk_type: &ChunkType) -> Option<&OrderingConstraint> {
+        self.constraints
+            .iter()
+            .find(|constraint| &constraint.chunk_type == chunk_type)
+    }
+
+    fn entry(&mut self, chunk_type: ChunkType) -> &mut OrderingConstraint {
+        let index = match self
+            .constraints
+            .iter()
+            .position(|constraint| constraint.chunk_type == chunk_type)
+        {
+            Some(index) => index,
+            None => {
+                self.constraints.push(OrderingConstraint {
+                    chunk_type,
+                    position: None,
+                    unique: false,
+                });
+                self.constraints.len() - 1
+            }
+        };
+        &mut self.constraints[index]
+    }
+
+    /// Require chunks of given type to be at `position`, replacing an earlier position
+    pub fn set_position(&mut self, chunk_type: ChunkType, position: Position) -> &mut Self {
+        self.entry(chunk_type).position = Some(position);
+        self
+    }
+
+    /// Allow at most one chunk of given type
+    pub fn set_unique(&mut self, chunk_type: ChunkType) -> &mut Self {
+        self.entry(chunk_type).unique = true;
+        self
+    }
+
+    /// Index and message of every broken constraint in a sequence of chunk types
+    fn violations(&self, types: &[&ChunkType]) -> Vec<(usize, String)> {
+        let mut violations = Vec::new();
+        if self.is_empty() {
+            return violations;
+        }
+        for (index, chunk_type) in types.iter().enumerate() {
+            let constraint = match self.get(chunk_type) {
+                Some(constraint) => constraint,
+                None => continue,
+            };
+            if let Some(position) = constraint
+                .position
+                .filter(|position| !position.holds(types, index))
+            {
+                violations.push((
+                    index,
+                    format!("{} must come {}", chunk_type, position.describe()),
+                ));
+            }
+            if constraint.unique && types[..index].contains(chunk_type) {
+                violations.push((
+                    index,
+                    format!("{} must be unique but appears again", chunk_type),
+                ));
+            }
+        }
+        violations
+    }
+
+    /// One error per broken constraint
+    pub fn validate(&self, chunks: &[Chunk]) -> Vec<Issue> {
+        let types: Vec<&ChunkType> = chunks.iter().map(|chunk| chunk.chunk_type()).collect();
+        self.violations(&types)
+            .into_iter()
+            .map(|(index, message)| Issue::error(Some(index), message))
+            .collect()
+    }
+
+    /// Fail when inserting a chunk of `chunk_type` at `index` breaks a constraint that holds
+    /// in `chunks`. Constraints `chunks` already breaks are not held against the insertion.
+    pub fn check_insert(
+        &self,
+        chunks: &[Chunk],
+        index: usize,
+        chunk_type: &ChunkType,
+    ) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let mut types: Vec<&ChunkType> = chunks.iter().map(|chunk| chunk.chunk_type()).collect();
+        let before: Vec<(usize, String)> = self
+            .violations(&types)
+            .into_iter()
+            .map(|(at, message)| (if at >= index { at + 1 } else { at }, message))
+            .collect();
+        types.insert(index, chunk_type);
+        match self
+            .violations(&types)
+            .into_iter()
+            .find(|violation| !before.contains(violation))
+        {
+            Some((_, message)) => Err(anyhow!(
+                "Can't insert {} at index {}: {}",
+                chunk_type,
+                index,
+                message
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Where [try_append_chunk](crate::png::PNG::try_append_chunk) puts a chunk of given type:
+    /// before the last chunk, or before PLTE or IDAT when its position asks for it.
+    pub fn append_index(&self, chunks: &[Chunk], chunk_type: &ChunkType) -> usize {
+        let last = chunks.len().saturating_sub(1);
+        let first_of = |names: &[&[u8; 4]]| {
+            chunks
+                .iter()
+                .position(|chunk| names.contains(&&chunk.chunk_type().bytes()))
+        };
+        let limit = match self
+            .get(chunk_type)
+            .and_then(|constraint| constraint.position)
+        {
+            Some(Position::BeforePlte) => first_of(&[b"PLTE", b"IDAT"]),
+            Some(Position::BeforeIdat) => first_of(&[b"IDAT"]),
+            _ => None,
+        };
+        limit.map_or(last, |limit| limit.min(last))
+    }
+}
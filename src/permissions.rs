@@ -0,0 +1,69 @@
+#![allow(dead_code)]
+//! # Permissions
+//! Access mode of the files this tool creates.
+//!
+//! Output files are created like any program would by default, `0666` reduced by the umask.
+//! When an explicit mode is asked for, the file gets exactly that mode whatever the umask, an
+//! existing file being replaced included. The file is opened with the mode already restricted
+//! so its content is never readable by more users than asked for, not even for a moment.
+//!
+//! Temporary copies of payloads, like a png spooled from stdin, are only readable by their owner.
+//!
+//! Modes only exist on unix, elsewhere files get the default permissions of the platform.
+use anyhow::Result;
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+/// Mode of temporary files holding payloads
+pub const PRIVATE_MODE: u32 = 0o600;
+
+/// Create or truncate `path` for writing, with exactly `mode` when given
+pub fn create(path: impl AsRef<Path>, mode: Option<u32>) -> Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    if let Some(mode) = mode {
+        restrict(&mut options, mode);
+    }
+    let file = options.open(path.as_ref())?;
+    if let Some(mode) = mode {
+        set_mode(&file, mode)?;
+    }
+    Ok(file)
+}
+
+/// Write `bytes` to `path` like [std::fs::write], with exactly `mode` when given
+pub fn write(path: impl AsRef<Path>, bytes: impl AsRef<[u8]>, mode: Option<u32>) -> Result<()> {
+    use std::io::Write;
+    create(path, mode)?.write_all(bytes.as_ref())?;
+    Ok(())
+}
+
+/// Create a new file only its owner can read and write. Fails when `path` exists, so a file or
+/// symbolic link planted in a shared temporary directory is never written through.
+pub fn create_private(path: &Path) -> Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    restrict(&mut options, PRIVATE_MODE);
+    Ok(options.open(path)?)
+}
+
+#[cfg(unix)]
+fn restrict(options: &mut OpenOptions, mode: u32) {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(mode);
+}
+
+#[cfg(not(unix))]
+fn restrict(_options: &mut OpenOptions, _mode: u32) {}
+
+#[cfg(unix)]
+fn set_mode(file: &File, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    file.set_permissions(std::fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_mode(_file: &File, _mode: u32) -> Result<()> {
+    Ok(())
+}
@@ -0,0 +1,252 @@
+#![allow(dead_code)]
+//! # Plan
+//! Tell in advance what embedding a payload of some size would do to a png, without the payload
+//! and without touching the file, so orchestration can pick a carrier before committing.
+//!
+//! Sizes are exact for payloads that do not themselves start like a split part, see
+//! [split_size](crate::split::split_size).
+use crate::chunk::MAX_LENGTH;
+use crate::chunk_type::ChunkType;
+use crate::digest::HashAlgorithm;
+use crate::png::PNG;
+use crate::split;
+use crate::store::PayloadRef;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+/// Placeholder standing for the payload in [Plan::command]
+pub const MESSAGE_PLACEHOLDER: &str = "<MESSAGE>";
+
+/// Length, type and CRC fields around chunk data
+const CHUNK_OVERHEAD: usize = 12;
+
+/// Size of a tIME chunk, data included
+const TIME_CHUNK_BYTES: usize = 7 + CHUNK_OVERHEAD;
+
+/// How the payload would be embedded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Method {
+    /// Payload in chunks of its own, split when larger than a chunk may be
+    Chunk,
+    /// Payload kept in a content addressed store, only a reference is embedded
+    Store(PathBuf),
+}
+
+impl Method {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Method::Chunk => "chunk",
+            Method::Store(_) => "store",
+        }
+    }
+}
+
+/// What would be embedded, mirroring the options of `encode`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanRequest {
+    pub png_path: PathBuf,
+    pub chunk_type: ChunkType,
+    pub payload_bytes: usize,
+    pub method: Method,
+    pub max_chunk_size: usize,
+    pub no_split: bool,
+    pub store_url: Option<String>,
+    /// Algorithm the payload reference is hashed with
+    pub hash: HashAlgorithm,
+    /// Whether writing the file sets its tIME chunk
+    pub touch_time: bool,
+    /// Largest file later commands accept, `None` when not limited
+    pub max_file_size: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    pub method: &'static str,
+    pub payload_bytes: usize,
+    pub fits: bool,
+    /// Why the payload does not fit, `None` when it does
+    pub reason: Option<String>,
+    /// Chunks that would be added for the payload
+    pub chunks: usize,
+    /// Bytes the png would grow by, every added chunk included
+    pub file_growth: usize,
+    pub file_size: usize,
+    pub new_file_size: usize,
+    /// Arguments of the `encode` call doing it, the payload replaced by [MESSAGE_PLACEHOLDER]
+    pub command: Vec<String>,
+}
+
+/// Work out the outcome of embedding `request` into `png`
+pub fn plan(png: &PNG, request: &PlanRequest) -> Plan {
+    let limit = request.max_chunk_size.min(MAX_LENGTH);
+    let data_bytes = match &request.method {
+        Method::Chunk => request.payload_bytes,
+        Method::Store(_) => {
+            let url = request.store_url.as_ref().map_or(0, |url| url.len() + 1);
+            PayloadRef::len_without_url(request.hash) + url
+        }
+    };
+    let mut reason = None;
+    let (chunks, chunk_data_bytes) = if request.no_split && data_bytes > limit {
+        reason = Some(format!(
+            "{} bytes do not fit a single chunk of at most {} bytes",
+            data_bytes, limit
+        ));
+        (0, 0)
+    } else {
+        match split::split_size(data_bytes, limit) {
+            Ok(size) => size,
+            Err(e) => {
+                reason = Some(e.to_string());
+                (0, 0)
+            }
+        }
+    };
+    let mut file_growth = chunk_data_bytes + chunks * CHUNK_OVERHEAD;
+    // Nothing is written when the payload can't be stored at all
+    if chunks > 0 {
+        if !png.ends_with_iend() {
+            file_growth += CHUNK_OVERHEAD;
+        }
+        if request.touch_time && png.chunk_by_type("tIME").is_none() {
+            file_growth += TIME_CHUNK_BYTES;
+        }
+    }
+    let file_size = png.byte_len();
+    let new_file_size = file_size + file_growth;
+    if let Some(max_file_size) = request.max_file_size {
+        if reason.is_none() && new_file_size as u64 > max_file_size {
+            reason = Some(format!(
+                "the file would be {} bytes, above --max-file-size of {} bytes",
+                new_file_size, max_file_size
+            ));
+        }
+    }
+    Plan {
+        method: request.method.name(),
+        payload_bytes: request.payload_bytes,
+        fits: reason.is_none(),
+        reason,
+        chunks,
+        file_growth,
+        file_size,
+        new_file_size,
+        command: command(png, request),
+    }
+}
+
+fn command(png: &PNG, request: &PlanRequest) -> Vec<String> {
+    let mut args: Vec<String> = vec![
+        env!("CARGO_PKG_NAME").to_string(),
+        "-p".to_string(),
+        request.png_path.display().to_string(),
+        "encode".to_string(),
+        request.chunk_type.to_string(),
+        MESSAGE_PLACEHOLDER.to_string(),
+    ];
+    if request.max_chunk_size < MAX_LENGTH {
+        args.push("--max-chunk-size".to_string());
+        args.push(request.max_chunk_size.to_string());
+    }
+    if request.no_split {
+        args.push("--no-split".to_string());
+    }
+    if let Method::Store(store) = &request.method {
+        args.push("--store".to_string());
+        args.push(store.display().to_string());
+        if let Some(url) = &request.store_url {
+            args.push("--store-url".to_string());
+            args.push(url.clone());
+        }
+        if request.hash != HashAlgorithm::default() {
+            args.push("--hash".to_string());
+            args.push(request.hash.to_string());
+        }
+    }
+    if !png.ends_with_iend() {
+        args.push("--repair-trailer".to_string());
+    }
+    if !request.touch_time {
+        args.push("--no-touch-time".to_string());
+    }
+    args
+}
+
+/// Quote an argument for a POSIX shell when it needs it. The payload placeholder is left as is
+/// to be replaced.
+fn shell_quote(arg: &str) -> String {
+    let plain = arg.chars().all(|c| {
+        c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | ':' | '=' | ',' | '+')
+    });
+    if plain || arg == MESSAGE_PLACEHOLDER {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// JSON string literal for `text`
+pub fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+impl Plan {
+    /// The command as one line for a POSIX shell
+    pub fn command_line(&self) -> String {
+        self.command
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"method\":\"{}\",\"payload_bytes\":{},\"fits\":{},\"reason\":{},\"chunks\":{},\
+             \"file_growth\":{},\"file_size\":{},\"new_file_size\":{},\"command\":[{}]}}",
+            self.method,
+            self.payload_bytes,
+            self.fits,
+            self.reason
+                .as_deref()
+                .map_or("null".to_string(), json_string),
+            self.chunks,
+            self.file_growth,
+            self.file_size,
+            self.new_file_size,
+            self.command
+                .iter()
+                .map(|arg| json_string(arg))
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+}
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Method:        {}", self.method)?;
+        match &self.reason {
+            None => writeln!(f, "Fits:          yes")?,
+            Some(reason) => writeln!(f, "Fits:          no, {}", reason)?,
+        }
+        writeln!(f, "Chunks:        {}", self.chunks)?;
+        writeln!(f, "File growth:   {} bytes", self.file_growth)?;
+        writeln!(f, "New file size: {} bytes", self.new_file_size)?;
+        writeln!(f, "Command:       {}", self.command_line())
+    }
+}
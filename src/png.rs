@@ -1,23 +1,109 @@
 #![allow(dead_code)]
 //! # PNG
 //! Png file structure according to its spec.
-use crate::chunk::Chunk;
+//!
+//! MNG and JNG files share the chunk layout and only differ by their signature and the chunks
+//! they start and end with, see [Format]. They are read and written by the same [PNG] type, so
+//! messages can be embedded in them the same way.
+use crate::chunk::{checked_length, Chunk};
 use crate::chunk_type::ChunkType;
+use crate::merge::{self, MergePolicy, MergeReport};
+use crate::metadata::{Background, ImageHeader, Transparency};
+use crate::ordering::OrderingRules;
+use crate::sha256::Sha256;
+use crate::signature;
+use crate::verify::{ChunkFailure, FieldError};
 use anyhow::{anyhow, Result};
 use std::fmt::{Display, Formatter};
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
 use std::str::FromStr;
 
+/// Chunks of a png at one point in time, see [snapshot](PNG::snapshot)
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    signature: [u8; 8],
+    chunks: Vec<Chunk>,
+}
+
+impl Snapshot {
+    pub fn chunks(&self) -> &[Chunk] {
+        self.chunks.as_slice()
+    }
+}
+
+/// File formats using the png chunk layout, told apart by their signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    /// Multiple-image Network Graphics, animations made of embedded png and jng images
+    Mng,
+    /// JPEG Network Graphics, a JPEG image with an optional alpha channel in png chunks
+    Jng,
+}
+
+impl Format {
+    pub fn from_signature(bytes: &[u8]) -> Option<Format> {
+        [Format::Png, Format::Mng, Format::Jng]
+            .into_iter()
+            .find(|format| bytes.starts_with(&format.signature()))
+    }
+
+    pub fn signature(&self) -> [u8; 8] {
+        match self {
+            Format::Png => PNG::STANDARD_HEADER,
+            Format::Mng => PNG::MNG_HEADER,
+            Format::Jng => PNG::JNG_HEADER,
+        }
+    }
+
+    /// Type of the chunk a file of this format must start with
+    pub fn header_type(&self) -> [u8; 4] {
+        match self {
+            Format::Png => *b"IHDR",
+            Format::Mng => *b"MHDR",
+            Format::Jng => *b"JHDR",
+        }
+    }
+
+    /// Type of the chunk a file of this format must end with
+    pub fn trailer_type(&self) -> [u8; 4] {
+        match self {
+            Format::Mng => *b"MEND",
+            Format::Png | Format::Jng => *b"IEND",
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Format::Png => write!(f, "png"),
+            Format::Mng => write!(f, "mng"),
+            Format::Jng => write!(f, "jng"),
+        }
+    }
+}
+
 /// The PNG file structure
+#[allow(clippy::upper_case_acronyms)]
 pub struct PNG {
-    /// Signature of a png file will always be `89 50 4E 47 0D 0A 1A 0A`
+    /// Signature of a png file will always be `89 50 4E 47 0D 0A 1A 0A`, MNG and JNG files have
+    /// their own
     signature: [u8; 8],
     /// A list of chunks, a valid png file must start with IHDR chunk and end with IEND chunk
     chunks: Vec<Chunk>,
+    /// Placement rules enforced when adding chunks, empty unless set by the user
+    ordering: OrderingRules,
 }
 
 impl PNG {
     pub const STANDARD_HEADER: [u8; 8] = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+    pub const MNG_HEADER: [u8; 8] = [0x8A, 0x4D, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+    pub const JNG_HEADER: [u8; 8] = [0x8B, 0x4A, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+
+    /// Chunks whose data changes on every edit, left out of the
+    /// [structural digest](PNG::structural_digest)
+    pub const VOLATILE_TYPES: [[u8; 4]; 1] = [*b"tIME"];
 
     /// Constructor for a png structure, be ware that this method does not check
     /// if given chunks are all valid. For example, "IHDR" and "IEND" chunk can appear anywhere in
@@ -26,104 +112,700 @@ impl PNG {
         PNG {
             signature: Self::STANDARD_HEADER,
             chunks,
+            ordering: OrderingRules::default(),
+        }
+    }
+
+    /// Like [from_chunks](PNG::from_chunks), with the signature of given format
+    pub fn from_chunks_with_format(chunks: Vec<Chunk>, format: Format) -> PNG {
+        PNG {
+            signature: format.signature(),
+            chunks,
+            ordering: OrderingRules::default(),
         }
     }
 
     /// Append chunk to the tail of the png but before the IEND chunk.
-    /// It assumes that the png being manipulated has IEND as its last chunk.
+    /// It assumes that the png being manipulated has IEND as its last chunk, check
+    /// [ends_with_iend](PNG::ends_with_iend) first when that is not guaranteed.
+    ///
+    /// # Panics
+    /// When the chunk breaks an [ordering rule](PNG::set_ordering_rules), use
+    /// [try_append_chunk](PNG::try_append_chunk) to get an error instead.
     pub fn append_chunk(&mut self, chunk: Chunk) {
-        if let Some(last_chunk) = self.chunks.pop() {
-            self.chunks.push(chunk);
-            self.chunks.push(last_chunk);
-        } else {
-            self.chunks.push(chunk);
+        self.try_append_chunk(chunk).unwrap();
+    }
+
+    /// Like [append_chunk](PNG::append_chunk), but chunks whose ordering rule asks to precede
+    /// PLTE or IDAT go right before them. Fails when the chunk breaks an ordering rule.
+    /// Returns the index the chunk was inserted at.
+    pub fn try_append_chunk(&mut self, chunk: Chunk) -> Result<usize> {
+        let index = self.ordering.append_index(&self.chunks, chunk.chunk_type());
+        self.insert_chunk(index, chunk)?;
+        Ok(index)
+    }
+
+    /// Rules [insert_chunk](PNG::insert_chunk) and [try_append_chunk](PNG::try_append_chunk)
+    /// enforce, and [verify](crate::verify::verify) reports. Chunks already in the png are not
+    /// checked when setting them.
+    pub fn set_ordering_rules(&mut self, rules: OrderingRules) {
+        self.ordering = rules;
+    }
+
+    pub fn ordering_rules(&self) -> &OrderingRules {
+        &self.ordering
+    }
+
+    /// Parse a fragment, a bare sequence of chunks without the png signature in front of them.
+    /// Like [try_from](PNG::try_from), no assumption is made about which chunks it contains.
+    pub fn from_fragment(bytes: &[u8]) -> Result<PNG> {
+        let mut reader = BufReader::new(bytes);
+        Ok(PNG::from_chunks(read_chunks(&mut reader)?))
+    }
+
+    /// Serialize chunks only, leaving out the signature. This is the counterpart of
+    /// [from_fragment](PNG::from_fragment).
+    pub fn fragment_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.byte_len() - self.signature.len());
+        // Writing to a Vec can't fail
+        self.write_fragment_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Write every chunk one at a time, leaving out the signature like
+    /// [fragment_bytes](PNG::fragment_bytes)
+    pub fn write_fragment_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        for chunk in self.chunks.iter() {
+            chunk.write_to(&mut writer)?;
         }
+        writer.flush()?;
+        Ok(())
     }
 
-    /// Removes the first chunk that matches given `chunk_type`
-    pub fn remove_chunk(&mut self, chunk_type: &str) -> Result<Chunk> {
-        let chunk_type = ChunkType::from_str(chunk_type)?;
-        let result = self
+    /// Push chunk to the very end, after IEND if there is one. Use
+    /// [append_chunk](PNG::append_chunk) to keep IEND last.
+    pub fn push_chunk(&mut self, chunk: Chunk) {
+        self.chunks.push(chunk);
+    }
+
+    /// Insert chunk at given index of [chunks](PNG::chunks), shifting every chunk after it.
+    /// Fails when the chunk would break an [ordering rule](PNG::set_ordering_rules).
+    pub fn insert_chunk(&mut self, index: usize, chunk: Chunk) -> Result<()> {
+        if index > self.chunks.len() {
+            return Err(anyhow!(
+                "Index {} is out of range for {} chunks",
+                index,
+                self.chunks.len()
+            ));
+        }
+        self.ordering
+            .check_insert(&self.chunks, index, chunk.chunk_type())?;
+        self.chunks.insert(index, chunk);
+        Ok(())
+    }
+
+    /// Replace the chunk at given index of [chunks](PNG::chunks), returning the old one.
+    pub fn replace_chunk(&mut self, index: usize, chunk: Chunk) -> Result<Chunk> {
+        let count = self.chunks.len();
+        let slot = self
+            .chunks
+            .get_mut(index)
+            .ok_or_else(|| anyhow!("Index {} is out of range for {} chunks", index, count))?;
+        Ok(std::mem::replace(slot, chunk))
+    }
+
+    /// Replace the first chunk of the same type, or insert the chunk before the first IDAT when
+    /// there is none. This is where most ancillary chunks describing the image must go.
+    pub fn set_chunk_before_idat(&mut self, chunk: Chunk) -> Result<()> {
+        let existing = self
             .chunks
             .iter()
-            .enumerate()
-            .find(|(_, item)| item.chunk_type().eq(&chunk_type));
-        match result {
-            None => {}
-            Some((index, _)) => {
-                return Ok(self.chunks.remove(index));
+            .position(|existing| existing.chunk_type() == chunk.chunk_type());
+        match (existing, self.position_of("IDAT", 0)?) {
+            (Some(index), _) => {
+                self.chunks[index] = chunk;
             }
+            (None, Some(index)) => self.insert_chunk(index, chunk)?,
+            (None, None) => {
+                self.try_append_chunk(chunk)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Bring ancillary chunks of `other`, another version of the same image, into this png.
+    /// Types this png lacks are copied, before IDAT when they are before IDAT in `other` and
+    /// before IEND otherwise. `policy` decides which chunks to keep for types both have with
+    /// different data. Critical chunks are left alone.
+    /// Fails when a chunk to add breaks an [ordering rule](PNG::set_ordering_rules), this png
+    /// is then left as it was.
+    pub fn merge_ancillary(&mut self, other: &PNG, policy: &MergePolicy) -> Result<MergeReport> {
+        merge::merge_ancillary(self, other, policy)
+    }
+
+    /// Tells whether the last chunk is IEND, or MEND for MNG. A png without it is usually
+    /// truncated.
+    pub fn ends_with_iend(&self) -> bool {
+        let trailer = self.format().trailer_type();
+        self.chunks
+            .last()
+            .map(|chunk| chunk.chunk_type().bytes() == trailer)
+            .unwrap_or(false)
+    }
+
+    /// Push an empty IEND chunk, or MEND for MNG, to the end unless the png already ends with
+    /// one.
+    pub fn repair_trailer(&mut self) {
+        if !self.ends_with_iend() {
+            let inner = self.format().trailer_type();
+            self.chunks
+                .push(Chunk::new(ChunkType { inner }, Vec::new()));
         }
-        Err(anyhow!("No such type"))
+    }
+
+    /// Removes the first chunk that matches given `chunk_type`.
+    /// Returns the removed chunk along with the index it had in [chunks](PNG::chunks) before removal.
+    pub fn remove_chunk(&mut self, chunk_type: &str) -> Result<(usize, Chunk)> {
+        let index = self
+            .position_of(chunk_type, 0)?
+            .ok_or_else(|| anyhow!("No such type"))?;
+        Ok((index, self.chunks.remove(index)))
+    }
+
+    /// Removes the `n`th (zero based) chunk of given type, `remove_nth(chunk_type, 0)` is the same
+    /// as [remove_chunk](PNG::remove_chunk).
+    /// Returns the removed chunk along with the index it had in [chunks](PNG::chunks) before removal.
+    pub fn remove_nth(&mut self, chunk_type: &str, n: usize) -> Result<(usize, Chunk)> {
+        let index = self.position_of(chunk_type, n)?.ok_or_else(|| {
+            anyhow!(
+                "There is no {} chunk at position {} among chunks of its type",
+                chunk_type,
+                n
+            )
+        })?;
+        Ok((index, self.chunks.remove(index)))
+    }
+
+    /// Removes every chunk that matches given `chunk_type`, in one pass.
+    /// Returns the removed chunks in file order, along with the indices they had before removal.
+    pub fn remove_chunks_by_type(&mut self, chunk_type: &str) -> Result<Vec<(usize, Chunk)>> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        let mut removed = Vec::new();
+        let mut kept = Vec::with_capacity(self.chunks.len());
+        for (index, chunk) in std::mem::take(&mut self.chunks).into_iter().enumerate() {
+            if chunk.chunk_type().eq(&chunk_type) {
+                removed.push((index, chunk));
+            } else {
+                kept.push(chunk);
+            }
+        }
+        self.chunks = kept;
+        Ok(removed)
+    }
+
+    /// Give every chunk of type `old` the type `new`, in place, with data untouched and CRC
+    /// recomputed. Only ancillary types can be renamed, to an ancillary type with a valid
+    /// reserved bit, and the new layout must not break an [ordering rule](PNG::set_ordering_rules)
+    /// the old one held. Returns the indices of the renamed chunks.
+    pub fn rename_chunk_type(&mut self, old: &str, new: &str) -> Result<Vec<usize>> {
+        let old = ChunkType::from_str(old)?;
+        let new = ChunkType::from_str(new)?;
+        if !new.is_valid() {
+            return Err(anyhow!(
+                "{} is not a valid chunk type, its third letter must be uppercase",
+                new
+            ));
+        }
+        if old.is_critical() || new.is_critical() {
+            return Err(anyhow!(
+                "Can't rename {} to {}, only ancillary chunk types can be renamed",
+                old,
+                new
+            ));
+        }
+        if old == new {
+            return Err(anyhow!("{} and {} are the same chunk type", old, new));
+        }
+        let mut renamed = self.chunks.clone();
+        let mut indices = Vec::new();
+        for (index, chunk) in renamed.iter_mut().enumerate() {
+            if chunk.chunk_type() == &old {
+                chunk.set_type(new.clone());
+                indices.push(index);
+            }
+        }
+        let before = self.ordering.validate(&self.chunks);
+        if let Some(issue) = self
+            .ordering
+            .validate(&renamed)
+            .into_iter()
+            .find(|issue| !before.contains(issue))
+        {
+            return Err(anyhow!("Can't rename {} to {}: {}", old, new, issue));
+        }
+        self.chunks = renamed;
+        Ok(indices)
+    }
+
+    /// Remove the chunk at given index of [chunks](PNG::chunks), shifting every chunk after it.
+    pub fn remove_chunk_at(&mut self, index: usize) -> Result<Chunk> {
+        if index >= self.chunks.len() {
+            return Err(anyhow!(
+                "Index {} is out of range for {} chunks",
+                index,
+                self.chunks.len()
+            ));
+        }
+        Ok(self.chunks.remove(index))
+    }
+
+    /// Remember the current chunks to [revert](PNG::revert) to them later.
+    /// Chunk data is shared with the png rather than copied, a chunk only gets its own copy when
+    /// it is modified in place, so keeping many snapshots around for undo and redo is cheap.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            signature: self.signature,
+            chunks: self.chunks.clone(),
+        }
+    }
+
+    /// Put back the chunks of a snapshot. Ordering rules are kept as they are.
+    pub fn revert(&mut self, snapshot: &Snapshot) {
+        self.signature = snapshot.signature;
+        self.chunks = snapshot.chunks.clone();
     }
 
     pub fn header(&self) -> &[u8; 8] {
         &self.signature
     }
 
+    pub fn format(&self) -> Format {
+        Format::from_signature(&self.signature).unwrap_or(Format::Png)
+    }
+
+    /// All chunks in file order. The index of a chunk in this slice is what every index based
+    /// method of `PNG` refers to, it only changes when chunks before it are inserted or removed.
     pub fn chunks(&self) -> &[Chunk] {
         self.chunks.as_slice()
     }
 
+    /// Index of the `n`th (zero based) chunk of given type in [chunks](PNG::chunks).
+    /// Returns `Ok(None)` when there are not that many chunks of this type.
+    pub fn position_of(&self, chunk_type: &str, n: usize) -> Result<Option<usize>> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        Ok(self
+            .chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| chunk.chunk_type().eq(&chunk_type))
+            .nth(n)
+            .map(|(index, _)| index))
+    }
+
+    /// The `n`th (zero based) chunk of given type, `chunk_by_type_nth(chunk_type, 0)` is the same
+    /// as [chunk_by_type](PNG::chunk_by_type).
+    pub fn chunk_by_type_nth(&self, chunk_type: &str, n: usize) -> Option<&Chunk> {
+        match self.position_of(chunk_type, n) {
+            Ok(Some(index)) => self.chunks.get(index),
+            _ => None,
+        }
+    }
+
+    /// Every chunk of given type in file order, none for an invalid type
+    pub fn chunks_by_type(&self, chunk_type: &str) -> Vec<&Chunk> {
+        match ChunkType::from_str(chunk_type) {
+            Ok(chunk_type) => self
+                .chunks
+                .iter()
+                .filter(|chunk| chunk.chunk_type().eq(&chunk_type))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
     pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
         let chunk_type = ChunkType::from_str(chunk_type);
         if chunk_type.is_err() {
             return None;
         }
         let chunk_type = chunk_type.unwrap();
-        for chunk in self.chunks.iter() {
-            if chunk.chunk_type().eq(&chunk_type) {
-                return Some(&chunk);
+        self.chunks
+            .iter()
+            .find(|chunk| chunk.chunk_type().eq(&chunk_type))
+    }
+
+    /// Parsed IHDR chunk, which every png must start with
+    pub fn image_header(&self) -> Result<ImageHeader> {
+        let header = self
+            .chunk_by_type("IHDR")
+            .ok_or_else(|| anyhow!("Png has no IHDR chunk"))?;
+        ImageHeader::try_from(header)
+    }
+
+    /// Parsed bKGD chunk, interpreted according to the color type in IHDR.
+    /// Returns `Ok(None)` when there is no bKGD chunk.
+    pub fn background(&self) -> Result<Option<Background>> {
+        match self.chunk_by_type("bKGD") {
+            Some(chunk) => Background::from_chunk(chunk, self.image_header()?.color_type).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Parsed tRNS chunk, interpreted according to the color type in IHDR.
+    /// Returns `Ok(None)` when there is no tRNS chunk.
+    pub fn transparency(&self) -> Result<Option<Transparency>> {
+        match self.chunk_by_type("tRNS") {
+            Some(chunk) => {
+                Transparency::from_chunk(chunk, self.image_header()?.color_type).map(Some)
             }
+            None => Ok(None),
         }
-        None
     }
 
-    pub fn as_bytes(&self) -> Vec<u8> {
-        self.signature
-            .iter()
-            .copied()
-            .chain(self.chunks.iter().flat_map(|chunk| chunk.as_bytes()))
-            .collect()
+    /// SHA-256 over type and data of IHDR, PLTE and IDAT chunks in file order, plus the header
+    /// and image data chunks of MNG and JNG. Ancillary chunks are ignored, so two copies of the
+    /// same image carrying different messages share the same content id.
+    pub fn content_id(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        for chunk in self.chunks.iter() {
+            if matches!(
+                &chunk.chunk_type().bytes(),
+                b"IHDR" | b"PLTE" | b"IDAT" | b"MHDR" | b"JHDR" | b"JDAT" | b"JDAA"
+            ) {
+                hasher.update(&chunk.chunk_type().bytes());
+                hasher.update(chunk.data());
+            }
+        }
+        hasher.finalize()
     }
-}
 
-impl TryFrom<&[u8]> for PNG {
-    type Error = anyhow::Error;
+    /// SHA-256 of the chunk layout: signature, then type, length and data of every chunk in file
+    /// order. Only the type of [volatile](PNG::VOLATILE_TYPES) chunks is hashed, so touching the
+    /// modification time keeps the digest while any other change to the file alters it.
+    pub fn structural_digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(&self.signature);
+        for chunk in self.chunks.iter() {
+            let chunk_type = chunk.chunk_type().bytes();
+            hasher.update(&chunk_type);
+            if !Self::VOLATILE_TYPES.contains(&chunk_type) {
+                hasher.update(&chunk.length().to_be_bytes());
+                hasher.update(chunk.data());
+            }
+        }
+        hasher.finalize()
+    }
 
-    /// This implementation always tries to construct a chunk,
-    /// doesn't care if it starts with a IHDR chunk and ends with a IEND chunk.
-    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        let mut reader = BufReader::new(value);
+    /// Scan a png stream for the first chunk of given type without building the whole `PNG`.
+    /// Chunks before the match are skipped without being stored or CRC checked, and reading stops
+    /// right after the matching chunk, so large IDAT chunks that follow it are never read.
+    /// Returns `Ok(None)` when the stream ends without such chunk.
+    pub fn find_chunk_streaming<R: Read>(reader: R, chunk_type: &str) -> Result<Option<Chunk>> {
+        let target = ChunkType::from_str(chunk_type)?;
+        let mut reader = BufReader::new(reader);
         let mut signature: [u8; 8] = [0; 8];
         reader.read_exact(&mut signature)?;
-        if !signature.eq(&PNG::STANDARD_HEADER) {
-            return Err(anyhow!("Header signature does not match PNG spec"));
+        if Format::from_signature(&signature).is_none() {
+            return Err(signature_error(&signature));
         }
         let mut len_four_bytes: [u8; 4] = [0; 4];
         let mut type_four_bytes: [u8; 4] = [0; 4];
         let mut crc_four_bytes: [u8; 4] = [0; 4];
-        let mut chunks: Vec<Chunk> = Vec::new();
-        while reader.fill_buf().map(|b| !b.is_empty())? {
-            reader.read_exact(&mut len_four_bytes)?;
+        loop {
+            match reader.read_exact(&mut len_four_bytes) {
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                result => result?,
+            }
             reader.read_exact(&mut type_four_bytes)?;
             let length = u32::from_be_bytes(len_four_bytes);
-            let chunk_type = ChunkType::try_from(type_four_bytes)?;
-            let mut data: Vec<u8> = vec![0; length as usize];
-            reader.read_exact(data.as_mut_slice())?;
+            checked_length(length as usize)?;
+            if type_four_bytes != target.bytes() {
+                let skipped = std::io::copy(
+                    &mut reader.by_ref().take(length as u64 + 4),
+                    &mut std::io::sink(),
+                )?;
+                if skipped != length as u64 + 4 {
+                    return Err(anyhow!("Unexpected end of file inside a chunk"));
+                }
+                continue;
+            }
+            // Grow with what is actually read, like read_chunks does
+            let mut data: Vec<u8> = Vec::new();
+            reader.by_ref().take(length as u64).read_to_end(&mut data)?;
+            if data.len() != length as usize {
+                return Err(anyhow!("Unexpected end of file inside a chunk"));
+            }
             reader.read_exact(&mut crc_four_bytes)?;
-            let crc = u32::from_be_bytes(crc_four_bytes);
-            let chunk = Chunk::new(chunk_type, data);
-            if chunk.length() != length {
-                return Err(Self::Error::msg("Length does not match actual data size"));
+            let chunk = Chunk::try_new(ChunkType::try_from(type_four_bytes)?, data)?;
+            if chunk.crc() != u32::from_be_bytes(crc_four_bytes) {
+                return Err(anyhow!("CRC check failed"));
             }
-            if chunk.crc() != crc {
-                return Err(Self::Error::msg("CRC check failed"));
+            return Ok(Some(chunk));
+        }
+    }
+
+    /// Size of [as_bytes](PNG::as_bytes) output, computed without serializing
+    pub fn byte_len(&self) -> usize {
+        self.signature.len()
+            + self
+                .chunks
+                .iter()
+                .map(|chunk| chunk.data().len() + 12)
+                .sum::<usize>()
+    }
+
+    /// Where each chunk starts in [as_bytes](PNG::as_bytes), its length field included
+    pub fn chunk_offsets(&self) -> Vec<usize> {
+        let mut offset = self.signature.len();
+        self.chunks
+            .iter()
+            .map(|chunk| {
+                let start = offset;
+                offset += chunk.data().len() + 12;
+                start
+            })
+            .collect()
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.byte_len());
+        // Writing to a Vec can't fail
+        self.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Write the signature then every chunk, one at a time, without building the whole file in
+    /// memory first
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(&self.signature)?;
+        self.write_fragment_to(writer)
+    }
+
+    /// Parse a png stream chunk by chunk, so only the chunks themselves are held in memory and
+    /// never a copy of the whole file. Same checks as [TryFrom<&[u8]>](PNG::try_from).
+    pub fn from_reader<R: Read>(reader: R) -> Result<PNG> {
+        let mut reader = BufReader::new(reader);
+        let mut start = Vec::with_capacity(8);
+        reader.by_ref().take(8).read_to_end(&mut start)?;
+        let signature: [u8; 8] = match start.as_slice().try_into() {
+            Ok(signature) if Format::from_signature(&start).is_some() => signature,
+            _ => return Err(signature_error(&start)),
+        };
+        let chunks = read_chunks(&mut reader)?;
+        Ok(PNG {
+            signature,
+            chunks,
+            ordering: OrderingRules::default(),
+        })
+    }
+}
+
+impl PNG {
+    /// Parse like [TryFrom<&[u8]>](PNG::try_from) but go on past wrong CRC and length fields,
+    /// returning every one found along with the png as it would be with them fixed.
+    ///
+    /// A length is kept when it ends the chunk with a matching CRC, right before what looks like
+    /// a chunk header, or at the end of the file. Otherwise the right length is the first one
+    /// giving a matching CRC, or failing that the first one followed by a whole valid chunk.
+    /// Parsing stops at a chunk the file ends inside of, which is left out.
+    pub fn parse_lenient(bytes: &[u8]) -> Result<(PNG, Vec<ChunkFailure>)> {
+        let signature: [u8; 8] = match bytes.get(..8) {
+            Some(start) if Format::from_signature(start).is_some() => start.try_into()?,
+            _ => return Err(signature_error(bytes)),
+        };
+        let mut chunks = Vec::new();
+        let mut failures = Vec::new();
+        let mut offset = 8;
+        while offset < bytes.len() {
+            let failure = |chunk_type: Option<ChunkType>, error: FieldError| ChunkFailure {
+                chunk_index: chunks.len(),
+                offset,
+                chunk_type,
+                error,
+            };
+            if bytes.len() - offset < 12 {
+                failures.push(failure(None, FieldError::Truncated(bytes.len() - offset)));
+                break;
+            }
+            let type_bytes: [u8; 4] = bytes[offset + 4..offset + 8].try_into()?;
+            if !type_bytes.iter().all(u8::is_ascii_alphabetic) {
+                return Err(anyhow!(
+                    "Chunk {} at byte {} has an invalid type",
+                    chunks.len(),
+                    offset
+                ));
+            }
+            let chunk_type = ChunkType { inner: type_bytes };
+            let declared = read_u32(bytes, offset);
+            let data_start = offset + 8;
+            let declared_end = data_start + declared as usize;
+            let declared_fits = declared as usize <= crate::chunk::MAX_LENGTH
+                && declared_end + 4 <= bytes.len()
+                && (declared_end + 4 == bytes.len()
+                    || crc_at(bytes, offset, declared_end)
+                    || header_shape_at(bytes, declared_end + 4));
+            let data_end = if declared_fits {
+                declared_end
+            } else {
+                match actual_data_end(bytes, offset) {
+                    Some(data_end) => {
+                        let actual = (data_end - data_start) as u32;
+                        failures.push(failure(
+                            Some(chunk_type.clone()),
+                            FieldError::Length {
+                                declared,
+                                actual: Some(actual),
+                            },
+                        ));
+                        data_end
+                    }
+                    None => {
+                        failures.push(failure(
+                            Some(chunk_type),
+                            FieldError::Length {
+                                declared,
+                                actual: None,
+                            },
+                        ));
+                        break;
+                    }
+                }
+            };
+            let chunk = Chunk::new(chunk_type.clone(), bytes[data_start..data_end].to_vec());
+            let stored = read_u32(bytes, data_end);
+            if chunk.crc() != stored {
+                failures.push(failure(
+                    Some(chunk_type),
+                    FieldError::Crc {
+                        stored,
+                        computed: chunk.crc(),
+                    },
+                ));
             }
             chunks.push(chunk);
+            offset = data_end + 4;
+        }
+        Ok((
+            PNG {
+                signature,
+                chunks,
+                ordering: OrderingRules::default(),
+            },
+            failures,
+        ))
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+/// Whether the chunk starting at `offset` gets a matching CRC when its data ends at `data_end`
+fn crc_at(bytes: &[u8], offset: usize, data_end: usize) -> bool {
+    crc32fast::hash(&bytes[offset + 4..data_end]) == read_u32(bytes, data_end)
+}
+
+/// Whether what starts at `offset` looks like a chunk header: room for a chunk and a type made
+/// of letters
+fn header_shape_at(bytes: &[u8], offset: usize) -> bool {
+    offset + 12 <= bytes.len()
+        && bytes[offset + 4..offset + 8]
+            .iter()
+            .all(u8::is_ascii_alphabetic)
+}
+
+/// Whether a whole chunk with a matching CRC starts at `offset`
+fn chunk_at(bytes: &[u8], offset: usize) -> bool {
+    if !header_shape_at(bytes, offset) {
+        return false;
+    }
+    let data_end = offset + 8 + read_u32(bytes, offset) as usize;
+    data_end + 4 <= bytes.len() && crc_at(bytes, offset, data_end)
+}
+
+/// Where the data of the chunk at `offset` really ends, see [PNG::parse_lenient]. CRCs are
+/// computed incrementally so the search stays linear in the size of the file.
+fn actual_data_end(bytes: &[u8], offset: usize) -> Option<usize> {
+    let data_start = offset + 8;
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&bytes[offset + 4..data_start]);
+    let mut hashed_to = data_start;
+    let mut fallback = None;
+    for data_end in data_start..=bytes.len() - 4 {
+        let next = data_end + 4;
+        if next != bytes.len() && !header_shape_at(bytes, next) {
+            continue;
+        }
+        hasher.update(&bytes[hashed_to..data_end]);
+        hashed_to = data_end;
+        if hasher.clone().finalize() == read_u32(bytes, data_end) {
+            return Some(data_end);
+        }
+        if fallback.is_none() && chunk_at(bytes, next) {
+            fallback = Some(data_end);
+        }
+    }
+    fallback
+}
+
+impl TryFrom<&[u8]> for PNG {
+    type Error = anyhow::Error;
+
+    /// This implementation always tries to construct a chunk,
+    /// doesn't care if it starts with a IHDR chunk and ends with a IEND chunk.
+    /// MNG and JNG signatures are accepted too.
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        if Format::from_signature(value).is_none() {
+            return Err(signature_error(value));
+        }
+        PNG::from_reader(value)
+    }
+}
+
+/// Error for a file not starting with the png signature, explaining the likely cause
+pub(crate) fn signature_error(bytes: &[u8]) -> anyhow::Error {
+    match signature::diagnose(bytes) {
+        Some(corruption) => anyhow!("Header signature does not match PNG spec: {}", corruption),
+        None => anyhow!("Header signature does not match PNG spec"),
+    }
+}
+
+/// Read chunks until the reader is exhausted, checking length and CRC of each one.
+fn read_chunks<R: BufRead>(reader: &mut R) -> Result<Vec<Chunk>> {
+    let mut len_four_bytes: [u8; 4] = [0; 4];
+    let mut type_four_bytes: [u8; 4] = [0; 4];
+    let mut crc_four_bytes: [u8; 4] = [0; 4];
+    let mut chunks: Vec<Chunk> = Vec::new();
+    while reader.fill_buf().map(|b| !b.is_empty())? {
+        reader.read_exact(&mut len_four_bytes)?;
+        reader.read_exact(&mut type_four_bytes)?;
+        let length = u32::from_be_bytes(len_four_bytes);
+        checked_length(length as usize)?;
+        let chunk_type = ChunkType::try_from(type_four_bytes)?;
+        // Grow with what is actually read, a truncated stream can't make us allocate the
+        // whole length field up front
+        let mut data: Vec<u8> = Vec::new();
+        reader.by_ref().take(length as u64).read_to_end(&mut data)?;
+        if data.len() != length as usize {
+            return Err(anyhow!("Unexpected end of file inside a chunk"));
+        }
+        reader.read_exact(&mut crc_four_bytes)?;
+        let crc = u32::from_be_bytes(crc_four_bytes);
+        let chunk = Chunk::new(chunk_type, data);
+        if chunk.length() != length {
+            return Err(anyhow!("Length does not match actual data size"));
+        }
+        if chunk.crc() != crc {
+            return Err(anyhow!("CRC check failed"));
         }
-        Ok(PNG { signature, chunks })
+        chunks.push(chunk);
     }
+    Ok(chunks)
 }
 
 impl Display for PNG {
@@ -0,0 +1,127 @@
+#![allow(dead_code)]
+//! # Pointer
+//! Messages standing for another file of the same asset bundle, so a small image can vouch for
+//! large sidecar data.
+//!
+//! A pointer holds the path of the file relative to the png and the hash of its content, along
+//! with the algorithm it was made with. Following it reads the file and checks the hash, a file
+//! that was modified or replaced is an error. Unlike [PayloadRef](crate::store::PayloadRef),
+//! there is no store involved, the file lives next to the image wherever the bundle is copied.
+use crate::digest::{Digest, HashAlgorithm};
+use anyhow::{anyhow, Result};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePointer {
+    /// Hash of the file content
+    pub hash: Digest,
+    /// Relative to the directory of the png, with `/` separators
+    pub path: String,
+}
+
+impl FilePointer {
+    /// Every pointer chunk data starts with this prefix
+    pub const PREFIX: &'static str = "msgptr:";
+
+    /// Point at `target`, hashing its current content with `algorithm`. `target` must be inside
+    /// `base`, the directory of the png.
+    pub fn to_file(base: &Path, target: &Path, algorithm: HashAlgorithm) -> Result<Self> {
+        let content = fs::read(target)?;
+        let base = base.canonicalize()?;
+        let target = target.canonicalize()?;
+        let relative = target.strip_prefix(&base).map_err(|_| {
+            anyhow!(
+                "{} is not inside {}, the directory of the png",
+                target.display(),
+                base.display()
+            )
+        })?;
+        let parts = relative
+            .components()
+            .map(|part| {
+                part.as_os_str()
+                    .to_str()
+                    .ok_or_else(|| anyhow!("Path is not valid UTF-8: {}", relative.display()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(FilePointer {
+            hash: algorithm.hash(&content),
+            path: parts.join("/"),
+        })
+    }
+
+    /// Serialized form: `msgptr:<algorithm>:<hex>\n<path>`
+    pub fn as_bytes(&self) -> Vec<u8> {
+        self.to_string().into_bytes()
+    }
+
+    /// Tells whether given chunk data looks like a pointer
+    pub fn is_pointer(data: &[u8]) -> bool {
+        data.starts_with(Self::PREFIX.as_bytes())
+    }
+
+    /// Where the file is for a png in `base`. Absolute paths and paths leaving `base` are
+    /// refused, a pointer only reaches into its own bundle.
+    pub fn target(&self, base: &Path) -> Result<PathBuf> {
+        let relative = Path::new(&self.path);
+        if !relative
+            .components()
+            .all(|part| matches!(part, Component::Normal(_)))
+        {
+            return Err(anyhow!(
+                "Pointer to {} leaves the directory of the png",
+                self.path
+            ));
+        }
+        Ok(base.join(relative))
+    }
+
+    /// Read the file for a png in `base`, checking its content against the hash
+    pub fn follow(&self, base: &Path) -> Result<Vec<u8>> {
+        let target = self.target(base)?;
+        let content =
+            fs::read(&target).map_err(|e| anyhow!("Can't read {}: {}", target.display(), e))?;
+        let hash = self.hash.algorithm.hash(&content);
+        if hash != self.hash {
+            return Err(anyhow!(
+                "{} was modified, its hash is {} instead of {}",
+                target.display(),
+                hash,
+                self.hash
+            ));
+        }
+        Ok(content)
+    }
+}
+
+impl TryFrom<&[u8]> for FilePointer {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        let text = std::str::from_utf8(value)?;
+        let text = text
+            .strip_prefix(Self::PREFIX)
+            .ok_or_else(|| anyhow!("Not a file pointer"))?;
+        let (hash, path) = text
+            .split_once('\n')
+            .ok_or_else(|| anyhow!("File pointer has no path"))?;
+        let hash = hash
+            .parse()
+            .map_err(|e| anyhow!("Malformed file hash: {}", e))?;
+        if path.is_empty() {
+            return Err(anyhow!("File pointer has no path"));
+        }
+        Ok(Self {
+            hash,
+            path: path.to_string(),
+        })
+    }
+}
+
+impl Display for FilePointer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}\n{}", Self::PREFIX, self.hash, self.path)
+    }
+}
@@ -0,0 +1,242 @@
+#![allow(dead_code)]
+//! # Registry
+//! Descriptions and expected layout of custom chunk types, so chunks written by other tools can
+//! be recognized and checked.
+//!
+//! A registry is a TOML file with one table per chunk type, every key is optional:
+//! ```toml
+//! [ruSt]
+//! description = "Build metadata from the asset pipeline"
+//! # What the data must be: "binary" (anything, the default), "utf8" or "ascii"
+//! format = "utf8"
+//! min_length = 1
+//! max_length = 4096
+//! # At most one chunk of this type per file
+//! unique = true
+//! # Where chunks must be: "before_plte", "before_idat" or "after_idat"
+//! position = "before_idat"
+//! ```
+//! Placement keys turn into [OrderingRules], enforced when chunks are added to a png.
+use crate::chunk_type::ChunkType;
+use crate::ordering::{OrderingRules, Position};
+use crate::png::PNG;
+use crate::toml::{self, Line, Value};
+use crate::verify::Issue;
+use anyhow::{anyhow, Result};
+use std::str::FromStr;
+
+/// Expected content of chunk data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFormat {
+    Binary,
+    Utf8,
+    Ascii,
+}
+
+impl FromStr for DataFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "binary" => Ok(DataFormat::Binary),
+            "utf8" => Ok(DataFormat::Utf8),
+            "ascii" => Ok(DataFormat::Ascii),
+            _ => Err(anyhow!(
+                "unknown format {:?}, use \"binary\", \"utf8\" or \"ascii\"",
+                s
+            )),
+        }
+    }
+}
+
+/// What the registry knows about one chunk type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkSpec {
+    pub chunk_type: ChunkType,
+    pub description: Option<String>,
+    pub format: DataFormat,
+    pub min_length: Option<u32>,
+    pub max_length: Option<u32>,
+    pub unique: bool,
+    pub position: Option<Position>,
+}
+
+impl ChunkSpec {
+    fn new(chunk_type: ChunkType) -> Self {
+        Self {
+            chunk_type,
+            description: None,
+            format: DataFormat::Binary,
+            min_length: None,
+            max_length: None,
+            unique: false,
+            position: None,
+        }
+    }
+
+    /// Chunk type followed by its description, used in messages
+    fn label(&self) -> String {
+        match &self.description {
+            Some(description) => format!("{} ({})", self.chunk_type, description),
+            None => self.chunk_type.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Registry {
+    specs: Vec<ChunkSpec>,
+}
+
+impl Registry {
+    /// Parse the TOML layout described in the module documentation.
+    pub fn from_toml(source: &str) -> Result<Self> {
+        let mut specs: Vec<ChunkSpec> = Vec::new();
+        for (line_number, line) in source.lines().enumerate() {
+            let context = |e: anyhow::Error| anyhow!("line {}: {}", line_number + 1, e);
+            match toml::parse_line(line).map_err(context)? {
+                None => {}
+                Some(Line::Table(name)) => {
+                    let chunk_type = ChunkType::from_str(&name).map_err(context)?;
+                    if specs.iter().any(|spec| spec.chunk_type == chunk_type) {
+                        return Err(context(anyhow!("duplicate chunk type {}", name)));
+                    }
+                    specs.push(ChunkSpec::new(chunk_type));
+                }
+                Some(Line::KeyValue(key, value)) => {
+                    let spec = specs.last_mut().ok_or_else(|| {
+                        context(anyhow!("{} must be inside a [chunk type] table", key))
+                    })?;
+                    set_field(spec, &key, value).map_err(context)?;
+                }
+            }
+        }
+        Ok(Self { specs })
+    }
+
+    pub fn get(&self, chunk_type: &ChunkType) -> Option<&ChunkSpec> {
+        self.specs
+            .iter()
+            .find(|spec| &spec.chunk_type == chunk_type)
+    }
+
+    /// Registered description of a chunk type
+    pub fn describe(&self, chunk_type: &ChunkType) -> Option<&str> {
+        self.get(chunk_type)
+            .and_then(|spec| spec.description.as_deref())
+    }
+
+    /// Placement constraints of every registered type that has some
+    pub fn ordering_rules(&self) -> OrderingRules {
+        let mut rules = OrderingRules::new();
+        for spec in self.specs.iter() {
+            if spec.unique {
+                rules.set_unique(spec.chunk_type.clone());
+            }
+            if let Some(position) = spec.position {
+                rules.set_position(spec.chunk_type.clone(), position);
+            }
+        }
+        rules
+    }
+
+    /// Check every chunk of a registered type against its spec
+    pub fn validate(&self, png: &PNG) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        let types: Vec<&ChunkType> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type())
+            .collect();
+        for (index, chunk) in png.chunks().iter().enumerate() {
+            let spec = match self.get(chunk.chunk_type()) {
+                Some(spec) => spec,
+                None => continue,
+            };
+            let length = chunk.length();
+            if let Some(min_length) = spec.min_length.filter(|min| length < *min) {
+                issues.push(Issue::error(
+                    Some(index),
+                    format!(
+                        "{} is {} bytes long, below the registered minimum of {}",
+                        spec.label(),
+                        length,
+                        min_length
+                    ),
+                ));
+            }
+            if let Some(max_length) = spec.max_length.filter(|max| length > *max) {
+                issues.push(Issue::error(
+                    Some(index),
+                    format!(
+                        "{} is {} bytes long, above the registered maximum of {}",
+                        spec.label(),
+                        length,
+                        max_length
+                    ),
+                ));
+            }
+            let format_error = match spec.format {
+                DataFormat::Binary => None,
+                DataFormat::Utf8 => std::str::from_utf8(chunk.data())
+                    .err()
+                    .map(|e| format!("is not valid UTF-8: {}", e)),
+                DataFormat::Ascii => chunk
+                    .data()
+                    .iter()
+                    .position(|byte| !byte.is_ascii())
+                    .map(|position| format!("has a non ASCII byte at offset {}", position)),
+            };
+            if let Some(format_error) = format_error {
+                issues.push(Issue::error(
+                    Some(index),
+                    format!("{} {}", spec.label(), format_error),
+                ));
+            }
+            if spec.unique
+                && png.chunks()[..index]
+                    .iter()
+                    .any(|earlier| earlier.chunk_type() == chunk.chunk_type())
+            {
+                issues.push(Issue::error(
+                    Some(index),
+                    format!("{} is registered as unique but appears again", spec.label()),
+                ));
+            }
+            if let Some(position) = spec
+                .position
+                .filter(|position| !position.holds(&types, index))
+            {
+                issues.push(Issue::error(
+                    Some(index),
+                    format!("{} must come {}", spec.label(), position.describe()),
+                ));
+            }
+        }
+        issues
+    }
+}
+
+fn set_field(spec: &mut ChunkSpec, key: &str, value: Value) -> Result<()> {
+    match (key, value) {
+        ("description", Value::String(description)) => spec.description = Some(description),
+        ("format", Value::String(format)) => spec.format = DataFormat::from_str(&format)?,
+        ("min_length", Value::Integer(length)) => spec.min_length = Some(length_of(key, length)?),
+        ("max_length", Value::Integer(length)) => spec.max_length = Some(length_of(key, length)?),
+        ("unique", Value::Boolean(unique)) => spec.unique = unique,
+        ("position", Value::String(position)) => {
+            spec.position = Some(Position::from_str(&position)?)
+        }
+        ("description" | "format" | "position", _) => {
+            return Err(anyhow!("{} must be a string", key))
+        }
+        ("min_length" | "max_length", _) => return Err(anyhow!("{} must be an integer", key)),
+        ("unique", _) => return Err(anyhow!("{} must be a boolean", key)),
+        _ => return Err(anyhow!("unknown key {}", key)),
+    }
+    Ok(())
+}
+
+fn length_of(key: &str, length: i64) -> Result<u32> {
+    u32::try_from(length).map_err(|_| anyhow!("{} must be between 0 and {}", key, u32::MAX))
+}
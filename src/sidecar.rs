@@ -0,0 +1,112 @@
+#![allow(dead_code)]
+//! # Sidecar
+//! Chunks taken out of a png, kept in a separate file so a destructive edit can be undone.
+//!
+//! A sidecar is a fragment (see [from_fragment](PNG::from_fragment)): a `rmIx` chunk holding the
+//! original index of every removed chunk as a 4 byte big endian integer, followed by the removed
+//! chunks in the same order. Being a plain fragment, it can be looked at with `--fragment`.
+//! [restore](RemovedChunks::restore) puts the chunks back where they were.
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::PNG;
+use anyhow::{anyhow, Result};
+
+/// Type of the leading chunk recording original indices. Ancillary, private and safe to copy.
+pub const INDEX_CHUNK_TYPE: [u8; 4] = *b"rmIx";
+
+/// Removed chunks along with the index each one had in the png it was removed from
+#[derive(Debug, Clone, Default)]
+pub struct RemovedChunks {
+    entries: Vec<(usize, Chunk)>,
+}
+
+impl RemovedChunks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a chunk removed from `index`. Indices refer to the png before any removal.
+    pub fn push(&mut self, index: usize, chunk: Chunk) {
+        self.entries.push((index, chunk));
+    }
+
+    pub fn entries(&self) -> &[(usize, Chunk)] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Serialize as a fragment, see the module documentation for the layout
+    pub fn to_fragment(&self) -> Result<Vec<u8>> {
+        let mut indices = Vec::with_capacity(self.entries.len() * 4);
+        for (index, _) in self.entries.iter() {
+            let index = u32::try_from(*index)
+                .map_err(|_| anyhow!("Chunk index {} does not fit a sidecar", index))?;
+            indices.extend_from_slice(&index.to_be_bytes());
+        }
+        let mut chunks = vec![Chunk::try_new(
+            ChunkType {
+                inner: INDEX_CHUNK_TYPE,
+            },
+            indices,
+        )?];
+        chunks.extend(self.entries.iter().map(|(_, chunk)| chunk.clone()));
+        Ok(PNG::from_chunks(chunks).fragment_bytes())
+    }
+
+    /// Parse a fragment written by [to_fragment](RemovedChunks::to_fragment)
+    pub fn from_fragment(bytes: &[u8]) -> Result<Self> {
+        let fragment = PNG::from_fragment(bytes)?;
+        let (header, chunks) = fragment
+            .chunks()
+            .split_first()
+            .ok_or_else(|| anyhow!("Sidecar is empty"))?;
+        if header.chunk_type().bytes() != INDEX_CHUNK_TYPE {
+            return Err(anyhow!(
+                "Sidecar must start with a {} chunk, found {}",
+                String::from_utf8_lossy(&INDEX_CHUNK_TYPE),
+                header.chunk_type()
+            ));
+        }
+        if header.data().len() != chunks.len() * 4 {
+            return Err(anyhow!(
+                "Sidecar records {} indices for {} chunks",
+                header.data().len() / 4,
+                chunks.len()
+            ));
+        }
+        let entries = header
+            .data()
+            .chunks(4)
+            .map(|index| u32::from_be_bytes([index[0], index[1], index[2], index[3]]) as usize)
+            .zip(chunks.iter().cloned())
+            .collect();
+        Ok(Self { entries })
+    }
+
+    /// Put every chunk back at its original index. Chunks are inserted from the lowest index up,
+    /// so each index means the same as before removal as long as the png was not edited around
+    /// them since. Nothing is inserted when an index is out of range.
+    /// Returns the indices chunks were restored at, in ascending order.
+    pub fn restore(&self, png: &mut PNG) -> Result<Vec<usize>> {
+        let mut entries = self.entries.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|(index, _)| *index);
+        let length = png.chunks().len();
+        for (restored, (index, chunk)) in entries.iter().enumerate() {
+            if *index > length + restored {
+                return Err(anyhow!(
+                    "Can't restore {} at index {}, the png only has {} chunks",
+                    chunk.chunk_type(),
+                    index,
+                    length
+                ));
+            }
+        }
+        for (index, chunk) in entries.iter() {
+            png.insert_chunk(*index, chunk.clone())?;
+        }
+        Ok(entries.iter().map(|(index, _)| *index).collect())
+    }
+}
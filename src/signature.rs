@@ -0,0 +1,130 @@
+#![allow(dead_code)]
+//! # Signature
+//! Diagnosis of png signatures damaged in transfer.
+//!
+//! The png signature was designed to detect the classic ways files get mangled on their way
+//! between systems, `89` catches 7-bit channels, `0D 0A` and `0A` catch line ending conversion and
+//! `1A` stops `type` on DOS. This module turns such a damaged signature back into an explanation
+//! and, where the damage can be undone, a repaired copy.
+use crate::png::{Format, PNG};
+use anyhow::{anyhow, Result};
+use std::fmt::{Display, Formatter};
+
+const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+/// Signature after every LF was turned into CRLF, as done by ASCII mode FTP to a Windows host
+const EXPANDED_SIGNATURE: [u8; 10] = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0D, 0x0A, 0x1A, 0x0D, 0x0A];
+/// Signature after every CRLF was turned into LF, as done by ASCII mode FTP to a Unix host
+const COLLAPSED_SIGNATURE: [u8; 7] = [0x89, 0x50, 0x4E, 0x47, 0x0A, 0x1A, 0x0A];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corruption {
+    /// A UTF-8 byte order mark was put in front of the file
+    Utf8Bom,
+    /// LF bytes were converted to CRLF
+    LineEndingsExpanded,
+    /// CRLF bytes were converted to LF
+    LineEndingsCollapsed,
+    /// The file went through a 7-bit channel and lost the high bit of every byte
+    HighBitStripped,
+    /// The file is a gzip stream, probably a compressed png
+    Gzip,
+    /// The file is a zip archive
+    Zip,
+    /// The file is shorter than a png signature
+    Truncated,
+    /// Not a png, and none of the known corruptions
+    Unknown,
+}
+
+impl Corruption {
+    /// Tells whether [repair] can undo this corruption
+    pub fn is_repairable(&self) -> bool {
+        matches!(self, Corruption::Utf8Bom | Corruption::LineEndingsExpanded)
+    }
+}
+
+impl Display for Corruption {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            Corruption::Utf8Bom => {
+                "file starts with a UTF-8 byte order mark added by a text editor"
+            }
+            Corruption::LineEndingsExpanded => {
+                "line endings were converted from LF to CRLF, was it transferred in ASCII mode?"
+            }
+            Corruption::LineEndingsCollapsed => {
+                "line endings were converted from CRLF to LF, was it transferred in ASCII mode? \
+                 This can not be undone, transfer the file again in binary mode"
+            }
+            Corruption::HighBitStripped => {
+                "the high bit of every byte was stripped by a 7-bit channel, \
+                 transfer the file again in binary mode"
+            }
+            Corruption::Gzip => "file is gzip compressed, decompress it first",
+            Corruption::Zip => "file is a zip archive, extract the png from it first",
+            Corruption::Truncated => "file is too short to be a png",
+            Corruption::Unknown => "file is not a png",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+/// Look at the beginning of a file whose signature is not the png one and guess what happened
+/// to it. Returns `None` when the signature is valid, MNG and JNG signatures included.
+pub fn diagnose(bytes: &[u8]) -> Option<Corruption> {
+    if Format::from_signature(bytes).is_some() {
+        return None;
+    }
+    let corruption = if bytes.starts_with(&UTF8_BOM) {
+        Corruption::Utf8Bom
+    } else if starts_with_signature(bytes, &EXPANDED_SIGNATURE) {
+        Corruption::LineEndingsExpanded
+    } else if bytes.starts_with(&COLLAPSED_SIGNATURE) {
+        Corruption::LineEndingsCollapsed
+    } else if bytes.len() >= 4 && bytes[..4] == [0x09, 0x50, 0x4E, 0x47] {
+        Corruption::HighBitStripped
+    } else if bytes.starts_with(&[0x1F, 0x8B]) {
+        Corruption::Gzip
+    } else if bytes.starts_with(b"PK\x03\x04") {
+        Corruption::Zip
+    } else if bytes.len() < PNG::STANDARD_HEADER.len() && PNG::STANDARD_HEADER.starts_with(bytes) {
+        Corruption::Truncated
+    } else {
+        Corruption::Unknown
+    };
+    Some(corruption)
+}
+
+/// Like `starts_with`, but also accepts a signature sized prefix of `pattern`, which is all a
+/// streaming reader has at hand.
+fn starts_with_signature(bytes: &[u8], pattern: &[u8]) -> bool {
+    bytes.starts_with(pattern)
+        || (bytes.len() >= PNG::STANDARD_HEADER.len() && pattern.starts_with(bytes))
+}
+
+/// Best effort repair of a damaged file. Fails when the damage is not one [diagnose] recognizes
+/// as repairable, returns the bytes unchanged when the signature is fine.
+pub fn repair(bytes: &[u8]) -> Result<Vec<u8>> {
+    match diagnose(bytes) {
+        None => Ok(bytes.to_vec()),
+        Some(Corruption::Utf8Bom) => {
+            let stripped = &bytes[UTF8_BOM.len()..];
+            // The BOM may come on top of other damage
+            repair(stripped)
+        }
+        Some(Corruption::LineEndingsExpanded) => {
+            // Conversion put a CR in front of every LF, including the ones which already had one,
+            // so dropping the CR of every CRLF gives back the original bytes
+            let mut repaired = Vec::with_capacity(bytes.len());
+            let mut iter = bytes.iter().peekable();
+            while let Some(byte) = iter.next() {
+                if *byte == 0x0D && iter.peek() == Some(&&0x0A) {
+                    continue;
+                }
+                repaired.push(*byte);
+            }
+            Ok(repaired)
+        }
+        Some(corruption) => Err(anyhow!("Can not repair file: {}", corruption)),
+    }
+}
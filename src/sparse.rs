@@ -0,0 +1,159 @@
+#![allow(dead_code)]
+//! # Sparse
+//! Fast triage of large png collections for data hidden after IEND.
+//!
+//! Only a window at the head and at the tail of each file is read, the chunks in between are
+//! never parsed. The tail is searched for the IEND chunk, anything after it is trailing data,
+//! and trailing data starting like a ZIP, RAR or ELF file is flagged by name. A file whose IEND
+//! is not in the tail window either carries more trailing data than the window or is truncated.
+//! MNG files are searched for MEND instead.
+use crate::plan::json_string;
+use crate::png::Format;
+use anyhow::Result;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Bytes read at each end of a file unless told otherwise
+pub const DEFAULT_WINDOW: u64 = 64 * 1024;
+
+/// Complete trailer chunk of given format, IEND or MEND: zero length, type and its CRC
+pub fn trailer_chunk(format: Format) -> [u8; 12] {
+    let trailer = format.trailer_type();
+    let mut chunk = [0u8; 12];
+    chunk[4..8].copy_from_slice(&trailer);
+    chunk[8..].copy_from_slice(&crc32fast::hash(&trailer).to_be_bytes());
+    chunk
+}
+
+/// Signatures worth naming when found in trailing data
+pub const SUSPICIOUS_SIGNATURES: [(&str, &[u8]); 5] = [
+    ("zip", b"PK\x03\x04"),
+    ("zip-end", b"PK\x05\x06"),
+    ("rar", b"Rar!\x1a\x07"),
+    ("elf", b"\x7fELF"),
+    ("png", &crate::png::PNG::STANDARD_HEADER),
+];
+
+/// A signature found in trailing data, at an offset from the start of the file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Found {
+    pub kind: &'static str,
+    pub offset: u64,
+}
+
+/// What the head and tail of one file tell
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparseReport {
+    pub path: PathBuf,
+    pub size: u64,
+    /// `None` when the file does not start with a png, MNG or JNG signature
+    pub format: Option<Format>,
+    /// Offset of the first IEND chunk, or MEND for MNG, found in the tail window
+    pub iend_offset: Option<u64>,
+    /// Signatures found after IEND, or anywhere in the tail window without IEND
+    pub signatures: Vec<Found>,
+}
+
+impl SparseReport {
+    /// Bytes after IEND, `None` when IEND is not in the tail window
+    pub fn trailing_bytes(&self) -> Option<u64> {
+        self.iend_offset.map(|offset| self.size - offset - 12)
+    }
+
+    /// Worth a closer look: not a png, IEND out of reach, trailing data or a known signature
+    pub fn is_suspicious(&self) -> bool {
+        self.format.is_none() || self.trailing_bytes() != Some(0) || !self.signatures.is_empty()
+    }
+
+    /// One line JSON object
+    pub fn to_json(&self) -> String {
+        let optional = |value: Option<u64>| value.map_or("null".to_string(), |v| v.to_string());
+        let signatures: Vec<String> = self
+            .signatures
+            .iter()
+            .map(|found| {
+                format!(
+                    "{{\"kind\":{},\"offset\":{}}}",
+                    json_string(found.kind),
+                    found.offset
+                )
+            })
+            .collect();
+        format!(
+            "{{\"path\":{},\"size\":{},\"format\":{},\"iend_offset\":{},\"trailing_bytes\":{},\"signatures\":[{}]}}",
+            json_string(&self.path.to_string_lossy()),
+            self.size,
+            self.format
+                .map_or("null".to_string(), |format| json_string(&format.to_string())),
+            optional(self.iend_offset),
+            optional(self.trailing_bytes()),
+            signatures.join(",")
+        )
+    }
+}
+
+/// JSON line for a file that could not be read
+pub fn error_json(path: &Path, error: &anyhow::Error) -> String {
+    format!(
+        "{{\"path\":{},\"error\":{}}}",
+        json_string(&path.to_string_lossy()),
+        json_string(&error.to_string())
+    )
+}
+
+/// Read the first and last `window` bytes of a file, the whole file when that is about as much
+pub fn scan_file(path: &Path, window: u64) -> Result<SparseReport> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    if size <= window.saturating_mul(2) {
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        return Ok(scan_bytes(path, size, &bytes, (0, bytes.clone())));
+    }
+    let mut head = Vec::new();
+    (&mut file).take(window).read_to_end(&mut head)?;
+    file.seek(SeekFrom::Start(size - window))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    Ok(scan_bytes(path, size, &head, (size - window, tail)))
+}
+
+/// [scan_file] on bytes already read, `tail` being the bytes from an offset to the end
+pub fn scan_bytes(path: &Path, size: u64, head: &[u8], tail: (u64, Vec<u8>)) -> SparseReport {
+    let (tail_start, tail) = tail;
+    let format = Format::from_signature(head);
+    let trailer = trailer_chunk(format.unwrap_or(Format::Png));
+    let iend = find(&tail, &trailer);
+    // Without IEND, the whole window is searched but for the file's own signature
+    let trailing_from = match (iend, tail_start) {
+        (Some(position), _) => position + trailer.len(),
+        (None, 0) => tail.len().min(8),
+        (None, _) => 0,
+    };
+    let mut signatures = Vec::new();
+    for (kind, signature) in SUSPICIOUS_SIGNATURES.iter() {
+        let mut from = trailing_from;
+        while let Some(position) = find(&tail[from..], signature) {
+            signatures.push(Found {
+                kind,
+                offset: tail_start + (from + position) as u64,
+            });
+            from += position + signature.len();
+        }
+    }
+    signatures.sort_by_key(|found| found.offset);
+    SparseReport {
+        path: path.to_path_buf(),
+        size,
+        format,
+        iend_offset: iend.map(|position| tail_start + position as u64),
+        signatures,
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
@@ -0,0 +1,525 @@
+#![allow(dead_code)]
+//! # Split
+//! Payloads too large for a single chunk, stored across several chunks of the same type.
+//!
+//! Each part starts with a `msgsplit:<index>/<count>:<token>\n` header followed by a piece of the
+//! payload. Parts are written in order and [join_payload] puts the pieces back together. Payloads
+//! small enough for one chunk are stored as they are, without a header.
+//!
+//! The [Token] chains every part to the one before it and to its own piece, so parts reordered,
+//! dropped or swapped with parts of another payload fail to join instead of giving a corrupt
+//! payload. Without a key anyone can compute tokens, which is enough against tools shuffling
+//! chunks. With a key they are signatures, an attacker rewriting the headers can't forge them.
+//! Headers without a token, written before tokens existed, are still accepted.
+use crate::chunk::{checked_length, Chunk, MAX_LENGTH};
+use crate::chunk_type::ChunkType;
+use crate::png::{signature_error, Format, PNG};
+use crate::sha256::{hmac_sha256, sha256, to_hex, Sha256};
+use anyhow::{anyhow, Result};
+use crc32fast::Hasher;
+use std::io::{ErrorKind, Read, Write};
+
+/// Every part of a split payload starts with this prefix
+pub const PREFIX: &str = "msgsplit:";
+
+/// Bytes read from the start of a chunk to look for a part header when streaming, longer than
+/// any header
+const HEADER_PEEK: usize = 96;
+
+/// Bytes of a [Token], written as twice as many hex digits
+pub const TOKEN_LEN: usize = 8;
+
+/// Size of the buffer payloads are streamed through
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Ordering token of a part, a MAC over the token of the previous part, the position of the part
+/// and the SHA-256 of its piece
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token {
+    /// Computed without a key, written `c<hex>`
+    Chained([u8; TOKEN_LEN]),
+    /// Computed with a key, written `s<hex>`
+    Signed([u8; TOKEN_LEN]),
+}
+
+impl Token {
+    fn compute(
+        key: Option<&[u8]>,
+        previous: &[u8; TOKEN_LEN],
+        index: usize,
+        count: usize,
+        digest: &[u8; 32],
+    ) -> Token {
+        let mut message = previous.to_vec();
+        message.extend_from_slice(format!("{}/{}", index, count).as_bytes());
+        message.extend_from_slice(digest);
+        let mut token = [0u8; TOKEN_LEN];
+        token.copy_from_slice(&hmac_sha256(key.unwrap_or_default(), &message)[..TOKEN_LEN]);
+        match key {
+            Some(_) => Token::Signed(token),
+            None => Token::Chained(token),
+        }
+    }
+
+    pub fn bytes(&self) -> &[u8; TOKEN_LEN] {
+        match self {
+            Token::Chained(token) | Token::Signed(token) => token,
+        }
+    }
+
+    fn parse(text: &str) -> Result<Token> {
+        let token = |hex: &str| -> Option<[u8; TOKEN_LEN]> {
+            // from_str_radix alone would take a `+` sign in front of a digit
+            if hex.len() != TOKEN_LEN * 2 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return None;
+            }
+            let mut token = [0u8; TOKEN_LEN];
+            for (index, byte) in token.iter_mut().enumerate() {
+                *byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok()?;
+            }
+            Some(token)
+        };
+        match (text.get(..1), text.get(1..).and_then(token)) {
+            (Some("c"), Some(token)) => Ok(Token::Chained(token)),
+            (Some("s"), Some(token)) => Ok(Token::Signed(token)),
+            _ => Err(anyhow!("Malformed split payload token {:?}", text)),
+        }
+    }
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let marker = match self {
+            Token::Chained(_) => 'c',
+            Token::Signed(_) => 's',
+        };
+        write!(f, "{}{}", marker, to_hex(self.bytes()))
+    }
+}
+
+/// Checks the tokens of the parts of one payload, in order
+pub struct TokenChain<'a> {
+    key: Option<&'a [u8]>,
+    previous: [u8; TOKEN_LEN],
+    /// Kind of token part 0 had, later parts must have the same
+    first_kind: Option<u8>,
+}
+
+impl<'a> TokenChain<'a> {
+    /// With a key, every part must be signed with it. Without one, chained tokens are checked
+    /// and signed ones are taken as they are.
+    pub fn new(key: Option<&'a [u8]>) -> Self {
+        TokenChain {
+            key,
+            previous: [0; TOKEN_LEN],
+            first_kind: None,
+        }
+    }
+
+    /// Check the token of the part at `index` of `count`, whose piece hashes to `digest`
+    pub fn check(
+        &mut self,
+        index: usize,
+        count: usize,
+        token: Option<Token>,
+        digest: &[u8; 32],
+    ) -> Result<()> {
+        let kind = match token {
+            None => 0,
+            Some(Token::Chained(_)) => 1,
+            Some(Token::Signed(_)) => 2,
+        };
+        match self.first_kind {
+            None => self.first_kind = Some(kind),
+            Some(first_kind) if first_kind == kind => {}
+            Some(_) => {
+                return Err(anyhow!(
+                    "part {} of the split payload does not have the same kind of ordering token as part 0",
+                    index
+                ))
+            }
+        }
+        let expected = match (token, self.key) {
+            (Some(Token::Signed(_)), Some(_)) | (Some(Token::Chained(_)), None) => {
+                Token::compute(self.key, &self.previous, index, count, digest)
+            }
+            (_, Some(_)) => {
+                return Err(anyhow!(
+                    "part {} of the split payload is not signed, a key was given",
+                    index
+                ))
+            }
+            // Nothing to check against without the key, or without a token at all
+            (Some(token), None) => token,
+            (None, None) => return Ok(()),
+        };
+        if token != Some(expected) {
+            return Err(anyhow!(
+                "part {} of {} of the split payload fails its ordering token check, parts were reordered, replaced or modified",
+                index,
+                count
+            ));
+        }
+        self.previous = *expected.bytes();
+        Ok(())
+    }
+}
+
+/// Length of the header of any part of a payload split in `count` parts
+fn header_len(count: usize) -> usize {
+    let digits = count.to_string().len();
+    // The index never has more digits than the count
+    PREFIX.len() + digits * 2 + 4 + TOKEN_LEN * 2
+}
+
+fn header(index: usize, count: usize, token: &Token) -> String {
+    format!("{}{}/{}:{}\n", PREFIX, index, count, token)
+}
+
+/// A part of a split payload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Part<'a> {
+    pub index: usize,
+    pub count: usize,
+    /// `None` for parts written before tokens existed
+    pub token: Option<Token>,
+    pub piece: &'a [u8],
+}
+
+/// Tells whether given chunk data is a part of a split payload
+pub fn is_part(data: &[u8]) -> bool {
+    data.starts_with(PREFIX.as_bytes())
+}
+
+/// Parse the header of a part, returns its index, the part count and the piece of payload.
+/// Returns `Ok(None)` when data is not a part at all.
+pub fn parse_part(data: &[u8]) -> Result<Option<(usize, usize, &[u8])>> {
+    Ok(read_part(data)?.map(|part| (part.index, part.count, part.piece)))
+}
+
+/// Like [parse_part], with the ordering token of the part
+pub fn read_part(data: &[u8]) -> Result<Option<Part<'_>>> {
+    if !is_part(data) {
+        return Ok(None);
+    }
+    let rest = &data[PREFIX.len()..];
+    let line_end = rest
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .ok_or_else(|| anyhow!("Split payload header is not terminated"))?;
+    let header = std::str::from_utf8(&rest[..line_end])?;
+    let (position, token) = match header.split_once(':') {
+        Some((position, token)) => (position, Some(Token::parse(token)?)),
+        None => (header, None),
+    };
+    let (index, count) = position
+        .split_once('/')
+        .ok_or_else(|| anyhow!("Split payload header {:?} is not <index>/<count>", header))?;
+    let index: usize = index.parse()?;
+    let count: usize = count.parse()?;
+    if index >= count {
+        return Err(anyhow!(
+            "Split payload part {} is out of range, there are {} parts",
+            index,
+            count
+        ));
+    }
+    Ok(Some(Part {
+        index,
+        count,
+        token,
+        piece: &rest[line_end + 1..],
+    }))
+}
+
+/// Store payload in chunks whose data is at most `max_chunk_size` bytes, capped to the spec limit.
+/// A payload that already looks like a part is framed even when it fits, so decoding it
+/// can't mistake it for one. Tokens are signed with `key` when there is one.
+pub fn split_payload(
+    chunk_type: &ChunkType,
+    payload: &[u8],
+    max_chunk_size: usize,
+    key: Option<&[u8]>,
+) -> Result<Vec<Chunk>> {
+    let max_chunk_size = max_chunk_size.min(MAX_LENGTH);
+    if payload.len() <= max_chunk_size && !is_part(payload) {
+        return Ok(vec![Chunk::try_new(chunk_type.clone(), payload.to_vec())?]);
+    }
+    let count = part_count(payload.len(), max_chunk_size)?;
+    let room = max_chunk_size - header_len(count);
+    let mut pieces: Vec<&[u8]> = payload.chunks(room).collect();
+    // An empty payload still needs its single part
+    pieces.resize(count, &[]);
+    let mut previous = [0u8; TOKEN_LEN];
+    pieces
+        .into_iter()
+        .enumerate()
+        .map(|(index, piece)| {
+            let token = Token::compute(key, &previous, index, count, &sha256(piece));
+            previous = *token.bytes();
+            let mut data = header(index, count, &token).into_bytes();
+            data.extend_from_slice(piece);
+            Chunk::try_new(chunk_type.clone(), data)
+        })
+        .collect()
+}
+
+/// Number of parts a payload of `payload_len` bytes is split in when it does not fit a chunk
+fn part_count(payload_len: usize, max_chunk_size: usize) -> Result<usize> {
+    // More parts can mean a longer header and less room per part, grow until it settles
+    let mut count = 1;
+    loop {
+        let room = max_chunk_size
+            .checked_sub(header_len(count))
+            .filter(|room| *room > 0)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Chunk size limit of {} bytes leaves no room for payload after the split header",
+                    max_chunk_size
+                )
+            })?;
+        let needed = payload_len.div_ceil(room).max(1);
+        if needed <= count {
+            break;
+        }
+        count = needed;
+    }
+    Ok(count)
+}
+
+/// Number of chunks and total chunk data bytes [split_payload] writes for a payload of
+/// `payload_len` bytes, without needing the payload. Payloads looking like a part are not
+/// accounted for, they take one more header.
+pub fn split_size(payload_len: usize, max_chunk_size: usize) -> Result<(usize, usize)> {
+    let max_chunk_size = max_chunk_size.min(MAX_LENGTH);
+    if payload_len <= max_chunk_size {
+        return Ok((1, payload_len));
+    }
+    let count = part_count(payload_len, max_chunk_size)?;
+    let token = Token::Chained([0; TOKEN_LEN]);
+    let headers: usize = (0..count)
+        .map(|index| header(index, count, &token).len())
+        .sum();
+    Ok((count, payload_len + headers))
+}
+
+/// Payload of the first chunk of given type, put back together when it was split.
+/// Tokens are checked as [TokenChain] does with `key`.
+/// Returns `Ok(None)` when there is no chunk of that type.
+pub fn join_payload(
+    png: &PNG,
+    chunk_type: &ChunkType,
+    key: Option<&[u8]>,
+) -> Result<Option<Vec<u8>>> {
+    join_parts(
+        png.chunks()
+            .iter()
+            .filter(|chunk| chunk.chunk_type() == chunk_type)
+            .map(|chunk| chunk.data()),
+        key,
+    )
+    .map_err(|e| anyhow!("{} payload: {}", chunk_type, e))
+}
+
+/// Every payload of given type in file order, split payloads put back together
+pub fn join_all(png: &PNG, chunk_type: &ChunkType, key: Option<&[u8]>) -> Result<Vec<Vec<u8>>> {
+    let mut parts = png
+        .chunks()
+        .iter()
+        .filter(|chunk| chunk.chunk_type() == chunk_type)
+        .map(|chunk| chunk.data());
+    let mut payloads = Vec::new();
+    while let Some(payload) =
+        join_parts(parts.by_ref(), key).map_err(|e| anyhow!("{} payload: {}", chunk_type, e))?
+    {
+        payloads.push(payload);
+    }
+    Ok(payloads)
+}
+
+/// Put a payload back together from the data of consecutive chunks, the first one decides
+/// whether it was split. Parts after the last one are ignored.
+/// Returns `Ok(None)` when there is no data at all.
+pub fn join_parts<'a, I: IntoIterator<Item = &'a [u8]>>(
+    parts: I,
+    key: Option<&[u8]>,
+) -> Result<Option<Vec<u8>>> {
+    let mut parts = parts.into_iter();
+    let first = match parts.next() {
+        Some(first) => first,
+        None => return Ok(None),
+    };
+    let first = match read_part(first)? {
+        Some(part) => part,
+        None => return Ok(Some(first.to_vec())),
+    };
+    let count = first.count;
+    if first.index != 0 {
+        return Err(anyhow!(
+            "first chunk is part {} of a split payload, part 0 is missing",
+            first.index
+        ));
+    }
+    let mut chain = TokenChain::new(key);
+    chain.check(0, count, first.token, &sha256(first.piece))?;
+    let mut payload = first.piece.to_vec();
+    for expected in 1..count {
+        let data = parts.next().ok_or_else(|| {
+            anyhow!(
+                "split payload has {} parts, only {} were found",
+                count,
+                expected
+            )
+        })?;
+        match read_part(data)? {
+            Some(part) if part.index == expected && part.count == count => {
+                chain.check(part.index, count, part.token, &sha256(part.piece))?;
+                payload.extend_from_slice(part.piece);
+            }
+            _ => {
+                return Err(anyhow!(
+                    "expected part {} of {} of the split payload",
+                    expected,
+                    count
+                ))
+            }
+        }
+    }
+    Ok(Some(payload))
+}
+
+/// Like [join_payload], but reads a png stream and writes the payload to `writer` as it goes,
+/// so it is never held in memory. CRCs are checked on the way: on error, `writer` may already
+/// have received part of the payload. Tokens are checked as [TokenChain] does with `key`, at
+/// the end of each part.
+/// Returns the payload size, or `Ok(None)` when there is no chunk of that type.
+pub fn copy_payload<R: Read, W: Write>(
+    mut reader: R,
+    chunk_type: &ChunkType,
+    mut writer: W,
+    key: Option<&[u8]>,
+) -> Result<Option<u64>> {
+    let mut signature = [0u8; 8];
+    reader.read_exact(&mut signature)?;
+    if Format::from_signature(&signature).is_none() {
+        return Err(signature_error(&signature));
+    }
+    let mut written = 0u64;
+    let mut found = false;
+    // Next part index and part count once the first part was seen
+    let mut parts: Option<(usize, usize)> = None;
+    let mut chain = TokenChain::new(key);
+    loop {
+        let mut header = [0u8; 8];
+        match reader.read_exact(&mut header) {
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            result => result?,
+        }
+        let length = checked_length(
+            u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize,
+        )? as usize;
+        if header[4..] != chunk_type.bytes() {
+            let skipped = std::io::copy(
+                &mut reader.by_ref().take(length as u64 + 4),
+                &mut std::io::sink(),
+            )?;
+            if skipped != length as u64 + 4 {
+                return Err(anyhow!("Unexpected end of file inside a chunk"));
+            }
+            continue;
+        }
+        found = true;
+        let mut crc_hasher = Hasher::new();
+        crc_hasher.update(&header[4..]);
+        let mut prefix = vec![0u8; length.min(HEADER_PEEK)];
+        reader.read_exact(&mut prefix)?;
+        crc_hasher.update(&prefix);
+        let part = read_part(&prefix)?;
+        let position = part
+            .as_ref()
+            .map(|part| (part.index, part.count, part.token));
+        let body_offset = match (parts, part) {
+            (None, None) => 0,
+            (None, Some(part)) if part.index == 0 => {
+                parts = Some((0, part.count));
+                prefix.len() - part.piece.len()
+            }
+            (None, Some(part)) => {
+                return Err(anyhow!(
+                    "First {} chunk is part {} of a split payload, part 0 is missing",
+                    chunk_type,
+                    part.index
+                ))
+            }
+            (Some((next, count)), Some(part)) if part.index == next && part.count == count => {
+                prefix.len() - part.piece.len()
+            }
+            (Some((next, count)), _) => {
+                return Err(anyhow!(
+                    "Expected part {} of {} of the split payload",
+                    next,
+                    count
+                ))
+            }
+        };
+        writer.write_all(&prefix[body_offset..])?;
+        written += (prefix.len() - body_offset) as u64;
+        let mut digest = Sha256::new();
+        digest.update(&prefix[body_offset..]);
+        written += copy_checked(
+            &mut reader,
+            length - prefix.len(),
+            crc_hasher,
+            &mut writer,
+            &mut digest,
+        )?;
+        if let Some((index, count, token)) = position {
+            chain.check(index, count, token, &digest.finalize())?;
+        }
+        match parts.as_mut() {
+            None => break,
+            Some((next, count)) => {
+                *next += 1;
+                if next == count {
+                    break;
+                }
+            }
+        }
+    }
+    match parts {
+        Some((next, count)) if next < count => Err(anyhow!(
+            "Split payload has {} parts, only {} were found",
+            count,
+            next
+        )),
+        _ if !found => Ok(None),
+        _ => Ok(Some(written)),
+    }
+}
+
+/// Copy `remaining` data bytes of a chunk and check its CRC, `crc_hasher` has seen everything
+/// before them. The bytes are fed to `digest` too.
+fn copy_checked<R: Read, W: Write>(
+    reader: &mut R,
+    mut remaining: usize,
+    mut crc_hasher: Hasher,
+    writer: &mut W,
+    digest: &mut Sha256,
+) -> Result<u64> {
+    let copied = remaining as u64;
+    let mut buffer = vec![0u8; remaining.min(COPY_BUFFER_SIZE)];
+    while remaining > 0 {
+        let piece = &mut buffer[..remaining.min(COPY_BUFFER_SIZE)];
+        reader.read_exact(piece)?;
+        crc_hasher.update(piece);
+        digest.update(piece);
+        writer.write_all(piece)?;
+        remaining -= piece.len();
+    }
+    let mut crc = [0u8; 4];
+    reader.read_exact(&mut crc)?;
+    if u32::from_be_bytes(crc) != crc_hasher.finalize() {
+        return Err(anyhow!("CRC check failed"));
+    }
+    Ok(copied)
+}
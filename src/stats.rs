@@ -0,0 +1,197 @@
+#![allow(dead_code)]
+//! # Stats
+//! Where the bytes of a png go, per chunk type and per kind of chunk.
+//!
+//! Sizes count whole chunks, length, type and CRC fields included, so they add up to the file
+//! size together with the signature.
+use crate::chunk_type::ChunkType;
+use crate::graph::human_size;
+use crate::plan::json_string;
+use crate::png::PNG;
+use std::fmt::Write;
+
+/// Share of the file above which metadata and messages are pointed out
+pub const SIGNIFICANT_PERCENT: f64 = 10.0;
+
+/// Bytes taken by every chunk of one type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeStats {
+    pub chunk_type: ChunkType,
+    pub count: usize,
+    pub bytes: u64,
+}
+
+/// What [by_type] rows are ordered by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// Biggest first
+    Size,
+    /// Most chunks first
+    Count,
+    /// Chunk type name
+    Type,
+    /// First appearance in the file
+    Order,
+}
+
+/// Bytes per kind of chunk
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Breakdown {
+    pub signature: u64,
+    /// Critical chunks: header, palette, image data and trailer
+    pub image: u64,
+    /// Public ancillary chunks
+    pub metadata: u64,
+    /// Private ancillary chunks, see [is_message](ChunkType::is_message)
+    pub messages: u64,
+}
+
+impl Breakdown {
+    pub fn total(&self) -> u64 {
+        self.signature + self.image + self.metadata + self.messages
+    }
+}
+
+fn chunk_bytes(length: u32) -> u64 {
+    length as u64 + 12
+}
+
+/// Aggregate chunks per type, sorted by `sort`. Ties keep file order.
+pub fn by_type(png: &PNG, sort: SortKey) -> Vec<TypeStats> {
+    let mut stats: Vec<TypeStats> = Vec::new();
+    for chunk in png.chunks() {
+        match stats
+            .iter_mut()
+            .find(|stat| &stat.chunk_type == chunk.chunk_type())
+        {
+            Some(stat) => {
+                stat.count += 1;
+                stat.bytes += chunk_bytes(chunk.length());
+            }
+            None => stats.push(TypeStats {
+                chunk_type: chunk.chunk_type().clone(),
+                count: 1,
+                bytes: chunk_bytes(chunk.length()),
+            }),
+        }
+    }
+    match sort {
+        SortKey::Size => stats.sort_by_key(|stat| std::cmp::Reverse(stat.bytes)),
+        SortKey::Count => stats.sort_by_key(|stat| std::cmp::Reverse(stat.count)),
+        SortKey::Type => stats.sort_by_key(|stat| stat.chunk_type.to_string()),
+        SortKey::Order => {}
+    }
+    stats
+}
+
+pub fn breakdown(png: &PNG) -> Breakdown {
+    let mut breakdown = Breakdown {
+        signature: png.header().len() as u64,
+        ..Breakdown::default()
+    };
+    for chunk in png.chunks() {
+        let bytes = chunk_bytes(chunk.length());
+        let chunk_type = chunk.chunk_type();
+        if chunk_type.is_critical() {
+            breakdown.image += bytes;
+        } else if chunk_type.is_message() {
+            breakdown.messages += bytes;
+        } else {
+            breakdown.metadata += bytes;
+        }
+    }
+    breakdown
+}
+
+fn percent(bytes: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        bytes as f64 * 100.0 / total as f64
+    }
+}
+
+/// Per kind summary, followed by a note when metadata and messages take a significant share
+pub fn to_text(png: &PNG) -> String {
+    let breakdown = breakdown(png);
+    let total = breakdown.total();
+    let mut text = String::new();
+    for (name, bytes) in [
+        ("Signature", breakdown.signature),
+        ("Image", breakdown.image),
+        ("Metadata", breakdown.metadata),
+        ("Messages", breakdown.messages),
+    ] {
+        // Writing to a String can't fail
+        writeln!(
+            text,
+            "{:<10} {:>12} {:>6.1}%",
+            name,
+            human_size(bytes),
+            percent(bytes, total)
+        )
+        .unwrap();
+    }
+    writeln!(text, "{:<10} {:>12}", "Total", human_size(total)).unwrap();
+    let extra = percent(breakdown.metadata + breakdown.messages, total);
+    if extra >= SIGNIFICANT_PERCENT {
+        writeln!(text, "Metadata and messages take {:.1}% of the file", extra).unwrap();
+    }
+    text
+}
+
+/// One row per chunk type, ancillary types above [SIGNIFICANT_PERCENT] are marked with `!`
+pub fn by_type_to_text(png: &PNG, sort: SortKey) -> String {
+    let total = png.byte_len() as u64;
+    let mut text = String::new();
+    writeln!(
+        text,
+        "  {:<4} {:>6} {:>12} {:>7}",
+        "TYPE", "COUNT", "SIZE", "SHARE"
+    )
+    .unwrap();
+    for stat in by_type(png, sort) {
+        let share = percent(stat.bytes, total);
+        let marker = if !stat.chunk_type.is_critical() && share >= SIGNIFICANT_PERCENT {
+            '!'
+        } else {
+            ' '
+        };
+        writeln!(
+            text,
+            "{} {:<4} {:>6} {:>12} {:>6.1}%",
+            marker,
+            stat.chunk_type,
+            stat.count,
+            human_size(stat.bytes),
+            share
+        )
+        .unwrap();
+    }
+    text
+}
+
+/// Per kind summary and per type rows, sorted by `sort`, as one JSON object
+pub fn to_json(png: &PNG, sort: SortKey) -> String {
+    let breakdown = breakdown(png);
+    let types: Vec<String> = by_type(png, sort)
+        .iter()
+        .map(|stat| {
+            format!(
+                "{{\"type\":{},\"count\":{},\"bytes\":{}}}",
+                json_string(&stat.chunk_type.to_string()),
+                stat.count,
+                stat.bytes
+            )
+        })
+        .collect();
+    format!(
+        "{{\"signature\":{},\"image\":{},\"metadata\":{},\"messages\":{},\"total\":{},\"types\":[{}]}}",
+        breakdown.signature,
+        breakdown.image,
+        breakdown.metadata,
+        breakdown.messages,
+        breakdown.total(),
+        types.join(",")
+    )
+}
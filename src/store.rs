@@ -0,0 +1,138 @@
+#![allow(dead_code)]
+//! # Store
+//! A content addressed payload store living on the local file system.
+//!
+//! Instead of carrying a large payload itself, a png can carry a small reference chunk holding the
+//! hash of the payload (and optionally a URL it can be downloaded from). The payload bytes are
+//! written once into the store directory, named after their hash, so the same payload embedded
+//! into many images only takes space once. The hash algorithm is recorded in the reference, see
+//! [Digest].
+use crate::digest::{Digest, HashAlgorithm};
+use anyhow::{anyhow, Result};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Reference to a payload kept outside of the png file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PayloadRef {
+    /// Hash of the referenced payload
+    pub hash: Digest,
+    /// Where the payload can be fetched from when it is not in the local store
+    pub url: Option<String>,
+}
+
+impl PayloadRef {
+    /// Every reference chunk data starts with this prefix
+    pub const PREFIX: &'static str = "msgref:";
+
+    pub fn new(hash: Digest, url: Option<String>) -> Self {
+        Self { hash, url }
+    }
+
+    /// Serialized length of a reference hashed with `algorithm`, without url
+    pub fn len_without_url(algorithm: HashAlgorithm) -> usize {
+        Self::PREFIX.len() + algorithm.text_len()
+    }
+
+    /// Serialized form: `msgref:<algorithm>:<hex>`, followed by `\n<url>` when a url is present.
+    pub fn as_bytes(&self) -> Vec<u8> {
+        self.to_string().into_bytes()
+    }
+
+    /// Tells whether given chunk data looks like a payload reference
+    pub fn is_ref(data: &[u8]) -> bool {
+        data.starts_with(Self::PREFIX.as_bytes())
+    }
+}
+
+impl TryFrom<&[u8]> for PayloadRef {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        let text = std::str::from_utf8(value)?;
+        let text = text
+            .strip_prefix(Self::PREFIX)
+            .ok_or_else(|| anyhow!("Not a payload reference"))?;
+        let (hash, url) = match text.split_once('\n') {
+            Some((hash, url)) => (hash, Some(url.to_string())),
+            None => (text, None),
+        };
+        let hash = hash
+            .parse()
+            .map_err(|e| anyhow!("Malformed payload hash: {}", e))?;
+        Ok(Self { hash, url })
+    }
+}
+
+impl Display for PayloadRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", Self::PREFIX, self.hash)?;
+        if let Some(url) = &self.url {
+            write!(f, "\n{}", url)?;
+        }
+        Ok(())
+    }
+}
+
+/// Directory holding payloads named by the hash of their content: the bare hex for SHA-256,
+/// `<algorithm>-<hex>` for the others.
+pub struct PayloadStore {
+    root: PathBuf,
+}
+
+impl PayloadStore {
+    /// Open a store rooted at given directory, the directory is created when missing.
+    pub fn open(root: &Path) -> Result<Self> {
+        fs::create_dir_all(root)?;
+        Ok(Self {
+            root: root.to_path_buf(),
+        })
+    }
+
+    /// Path a payload with given hash is stored at
+    pub fn path_of(&self, hash: &Digest) -> PathBuf {
+        match hash.algorithm {
+            HashAlgorithm::Sha256 => self.root.join(hash.hex()),
+            algorithm => self.root.join(format!("{}-{}", algorithm, hash.hex())),
+        }
+    }
+
+    /// Write payload into the store unless an identical one is already there.
+    pub fn put(&self, payload: &[u8], algorithm: HashAlgorithm) -> Result<Digest> {
+        let hash = algorithm.hash(payload);
+        let path = self.path_of(&hash);
+        if !path.exists() {
+            fs::write(path, payload)?;
+        }
+        Ok(hash)
+    }
+
+    /// Read a payload back, its content is checked against the hash it was requested by.
+    pub fn get(&self, hash: &Digest) -> Result<Vec<u8>> {
+        let path = self.path_of(hash);
+        let payload =
+            fs::read(&path).map_err(|e| anyhow!("Payload {} is not in the store: {}", hash, e))?;
+        if !hash.matches(&payload) {
+            return Err(anyhow!("Stored payload {:?} is corrupted", path));
+        }
+        Ok(payload)
+    }
+
+    /// Resolve a reference against this store.
+    /// Fetching from `url` is not supported, it is only reported so the payload can be downloaded
+    /// into the store by other means.
+    pub fn resolve(&self, payload_ref: &PayloadRef) -> Result<Vec<u8>> {
+        if self.path_of(&payload_ref.hash).exists() {
+            return self.get(&payload_ref.hash);
+        }
+        match &payload_ref.url {
+            Some(url) => Err(anyhow!(
+                "Payload {} is not in the store, it can be downloaded from {}",
+                payload_ref.hash,
+                url
+            )),
+            None => Err(anyhow!("Payload {} is not in the store", payload_ref.hash)),
+        }
+    }
+}
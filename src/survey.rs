@@ -0,0 +1,278 @@
+#![allow(dead_code)]
+//! # Survey
+//! Validate every png under a directory and sum up how healthy the corpus is.
+//!
+//! Each file goes through [validate_stream], so memory use does not depend on file sizes. A file
+//! is ok, has warnings only, or has errors; a file that can't be read counts as an error. Error
+//! kinds are issue messages, which name the chunk type but not the chunk index, so the same
+//! problem found in many files adds up to one kind.
+//!
+//! [to_junit](Survey::to_junit) gives a JUnit XML report with a test case per file, for CI
+//! dashboards to show png health checks as test results.
+use crate::confine::PathPolicy;
+use crate::plan::json_string;
+use crate::tree;
+use crate::verify::{validate_stream, Issue, StreamOptions, StreamReport};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+/// Error kinds shown in a summary
+pub const TOP_ERROR_KINDS: usize = 5;
+
+/// Kind reported for files that could not be read
+pub const UNREADABLE_KIND: &str = "file can't be read";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    Ok,
+    Warnings,
+    Errors,
+}
+
+impl Health {
+    fn name(&self) -> &'static str {
+        match self {
+            Health::Ok => "ok",
+            Health::Warnings => "warnings",
+            Health::Errors => "errors",
+        }
+    }
+}
+
+/// Validation of one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileOutcome {
+    pub path: PathBuf,
+    /// Read error message when the file could not be read through
+    pub report: Result<StreamReport, String>,
+}
+
+impl FileOutcome {
+    pub fn check(path: &Path, options: StreamOptions) -> Self {
+        let report = File::open(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| validate_stream(BufReader::new(file), options))
+            .map_err(|e| format!("{:#}", e));
+        FileOutcome {
+            path: path.to_path_buf(),
+            report,
+        }
+    }
+
+    pub fn health(&self) -> Health {
+        match &self.report {
+            Err(_) => Health::Errors,
+            Ok(report) if !report.is_valid() => Health::Errors,
+            Ok(report) if !report.issues.is_empty() => Health::Warnings,
+            Ok(_) => Health::Ok,
+        }
+    }
+
+    /// Issues found, empty when the file could not be read
+    pub fn issues(&self) -> &[Issue] {
+        self.report.as_ref().map_or(&[], |report| &report.issues)
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.report.as_ref().map_or(0, |report| report.bytes)
+    }
+
+    /// `report` is the [StreamReport] as JSON, `null` along with an `error` for unreadable files
+    pub fn to_json(&self) -> String {
+        let (report, error) = match &self.report {
+            Ok(report) => (report.to_json(), "null".to_string()),
+            Err(message) => ("null".to_string(), json_string(message)),
+        };
+        format!(
+            "{{\"path\":{},\"health\":\"{}\",\"report\":{},\"error\":{}}}",
+            json_string(&self.path.display().to_string()),
+            self.health().name(),
+            report,
+            error
+        )
+    }
+}
+
+/// Validation of every png under a directory, in path order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Survey {
+    pub root: PathBuf,
+    pub files: Vec<FileOutcome>,
+}
+
+impl Survey {
+    /// Validate every png under `root` that `policy` allows
+    pub fn run(root: &Path, policy: &PathPolicy, options: StreamOptions) -> Result<Self> {
+        let files = tree::png_files(root, policy)?
+            .iter()
+            .map(|path| FileOutcome::check(path, options))
+            .collect();
+        Ok(Survey {
+            root: root.to_path_buf(),
+            files,
+        })
+    }
+
+    /// Number of files in given health
+    pub fn count(&self, health: Health) -> usize {
+        self.files
+            .iter()
+            .filter(|file| file.health() == health)
+            .count()
+    }
+
+    /// Bytes read over every file
+    pub fn bytes_scanned(&self) -> u64 {
+        self.files.iter().map(FileOutcome::bytes).sum()
+    }
+
+    /// The `n` most frequent error kinds with the number of files showing them, most frequent
+    /// first, ties in alphabetical order
+    pub fn top_error_kinds(&self, n: usize) -> Vec<(String, usize)> {
+        let mut kinds: HashMap<&str, usize> = HashMap::new();
+        for file in self.files.iter() {
+            let mut file_kinds: Vec<&str> = match &file.report {
+                Err(_) => vec![UNREADABLE_KIND],
+                Ok(report) => report
+                    .issues
+                    .iter()
+                    .filter(|issue| issue.is_error())
+                    .map(|issue| issue.message.as_str())
+                    .collect(),
+            };
+            file_kinds.sort_unstable();
+            file_kinds.dedup();
+            for kind in file_kinds {
+                *kinds.entry(kind).or_default() += 1;
+            }
+        }
+        let mut kinds: Vec<(String, usize)> = kinds
+            .into_iter()
+            .map(|(kind, count)| (kind.to_string(), count))
+            .collect();
+        kinds.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        kinds.truncate(n);
+        kinds
+    }
+
+    /// Every file outcome, the counts shown in a summary and the top `kinds` error kinds
+    pub fn to_json(&self, kinds: usize) -> String {
+        let top_errors: Vec<String> = self
+            .top_error_kinds(kinds)
+            .iter()
+            .map(|(kind, count)| format!("{{\"kind\":{},\"files\":{}}}", json_string(kind), count))
+            .collect();
+        format!(
+            "{{\"files\":[{}],\"ok\":{},\"warnings\":{},\"errors\":{},\"bytes\":{},\"top_errors\":[{}]}}",
+            self.files
+                .iter()
+                .map(FileOutcome::to_json)
+                .collect::<Vec<_>>()
+                .join(","),
+            self.count(Health::Ok),
+            self.count(Health::Warnings),
+            self.count(Health::Errors),
+            self.bytes_scanned(),
+            top_errors.join(",")
+        )
+    }
+
+    /// JUnit XML report: files with errors are failures, unreadable files are errors, warnings
+    /// go to the output of their test case
+    pub fn to_junit(&self) -> String {
+        let tests = self.files.len();
+        let errors = self
+            .files
+            .iter()
+            .filter(|file| file.report.is_err())
+            .count();
+        let failures = self.count(Health::Errors) - errors;
+        let suite = xml_escape(&self.root.display().to_string());
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let counts = format!(
+            "tests=\"{}\" failures=\"{}\" errors=\"{}\"",
+            tests, failures, errors
+        );
+        let _ = writeln!(xml, "<testsuites name=\"{}\" {}>", suite, counts);
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" {} skipped=\"0\">",
+            suite, counts
+        );
+        for file in self.files.iter() {
+            let name = file
+                .path
+                .strip_prefix(&self.root)
+                .unwrap_or(&file.path)
+                .display()
+                .to_string();
+            let _ = write!(
+                xml,
+                "    <testcase classname=\"png\" name=\"{}\"",
+                xml_escape(&name)
+            );
+            let (errors, warnings): (Vec<&Issue>, Vec<&Issue>) =
+                file.issues().iter().partition(|issue| issue.is_error());
+            if file.report.is_ok() && file.issues().is_empty() {
+                xml.push_str("/>\n");
+                continue;
+            }
+            xml.push_str(">\n");
+            match &file.report {
+                Err(message) => {
+                    let _ = writeln!(
+                        xml,
+                        "      <error type=\"unreadable\" message=\"{}\"/>",
+                        xml_escape(message)
+                    );
+                }
+                Ok(_) if !errors.is_empty() => {
+                    let _ = writeln!(
+                        xml,
+                        "      <failure type=\"invalid\" message=\"{}\">{}</failure>",
+                        xml_escape(&errors[0].to_string()),
+                        xml_escape(&lines(&errors))
+                    );
+                }
+                Ok(_) => {}
+            }
+            if !warnings.is_empty() {
+                let _ = writeln!(
+                    xml,
+                    "      <system-out>{}</system-out>",
+                    xml_escape(&lines(&warnings))
+                );
+            }
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+}
+
+fn lines(issues: &[&Issue]) -> String {
+    issues
+        .iter()
+        .map(|issue| issue.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Escape text for XML content and attributes, control characters XML can't hold are replaced
+fn xml_escape(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .map(|c| {
+            if c.is_control() && !matches!(c, '\t' | '\n' | '\r') {
+                char::REPLACEMENT_CHARACTER
+            } else {
+                c
+            }
+        })
+        .collect();
+    crate::xmp::escape(&text, true)
+}
@@ -0,0 +1,209 @@
+#![allow(dead_code)]
+//! # Sync
+//! Copy embedded message chunks between two directory trees holding the same images, for example
+//! an asset tree and its re-exported copy.
+//!
+//! A file is only touched when its counterpart in the destination tree has the same pixel content,
+//! see [PNG::content_id](crate::png::PNG::content_id).
+use crate::confine::PathPolicy;
+use crate::plan::json_string;
+use crate::png::PNG;
+use crate::tree::png_files;
+use anyhow::Result;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What happened to a single file during a sync
+#[derive(Debug, PartialEq, Eq)]
+pub enum SyncOutcome {
+    /// Given number of message chunks were copied into the destination file
+    Synced(usize),
+    /// Destination already carries every message chunk of the source file
+    UpToDate,
+    /// Source file has no message chunks at all
+    NoMessages,
+    /// There is no file with the same relative path in destination tree
+    MissingDestination,
+    /// Both files exist but their pixel content differs
+    ContentMismatch,
+    /// Destination carries different data under the same chunk types, nothing is copied
+    Conflict(Vec<String>),
+    /// One of the files could not be read or parsed as png
+    Unreadable(String),
+}
+
+/// Per file outcome of a sync, keyed by path relative to the tree roots
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub entries: Vec<(PathBuf, SyncOutcome)>,
+}
+
+impl SyncReport {
+    /// Total number of chunks copied
+    pub fn synced_chunks(&self) -> usize {
+        self.entries
+            .iter()
+            .map(|(_, outcome)| match outcome {
+                SyncOutcome::Synced(count) => *count,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Number of files that were skipped because of a conflict
+    pub fn conflicts(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|(_, outcome)| matches!(outcome, SyncOutcome::Conflict(_)))
+            .count()
+    }
+
+    /// One object per file with its outcome, `chunks` for synced files, `types` for conflicts
+    /// and `reason` for unreadable ones, followed by the totals
+    pub fn to_json(&self) -> String {
+        let files: Vec<String> = self
+            .entries
+            .iter()
+            .map(|(path, outcome)| {
+                let detail = match outcome {
+                    SyncOutcome::Synced(count) => format!(",\"chunks\":{}", count),
+                    SyncOutcome::Conflict(types) => format!(
+                        ",\"types\":[{}]",
+                        types
+                            .iter()
+                            .map(|chunk_type| json_string(chunk_type))
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    ),
+                    SyncOutcome::Unreadable(reason) => {
+                        format!(",\"reason\":{}", json_string(reason))
+                    }
+                    _ => String::new(),
+                };
+                let outcome = match outcome {
+                    SyncOutcome::Synced(_) => "synced",
+                    SyncOutcome::UpToDate => "up_to_date",
+                    SyncOutcome::NoMessages => "no_messages",
+                    SyncOutcome::MissingDestination => "missing_destination",
+                    SyncOutcome::ContentMismatch => "content_mismatch",
+                    SyncOutcome::Conflict(_) => "conflict",
+                    SyncOutcome::Unreadable(_) => "unreadable",
+                };
+                format!(
+                    "{{\"path\":{},\"outcome\":\"{}\"{}}}",
+                    json_string(&path.display().to_string()),
+                    outcome,
+                    detail
+                )
+            })
+            .collect();
+        format!(
+            "{{\"files\":[{}],\"synced_chunks\":{},\"conflicts\":{}}}",
+            files.join(","),
+            self.synced_chunks(),
+            self.conflicts()
+        )
+    }
+}
+
+impl Display for SyncReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (path, outcome) in self.entries.iter() {
+            let path = path.display();
+            match outcome {
+                SyncOutcome::Synced(count) => writeln!(f, "synced   {}: {} chunk(s)", path, count)?,
+                SyncOutcome::UpToDate => writeln!(f, "skipped  {}: up to date", path)?,
+                SyncOutcome::NoMessages => writeln!(f, "skipped  {}: no messages", path)?,
+                SyncOutcome::MissingDestination => {
+                    writeln!(f, "skipped  {}: missing in destination", path)?
+                }
+                SyncOutcome::ContentMismatch => {
+                    writeln!(f, "skipped  {}: pixel content differs", path)?
+                }
+                SyncOutcome::Conflict(types) => {
+                    writeln!(f, "conflict {}: {} differ", path, types.join(", "))?
+                }
+                SyncOutcome::Unreadable(reason) => writeln!(f, "error    {}: {}", path, reason)?,
+            }
+        }
+        writeln!(
+            f,
+            "{} file(s) checked, {} chunk(s) synced, {} conflict(s)",
+            self.entries.len(),
+            self.synced_chunks(),
+            self.conflicts()
+        )
+    }
+}
+
+/// Copy message chunks from every png under `source` into the file with the same relative path
+/// under `destination`. With `dry_run` set, the report is produced without writing anything.
+/// Source and destination files `policy` refuses are reported as unreadable.
+pub fn sync_meta(
+    source: &Path,
+    destination: &Path,
+    dry_run: bool,
+    policy: &PathPolicy,
+) -> Result<SyncReport> {
+    let mut report = SyncReport::default();
+    for source_path in png_files(source, policy)? {
+        let relative = source_path.strip_prefix(source)?.to_path_buf();
+        let destination_path = destination.join(&relative);
+        let outcome = policy
+            .check(&destination_path)
+            .and_then(|_| sync_file(&source_path, &destination_path, dry_run))
+            .unwrap_or_else(|e| SyncOutcome::Unreadable(e.to_string()));
+        report.entries.push((relative, outcome));
+    }
+    Ok(report)
+}
+
+fn sync_file(source_path: &Path, destination_path: &Path, dry_run: bool) -> Result<SyncOutcome> {
+    let source = PNG::try_from(fs::read(source_path)?.as_slice())?;
+    let messages: Vec<_> = source
+        .chunks()
+        .iter()
+        .filter(|chunk| chunk.chunk_type().is_message())
+        .collect();
+    if messages.is_empty() {
+        return Ok(SyncOutcome::NoMessages);
+    }
+    if !destination_path.exists() {
+        return Ok(SyncOutcome::MissingDestination);
+    }
+    let mut destination = PNG::try_from(fs::read(destination_path)?.as_slice())?;
+    if source.content_id() != destination.content_id() {
+        return Ok(SyncOutcome::ContentMismatch);
+    }
+
+    let mut conflicts = Vec::new();
+    let mut missing = Vec::new();
+    for message in messages {
+        let existing: Vec<_> = destination
+            .chunks()
+            .iter()
+            .filter(|chunk| chunk.chunk_type() == message.chunk_type())
+            .collect();
+        if existing.is_empty() {
+            missing.push(message);
+        } else if !existing.iter().any(|chunk| chunk.data() == message.data()) {
+            conflicts.push(message.chunk_type().to_string());
+        }
+    }
+    if !conflicts.is_empty() {
+        conflicts.dedup();
+        return Ok(SyncOutcome::Conflict(conflicts));
+    }
+    if missing.is_empty() {
+        return Ok(SyncOutcome::UpToDate);
+    }
+    let count = missing.len();
+    if !dry_run {
+        for message in missing {
+            destination.append_chunk(message.clone());
+        }
+        fs::write(destination_path, destination.as_bytes())?;
+    }
+    Ok(SyncOutcome::Synced(count))
+}
@@ -0,0 +1,229 @@
+#![allow(dead_code)]
+//! # Tee
+//! Pass a png stream through unchanged while picking messages out of it, for looking at files
+//! flowing through an existing pipeline.
+//!
+//! Every byte read is written to the output as soon as it is read, chunks are parsed on the way
+//! with no second pass. Anything after IEND, or after a parse error, is still copied through.
+use crate::chunk::checked_length;
+use crate::chunk_type::ChunkType;
+use crate::mime::{self, MimeType};
+use crate::plan::json_string;
+use crate::png::{signature_error, Format};
+use crate::split;
+use anyhow::{anyhow, Result};
+use crc32fast::Hasher;
+use std::io::{ErrorKind, Read, Write};
+
+/// A message found in the stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TeeMessage {
+    pub chunk_type: ChunkType,
+    /// Index of the chunk holding the message, or its first part
+    pub index: usize,
+    pub mime: Option<MimeType>,
+    pub message: Vec<u8>,
+}
+
+impl TeeMessage {
+    /// `ruSt[3]: message`, or a size for messages that are not UTF-8
+    pub fn to_text(&self) -> String {
+        let mime = match &self.mime {
+            Some(mime) => format!(" ({})", mime),
+            None => String::new(),
+        };
+        match std::str::from_utf8(&self.message) {
+            Ok(text) => format!("{}[{}]{}: {}", self.chunk_type, self.index, mime, text),
+            Err(_) => format!(
+                "{}[{}]{}: <{} bytes of binary data>",
+                self.chunk_type,
+                self.index,
+                mime,
+                self.message.len()
+            ),
+        }
+    }
+
+    /// One line JSON object, `message` is `null` when the message is not UTF-8
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"chunk_type\":\"{}\",\"index\":{},\"mime\":{},\"bytes\":{},\"message\":{}}}",
+            self.chunk_type,
+            self.index,
+            self.mime
+                .as_ref()
+                .map_or("null".to_string(), |mime| json_string(mime.as_str())),
+            self.message.len(),
+            std::str::from_utf8(&self.message).map_or("null".to_string(), json_string)
+        )
+    }
+}
+
+/// Copies everything read from `inner` to `copy`
+struct TeeReader<R, W> {
+    inner: R,
+    copy: W,
+}
+
+impl<R: Read, W: Write> Read for TeeReader<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.copy.write_all(&buf[..read])?;
+        Ok(read)
+    }
+}
+
+/// Parts of split messages waiting for the rest, per chunk type
+struct PendingParts {
+    chunk_type: ChunkType,
+    index: usize,
+    parts: Vec<Vec<u8>>,
+}
+
+/// Next chunk length and type, `None` at the end of input. Input ending within them is an error.
+fn read_header<R: Read>(reader: &mut R) -> Result<Option<[u8; 8]>> {
+    let mut header = [0u8; 8];
+    let mut filled = 0;
+    while filled < header.len() {
+        match reader.read(&mut header[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => return Err(anyhow!("Unexpected end of file inside a chunk header")),
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(Some(header))
+}
+
+/// Parse chunks from `reader`, report messages of `chunk_types` and skip over the others.
+/// Every message chunk counts when `chunk_types` is empty.
+fn scan<R: Read, F: FnMut(TeeMessage) -> Result<()>>(
+    mut reader: R,
+    chunk_types: &[ChunkType],
+    report: &mut F,
+) -> Result<usize> {
+    let mut signature = [0u8; 8];
+    reader.read_exact(&mut signature)?;
+    let trailer = Format::from_signature(&signature)
+        .ok_or_else(|| signature_error(&signature))?
+        .trailer_type();
+    let mut pending: Vec<PendingParts> = Vec::new();
+    let mut found = 0;
+    for index in 0.. {
+        let header = match read_header(&mut reader)? {
+            Some(header) => header,
+            None => break,
+        };
+        let length = checked_length(
+            u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize,
+        )? as usize;
+        let chunk_type = ChunkType::try_from([header[4], header[5], header[6], header[7]])?;
+        let wanted = if chunk_types.is_empty() {
+            chunk_type.is_message()
+        } else {
+            chunk_types.contains(&chunk_type)
+        };
+        if !wanted {
+            let skipped = std::io::copy(
+                &mut reader.by_ref().take(length as u64 + 4),
+                &mut std::io::sink(),
+            )?;
+            if skipped != length as u64 + 4 {
+                return Err(anyhow!("Unexpected end of file inside a chunk"));
+            }
+            if chunk_type.bytes() == trailer {
+                break;
+            }
+            continue;
+        }
+        let mut data = vec![0u8; length];
+        reader.read_exact(&mut data)?;
+        let mut crc = [0u8; 4];
+        reader.read_exact(&mut crc)?;
+        let mut hasher = Hasher::new();
+        hasher.update(&header[4..]);
+        hasher.update(&data);
+        if hasher.finalize() != u32::from_be_bytes(crc) {
+            return Err(anyhow!("CRC mismatch in {} chunk {}", chunk_type, index));
+        }
+        let payload = match split::parse_part(&data)? {
+            None => Some((index, data)),
+            Some((part, count, _)) => {
+                let position = pending
+                    .iter()
+                    .position(|parts| parts.chunk_type == chunk_type);
+                let parts = match (part, position) {
+                    (0, Some(position)) => {
+                        pending[position].index = index;
+                        pending[position].parts.clear();
+                        &mut pending[position]
+                    }
+                    (0, None) => {
+                        pending.push(PendingParts {
+                            chunk_type: chunk_type.clone(),
+                            index,
+                            parts: Vec::new(),
+                        });
+                        pending.last_mut().unwrap()
+                    }
+                    (_, Some(position)) => &mut pending[position],
+                    (_, None) => {
+                        return Err(anyhow!(
+                            "{} chunk {} is part {} of a split payload, part 0 is missing",
+                            chunk_type,
+                            index,
+                            part
+                        ))
+                    }
+                };
+                parts.parts.push(data);
+                if part + 1 < count {
+                    None
+                } else {
+                    let first = parts.index;
+                    let joined = split::join_parts(parts.parts.iter().map(Vec::as_slice), None)?;
+                    pending.retain(|parts| parts.chunk_type != chunk_type);
+                    joined.map(|payload| (first, payload))
+                }
+            }
+        };
+        if let Some((index, payload)) = payload {
+            let (mime, message) = mime::unwrap(&payload)?;
+            report(TeeMessage {
+                chunk_type,
+                index,
+                mime,
+                message: message.to_vec(),
+            })?;
+            found += 1;
+        }
+    }
+    if let Some(parts) = pending.first() {
+        return Err(anyhow!(
+            "Split {} payload starting at chunk {} is incomplete",
+            parts.chunk_type,
+            parts.index
+        ));
+    }
+    Ok(found)
+}
+
+/// Copy `reader` to `writer` unchanged, calling `report` for every message found on the way.
+/// The whole input is copied even when it is not a valid png, the parse error is returned once
+/// everything went through. Returns the number of messages found.
+pub fn tee<R: Read, W: Write, F: FnMut(TeeMessage) -> Result<()>>(
+    reader: R,
+    mut writer: W,
+    chunk_types: &[ChunkType],
+    mut report: F,
+) -> Result<usize> {
+    let mut reader = TeeReader {
+        inner: reader,
+        copy: &mut writer,
+    };
+    let scanned = scan(&mut reader, chunk_types, &mut report);
+    std::io::copy(&mut reader, &mut std::io::sink())?;
+    writer.flush()?;
+    scanned
+}
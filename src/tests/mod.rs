@@ -1,6 +1,70 @@
 //! # Unit Tests
 //! I hate write unit tests in the same place where my other code lives.
 //! So by moving all the tests to a dedicated `tests` mod, I can only test public functions.
+mod test_archive;
+mod test_args;
+mod test_attachment;
+mod test_base64;
+mod test_blake3;
+mod test_bundle;
+mod test_cgbi;
 mod test_chunk;
 mod test_chunk_type;
+mod test_color;
+mod test_confine;
+mod test_corpus;
+mod test_deflate;
+mod test_digest;
+mod test_dump;
+mod test_duplicates;
+mod test_encode;
+mod test_graph;
+mod test_guard;
+mod test_hook;
+mod test_i18n;
+mod test_ico;
+mod test_integration;
+mod test_lazy;
+mod test_manifest;
+mod test_merge;
+mod test_message_store;
+mod test_metadata;
+mod test_mime;
+mod test_ordering;
+mod test_permissions;
+mod test_plan;
 mod test_png;
+mod test_pointer;
+mod test_registry;
+mod test_sha256;
+mod test_sha512;
+mod test_sidecar;
+mod test_signature;
+mod test_sparse;
+mod test_split;
+mod test_stats;
+mod test_store;
+mod test_survey;
+mod test_sync;
+mod test_tee;
+mod test_text;
+mod test_verify;
+mod test_wizard;
+mod test_xmp;
+
+/// Chunk of given type and data, the type must be valid
+#[cfg(test)]
+pub(crate) fn chunk(chunk_type: &str, data: &[u8]) -> crate::chunk::Chunk {
+    use std::str::FromStr;
+    let chunk_type = crate::chunk_type::ChunkType::from_str(chunk_type).unwrap();
+    crate::chunk::Chunk::new(chunk_type, data.to_vec())
+}
+
+/// Png with a 13 byte IHDR, an IDAT holding `pixels`, the `ancillary` chunks and IEND
+#[cfg(test)]
+pub(crate) fn testing_png(pixels: &[u8], ancillary: &[crate::chunk::Chunk]) -> crate::png::PNG {
+    let mut chunks = vec![chunk("IHDR", &[0; 13]), chunk("IDAT", pixels)];
+    chunks.extend_from_slice(ancillary);
+    chunks.push(chunk("IEND", b""));
+    crate::png::PNG::from_chunks(chunks)
+}
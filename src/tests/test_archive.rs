@@ -0,0 +1,143 @@
+#[cfg(test)]
+mod tests {
+    use crate::archive::Container;
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_open_gzip() {
+        let bytes =
+            from_hex("1f8b08080000000002ff612e706e6700cb48cdc9c95728cf2fca49010085114a0d0b000000");
+        let (container, content) = Container::open(&bytes).unwrap();
+        assert_eq!(content, b"hello world".to_vec());
+        assert_eq!(container.kind(), "gzip");
+        assert_eq!(
+            container,
+            Container::Gzip {
+                name: Some(b"a.png".to_vec()),
+                mtime: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_open_zip() {
+        let bytes = from_hex(
+            "504b03041400000008000000215026e65a810f0000001700000005000000612e706e67cb48cdc9c957c8\
+             4022cbf38b725200504b010214031400000008000000215026e65a810f00000017000000050000000000\
+             000000000000800100000000612e706e67504b0506000000000100010033000000320000000000",
+        );
+        let (container, content) = Container::open(&bytes).unwrap();
+        assert_eq!(content, b"hello hello hello world".to_vec());
+        assert_eq!(container.kind(), "zip");
+        match container {
+            Container::Zip { name, method, .. } => {
+                assert_eq!(name, b"a.png".to_vec());
+                assert_eq!(method, 8);
+            }
+            other => panic!("Expected zip, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_open_limited() {
+        let gzip =
+            from_hex("1f8b08080000000002ff612e706e6700cb48cdc9c95728cf2fca49010085114a0d0b000000");
+        assert!(Container::open_limited(&gzip, 11).is_ok());
+        assert!(Container::open_limited(&gzip, 10).is_err());
+        let stored = Container::Zip {
+            name: b"a.png".to_vec(),
+            method: 0,
+            flags: 0,
+            time: 0,
+            date: 0,
+            local_extra: Vec::new(),
+            extra: Vec::new(),
+        }
+        .wrap(b"hello world");
+        assert!(Container::open_limited(&stored, 11).is_ok());
+        assert!(Container::open_limited(&stored, 10).is_err());
+    }
+
+    #[test]
+    fn test_rewrap() {
+        let containers = [
+            Container::Gzip {
+                name: Some(b"image.png".to_vec()),
+                mtime: 1650000000,
+            },
+            Container::Gzip {
+                name: None,
+                mtime: 0,
+            },
+            Container::Zip {
+                name: b"image.png".to_vec(),
+                method: 8,
+                flags: 0,
+                time: 0x6000,
+                date: 0x5021,
+                local_extra: Vec::new(),
+                extra: Vec::new(),
+            },
+            Container::Zip {
+                name: "ïmage.png".as_bytes().to_vec(),
+                method: 0,
+                // Name is UTF-8
+                flags: 0x0800,
+                time: 0,
+                date: 0x21,
+                // Extended timestamps, modification and access time locally
+                local_extra: from_hex("5455090003c0a9346200a93462"),
+                extra: from_hex("5455050003c0a93462"),
+            },
+        ];
+        for container in containers {
+            let bytes = container.wrap(b"new content");
+            let (reopened, content) = Container::open(&bytes).unwrap();
+            assert_eq!(reopened, container);
+            assert_eq!(content, b"new content".to_vec());
+        }
+    }
+
+    #[test]
+    fn test_rewrap_drops_data_descriptor_flag() {
+        let mut bytes = from_hex(
+            "504b03041400000008000000215026e65a810f0000001700000005000000612e706e67cb48cdc9c957c8\
+             4022cbf38b725200504b010214031400000008000000215026e65a810f00000017000000050000000000\
+             000000000000800100000000612e706e67504b0506000000000100010033000000320000000000",
+        );
+        // Data descriptor and UTF-8 name flags in the local and central headers
+        for offset in [6, 0x32 + 8] {
+            bytes[offset..offset + 2].copy_from_slice(&0x0808u16.to_le_bytes());
+        }
+        let (container, _) = Container::open(&bytes).unwrap();
+        let (reopened, content) = Container::open(&container.wrap(b"new content")).unwrap();
+        match reopened {
+            Container::Zip { flags, .. } => assert_eq!(flags, 0x0800),
+            other => panic!("Expected zip, got {:?}", other),
+        }
+        assert_eq!(content, b"new content".to_vec());
+    }
+
+    #[test]
+    fn test_open_plain_file() {
+        assert!(Container::open(b"\x89PNG\r\n\x1a\n").is_err());
+    }
+
+    #[test]
+    fn test_corrupted_gzip() {
+        let mut bytes = Container::Gzip {
+            name: None,
+            mtime: 0,
+        }
+        .wrap(b"content");
+        let crc_offset = bytes.len() - 8;
+        bytes[crc_offset] ^= 0xFF;
+        assert!(Container::open(&bytes).is_err());
+    }
+}
@@ -0,0 +1,38 @@
+#[cfg(test)]
+mod tests {
+    use crate::attachment::{is_attachment, unwrap, wrap, Attachment};
+    use std::path::Path;
+
+    #[test]
+    fn test_round_trip_binary() {
+        let content: Vec<u8> = (0..=255).collect();
+        let attachment = Attachment::of(Path::new("/some/dir/report.pdf"), &content).unwrap();
+        assert_eq!(attachment.name, "report.pdf");
+        assert_eq!(attachment.size, 256);
+
+        let data = wrap(&attachment, &content);
+        assert!(is_attachment(&data));
+        assert!(data.starts_with(b"msgfile:256 report.pdf\n"));
+        let (unwrapped, bytes) = unwrap(&data).unwrap();
+        assert_eq!(unwrapped, attachment);
+        assert_eq!(bytes, content.as_slice());
+    }
+
+    #[test]
+    fn test_name_with_spaces() {
+        let attachment = Attachment::of(Path::new("my notes.tar"), b"tar").unwrap();
+        let data = wrap(&attachment, b"tar");
+        assert_eq!(unwrap(&data).unwrap().0.name, "my notes.tar");
+    }
+
+    #[test]
+    fn test_unwrap_errors() {
+        assert!(unwrap(b"plain message").is_err());
+        assert!(unwrap(b"msgfile:3 a.txt").is_err());
+        assert!(unwrap(b"msgfile:4 a.txt\nabc").is_err());
+        assert!(unwrap(b"msgfile:x a.txt\nabc").is_err());
+        assert!(unwrap(b"msgfile:3 ../a.txt\nabc").is_err());
+        assert!(unwrap(b"msgfile:3 ..\nabc").is_err());
+        assert!(unwrap(b"msgfile:3\nabc").is_err());
+    }
+}
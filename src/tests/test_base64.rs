@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod tests {
+    use crate::base64::{decode, encode};
+
+    // Test vectors from RFC 4648
+    const VECTORS: [(&str, &str); 7] = [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
+
+    #[test]
+    fn test_encode() {
+        for (data, text) in VECTORS {
+            assert_eq!(encode(data.as_bytes()), text);
+        }
+        assert_eq!(encode(&[0xfb, 0xff]), "+/8=");
+    }
+
+    #[test]
+    fn test_decode() {
+        for (data, text) in VECTORS {
+            assert_eq!(decode(text).unwrap(), data.as_bytes());
+        }
+        let binary: Vec<u8> = (0..=255).collect();
+        assert_eq!(decode(&encode(&binary)).unwrap(), binary);
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed() {
+        assert!(decode("Zm9").is_err());
+        assert!(decode("Zm9*").is_err());
+        assert!(decode("Zg==Zm9v").is_err());
+        assert!(decode("Z===").is_err());
+    }
+}
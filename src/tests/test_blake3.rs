@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod tests {
+    use crate::blake3::{blake3, Blake3};
+    use crate::sha256::to_hex;
+
+    /// Input of the official test vectors
+    fn input(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_empty_digest() {
+        assert_eq!(
+            to_hex(&blake3(b"")),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+    }
+
+    #[test]
+    fn test_abc_digest() {
+        assert_eq!(
+            to_hex(&blake3(b"abc")),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+    }
+
+    #[test]
+    fn test_chunk_boundaries() {
+        assert_eq!(
+            to_hex(&blake3(&input(1))),
+            "2d3adedff11b61f14c886e35afa036736dcd87a74d27b5c1510225d0f592e213"
+        );
+        // Exactly one chunk, then the first one needing a parent node
+        assert_eq!(
+            to_hex(&blake3(&input(1024))),
+            "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7"
+        );
+        assert_eq!(
+            to_hex(&blake3(&input(1025))),
+            "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444"
+        );
+    }
+
+    #[test]
+    fn test_incremental_update() {
+        let data = input(5 * 1024 + 17);
+        for piece_len in [1, 63, 64, 1000, 1024] {
+            let mut hasher = Blake3::new();
+            for piece in data.chunks(piece_len) {
+                hasher.update(piece);
+            }
+            assert_eq!(hasher.finalize(), blake3(&data));
+        }
+    }
+}
@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod tests {
+    use crate::bundle::MessageBundle;
+    use crate::png::PNG;
+    use crate::text::{InternationalText, Keyword};
+    use std::str::FromStr;
+
+    const BUNDLE: &str = r#"
+# Greetings in a few languages
+en = "Hello"
+de = "Hallo"
+"zh-CN" = "你好" # chinese
+pt_BR = "Olá \"amigo\""
+"#;
+
+    #[test]
+    fn test_parse_bundle() {
+        let bundle = MessageBundle::from_toml(BUNDLE).unwrap();
+        assert_eq!(bundle.entries.len(), 4);
+        assert_eq!(bundle.entries[2], ("zh-CN".to_string(), "你好".to_string()));
+        assert_eq!(bundle.entries[3].1, "Olá \"amigo\"");
+    }
+
+    #[test]
+    fn test_parse_bundle_errors() {
+        assert!(MessageBundle::from_toml("").is_err());
+        assert!(MessageBundle::from_toml("en = Hello").is_err());
+        assert!(MessageBundle::from_toml("en = \"Hello").is_err());
+        assert!(MessageBundle::from_toml("en = \"a\"\nen = \"b\"").is_err());
+        let err = MessageBundle::from_toml("en = \"a\"\nde \"b\"").unwrap_err();
+        assert!(err.to_string().starts_with("line 2"));
+    }
+
+    #[test]
+    fn test_select_fallback() {
+        let bundle = MessageBundle::from_toml(BUNDLE).unwrap();
+        assert_eq!(bundle.select("de"), Some("Hallo"));
+        assert_eq!(bundle.select("DE-at"), Some("Hallo"));
+        assert_eq!(bundle.select("zh"), Some("你好"));
+        assert_eq!(bundle.select("pt-BR"), Some("Olá \"amigo\""));
+        assert_eq!(bundle.select("fr"), Some("Hello"));
+        assert_eq!(MessageBundle::default().select("en"), None);
+    }
+
+    #[test]
+    fn test_select_untagged_before_default() {
+        let bundle = MessageBundle::from_toml("de = \"Hallo\"\n\"\" = \"Hi\"").unwrap();
+        assert_eq!(bundle.select("fr"), Some("Hi"));
+    }
+
+    #[test]
+    fn test_bundle_png_round_trip() {
+        let bundle = MessageBundle::from_toml(BUNDLE).unwrap();
+        let mut png = PNG::from_chunks(Vec::new());
+        let greeting = Keyword::from_str("Greeting").unwrap();
+        for chunk in bundle.to_chunks(&greeting) {
+            png.insert_chunk(png.chunks().len(), chunk).unwrap();
+        }
+        assert_eq!(
+            MessageBundle::from_png(&png, &greeting, usize::MAX).unwrap(),
+            bundle
+        );
+        let other = Keyword::from_str("Other").unwrap();
+        assert!(MessageBundle::from_png(&png, &other, usize::MAX)
+            .unwrap()
+            .entries
+            .is_empty());
+    }
+
+    #[test]
+    fn test_bundle_png_limits_text() {
+        let greeting = Keyword::from_str("Greeting").unwrap();
+        let long = "Hallo ".repeat(1000);
+        let mut png = PNG::from_chunks(Vec::new());
+        png.push_chunk(
+            InternationalText::try_new(&greeting, "de", "", &long, true)
+                .unwrap()
+                .to_chunk(),
+        );
+        assert!(MessageBundle::from_png(&png, &greeting, 1000).is_err());
+        let other = Keyword::from_str("Other").unwrap();
+        assert!(MessageBundle::from_png(&png, &other, 1000).is_ok());
+        let bundle = MessageBundle::from_png(&png, &greeting, long.len()).unwrap();
+        assert_eq!(bundle.text_len(), long.len());
+    }
+}
@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod tests {
+    use crate::cgbi::{is_cgbi, to_standard, unfilter};
+    use crate::chunk::Chunk;
+    use crate::deflate::{deflate, zlib_decompress};
+    use crate::png::PNG;
+    use crate::tests::chunk;
+    use crate::verify::{validate_stream, verify, StreamOptions, CGBI_NOTE};
+
+    fn header(width: u32, height: u32, color_type: u8) -> Chunk {
+        let mut data = Vec::new();
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&[8, color_type, 0, 0, 0]);
+        chunk("IHDR", &data)
+    }
+
+    /// 2x2 BGRA premultiplied image. Row 0 uses the Sub filter, row 1 the Up filter.
+    fn cgbi_png() -> PNG {
+        // Pixels as BGRA, premultiplied: opaque red, half transparent green
+        // (premultiplied 255 -> 128), transparent black, opaque blue
+        let row0 = [0u8, 0, 255, 255, 0, 128, 0, 128];
+        let row1 = [0u8, 0, 0, 0, 255, 0, 0, 255];
+        let mut filtered = vec![1];
+        for x in 0..8 {
+            let left = if x >= 4 { row0[x - 4] } else { 0 };
+            filtered.push(row0[x].wrapping_sub(left));
+        }
+        filtered.push(2);
+        for x in 0..8 {
+            filtered.push(row1[x].wrapping_sub(row0[x]));
+        }
+        let compressed = deflate(&filtered);
+        let (first, second) = compressed.split_at(compressed.len() / 2);
+        PNG::from_chunks(vec![
+            chunk("CgBI", &[0x50, 0x00, 0x20, 0x06]),
+            header(2, 2, 6),
+            chunk("iDOT", &[0; 28]),
+            chunk("IDAT", first),
+            chunk("IDAT", second),
+            chunk("ruSt", b"message"),
+            chunk("IEND", b""),
+        ])
+    }
+
+    #[test]
+    fn test_is_cgbi() {
+        assert!(is_cgbi(&cgbi_png()));
+        assert!(!is_cgbi(&PNG::from_chunks(vec![header(1, 1, 6)])));
+    }
+
+    #[test]
+    fn test_unfilter() {
+        // Average and Paeth on a single channel
+        let data = [3, 10, 20, 4, 5, 5];
+        assert_eq!(unfilter(&data, 2, 2, 1).unwrap(), vec![10, 25, 15, 30]);
+        assert!(unfilter(&[5, 0, 0], 2, 1, 1).is_err());
+        assert!(unfilter(&[0, 0], 2, 1, 1).is_err());
+    }
+
+    #[test]
+    fn test_to_standard() {
+        let standard = to_standard(&cgbi_png()).unwrap();
+        let types: Vec<String> = standard
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "IDAT", "ruSt", "IEND"]);
+        assert!(verify(&standard).is_empty());
+        let raw = zlib_decompress(standard.chunk_by_type("IDAT").unwrap().data()).unwrap();
+        assert_eq!(
+            raw,
+            vec![
+                0, 255, 0, 0, 255, 0, 255, 0, 128, //
+                0, 0, 0, 0, 0, 0, 0, 255, 255,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_standard_unsupported() {
+        assert!(to_standard(&PNG::from_chunks(vec![header(1, 1, 6)])).is_err());
+        let grayscale = PNG::from_chunks(vec![
+            chunk("CgBI", &[0; 4]),
+            header(1, 1, 0),
+            chunk("IDAT", &deflate(&[0, 0])),
+            chunk("IEND", b""),
+        ]);
+        assert!(to_standard(&grayscale).is_err());
+    }
+
+    #[test]
+    fn test_verify_cgbi() {
+        let png = cgbi_png();
+        let issues = verify(&png);
+        assert_eq!(issues.len(), 1);
+        assert!(!issues[0].is_error());
+        assert_eq!(issues[0].message, CGBI_NOTE);
+        let report = validate_stream(png.as_bytes().as_slice(), StreamOptions::default()).unwrap();
+        assert_eq!(report.issues, issues);
+    }
+}
@@ -2,7 +2,7 @@
 
 #[cfg(test)]
 mod tests {
-    use crate::chunk::Chunk;
+    use crate::chunk::{checked_length, Chunk, CRC_CHECKPOINT_INTERVAL, MAX_LENGTH};
     use crate::chunk_type::ChunkType;
     use std::str::FromStr;
 
@@ -109,6 +109,116 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    fn large_data(len: usize, seed: u8) -> Vec<u8> {
+        (0..len)
+            .map(|i| (i as u8).wrapping_mul(31) ^ seed)
+            .collect()
+    }
+
+    #[test]
+    fn test_set_data() {
+        let mut chunk = testing_chunk();
+        chunk.set_data(b"This is where your secret message will be?".to_vec());
+        let expected = Chunk::new(
+            ChunkType::from_str("RuSt").unwrap(),
+            b"This is where your secret message will be?".to_vec(),
+        );
+        assert_eq!(chunk.crc(), expected.crc());
+        assert_eq!(chunk.length(), 42);
+    }
+
+    #[test]
+    fn test_set_type() {
+        let mut chunk = Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            large_data(CRC_CHECKPOINT_INTERVAL + 100, 3),
+        );
+        chunk.set_type(ChunkType::from_str("neWt").unwrap());
+        let expected = Chunk::new(
+            ChunkType::from_str("neWt").unwrap(),
+            large_data(CRC_CHECKPOINT_INTERVAL + 100, 3),
+        );
+        assert_eq!(chunk.crc(), expected.crc());
+        assert_eq!(chunk.as_bytes(), expected.as_bytes());
+    }
+
+    #[test]
+    fn test_set_data_across_checkpoints() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let mut data = large_data(CRC_CHECKPOINT_INTERVAL * 2 + 100, 7);
+        let mut chunk = Chunk::new(chunk_type.clone(), data.clone());
+        data[CRC_CHECKPOINT_INTERVAL + 5] ^= 0xff;
+        data.truncate(CRC_CHECKPOINT_INTERVAL * 2 - 1);
+        chunk.set_data(data.clone());
+        assert_eq!(chunk.crc(), Chunk::new(chunk_type, data).crc());
+    }
+
+    #[test]
+    fn test_replace_suffix() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let mut chunk = Chunk::new(
+            chunk_type.clone(),
+            large_data(CRC_CHECKPOINT_INTERVAL * 3, 1),
+        );
+        let suffix = large_data(CRC_CHECKPOINT_INTERVAL + 10, 2);
+        chunk
+            .replace_suffix(CRC_CHECKPOINT_INTERVAL * 2 + 3, &suffix)
+            .unwrap();
+
+        let mut expected = large_data(CRC_CHECKPOINT_INTERVAL * 2 + 3, 1);
+        expected.extend_from_slice(&suffix);
+        let expected = Chunk::new(chunk_type, expected);
+        assert_eq!(chunk.crc(), expected.crc());
+        assert_eq!(chunk.length(), expected.length());
+        assert_eq!(chunk.data(), expected.data());
+
+        // Checkpoints taken while replacing must still be valid for the next update
+        chunk
+            .replace_suffix(CRC_CHECKPOINT_INTERVAL * 3 + 1, b"tail")
+            .unwrap();
+        let expected = Chunk::new(ChunkType::from_str("ruSt").unwrap(), chunk.data().to_vec());
+        assert_eq!(chunk.crc(), expected.crc());
+    }
+
+    #[test]
+    fn test_replace_suffix_out_of_bounds() {
+        let mut chunk = testing_chunk();
+        assert!(chunk.replace_suffix(43, b"x").is_err());
+    }
+
+    #[test]
+    fn test_clone_shares_data_until_modified() {
+        let chunk = testing_chunk();
+        let mut clone = chunk.clone();
+        assert!(clone.shares_data(&chunk));
+        clone.replace_suffix(4, b"changed").unwrap();
+        assert!(!clone.shares_data(&chunk));
+        assert_eq!(chunk.as_bytes(), testing_chunk().as_bytes());
+        assert!(clone.data().ends_with(b"changed"));
+    }
+
+    #[test]
+    fn test_checked_length() {
+        assert_eq!(checked_length(0).unwrap(), 0);
+        assert_eq!(checked_length(MAX_LENGTH).unwrap(), 0x7fff_ffff);
+        assert!(checked_length(MAX_LENGTH + 1).is_err());
+        assert!(checked_length(usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_try_new() {
+        let chunk = Chunk::try_new(ChunkType::from_str("RuSt").unwrap(), vec![1, 2, 3]).unwrap();
+        assert_eq!(chunk.length(), 3);
+    }
+
+    #[test]
+    fn test_length_field_over_limit() {
+        let mut chunk_data = 0x8000_0000u32.to_be_bytes().to_vec();
+        chunk_data.extend_from_slice(b"RuSt");
+        chunk_data.extend_from_slice(&[0; 4]);
+        assert!(Chunk::try_from(chunk_data.as_ref()).is_err());
+    }
+
     #[test]
     pub fn test_chunk_trait_impls() {
         let data_length: u32 = 42;
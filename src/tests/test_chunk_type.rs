@@ -69,6 +69,13 @@ mod tests {
         assert!(!chunk.is_safe_to_copy());
     }
 
+    #[test]
+    pub fn test_chunk_type_is_message() {
+        assert!(ChunkType::from_str("ruSt").unwrap().is_message());
+        assert!(!ChunkType::from_str("tEXt").unwrap().is_message());
+        assert!(!ChunkType::from_str("RuSt").unwrap().is_message());
+    }
+
     #[test]
     pub fn test_valid_chunk_is_valid() {
         let chunk = ChunkType::from_str("RuSt").unwrap();
@@ -84,6 +91,68 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    fn parse_error(source: &str) -> ChunkTypeError {
+        ChunkType::from_str(source)
+            .unwrap_err()
+            .downcast::<ChunkTypeError>()
+            .unwrap()
+    }
+
+    #[test]
+    pub fn test_chunk_type_parse_errors() {
+        assert_eq!(parse_error(""), ChunkTypeError::Empty);
+        assert_eq!(
+            parse_error(" tEXt "),
+            ChunkTypeError::Whitespace(vec![0, 5])
+        );
+        assert_eq!(parse_error("tE\tXt"), ChunkTypeError::Whitespace(vec![2]));
+        // The Cyrillic е takes bytes 1 and 2
+        assert_eq!(parse_error("tеXt"), ChunkTypeError::NonAscii(vec![1, 2]));
+        assert_eq!(parse_error("ru5t"), ChunkTypeError::NotAlphabetic(vec![2]));
+        assert_eq!(parse_error("tEXtX"), ChunkTypeError::Length(5));
+        assert_eq!(
+            parse_error(" tEXt ").to_string(),
+            "Chunk type has whitespace at bytes 0, 5"
+        );
+        assert_eq!(
+            parse_error("ru5t").to_string(),
+            "Chunk type has characters other than ASCII letters at byte 2"
+        );
+    }
+
+    #[test]
+    pub fn test_chunk_type_from_invalid_bytes() {
+        let error = |bytes: [u8; 4]| {
+            ChunkType::try_from(bytes)
+                .unwrap_err()
+                .downcast::<ChunkTypeError>()
+                .unwrap()
+        };
+        assert_eq!(
+            error(*b"\0\0\0\0"),
+            ChunkTypeError::NotAlphabetic(vec![0, 1, 2, 3])
+        );
+        assert_eq!(error(*b"tE\xffX"), ChunkTypeError::NonAscii(vec![2]));
+        assert_eq!(error(*b"Rust"), ChunkTypeError::ReservedBit);
+    }
+
+    #[test]
+    pub fn test_chunk_type_is_registered() {
+        assert!(ChunkType::from_str("IHDR").unwrap().is_registered());
+        assert!(ChunkType::from_str("zTXt").unwrap().is_registered());
+        assert!(ChunkType::from_str("eXIf").unwrap().is_registered());
+        assert!(!ChunkType::from_str("ztxt").unwrap().is_registered());
+        assert!(!ChunkType::from_str("ruSt").unwrap().is_registered());
+    }
+
+    #[test]
+    pub fn test_chunk_type_display_escapes() {
+        let chunk_type = ChunkType {
+            inner: [b't', 0, 0xff, b't'],
+        };
+        assert_eq!(chunk_type.to_string(), "t\\x00\\xfft");
+    }
+
     #[test]
     pub fn test_chunk_type_string() {
         let chunk = ChunkType::from_str("RuSt").unwrap();
@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::color::{check, remove_redundant, SRGB_CHROMATICITIES, SRGB_GAMMA};
+    use crate::png::PNG;
+    use crate::tests::chunk;
+
+    fn testing_png(color_chunks: Vec<Chunk>) -> PNG {
+        let mut chunks = vec![chunk("IHDR", &[0; 13])];
+        chunks.extend(color_chunks);
+        chunks.push(chunk("IDAT", &[1, 2, 3]));
+        chunks.push(chunk("IEND", &[]));
+        PNG::from_chunks(chunks)
+    }
+
+    fn chrm(values: [u32; 8]) -> Chunk {
+        chunk(
+            "cHRM",
+            &values
+                .iter()
+                .flat_map(|v| v.to_be_bytes())
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn test_consistent_srgb() {
+        let png = testing_png(vec![
+            chunk("sRGB", &[0]),
+            chunk("gAMA", &SRGB_GAMMA.to_be_bytes()),
+            chrm(SRGB_CHROMATICITIES),
+        ]);
+        assert!(check(&png).is_empty());
+    }
+
+    #[test]
+    fn test_srgb_with_iccp() {
+        let png = testing_png(vec![
+            chunk("sRGB", &[0]),
+            chunk("iCCP", b"profile\0\0"),
+            chunk("gAMA", &100000u32.to_be_bytes()),
+        ]);
+        let conflicts = check(&png);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].redundant, Some(1));
+    }
+
+    #[test]
+    fn test_gamma_and_chromaticities_against_srgb() {
+        let mut chromaticities = SRGB_CHROMATICITIES;
+        chromaticities[2] = 70000;
+        let png = testing_png(vec![
+            chunk("sRGB", &[0]),
+            chunk("gAMA", &100000u32.to_be_bytes()),
+            chrm(chromaticities),
+        ]);
+        let conflicts = check(&png);
+        let redundant = conflicts
+            .iter()
+            .map(|conflict| conflict.redundant)
+            .collect::<Vec<_>>();
+        assert_eq!(redundant, vec![Some(2), Some(3)]);
+    }
+
+    #[test]
+    fn test_malformed_and_duplicated() {
+        let png = testing_png(vec![
+            chunk("sRGB", &[9]),
+            chunk("gAMA", &[0, 0, 0, 0]),
+            chunk("gAMA", &SRGB_GAMMA.to_be_bytes()),
+            chunk("cHRM", &[0; 5]),
+        ]);
+        let conflicts = check(&png);
+        assert_eq!(conflicts.len(), 4);
+        assert_eq!(conflicts[0].redundant, None);
+        assert_eq!(conflicts[1].redundant, None);
+        assert_eq!(conflicts[2].redundant, Some(3));
+        assert_eq!(conflicts[3].redundant, None);
+    }
+
+    #[test]
+    fn test_remove_redundant() {
+        let mut png = testing_png(vec![
+            chunk("sRGB", &[0]),
+            chunk("iCCP", b"profile\0\0"),
+            chunk("sRGB", &[0]),
+        ]);
+        let conflicts = check(&png);
+        assert_eq!(remove_redundant(&mut png, &conflicts), vec![1, 3]);
+        let types = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(types, vec!["IHDR", "iCCP", "IDAT", "IEND"]);
+        assert!(check(&png).is_empty());
+    }
+}
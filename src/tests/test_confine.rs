@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use crate::confine::{resolve, PathPolicy};
+    use crate::tree::png_files;
+    use std::fs;
+    use std::os::unix::fs::symlink;
+    use std::path::PathBuf;
+
+    fn testing_dir(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("msg-in-png-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("inside")).unwrap();
+        fs::create_dir_all(root.join("outside")).unwrap();
+        root
+    }
+
+    #[test]
+    fn test_default_allows_everything() {
+        let policy = PathPolicy::default();
+        assert!(policy.is_unrestricted());
+        assert!(policy.allows(&PathBuf::from("/does/not/exist.png")));
+    }
+
+    #[test]
+    fn test_refuses_symlinks() {
+        let root = testing_dir("confine-symlinks");
+        fs::write(root.join("inside/real.png"), b"png").unwrap();
+        symlink(root.join("inside/real.png"), root.join("inside/link.png")).unwrap();
+        let policy = PathPolicy::new(true, &[]).unwrap();
+        assert!(policy.allows(&root.join("inside/real.png")));
+        assert!(policy.check(&root.join("inside/link.png")).is_err());
+        assert!(PathPolicy::default().allows(&root.join("inside/link.png")));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_roots() {
+        let root = testing_dir("confine-roots");
+        fs::write(root.join("inside/a.png"), b"png").unwrap();
+        fs::write(root.join("outside/b.png"), b"png").unwrap();
+        let policy = PathPolicy::new(false, &[root.join("inside")]).unwrap();
+        assert!(policy.allows(&root.join("inside/a.png")));
+        assert!(!policy.allows(&root.join("outside/b.png")));
+        assert!(!policy.allows(&root.join("inside/../outside/b.png")));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_symlink_leaving_root() {
+        let root = testing_dir("confine-escape");
+        fs::write(root.join("outside/secret.png"), b"png").unwrap();
+        symlink(root.join("outside"), root.join("inside/escape")).unwrap();
+        let policy = PathPolicy::new(false, &[root.join("inside")]).unwrap();
+        assert!(!policy.allows(&root.join("inside/escape/secret.png")));
+        assert!(!policy.allows(&root.join("inside/escape/new.png")));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_output_not_existing_yet() {
+        let root = testing_dir("confine-output");
+        let policy = PathPolicy::new(false, &[root.join("inside")]).unwrap();
+        assert!(policy.allows(&root.join("inside/new.png")));
+        assert!(!policy.allows(&root.join("outside/new.png")));
+        assert_eq!(
+            resolve(&root.join("inside/new.png")).unwrap(),
+            root.canonicalize().unwrap().join("inside/new.png")
+        );
+        assert!(resolve(&root.join("missing/new.png")).is_err());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_missing_root() {
+        assert!(PathPolicy::new(false, &[PathBuf::from("/does/not/exist")]).is_err());
+    }
+
+    #[test]
+    fn test_tree_walk_skips_refused_files() {
+        let root = testing_dir("confine-tree");
+        fs::write(root.join("inside/a.png"), b"png").unwrap();
+        fs::write(root.join("outside/b.png"), b"png").unwrap();
+        symlink(root.join("outside/b.png"), root.join("inside/b.png")).unwrap();
+        let inside = root.join("inside");
+        assert_eq!(png_files(&inside, &PathPolicy::default()).unwrap().len(), 2);
+        let policy = PathPolicy::new(true, &[]).unwrap();
+        assert_eq!(
+            png_files(&inside, &policy).unwrap(),
+            vec![inside.join("a.png")]
+        );
+        let policy = PathPolicy::new(false, std::slice::from_ref(&inside)).unwrap();
+        assert_eq!(
+            png_files(&inside, &policy).unwrap(),
+            vec![inside.join("a.png")]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,118 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunk_type::ChunkType;
+    use crate::corpus::{
+        adam7_passes, animated, bit_depths, header, image, samples, scanlines,
+        with_zero_length_chunks,
+    };
+    use crate::deflate::zlib_decompress;
+    use crate::metadata::{COLOR_TYPE_GRAYSCALE, COLOR_TYPE_INDEXED, COLOR_TYPE_TRUECOLOR};
+    use crate::png::PNG;
+    use crate::split::join_payload;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_adam7_passes_cover_every_pixel() {
+        for (width, height) in [(1, 1), (3, 2), (8, 8), (13, 7), (33, 17)] {
+            let pixels: u32 = adam7_passes(width, height)
+                .iter()
+                .map(|(width, height)| width * height)
+                .sum();
+            assert_eq!(pixels, width * height);
+        }
+        assert_eq!(adam7_passes(1, 1)[1], (0, 1));
+    }
+
+    #[test]
+    fn test_scanlines_length() {
+        // One filter byte and 13 bits rounded up to 2 bytes per row
+        let gray = header(13, 5, COLOR_TYPE_GRAYSCALE, 1, false);
+        assert_eq!(scanlines(&gray).unwrap().len(), 5 * 3);
+        let truecolor = header(4, 2, COLOR_TYPE_TRUECOLOR, 16, false);
+        assert_eq!(scanlines(&truecolor).unwrap().len(), 2 * (1 + 4 * 6));
+        assert!(scanlines(&header(4, 4, COLOR_TYPE_TRUECOLOR, 4, false)).is_err());
+        assert!(scanlines(&header(4, 4, 5, 8, false)).is_err());
+    }
+
+    #[test]
+    fn test_every_valid_image_round_trips() {
+        let idat = ChunkType::from_str("IDAT").unwrap();
+        for color_type in [0, 2, 3, 4, 6] {
+            for bit_depth in bit_depths(color_type) {
+                for interlaced in [false, true] {
+                    let header = header(9, 6, color_type, *bit_depth, interlaced);
+                    let png = PNG::try_from(image(header).unwrap().as_bytes().as_slice()).unwrap();
+                    assert_eq!(png.image_header().unwrap(), header);
+                    assert_eq!(
+                        png.chunk_by_type("PLTE").is_some(),
+                        color_type == COLOR_TYPE_INDEXED
+                    );
+                    let pixels = join_payload(&png, &idat, None).unwrap().unwrap();
+                    assert_eq!(
+                        zlib_decompress(&pixels).unwrap(),
+                        scanlines(&header).unwrap()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_animated_sequence_numbers() {
+        let png = animated(header(4, 4, COLOR_TYPE_TRUECOLOR, 8, false), 3).unwrap();
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(
+            types,
+            ["IHDR", "acTL", "fcTL", "IDAT", "fcTL", "fdAT", "fcTL", "fdAT", "IEND"]
+        );
+        let sequence: Vec<u8> = png
+            .chunks()
+            .iter()
+            .filter(|chunk| ["fcTL", "fdAT"].contains(&chunk.chunk_type().to_string().as_str()))
+            .map(|chunk| chunk.data()[3])
+            .collect();
+        assert_eq!(sequence, [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_zero_length_chunks_keep_image_data() {
+        let header = header(8, 8, COLOR_TYPE_GRAYSCALE, 8, false);
+        let png = with_zero_length_chunks(header).unwrap();
+        let pixels: Vec<u8> = png
+            .chunks_by_type("IDAT")
+            .iter()
+            .flat_map(|chunk| chunk.data().to_vec())
+            .collect();
+        assert_eq!(
+            zlib_decompress(&pixels).unwrap(),
+            scanlines(&header).unwrap()
+        );
+        assert_eq!(png.chunk_by_type("zeRo").unwrap().length(), 0);
+    }
+
+    #[test]
+    fn test_samples() {
+        let samples = samples(1000).unwrap();
+        let mut names: Vec<&str> = samples.iter().map(|sample| sample.name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), samples.len());
+        for sample in samples.iter() {
+            let parsed = PNG::try_from(sample.bytes.as_slice());
+            match sample.name {
+                "corrupt-crc.png" | "max-length.png" => assert!(parsed.is_err()),
+                "large-chunk.png" => {
+                    assert_eq!(
+                        parsed.unwrap().chunk_by_type("laRg").unwrap().length(),
+                        1000
+                    )
+                }
+                name => assert!(parsed.is_ok(), "{} does not parse", name),
+            }
+        }
+    }
+}
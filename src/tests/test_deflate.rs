@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use crate::deflate::{
+        adler32, deflate, inflate, inflate_limited, zlib_compress, zlib_decompress,
+        zlib_decompress_limited,
+    };
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+    }
+
+    #[test]
+    fn test_decompress_fixed_block() {
+        let stream = from_hex("78dacb48cdc9c957c8402701680308b1");
+        assert_eq!(
+            zlib_decompress(&stream).unwrap(),
+            b"hello hello hello hello".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_decompress_dynamic_block() {
+        let stream = from_hex(
+            "78dabdcb510a80201084e1abcc01f2509bda2ae9122d4176fa5683e8047d4f3f0c53955d16b70923e510\
+             1535aa125b640189cfa5d0ded0779f0e597542fde542aff9c33fc21087a56383cba0199ce606387b5133",
+        );
+        let mut expected = b"msg-in-png hides messages in ancillary png chunks, ".repeat(3);
+        expected.extend_from_slice(
+            b"aaaaaaaaaabbbbbbbbbbbbcccccccddddddeeeeeefffffgggg zzzz yyyy xxxx",
+        );
+        assert_eq!(zlib_decompress(&stream).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_decompress_bad_checksum() {
+        let stream = from_hex("78dacb48cdc9c957c8402701680308b2");
+        assert!(zlib_decompress(&stream).is_err());
+    }
+
+    #[test]
+    fn test_decompress_truncated() {
+        let stream = from_hex("78dacb48cdc9c957c840");
+        assert!(zlib_decompress(&stream).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut data = b"Every chunk has a type, a length and a CRC. ".repeat(100);
+        data.extend((0..=255u8).cycle().take(70000));
+        assert_eq!(inflate(&deflate(&data)).unwrap(), data);
+        assert_eq!(zlib_decompress(&zlib_compress(&data)).unwrap(), data);
+        assert_eq!(inflate(&deflate(b"")).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_compresses_repetition() {
+        let data = b"msg".repeat(1000);
+        assert!(deflate(&data).len() < 100);
+    }
+
+    #[test]
+    fn test_stores_incompressible_data() {
+        // Xorshift noise, which LZ77 and fixed codes can only make bigger
+        let mut state = 0x2545_f491_u32;
+        let data: Vec<u8> = (0..70000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        let deflated = deflate(&data);
+        // Two stored blocks of 5 header bytes each
+        assert_eq!(deflated.len(), data.len() + 10);
+        assert_eq!(inflate(&deflated).unwrap(), data);
+        assert_eq!(zlib_decompress(&zlib_compress(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn test_inflate_limited_stops_bombs() {
+        let compressed = deflate(&vec![0; 1 << 20]);
+        assert!(compressed.len() < (1 << 20) / 100);
+        assert!(inflate_limited(&compressed, 1000).is_err());
+        let (output, consumed) = inflate_limited(&compressed, 1 << 20).unwrap();
+        assert_eq!(output.len(), 1 << 20);
+        assert_eq!(consumed, compressed.len());
+    }
+
+    #[test]
+    fn test_zlib_decompress_limited() {
+        let compressed = zlib_compress(&[b'a'; 4096]);
+        assert!(zlib_decompress_limited(&compressed, 4095).is_err());
+        assert_eq!(
+            zlib_decompress_limited(&compressed, 4096).unwrap().len(),
+            4096
+        );
+    }
+}
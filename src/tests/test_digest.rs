@@ -0,0 +1,57 @@
+#[cfg(test)]
+mod tests {
+    use crate::blake3::blake3;
+    use crate::digest::{Digest, HashAlgorithm};
+    use crate::sha256::sha256;
+
+    #[test]
+    fn test_default_is_blake3() {
+        assert_eq!(HashAlgorithm::default(), HashAlgorithm::Blake3);
+        assert_eq!(
+            HashAlgorithm::default().hash(b"payload").bytes(),
+            blake3(b"payload")
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for algorithm in HashAlgorithm::ALL {
+            let digest = algorithm.hash(b"payload");
+            let text = digest.to_string();
+            assert_eq!(text.len(), algorithm.text_len());
+            assert!(text.starts_with(&format!("{}:", algorithm)));
+            assert_eq!(text.parse::<Digest>().unwrap(), digest);
+            assert_eq!(
+                algorithm.to_string().parse::<HashAlgorithm>().unwrap(),
+                algorithm
+            );
+        }
+    }
+
+    #[test]
+    fn test_matches_with_recorded_algorithm() {
+        for algorithm in HashAlgorithm::ALL {
+            let digest = algorithm.hash(b"payload");
+            assert!(digest.matches(b"payload"));
+            assert!(!digest.matches(b"tampered"));
+        }
+        let sha256 = Digest::new(HashAlgorithm::Sha256, &sha256(b"payload")).unwrap();
+        assert!(sha256.matches(b"payload"));
+        assert_ne!(sha256, HashAlgorithm::Blake3.hash(b"payload"));
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!("md5".parse::<HashAlgorithm>().is_err());
+        assert!("sha256".parse::<Digest>().is_err());
+        assert!("md5:00".parse::<Digest>().is_err());
+        assert!("sha512:1234".parse::<Digest>().is_err());
+        assert!(format!("blake3:{}", "zz".repeat(32))
+            .parse::<Digest>()
+            .is_err());
+        assert!(format!("blake3:+{}", "0".repeat(63))
+            .parse::<Digest>()
+            .is_err());
+        assert!(Digest::new(HashAlgorithm::Sha512, &[0; 32]).is_err());
+    }
+}
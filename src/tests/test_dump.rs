@@ -0,0 +1,136 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::dump::{check, dump, dump_json, table, table_json, DUMP_HEADER};
+    use crate::png::PNG;
+    use crate::registry::Registry;
+    use crate::tests::chunk;
+    use crate::text::{InternationalText, Keyword};
+    use std::str::FromStr;
+
+    fn testing_png() -> PNG {
+        PNG::from_chunks(vec![chunk("IHDR", b"header"), chunk("IEND", b"")])
+    }
+
+    #[test]
+    fn test_dump_lines() {
+        let text = dump(&testing_png());
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], DUMP_HEADER);
+        assert!(lines[1].starts_with("IHDR length=6 crc="));
+        assert_eq!(
+            lines[2],
+            "IEND length=0 crc=ae426082 \
+             sha256=e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn test_dump_json() {
+        let json = dump_json(&testing_png());
+        assert!(json.starts_with("[{\"type\":\"IHDR\",\"length\":6,\"crc\":\""));
+        assert!(json.ends_with(
+            "{\"type\":\"IEND\",\"length\":0,\"crc\":\"ae426082\",\"sha256\":\
+             \"e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855\"}]"
+        ));
+    }
+
+    #[test]
+    fn test_check_matching_dump() {
+        let png = testing_png();
+        assert!(check(&png, &dump(&png)).is_empty());
+    }
+
+    #[test]
+    fn test_check_reports_changes() {
+        let stored = dump(&testing_png());
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            b"msg".to_vec(),
+        ));
+        let differences = check(&png, &stored);
+        assert_eq!(differences.len(), 2);
+        assert!(differences[0].starts_with("line 3: expected `IEND"));
+        assert!(differences[1].starts_with("line 4: unexpected `IEND"));
+    }
+
+    #[test]
+    fn test_table_rows() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            b"msg".to_vec(),
+        ));
+        let text = table(&png, None);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].trim_start().starts_with("INDEX"));
+        assert_eq!(
+            lines[1].split_whitespace().collect::<Vec<_>>()[..2],
+            ["0", "IHDR"]
+        );
+        assert!(lines[2].ends_with("ancillary  private  safe"));
+        assert_eq!(
+            lines[3].split_whitespace().collect::<Vec<_>>(),
+            ["2", "IEND", "0", "ae426082", "critical", "public", "unsafe"]
+        );
+    }
+
+    #[test]
+    fn test_table_json() {
+        let json = table_json(&testing_png(), None);
+        assert!(json.starts_with("[{\"index\":0,\"offset\":8,\"type\":\"IHDR\",\"length\":6,"));
+        assert!(json.ends_with(
+            "{\"index\":1,\"offset\":26,\"type\":\"IEND\",\"length\":0,\"crc\":\"ae426082\",\
+             \"critical\":true,\"public\":true,\"safe_to_copy\":false,\"keyword\":null,\
+             \"description\":null,\"problems\":[]}]"
+        ));
+    }
+
+    #[test]
+    fn test_table_keywords() {
+        let mut png = testing_png();
+        let keyword = Keyword::from_str("Title").unwrap();
+        png.insert_chunk(1, InternationalText::new(&keyword, "en", "Tea").to_chunk())
+            .unwrap();
+        let text = table(&png, None);
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].ends_with("COPY    KEYWORD"));
+        assert!(lines[2].ends_with("ancillary  public   safe    Title"));
+        assert!(lines[3].ends_with("unsafe"));
+        let json = table_json(&png, None);
+        assert!(json.contains("\"type\":\"iTXt\""));
+        assert!(json.contains("\"safe_to_copy\":true,\"keyword\":\"Title\","));
+    }
+
+    #[test]
+    fn test_table_registry() {
+        let registry = Registry::from_toml(
+            "[ruSt]\ndescription = \"Build metadata\"\nmax_length = 2\n[biNa]\nformat = \"utf8\"",
+        )
+        .unwrap();
+        let mut png = testing_png();
+        png.append_chunk(chunk("ruSt", b"msg"));
+        png.append_chunk(chunk("biNa", b"ok"));
+        let text = table(&png, Some(&registry));
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[3], "       Build metadata");
+        assert_eq!(
+            lines[4],
+            "       error: ruSt (Build metadata) is 3 bytes long, above the registered maximum of 2"
+        );
+        assert!(lines[5].starts_with("    2  biNa"));
+        assert!(lines[6].starts_with("    3  IEND"));
+        let json = table_json(&png, Some(&registry));
+        assert!(json.contains(
+            "\"description\":\"Build metadata\",\"problems\":[\"ruSt (Build metadata) is 3 bytes \
+             long, above the registered maximum of 2\"]}"
+        ));
+        assert!(json.contains("\"type\":\"biNa\",\"length\":2,"));
+        assert_eq!(json.matches("\"problems\":[]").count(), 3);
+    }
+}
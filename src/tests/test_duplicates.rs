@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod tests {
+    use crate::confine::PathPolicy;
+    use crate::duplicates::{find_duplicates, merge_group, merge_messages};
+    use crate::png::PNG;
+    use crate::tests::{chunk, testing_png};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn testing_tree(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("msg-in-png-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("nested")).unwrap();
+        root
+    }
+
+    fn write(path: &Path, png: &PNG) {
+        fs::write(path, png.as_bytes()).unwrap();
+    }
+
+    fn messages(png: &PNG) -> Vec<String> {
+        png.chunks()
+            .iter()
+            .filter(|chunk| chunk.chunk_type().is_message())
+            .map(|chunk| format!("{}:{}", chunk.chunk_type(), chunk.data_as_string().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_groups_files_by_pixel_content() {
+        let root = testing_tree("duplicates-groups");
+        write(
+            &root.join("a.png"),
+            &testing_png(b"px", &[chunk("ruSt", b"a")]),
+        );
+        write(&root.join("nested/b.png"), &testing_png(b"px", &[]));
+        write(&root.join("c.png"), &testing_png(b"other", &[]));
+        fs::write(root.join("broken.png"), b"not a png").unwrap();
+
+        let report = find_duplicates(&root, None, &PathPolicy::default()).unwrap();
+        assert_eq!(report.groups.len(), 1);
+        assert_eq!(
+            report.groups[0].paths,
+            vec![root.join("a.png"), root.join("nested/b.png")]
+        );
+        assert_eq!(report.groups[0].canonical(), root.join("a.png"));
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].0, root.join("broken.png"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_files_above_size_limit_are_skipped() {
+        let root = testing_tree("duplicates-size");
+        write(&root.join("a.png"), &testing_png(b"px", &[]));
+        write(&root.join("b.png"), &testing_png(b"px", &[]));
+
+        let report = find_duplicates(&root, Some(8), &PathPolicy::default()).unwrap();
+        assert!(report.groups.is_empty());
+        assert_eq!(report.skipped.len(), 2);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_merge_messages_adds_only_missing_ones() {
+        let mut canonical = testing_png(b"px", &[chunk("ruSt", b"a"), chunk("tEXt", b"kept")]);
+        let other = testing_png(
+            b"px",
+            &[
+                chunk("ruSt", b"a"),
+                chunk("ruSt", b"b"),
+                chunk("miNe", b"c"),
+                chunk("zTXt", b"z"),
+            ],
+        );
+        assert_eq!(merge_messages(&mut canonical, &other).unwrap(), 2);
+        assert_eq!(messages(&canonical), vec!["ruSt:a", "ruSt:b", "miNe:c"]);
+        assert!(canonical
+            .chunks()
+            .iter()
+            .all(|chunk| chunk.chunk_type().to_string() != "zTXt"));
+        assert!(canonical.ends_with_iend());
+    }
+
+    #[test]
+    fn test_merge_group_writes_canonical_only() {
+        let root = testing_tree("duplicates-merge");
+        let other = testing_png(b"px", &[chunk("miNe", b"b")]);
+        write(
+            &root.join("a.png"),
+            &testing_png(b"px", &[chunk("ruSt", b"a")]),
+        );
+        write(&root.join("b.png"), &other);
+
+        let report = find_duplicates(&root, None, &PathPolicy::default()).unwrap();
+        assert_eq!(merge_group(&report.groups[0]).unwrap(), 1);
+        let canonical = PNG::try_from(fs::read(root.join("a.png")).unwrap().as_slice()).unwrap();
+        assert_eq!(messages(&canonical), vec!["ruSt:a", "miNe:b"]);
+        assert_eq!(fs::read(root.join("b.png")).unwrap(), other.as_bytes());
+        assert_eq!(merge_group(&report.groups[0]).unwrap(), 0);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
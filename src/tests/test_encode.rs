@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod tests {
+    use crate::encode::embed;
+    use crate::png::PNG;
+    use crate::tests::chunk;
+
+    fn testing_png() -> PNG {
+        PNG::from_chunks(vec![
+            chunk("IHDR", b"header"),
+            chunk("ruSt", b"old"),
+            chunk("IEND", b""),
+        ])
+    }
+
+    #[test]
+    fn test_embed_report() {
+        let mut png = testing_png();
+        let original_size = png.as_bytes().len();
+        let report = embed(&mut png, vec![chunk("ruSt", b"hello")], 5).unwrap();
+        assert_eq!(report.chunk_index, 2);
+        assert_eq!(report.bytes_added, 17);
+        assert_eq!(report.file_size, original_size + 17);
+        assert_eq!(report.file_size, png.as_bytes().len());
+        assert_eq!(report.message_chunks, 2);
+        assert_eq!(report.compression_ratio, 1.0);
+        assert_eq!(png.chunks()[2].data(), b"hello");
+        assert!(png.ends_with_iend());
+    }
+
+    #[test]
+    fn test_embed_without_iend() {
+        let mut png = PNG::from_chunks(vec![chunk("IHDR", b"header")]);
+        let report = embed(&mut png, vec![chunk("ruSt", b"a"), chunk("ruSt", b"b")], 4).unwrap();
+        assert_eq!(report.chunk_index, 1);
+        assert_eq!(report.compression_ratio, 0.5);
+        assert_eq!(png.chunks().len(), 3);
+    }
+
+    #[test]
+    fn test_report_json() {
+        let mut png = testing_png();
+        let report = embed(&mut png, vec![chunk("ruSt", b"hello")], 5).unwrap();
+        assert_eq!(
+            report.to_json(),
+            format!(
+                "{{\"message_bytes\":5,\"bytes_added\":17,\"compression_ratio\":1.0000,\
+                 \"chunk_index\":2,\"file_size\":{},\"message_chunks\":2}}",
+                report.file_size
+            )
+        );
+    }
+}
@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod tests {
+    use crate::graph::{group_chunks, human_size, to_dot, to_json, to_mermaid, to_text};
+    use crate::png::PNG;
+    use crate::tests::chunk;
+
+    fn testing_png() -> PNG {
+        let zeroed = |chunk_type: &str, length: usize| chunk(chunk_type, &vec![0; length]);
+        PNG::from_chunks(vec![
+            zeroed("IHDR", 13),
+            zeroed("IDAT", 1000),
+            zeroed("IDAT", 1000),
+            zeroed("IDAT", 48),
+            zeroed("ruSt", 5),
+            zeroed("IEND", 0),
+        ])
+    }
+
+    #[test]
+    fn test_group_chunks() {
+        let groups = group_chunks(&testing_png());
+        assert_eq!(groups.len(), 4);
+        assert_eq!(groups[1].chunk_type.to_string(), "IDAT");
+        assert_eq!(groups[1].first_index, 1);
+        assert_eq!(groups[1].count, 3);
+        assert_eq!(groups[1].data_bytes, 2048);
+        assert_eq!(groups[2].first_index, 4);
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(
+            to_text(&testing_png()),
+            "      0 IHDR, 13 B\n    1-3 IDAT x3, 2.0 KiB\n      4 ruSt, 5 B\n      5 IEND, 0 B\n"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let json = to_json(&testing_png());
+        assert!(json.starts_with(
+            "[{\"type\":\"IHDR\",\"first_index\":0,\"count\":1,\"data_bytes\":13},\
+             {\"type\":\"IDAT\",\"first_index\":1,\"count\":3,\"data_bytes\":2048},"
+        ));
+        assert!(json.ends_with("\"first_index\":5,\"count\":1,\"data_bytes\":0}]"));
+    }
+
+    #[test]
+    fn test_dot() {
+        let dot = to_dot(&testing_png());
+        assert!(dot.starts_with("digraph png {"));
+        assert!(dot.contains("n1 [label=\"IDAT x3\\n2.0 KiB\", style=bold];"));
+        assert!(dot.contains("n2 [label=\"ruSt\\n5 B\", style=dashed];"));
+        assert!(dot.contains("n2 -> n3;"));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn test_mermaid() {
+        let mermaid = to_mermaid(&testing_png());
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        assert!(mermaid.contains("n0[\"IHDR<br/>13 B\"]"));
+        assert!(mermaid.contains("n2(\"ruSt<br/>5 B\")"));
+        assert!(mermaid.contains("n0 --> n1"));
+    }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(human_size(0), "0 B");
+        assert_eq!(human_size(1023), "1023 B");
+        assert_eq!(human_size(1536), "1.5 KiB");
+        assert_eq!(human_size(5 * 1024 * 1024), "5.0 MiB");
+    }
+}
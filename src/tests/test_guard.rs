@@ -0,0 +1,89 @@
+#[cfg(test)]
+mod tests {
+    use crate::guard::{run, Guard, LimitError, Limits};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    fn timeout(millis: u64) -> Limits {
+        Limits {
+            timeout: Some(Duration::from_millis(millis)),
+            memory: None,
+        }
+    }
+
+    #[test]
+    fn test_without_limits_runs_inline() {
+        let caller = thread::current().id();
+        let result = run(Limits::default(), move |_| {
+            Ok(thread::current().id() == caller)
+        });
+        assert!(result.unwrap());
+    }
+
+    #[test]
+    fn test_finishes_within_timeout() {
+        assert_eq!(run(timeout(5000), |_| Ok(42)).unwrap(), 42);
+        assert!(run(timeout(5000), |_| -> anyhow::Result<()> {
+            Err(anyhow::anyhow!("failed"))
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_slow_operation_is_abandoned() {
+        let wrote = Arc::new(AtomicBool::new(false));
+        let operation_wrote = wrote.clone();
+        let error = run(timeout(50), move |guard| {
+            thread::sleep(Duration::from_millis(300));
+            guard.commit()?;
+            operation_wrote.store(true, Ordering::SeqCst);
+            Ok(())
+        })
+        .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<LimitError>(),
+            Some(&LimitError::Timeout(Duration::from_millis(50)))
+        );
+        thread::sleep(Duration::from_millis(500));
+        assert!(!wrote.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_committed_operation_runs_to_end() {
+        let result = run(timeout(50), |guard| {
+            guard.commit()?;
+            thread::sleep(Duration::from_millis(200));
+            Ok("written")
+        });
+        assert_eq!(result.unwrap(), "written");
+    }
+
+    #[test]
+    fn test_check_memory() {
+        let limits = Limits {
+            timeout: None,
+            memory: Some(100),
+        };
+        run(limits, |guard| {
+            assert!(guard.check_memory("file", 100).is_ok());
+            let error = guard.check_memory("file", 101).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<LimitError>(),
+                Some(LimitError::Memory {
+                    bytes: 101,
+                    limit: 100,
+                    ..
+                })
+            ));
+            assert_eq!(guard.memory_left(30), Some(70));
+            assert_eq!(guard.memory_left(300), Some(0));
+            Ok(())
+        })
+        .unwrap();
+        let unlimited = Guard::unlimited();
+        assert!(unlimited.check_memory("file", u64::MAX).is_ok());
+        assert_eq!(unlimited.memory_left(30), None);
+    }
+}
@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::hook::check_file;
+    use crate::png::PNG;
+    use crate::registry::Registry;
+    use std::path::Path;
+    use std::str::FromStr;
+
+    fn testing_png_bytes(with_stamp: bool) -> Vec<u8> {
+        let mut png = PNG::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("IDAT").unwrap(), vec![1, 2, 3]),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![]),
+        ]);
+        if with_stamp {
+            png.append_chunk(Chunk::new(
+                ChunkType::from_str("stMp").unwrap(),
+                b"v1".to_vec(),
+            ));
+        }
+        png.as_bytes()
+    }
+
+    #[test]
+    fn test_file_with_stamp_passes() {
+        let required = vec![ChunkType::from_str("stMp").unwrap()];
+        let diagnostics = check_file(
+            Path::new("a.png"),
+            &testing_png_bytes(true),
+            &required,
+            None,
+        );
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_missing_stamp_is_reported() {
+        let required = vec![ChunkType::from_str("stMp").unwrap()];
+        let diagnostics = check_file(
+            Path::new("a.png"),
+            &testing_png_bytes(false),
+            &required,
+            None,
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "a.png: error: missing required stMp chunk"
+        );
+    }
+
+    #[test]
+    fn test_unparsable_file_is_reported() {
+        let diagnostics = check_file(Path::new("b.png"), b"not a png", &[], None);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].issue.is_error());
+    }
+
+    #[test]
+    fn test_registry_is_checked() {
+        let registry = Registry::from_toml("[stMp]\nmin_length = 3\n").unwrap();
+        let diagnostics = check_file(
+            Path::new("a.png"),
+            &testing_png_bytes(true),
+            &[],
+            Some(&registry),
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].issue.chunk_index, Some(2));
+    }
+}
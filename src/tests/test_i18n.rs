@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod tests {
+    use crate::i18n::{format, Catalog, Locale, Messages};
+
+    const EN: &str = include_str!("../../locales/en.ftl");
+    const ZH: &str = include_str!("../../locales/zh.ftl");
+
+    #[test]
+    fn test_catalogs_have_the_same_ids() {
+        let en = Catalog::parse(EN).unwrap();
+        let zh = Catalog::parse(ZH).unwrap();
+        for id in en.ids() {
+            assert!(zh.get(id).is_some(), "zh is missing {}", id);
+        }
+        for id in zh.ids() {
+            assert!(en.get(id).is_some(), "en is missing {}", id);
+        }
+    }
+
+    #[test]
+    fn test_parse_catalog() {
+        let catalog =
+            Catalog::parse("# comment\n\ngreeting = Hello { $name }\nlong = first\n    second\n")
+                .unwrap();
+        assert_eq!(catalog.get("greeting"), Some("Hello { $name }"));
+        assert_eq!(catalog.get("long"), Some("first\nsecond"));
+        assert_eq!(catalog.get("missing"), None);
+    }
+
+    #[test]
+    fn test_parse_catalog_errors() {
+        assert!(Catalog::parse("a = one\na = two\n").is_err());
+        assert!(Catalog::parse("not a message\n").is_err());
+        assert!(Catalog::parse("bad id! = text\n").is_err());
+    }
+
+    #[test]
+    fn test_from_tag() {
+        assert_eq!(Locale::from_tag("zh_CN.UTF-8"), Some(Locale::Zh));
+        assert_eq!(Locale::from_tag("zh-TW"), Some(Locale::Zh));
+        assert_eq!(Locale::from_tag("en_US.UTF-8"), Some(Locale::En));
+        assert_eq!(Locale::from_tag("C"), Some(Locale::En));
+        assert_eq!(Locale::from_tag("POSIX"), Some(Locale::En));
+        assert_eq!(Locale::from_tag("fr_FR"), None);
+        assert_eq!(Locale::from_tag(""), None);
+    }
+
+    #[test]
+    fn test_detect_explicit() {
+        assert_eq!(Locale::detect(Some("zh")), Locale::Zh);
+        assert_eq!(Locale::detect(Some("en")), Locale::En);
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            format(
+                "{ $count } of { $total }",
+                &[("count", &1), ("total", &"3")]
+            ),
+            "1 of 3"
+        );
+        assert_eq!(format("{ $missing } left", &[]), "{ $missing } left");
+        assert_eq!(format("unclosed { $x", &[("x", &1)]), "unclosed { $x");
+    }
+
+    #[test]
+    fn test_messages() {
+        let zh = Messages::new(Locale::Zh);
+        assert_eq!(zh.locale(), Locale::Zh);
+        assert_ne!(
+            zh.get("validate-invalid", &[]),
+            Messages::new(Locale::En).get("validate-invalid", &[])
+        );
+        assert_eq!(zh.get("no-such-message", &[]), "no-such-message");
+    }
+}
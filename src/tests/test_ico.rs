@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod tests {
+    use crate::archive::Container;
+    use crate::ico::{Ico, IcoEntry, KIND_ICON};
+    use crate::png::PNG;
+    use crate::tests::chunk;
+
+    fn png_bytes() -> Vec<u8> {
+        PNG::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("IDAT", b"pixels"),
+            chunk("IEND", b""),
+        ])
+        .as_bytes()
+    }
+
+    fn entry(width: u8, data: Vec<u8>) -> IcoEntry {
+        IcoEntry {
+            width,
+            height: width,
+            colors: 0,
+            reserved: 0,
+            planes: 1,
+            bit_count: 32,
+            data,
+        }
+    }
+
+    fn testing_ico() -> Ico {
+        Ico {
+            kind: KIND_ICON,
+            entries: vec![entry(16, b"BMP image data".to_vec()), entry(0, png_bytes())],
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let ico = testing_ico();
+        let bytes = ico.to_bytes();
+        assert!(Ico::is_ico(&bytes));
+        // Images follow the header and the two directory entries
+        assert_eq!(&bytes[6 + 12..6 + 16], &38u32.to_le_bytes());
+        assert_eq!(Ico::parse(&bytes).unwrap(), ico);
+        assert_eq!(ico.png_entries(), vec![1]);
+    }
+
+    #[test]
+    fn test_png_of_bmp_entry() {
+        let ico = testing_ico();
+        assert!(ico.png(0).unwrap_err().to_string().contains("[1]"));
+        assert!(ico.png(2).is_err());
+        assert_eq!(ico.png(1).unwrap(), png_bytes().as_slice());
+    }
+
+    #[test]
+    fn test_parse_rejects_entry_outside_file() {
+        let mut bytes = testing_ico().to_bytes();
+        bytes.truncate(bytes.len() - 1);
+        assert!(Ico::parse(&bytes).is_err());
+        assert!(Ico::parse(b"\x00\x00\x03\x00\x00\x00").is_err());
+    }
+
+    #[test]
+    fn test_container_rewrites_sizes() {
+        let original = Ico {
+            kind: KIND_ICON,
+            entries: vec![entry(0, png_bytes()), entry(16, b"BMP image data".to_vec())],
+        };
+        let (container, content) = Container::open_ico(&original.to_bytes(), 0).unwrap();
+        assert_eq!(container.kind(), "ico");
+        let mut png = PNG::try_from(content.as_slice()).unwrap();
+        png.append_chunk(chunk("ruSt", b"version 1.2.3"));
+
+        let wrapped = Ico::parse(&container.wrap(&png.as_bytes())).unwrap();
+        assert_eq!(wrapped.entries[0].data, png.as_bytes());
+        // The BMP after the grown png moved but is intact
+        assert_eq!(wrapped.entries[1], original.entries[1]);
+        assert!(Container::open_ico(&original.to_bytes(), 1).is_err());
+    }
+}
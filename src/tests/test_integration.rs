@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunk_type::ChunkType;
+    use crate::integration::{install, uninstall, Integration, Platform, DEFAULT_TERMINAL};
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    fn integration(platform: Platform, base_dir: PathBuf) -> Integration {
+        Integration {
+            platform,
+            executable: PathBuf::from("/opt/msg in png/msg-in-png"),
+            chunk_type: ChunkType::from_str("ruSt").unwrap(),
+            terminal: DEFAULT_TERMINAL.to_string(),
+            base_dir,
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("msg-in-png-integration-{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_linux_service_menu() {
+        let files = integration(Platform::Linux, PathBuf::from("/home/user")).files();
+        assert_eq!(files.len(), 1);
+        assert_eq!(
+            files[0].path,
+            PathBuf::from("/home/user/.local/share/kio/servicemenus/msg-in-png.desktop")
+        );
+        assert!(files[0].executable);
+        let contents = &files[0].contents;
+        assert!(contents.contains("MimeType=image/png;"));
+        assert!(contents.contains("Exec=\"/opt/msg in png/msg-in-png\" -p %f decode --open ruSt"));
+        assert!(contents.contains(
+            "Exec=x-terminal-emulator -e \"/opt/msg in png/msg-in-png\" -p %f encode --interactive"
+        ));
+    }
+
+    #[test]
+    fn test_macos_quick_actions() {
+        let files = integration(Platform::MacOs, PathBuf::from("/Users/user")).files();
+        let paths: Vec<String> = files
+            .iter()
+            .map(|file| file.path.display().to_string())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "/Users/user/Library/Services/Decode message.workflow/Contents/Info.plist",
+                "/Users/user/Library/Services/Decode message.workflow/Contents/document.wflow",
+                "/Users/user/Library/Services/Add message.workflow/Contents/Info.plist",
+                "/Users/user/Library/Services/Add message.workflow/Contents/document.wflow",
+            ]
+        );
+        assert!(files[0]
+            .contents
+            .contains("<string>Decode message</string>"));
+        assert!(files[1]
+            .contents
+            .contains("'/opt/msg in png/msg-in-png' -p \"$f\" decode --open ruSt"));
+        // Script is escaped inside the plist
+        assert!(files[3].contents.contains("&amp; \" -p \" &amp;"));
+    }
+
+    #[test]
+    fn test_windows_registry() {
+        let mut windows = integration(Platform::Windows, PathBuf::from(r"C:\Users\user\AppData"));
+        windows.executable = PathBuf::from(r"C:\Tools\msg-in-png.exe");
+        let files = windows.files();
+        assert_eq!(files.len(), 1);
+        let contents = &files[0].contents;
+        assert!(contents.starts_with("Windows Registry Editor Version 5.00\r\n"));
+        assert!(contents.contains(
+            r"[HKEY_CURRENT_USER\Software\Classes\SystemFileAssociations\.png\shell\msg-in-png.decode\command]"
+        ));
+        assert!(
+            contents.contains(r#"@="\"C:\\Tools\\msg-in-png.exe\" -p \"%1\" decode --open ruSt""#)
+        );
+        assert!(contents.contains("cmd.exe /K"));
+    }
+
+    #[test]
+    fn test_install_and_uninstall() {
+        for (name, platform) in [("linux", Platform::Linux), ("macos", Platform::MacOs)] {
+            let dir = temp_dir(name);
+            let integration = integration(platform, dir.clone());
+            let installed = install(&integration).unwrap();
+            assert!(installed.iter().all(|path| path.is_file()));
+            let removed = uninstall(&integration).unwrap();
+            assert!(!removed.is_empty());
+            assert!(installed.iter().all(|path| !path.exists()));
+            assert!(uninstall(&integration).unwrap().is_empty());
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
@@ -0,0 +1,159 @@
+#[cfg(test)]
+mod tests {
+    use crate::lazy::{ChunkEntry, LazyPng};
+    use crate::png::PNG;
+    use crate::tests::chunk;
+    use std::fs::{self, OpenOptions};
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    fn testing_png() -> PNG {
+        PNG::from_chunks(vec![
+            chunk("IHDR", b"header"),
+            chunk("IDAT", &[7; 4096]),
+            chunk("ruSt", b"message"),
+            chunk("IEND", b""),
+        ])
+    }
+
+    fn testing_file(name: &str, png: &PNG) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "msg-in-png-lazy-{}-{}.png",
+            name,
+            std::process::id()
+        ));
+        fs::write(&path, png.as_bytes()).unwrap();
+        path
+    }
+
+    fn open_file(path: &PathBuf) -> LazyPng<fs::File> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .unwrap();
+        LazyPng::open(file).unwrap()
+    }
+
+    #[test]
+    fn test_entries_match_png() {
+        let png = testing_png();
+        let lazy = LazyPng::open(Cursor::new(png.as_bytes())).unwrap();
+        assert_eq!(lazy.entries(), ChunkEntry::list(&png).as_slice());
+        assert_eq!(lazy.entries()[2].offset, 8 + 18 + 4108);
+        assert_eq!(lazy.signature(), &PNG::STANDARD_HEADER);
+    }
+
+    #[test]
+    fn test_data_on_demand() {
+        let png = testing_png();
+        let mut lazy = LazyPng::open(Cursor::new(png.as_bytes())).unwrap();
+        let index = lazy.position_of("ruSt", 0).unwrap().unwrap();
+        assert_eq!(lazy.data(index).unwrap(), b"message");
+        assert!(lazy.position_of("ruSt", 1).unwrap().is_none());
+        assert!(lazy.data(4).is_err());
+        assert_eq!(lazy.to_png().unwrap().as_bytes(), png.as_bytes());
+    }
+
+    #[test]
+    fn test_prefix() {
+        let mut lazy = LazyPng::open(Cursor::new(testing_png().as_bytes())).unwrap();
+        assert_eq!(lazy.prefix(2, 3).unwrap(), b"mes");
+        assert_eq!(lazy.prefix(2, 80).unwrap(), b"message");
+        assert!(lazy.prefix(3, 80).unwrap().is_empty());
+        assert!(lazy.prefix(4, 80).is_err());
+    }
+
+    #[test]
+    fn test_body_checked_when_read() {
+        let mut bytes = testing_png().as_bytes();
+        // Inside IDAT data, the header walk does not notice
+        bytes[8 + 18 + 8 + 100] ^= 0xff;
+        let mut lazy = LazyPng::open(Cursor::new(bytes)).unwrap();
+        assert_eq!(lazy.data(2).unwrap(), b"message");
+        assert!(lazy.data(1).is_err());
+    }
+
+    #[test]
+    fn test_check_crcs() {
+        let mut bytes = testing_png().as_bytes();
+        LazyPng::open(Cursor::new(bytes.clone()))
+            .unwrap()
+            .check_crcs()
+            .unwrap();
+        bytes[8 + 18 + 8 + 100] ^= 0xff;
+        let error = LazyPng::open(Cursor::new(bytes))
+            .unwrap()
+            .check_crcs()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "CRC check failed for IDAT chunk at index 1"
+        );
+    }
+
+    #[test]
+    fn test_truncated() {
+        let bytes = testing_png().as_bytes();
+        assert!(LazyPng::open(Cursor::new(bytes[..bytes.len() - 3].to_vec())).is_err());
+        assert!(LazyPng::open(Cursor::new(bytes[1..].to_vec())).is_err());
+        assert!(LazyPng::open(Cursor::new(bytes[..4].to_vec())).is_err());
+    }
+
+    #[test]
+    fn test_remove_in_place() {
+        let png = testing_png();
+        let path = testing_file("remove", &png);
+        let mut lazy = open_file(&path);
+        let removed = lazy.remove(2).unwrap();
+        assert_eq!(removed.data(), b"message");
+        let mut expected = testing_png();
+        expected.remove_chunk_at(2).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), expected.as_bytes());
+        assert_eq!(lazy.entries(), ChunkEntry::list(&expected).as_slice());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_splice_moves_large_tail() {
+        // Several blocks of data after the edit, moved one way then the other
+        let pixels: Vec<u8> = (0..200_000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let png = crate::tests::testing_png(&pixels, &[]);
+        let path = testing_file("large", &png);
+        let mut lazy = open_file(&path);
+        lazy.insert(2, chunk("ruSt", b"after the pixels")).unwrap();
+        let expected = crate::tests::testing_png(&pixels, &[chunk("ruSt", b"after the pixels")]);
+        assert_eq!(fs::read(&path).unwrap(), expected.as_bytes());
+        lazy.insert(1, chunk("ruSt", b"before the pixels")).unwrap();
+        lazy.remove(1).unwrap();
+        lazy.remove(2).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), png.as_bytes());
+        assert_eq!(lazy.data(1).unwrap(), pixels);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_splice_in_place() {
+        let png = testing_png();
+        let path = testing_file("splice", &png);
+        let mut lazy = open_file(&path);
+        lazy.insert(2, chunk("neWt", b"before")).unwrap();
+        lazy.splice(
+            3,
+            1,
+            &[chunk("ruSt", b"a longer message"), chunk("abCd", b"")],
+        )
+        .unwrap();
+        let mut expected = testing_png();
+        expected.insert_chunk(2, chunk("neWt", b"before")).unwrap();
+        expected
+            .replace_chunk(3, chunk("ruSt", b"a longer message"))
+            .unwrap();
+        expected.insert_chunk(4, chunk("abCd", b"")).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), expected.as_bytes());
+        assert_eq!(lazy.entries(), ChunkEntry::list(&expected).as_slice());
+        assert_eq!(lazy.data(5).unwrap(), b"");
+        assert!(lazy.splice(5, 2, &[]).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
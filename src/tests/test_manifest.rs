@@ -0,0 +1,140 @@
+#[cfg(test)]
+mod tests {
+    use crate::confine::PathPolicy;
+    use crate::manifest::{Change, Entry, Manifest, Status};
+    use crate::png::PNG;
+    use crate::tests::{chunk, testing_png};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn testing_tree(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("msg-in-png-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("nested")).unwrap();
+        write(
+            &root.join("a.png"),
+            &testing_png(b"px", &[chunk("ruSt", b"a")]),
+        );
+        write(
+            &root.join("nested/b.png"),
+            &testing_png(b"other", &[chunk("ruSt", b"b"), chunk("teSt", b"c")]),
+        );
+        fs::write(root.join("broken.png"), b"not a png").unwrap();
+        root
+    }
+
+    fn write(path: &Path, png: &PNG) {
+        fs::write(path, png.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn test_scan_lists_every_png() {
+        let root = testing_tree("manifest-scan");
+        let manifest = Manifest::scan(&root, None, &PathPolicy::default()).unwrap();
+        let paths: Vec<&str> = manifest
+            .entries
+            .iter()
+            .map(|entry| entry.path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["a.png", "broken.png", "nested/b.png"]);
+
+        let broken = manifest.get("broken.png").unwrap();
+        assert_eq!(broken.status, Status::Unreadable);
+        assert_eq!(broken.content_id, None);
+        let nested = manifest.get("nested/b.png").unwrap();
+        assert_eq!(nested.message_ids.len(), 2);
+        assert!(nested.message_ids[0].starts_with("ruSt:"));
+        assert!(nested.content_id.is_some());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let root = testing_tree("manifest-round-trip");
+        let manifest = Manifest::scan(&root, None, &PathPolicy::default()).unwrap();
+        assert_eq!(
+            Manifest::parse(&manifest.to_text(None), None).unwrap(),
+            manifest
+        );
+        let signed = manifest.to_text(Some(b"secret"));
+        assert_eq!(Manifest::parse(&signed, Some(b"secret")).unwrap(), manifest);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_parse_rejects_tampering() {
+        let root = testing_tree("manifest-tampering");
+        let manifest = Manifest::scan(&root, None, &PathPolicy::default()).unwrap();
+        let signed = manifest.to_text(Some(b"secret"));
+        assert!(Manifest::parse(&signed, Some(b"wrong")).is_err());
+        assert!(Manifest::parse(&signed, None).is_err());
+        let tampered = signed.replacen("a.png", "z.png", 1);
+        assert!(Manifest::parse(&tampered, Some(b"secret")).is_err());
+        let unsigned = manifest.to_text(None).replacen("a.png", "z.png", 1);
+        assert!(Manifest::parse(&unsigned, None).is_err());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_check_reports_changes() {
+        let root = testing_tree("manifest-check");
+        let before = Manifest::scan(&root, None, &PathPolicy::default()).unwrap();
+        assert!(before.check(&before).is_empty());
+
+        write(
+            &root.join("a.png"),
+            &testing_png(b"edited", &[chunk("ruSt", b"a")]),
+        );
+        write(
+            &root.join("nested/b.png"),
+            &testing_png(b"other", &[chunk("teSt", b"c")]),
+        );
+        fs::remove_file(root.join("broken.png")).unwrap();
+        write(&root.join("new.png"), &testing_png(b"new", &[]));
+        let after = Manifest::scan(&root, None, &PathPolicy::default()).unwrap();
+
+        let changes = before.check(&after);
+        assert_eq!(changes.len(), 4);
+        assert_eq!(changes[0], Change::Modified("a.png".to_string()));
+        assert_eq!(changes[1], Change::Missing("broken.png".to_string()));
+        assert!(
+            matches!(&changes[2], Change::MessagesLost(path, ids) if path == "nested/b.png" && ids.len() == 1)
+        );
+        assert_eq!(changes[3], Change::Added("new.png".to_string()));
+        assert!(!changes[3].is_problem());
+        assert!(changes[2]
+            .to_json()
+            .starts_with("{\"change\":\"messages_lost\",\"path\":\"nested/b.png\",\"problem\":true,\"messages\":[\"ruSt:"));
+        assert_eq!(
+            changes[3].to_json(),
+            "{\"change\":\"added\",\"path\":\"new.png\",\"problem\":false,\"messages\":[],\"status\":null}"
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_check_reports_verification_failures() {
+        let entry = |status: Status| Entry {
+            path: "a.png".to_string(),
+            content_id: Some([0; 32]),
+            message_ids: Vec::new(),
+            status,
+        };
+        let before = Manifest {
+            entries: vec![entry(Status::Valid)],
+        };
+        let after = Manifest {
+            entries: vec![entry(Status::Invalid(2))],
+        };
+        assert_eq!(
+            before.check(&after),
+            vec![Change::NoLongerValid(
+                "a.png".to_string(),
+                Status::Invalid(2)
+            )]
+        );
+        // Files failing already are not reported again
+        assert!(after.check(&before).is_empty());
+        assert!(after.check(&after).is_empty());
+    }
+}
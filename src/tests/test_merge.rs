@@ -0,0 +1,119 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::merge::{MergeOutcome, MergePolicy, Resolution, TypeResolution};
+    use crate::ordering::OrderingRules;
+    use crate::png::PNG;
+    use crate::tests::{self, chunk};
+    use std::str::FromStr;
+
+    /// Shared test png with `extra` chunks inserted at given indices
+    fn testing_png(extra: Vec<(usize, Chunk)>) -> PNG {
+        let mut png = tests::testing_png(b"pixels", &[]);
+        for (index, chunk) in extra {
+            png.insert_chunk(index, chunk).unwrap();
+        }
+        png
+    }
+
+    fn layout(png: &PNG) -> Vec<String> {
+        png.chunks()
+            .iter()
+            .map(|chunk| format!("{}:{}", chunk.chunk_type(), chunk.data().len()))
+            .collect()
+    }
+
+    #[test]
+    fn test_missing_types_are_added_in_place() {
+        let mut ours = testing_png(vec![]);
+        let theirs = testing_png(vec![
+            (1, chunk("pHYs", &[0; 9])),
+            (3, chunk("ruSt", b"msg")),
+        ]);
+        let report = ours
+            .merge_ancillary(&theirs, &MergePolicy::new(Resolution::Ours))
+            .unwrap();
+        assert!(report.changed());
+        assert_eq!(
+            layout(&ours),
+            ["IHDR:13", "pHYs:9", "IDAT:6", "ruSt:3", "IEND:0"]
+        );
+    }
+
+    #[test]
+    fn test_identical_and_kept_ours() {
+        let mut ours = testing_png(vec![(2, chunk("ruSt", b"same")), (2, chunk("tEXt", b"a"))]);
+        let theirs = testing_png(vec![(2, chunk("ruSt", b"same")), (2, chunk("tEXt", b"b"))]);
+        let report = ours
+            .merge_ancillary(&theirs, &MergePolicy::new(Resolution::Ours))
+            .unwrap();
+        assert!(!report.changed());
+        assert!(report.outcomes.contains(&(
+            ChunkType::from_str("ruSt").unwrap(),
+            MergeOutcome::Identical
+        )));
+        assert!(report
+            .outcomes
+            .contains(&(ChunkType::from_str("tEXt").unwrap(), MergeOutcome::KeptOurs)));
+        assert_eq!(ours.chunk_by_type("tEXt").unwrap().data(), b"a");
+    }
+
+    #[test]
+    fn test_theirs_and_both() {
+        let mut ours = testing_png(vec![(2, chunk("tEXt", b"a")), (2, chunk("ruSt", b"x"))]);
+        let theirs = testing_png(vec![
+            (2, chunk("tEXt", b"b")),
+            (2, chunk("tEXt", b"a")),
+            (2, chunk("ruSt", b"y")),
+        ]);
+        let mut policy = MergePolicy::new(Resolution::Theirs);
+        policy.set(ChunkType::from_str("tEXt").unwrap(), Resolution::Both);
+        let report = ours.merge_ancillary(&theirs, &policy).unwrap();
+        assert_eq!(
+            report.outcomes,
+            vec![
+                (
+                    ChunkType::from_str("ruSt").unwrap(),
+                    MergeOutcome::TookTheirs(1)
+                ),
+                (
+                    ChunkType::from_str("tEXt").unwrap(),
+                    MergeOutcome::KeptBoth(1)
+                ),
+            ]
+        );
+        assert_eq!(ours.chunk_by_type("ruSt").unwrap().data(), b"y");
+        assert_eq!(ours.chunk_by_type_nth("tEXt", 1).unwrap().data(), b"b");
+        assert_eq!(
+            report.to_json(),
+            "{\"changed\":true,\"types\":[\
+             {\"type\":\"ruSt\",\"outcome\":\"took_theirs\",\"chunks\":1},\
+             {\"type\":\"tEXt\",\"outcome\":\"kept_both\",\"chunks\":1}]}"
+        );
+    }
+
+    #[test]
+    fn test_merge_breaking_rules_fails_and_keeps_png() {
+        let mut ours = testing_png(vec![(2, chunk("ruSt", b"x")), (2, chunk("tEXt", b"a"))]);
+        let mut rules = OrderingRules::new();
+        rules.set_unique(ChunkType::from_str("ruSt").unwrap());
+        ours.set_ordering_rules(rules);
+        let theirs = testing_png(vec![(2, chunk("ruSt", b"y")), (2, chunk("zTXt", b"z"))]);
+        let before = layout(&ours);
+        assert!(ours
+            .merge_ancillary(&theirs, &MergePolicy::new(Resolution::Both))
+            .is_err());
+        // zTXt was added before ruSt failed, it is taken back
+        assert_eq!(layout(&ours), before);
+        assert_eq!(ours.chunk_by_type("ruSt").unwrap().data(), b"x");
+    }
+
+    #[test]
+    fn test_type_resolution_from_str() {
+        let parsed = TypeResolution::from_str("tEXt=both").unwrap();
+        assert_eq!(parsed.resolution, Resolution::Both);
+        assert!(TypeResolution::from_str("tEXt").is_err());
+        assert!(TypeResolution::from_str("tEXt=mine").is_err());
+    }
+}
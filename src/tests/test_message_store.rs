@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::message_store::MessageStore;
+    use crate::png::PNG;
+    use std::str::FromStr;
+
+    fn testing_store() -> MessageStore {
+        let chunk = |chunk_type: &str, data: &[u8]| {
+            Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+        };
+        MessageStore::new(PNG::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("IDAT", b"pixels"),
+            chunk("IEND", b""),
+        ]))
+    }
+
+    #[test]
+    fn test_put_get_list() {
+        let mut store = testing_store();
+        store.put("author", b"Ferris").unwrap();
+        store.put("license", b"MIT").unwrap();
+        assert_eq!(store.get("author").unwrap(), Some(b"Ferris".to_vec()));
+        assert_eq!(store.get("missing").unwrap(), None);
+        assert_eq!(store.list(), ["author", "license"]);
+        assert!(store.png().ends_with_iend());
+    }
+
+    #[test]
+    fn test_put_replaces_in_place() {
+        let mut store = testing_store();
+        store.put("a", b"1").unwrap();
+        store.put("b", b"2").unwrap();
+        store.put("a", b"3").unwrap();
+        assert_eq!(store.list(), ["a", "b"]);
+        assert_eq!(store.get("a").unwrap(), Some(b"3".to_vec()));
+        assert_eq!(store.png().chunks().len(), 5);
+    }
+
+    #[test]
+    fn test_large_value_spans_chunks() {
+        let mut store = testing_store();
+        store.set_max_chunk_size(64);
+        let value: Vec<u8> = (0..200u8).collect();
+        store.put("blob", &value).unwrap();
+        store.put("small", b"x").unwrap();
+        assert!(store.png().chunks().len() > 5);
+        assert_eq!(store.get("blob").unwrap(), Some(value));
+        assert_eq!(store.list(), ["blob", "small"]);
+    }
+
+    #[test]
+    fn test_delete() {
+        let mut store = testing_store();
+        store.set_max_chunk_size(56);
+        store.put("blob", &[7; 100]).unwrap();
+        assert!(store.delete("blob"));
+        assert!(!store.delete("blob"));
+        assert_eq!(store.into_png().chunks().len(), 3);
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        let mut store = testing_store();
+        assert!(store.put("", b"value").is_err());
+        assert!(store.put("a\0b", b"value").is_err());
+    }
+}
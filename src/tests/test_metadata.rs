@@ -0,0 +1,196 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::metadata::{
+        touch_time, Background, Dpi, ImageHeader, LastModified, PhysicalDimensions, PhysicalUnit,
+        Transparency, COLOR_TYPE_GRAYSCALE, COLOR_TYPE_GRAYSCALE_ALPHA, COLOR_TYPE_INDEXED,
+        COLOR_TYPE_TRUECOLOR, COLOR_TYPE_TRUECOLOR_ALPHA,
+    };
+    use crate::png::PNG;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_image_header() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&640u32.to_be_bytes());
+        data.extend_from_slice(&480u32.to_be_bytes());
+        data.extend_from_slice(&[8, 6, 0, 0, 1]);
+        let chunk = Chunk::new(ChunkType::from_str("IHDR").unwrap(), data);
+        let header = ImageHeader::try_from(&chunk).unwrap();
+        assert_eq!((header.width, header.height), (640, 480));
+        assert_eq!(header.color_type_name(), "truecolor with alpha");
+        assert_eq!(header.interlace_method, 1);
+        assert_eq!(header.channels(), Some(4));
+        assert_eq!(header.to_chunk().as_bytes(), chunk.as_bytes());
+        let short = Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 12]);
+        assert!(ImageHeader::try_from(&short).is_err());
+    }
+
+    #[test]
+    fn test_physical_dimensions_round_trip() {
+        let physical = PhysicalDimensions::from_dpi(Dpi::from_str("300").unwrap());
+        assert_eq!(physical.pixels_per_unit_x, 11811);
+        assert_eq!(physical.unit, PhysicalUnit::Metre);
+        let chunk = physical.to_chunk();
+        assert_eq!(chunk.chunk_type().to_string(), "pHYs");
+        assert_eq!(chunk.length(), 9);
+        assert_eq!(PhysicalDimensions::try_from(&chunk).unwrap(), physical);
+        assert_eq!(physical.dpi().unwrap().to_string(), "300 dpi");
+    }
+
+    #[test]
+    fn test_physical_dimensions_unknown_unit() {
+        let physical = PhysicalDimensions {
+            pixels_per_unit_x: 2,
+            pixels_per_unit_y: 1,
+            unit: PhysicalUnit::Unknown,
+        };
+        assert!(physical.dpi().is_none());
+        let mut data = physical.to_chunk().data().to_vec();
+        data[8] = 2;
+        let chunk = Chunk::new(ChunkType::from_str("pHYs").unwrap(), data);
+        assert!(PhysicalDimensions::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_dpi_from_str() {
+        assert_eq!(
+            Dpi::from_str("300x150").unwrap(),
+            Dpi { x: 300.0, y: 150.0 }
+        );
+        assert_eq!(Dpi::from_str("72").unwrap(), Dpi { x: 72.0, y: 72.0 });
+        assert_eq!(Dpi::from_str("300x150").unwrap().to_string(), "300x150 dpi");
+        assert!(Dpi::from_str("0").is_err());
+        assert!(Dpi::from_str("-72").is_err());
+        assert!(Dpi::from_str("seventy").is_err());
+    }
+
+    #[test]
+    fn test_last_modified_from_unix_seconds() {
+        assert_eq!(
+            LastModified::from_unix_seconds(0).to_string(),
+            "1970-01-01T00:00:00Z"
+        );
+        assert_eq!(
+            LastModified::from_unix_seconds(951782400).to_string(),
+            "2000-02-29T00:00:00Z"
+        );
+        assert_eq!(
+            LastModified::from_unix_seconds(1650000000).to_string(),
+            "2022-04-15T05:20:00Z"
+        );
+    }
+
+    #[test]
+    fn test_last_modified_round_trip() {
+        let time = LastModified::from_unix_seconds(1650000000);
+        let chunk = time.to_chunk();
+        assert_eq!(chunk.data(), &[0x07, 0xE6, 4, 15, 5, 20, 0]);
+        assert_eq!(LastModified::try_from(&chunk).unwrap(), time);
+        let invalid = Chunk::new(
+            ChunkType::from_str("tIME").unwrap(),
+            vec![0x07, 0xE6, 13, 15, 5, 20, 0],
+        );
+        assert!(LastModified::try_from(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_touch_time() {
+        let mut png = PNG::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![]),
+        ]);
+        touch_time(&mut png, LastModified::from_unix_seconds(0)).unwrap();
+        assert_eq!(png.position_of("tIME", 0).unwrap(), Some(1));
+        let later = LastModified::from_unix_seconds(1650000000);
+        touch_time(&mut png, later).unwrap();
+        assert_eq!(png.chunks().len(), 3);
+        let chunk = png.chunk_by_type("tIME").unwrap();
+        assert_eq!(LastModified::try_from(chunk).unwrap(), later);
+    }
+
+    fn png_with_color_type(color_type: u8, extra: Vec<Chunk>) -> PNG {
+        let header = vec![0, 0, 0, 1, 0, 0, 0, 1, 8, color_type, 0, 0, 0];
+        let mut chunks = vec![Chunk::new(ChunkType::from_str("IHDR").unwrap(), header)];
+        chunks.extend(extra);
+        chunks.push(Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![]));
+        PNG::from_chunks(chunks)
+    }
+
+    #[test]
+    fn test_background_round_trip() {
+        let cases = [
+            (Background::PaletteIndex(7), COLOR_TYPE_INDEXED),
+            (Background::Gray(0x1234), COLOR_TYPE_GRAYSCALE_ALPHA),
+            (Background::Rgb(1, 2, 0xffff), COLOR_TYPE_TRUECOLOR),
+        ];
+        for (background, color_type) in cases {
+            assert!(background.fits(color_type));
+            let chunk = background.to_chunk();
+            assert_eq!(
+                Background::from_chunk(&chunk, color_type).unwrap(),
+                background
+            );
+        }
+        assert!(!Background::Gray(0).fits(COLOR_TYPE_TRUECOLOR_ALPHA));
+    }
+
+    #[test]
+    fn test_background_wrong_length() {
+        let chunk = Background::Gray(3).to_chunk();
+        assert!(Background::from_chunk(&chunk, COLOR_TYPE_TRUECOLOR).is_err());
+        assert!(Background::from_chunk(&chunk, 5).is_err());
+    }
+
+    #[test]
+    fn test_transparency_round_trip() {
+        let cases = [
+            (Transparency::PaletteAlpha(vec![0, 128]), COLOR_TYPE_INDEXED),
+            (Transparency::Gray(9), COLOR_TYPE_GRAYSCALE),
+            (Transparency::Rgb(4, 5, 6), COLOR_TYPE_TRUECOLOR),
+        ];
+        for (transparency, color_type) in cases {
+            assert!(transparency.fits(color_type));
+            let chunk = transparency.to_chunk();
+            assert_eq!(
+                Transparency::from_chunk(&chunk, color_type).unwrap(),
+                transparency
+            );
+        }
+    }
+
+    #[test]
+    fn test_transparency_rejected_with_alpha_channel() {
+        let chunk = Transparency::Gray(9).to_chunk();
+        assert!(Transparency::from_chunk(&chunk, COLOR_TYPE_GRAYSCALE_ALPHA).is_err());
+        let too_many = Transparency::PaletteAlpha(vec![0; 257]).to_chunk();
+        assert!(Transparency::from_chunk(&too_many, COLOR_TYPE_INDEXED).is_err());
+    }
+
+    #[test]
+    fn test_png_background_and_transparency() {
+        let png = png_with_color_type(
+            COLOR_TYPE_TRUECOLOR,
+            vec![
+                Background::Rgb(10, 20, 30).to_chunk(),
+                Transparency::Rgb(0, 0, 0).to_chunk(),
+            ],
+        );
+        assert_eq!(png.background().unwrap(), Some(Background::Rgb(10, 20, 30)));
+        assert_eq!(
+            png.transparency().unwrap(),
+            Some(Transparency::Rgb(0, 0, 0))
+        );
+
+        let plain = png_with_color_type(COLOR_TYPE_GRAYSCALE, vec![]);
+        assert_eq!(plain.background().unwrap(), None);
+        assert_eq!(plain.transparency().unwrap(), None);
+
+        let mismatched = png_with_color_type(
+            COLOR_TYPE_GRAYSCALE,
+            vec![Background::Rgb(10, 20, 30).to_chunk()],
+        );
+        assert!(mismatched.background().is_err());
+    }
+}
@@ -0,0 +1,133 @@
+#[cfg(test)]
+mod tests {
+    use crate::mime::{
+        extension, hex_dump, is_typed, pretty_json, render, unwrap, wrap, MimeType, Rendering,
+        StripHeader, MAX_HEADER_LEN,
+    };
+    use std::io::Write;
+    use std::str::FromStr;
+
+    fn mime(text: &str) -> MimeType {
+        MimeType::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse_mime_type() {
+        assert_eq!(mime("application/json").essence(), "application/json");
+        assert_eq!(mime("Text/Plain; charset=utf-8").essence(), "text/plain");
+        assert!(MimeType::from_str("json").is_err());
+        assert!(MimeType::from_str("application/").is_err());
+        assert!(MimeType::from_str("text/plain\nx").is_err());
+        assert!(MimeType::from_str(&format!("text/{}", "x".repeat(MAX_HEADER_LEN))).is_err());
+    }
+
+    #[test]
+    fn test_wrap_and_unwrap() {
+        let data = wrap(&mime("application/json"), b"{\"a\":1}");
+        assert!(is_typed(&data));
+        let (found, message) = unwrap(&data).unwrap();
+        assert_eq!(found, Some(mime("application/json")));
+        assert_eq!(message, b"{\"a\":1}");
+
+        let (found, message) = unwrap(b"plain message").unwrap();
+        assert_eq!(found, None);
+        assert_eq!(message, b"plain message");
+        assert!(unwrap(b"msgmime:text/plain").is_err());
+    }
+
+    #[test]
+    fn test_rendering() {
+        assert_eq!(
+            Rendering::for_type(&mime("application/json")),
+            Rendering::Json
+        );
+        assert_eq!(
+            Rendering::for_type(&mime("application/ld+json")),
+            Rendering::Json
+        );
+        assert_eq!(
+            Rendering::for_type(&mime("application/x-yaml")),
+            Rendering::Yaml
+        );
+        assert_eq!(Rendering::for_type(&mime("text/yaml")), Rendering::Yaml);
+        assert_eq!(Rendering::for_type(&mime("text/csv")), Rendering::Text);
+        assert_eq!(Rendering::for_type(&mime("image/svg+xml")), Rendering::Text);
+        assert_eq!(Rendering::for_type(&mime("image/jpeg")), Rendering::HexDump);
+    }
+
+    #[test]
+    fn test_pretty_json() {
+        assert_eq!(
+            pretty_json(" {\"a\": [1, 2.5e3, \"x\\\"y\"], \"b\": {}, \"c\": [] , \"d\":null}")
+                .unwrap(),
+            "{\n  \"a\": [\n    1,\n    2.5e3,\n    \"x\\\"y\"\n  ],\n  \"b\": {},\n  \"c\": [],\n  \"d\": null\n}"
+        );
+        assert_eq!(pretty_json("true").unwrap(), "true");
+        assert!(pretty_json("{\"a\" 1}").is_err());
+        assert!(pretty_json("[1,]").is_err());
+        assert!(pretty_json("[1] 2").is_err());
+        assert!(pretty_json("\"open").is_err());
+        assert!(pretty_json(&"[".repeat(1000)).is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render(&mime("application/json"), b"[1,2]"),
+            "[\n  1,\n  2\n]"
+        );
+        assert_eq!(render(&mime("application/json"), b"not json"), "not json");
+        assert_eq!(render(&mime("text/yaml"), b"a: 1\n"), "a: 1\n");
+        assert_eq!(render(&mime("text/plain"), &[0xff]), hex_dump(&[0xff]));
+    }
+
+    #[test]
+    fn test_hex_dump() {
+        let dump = hex_dump(b"0123456789abcdef\x00!");
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(
+            lines[0],
+            "00000000  30 31 32 33 34 35 36 37  38 39 61 62 63 64 65 66  |0123456789abcdef|"
+        );
+        assert_eq!(
+            lines[1],
+            "00000010  00 21                                             |.!|"
+        );
+        assert_eq!(hex_dump(b""), "");
+    }
+
+    #[test]
+    fn test_extension() {
+        assert_eq!(extension(&mime("application/json")), "json");
+        assert_eq!(extension(&mime("image/PNG")), "png");
+        assert_eq!(extension(&mime("application/x-unknown")), "bin");
+    }
+
+    fn strip(pieces: &[&[u8]]) -> (Vec<u8>, Option<MimeType>, usize) {
+        let mut output = Vec::new();
+        let mut writer = StripHeader::new(&mut output);
+        for piece in pieces {
+            writer.write_all(piece).unwrap();
+        }
+        writer.finish().unwrap();
+        let (mime, header_len) = (writer.mime().cloned(), writer.header_len());
+        drop(writer);
+        (output, mime, header_len)
+    }
+
+    #[test]
+    fn test_strip_header() {
+        let data = wrap(&mime("text/plain"), b"hello");
+        let (split_at, rest) = data.split_at(5);
+        let (output, found, header_len) = strip(&[split_at, rest]);
+        assert_eq!(output, b"hello");
+        assert_eq!(found, Some(mime("text/plain")));
+        assert_eq!(header_len, data.len() - 5);
+
+        let (output, found, _) = strip(&[b"ms", b"g", b" not typed"]);
+        assert_eq!(output, b"msg not typed");
+        assert_eq!(found, None);
+        assert_eq!(strip(&[b"msg"]).0, b"msg");
+        assert_eq!(strip(&[b""]).0, b"");
+    }
+}
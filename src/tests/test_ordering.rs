@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::ordering::{OrderingRules, Position};
+    use crate::png::PNG;
+    use crate::verify::verify;
+    use std::str::FromStr;
+
+    fn chunk_type(name: &str) -> ChunkType {
+        ChunkType::from_str(name).unwrap()
+    }
+
+    fn chunk(name: &str) -> Chunk {
+        Chunk::new(chunk_type(name), b"data".to_vec())
+    }
+
+    fn testing_png() -> PNG {
+        PNG::from_chunks(vec![
+            chunk("IHDR"),
+            chunk("PLTE"),
+            chunk("IDAT"),
+            chunk("IDAT"),
+            chunk("IEND"),
+        ])
+    }
+
+    fn layout(png: &PNG) -> Vec<String> {
+        png.chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    fn rules() -> OrderingRules {
+        let mut rules = OrderingRules::new();
+        rules
+            .set_position(chunk_type("prEe"), Position::BeforeIdat)
+            .set_position(chunk_type("plTe"), Position::BeforePlte)
+            .set_position(chunk_type("poSt"), Position::AfterIdat)
+            .set_unique(chunk_type("prEe"));
+        rules
+    }
+
+    #[test]
+    fn test_parse_position() {
+        assert_eq!(
+            Position::from_str("before_idat").unwrap(),
+            Position::BeforeIdat
+        );
+        assert_eq!(
+            Position::from_str("before_plte").unwrap(),
+            Position::BeforePlte
+        );
+        assert_eq!(
+            Position::from_str("after_idat").unwrap(),
+            Position::AfterIdat
+        );
+        assert!(Position::from_str("first").is_err());
+    }
+
+    #[test]
+    fn test_append_follows_position() {
+        let mut png = testing_png();
+        png.set_ordering_rules(rules());
+        assert_eq!(png.try_append_chunk(chunk("prEe")).unwrap(), 2);
+        assert_eq!(png.try_append_chunk(chunk("plTe")).unwrap(), 1);
+        assert_eq!(png.try_append_chunk(chunk("poSt")).unwrap(), 6);
+        png.append_chunk(chunk("ruSt"));
+        assert_eq!(
+            layout(&png),
+            vec!["IHDR", "plTe", "PLTE", "prEe", "IDAT", "IDAT", "poSt", "ruSt", "IEND"]
+        );
+        assert!(png.ordering_rules().validate(png.chunks()).is_empty());
+    }
+
+    #[test]
+    fn test_insert_breaking_rules_fails() {
+        let mut png = testing_png();
+        png.set_ordering_rules(rules());
+        assert!(png.insert_chunk(4, chunk("prEe")).is_err());
+        assert!(png.insert_chunk(2, chunk("plTe")).is_err());
+        assert!(png.insert_chunk(3, chunk("poSt")).is_err());
+        png.insert_chunk(1, chunk("prEe")).unwrap();
+        let error = png.try_append_chunk(chunk("prEe")).unwrap_err();
+        assert!(error.to_string().contains("prEe must be unique"));
+        // Moving image data in front of a constrained chunk breaks it as well
+        assert!(png.insert_chunk(1, chunk("IDAT")).is_err());
+        assert_eq!(png.chunks().len(), 6);
+    }
+
+    #[test]
+    fn test_existing_violations_do_not_block_edits() {
+        let mut png = testing_png();
+        png.insert_chunk(4, chunk("prEe")).unwrap();
+        png.set_ordering_rules(rules());
+        png.append_chunk(chunk("ruSt"));
+        let issues = verify(&png)
+            .into_iter()
+            .filter(|issue| issue.message.starts_with("prEe"))
+            .collect::<Vec<_>>();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].chunk_index, Some(4));
+        assert_eq!(issues[0].message, "prEe must come before IDAT");
+    }
+
+    #[test]
+    fn test_no_rules_keeps_plain_append() {
+        let mut png = testing_png();
+        png.append_chunk(chunk("prEe"));
+        png.append_chunk(chunk("prEe"));
+        assert_eq!(
+            layout(&png),
+            vec!["IHDR", "PLTE", "IDAT", "IDAT", "prEe", "prEe", "IEND"]
+        );
+    }
+}
@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod tests {
+    use crate::permissions::{create_private, write};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn testing_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("msg-in-png-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[cfg(unix)]
+    fn mode(path: &std::path::Path) -> u32 {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path).unwrap().permissions().mode() & 0o7777
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_explicit_mode() {
+        let dir = testing_dir("permissions-explicit");
+        let path = dir.join("out.png");
+        write(&path, b"payload", Some(0o640)).unwrap();
+        assert_eq!(mode(&path), 0o640);
+        // Wider than the usual umask allows, and applied to a file being replaced
+        write(&path, b"again", Some(0o666)).unwrap();
+        assert_eq!(mode(&path), 0o666);
+        assert_eq!(fs::read(&path).unwrap(), b"again");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_default_mode_keeps_existing() {
+        let dir = testing_dir("permissions-default");
+        let path = dir.join("out.png");
+        write(&path, b"payload", Some(0o604)).unwrap();
+        write(&path, b"again", None).unwrap();
+        assert_eq!(mode(&path), 0o604);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_private_file() {
+        let dir = testing_dir("permissions-private");
+        let path = dir.join("spooled.png");
+        create_private(&path).unwrap();
+        #[cfg(unix)]
+        assert_eq!(mode(&path), 0o600);
+        // Never written through when something is already there
+        assert!(create_private(&path).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
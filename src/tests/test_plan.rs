@@ -0,0 +1,126 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunk_type::ChunkType;
+    use crate::digest::HashAlgorithm;
+    use crate::encode::embed;
+    use crate::plan::{plan, Method, PlanRequest};
+    use crate::split::split_payload;
+    use crate::store::PayloadRef;
+    use crate::tests::testing_png;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    fn request(payload_bytes: usize, max_chunk_size: usize) -> PlanRequest {
+        PlanRequest {
+            png_path: PathBuf::from("image.png"),
+            chunk_type: ChunkType::from_str("ruSt").unwrap(),
+            payload_bytes,
+            method: Method::Chunk,
+            max_chunk_size,
+            no_split: false,
+            store_url: None,
+            hash: HashAlgorithm::default(),
+            touch_time: false,
+            max_file_size: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_matches_encoding() {
+        for (payload_bytes, max_chunk_size) in [(0, 100), (10, 100), (1000, 100), (5000, 50)] {
+            let request = request(payload_bytes, max_chunk_size);
+            let planned = plan(&testing_png(b"pixels", &[]), &request);
+            let mut png = testing_png(b"pixels", &[]);
+            let chunks = split_payload(
+                &request.chunk_type,
+                &vec![b'x'; payload_bytes],
+                max_chunk_size,
+                None,
+            )
+            .unwrap();
+            let report = embed(&mut png, chunks, payload_bytes).unwrap();
+            assert!(planned.fits);
+            assert_eq!(planned.chunks, report.message_chunks);
+            assert_eq!(planned.file_growth, report.bytes_added);
+            assert_eq!(planned.new_file_size, report.file_size);
+        }
+    }
+
+    #[test]
+    fn test_plan_store_reference() {
+        let mut request = request(1 << 30, 1 << 20);
+        request.method = Method::Store(PathBuf::from("payloads"));
+        request.store_url = Some("https://example.com/p".to_string());
+        let planned = plan(&testing_png(b"pixels", &[]), &request);
+        let reference =
+            PayloadRef::new(request.hash.hash(b""), request.store_url.clone()).as_bytes();
+        assert!(planned.fits);
+        assert_eq!(planned.method, "store");
+        assert_eq!(planned.chunks, 1);
+        assert_eq!(planned.file_growth, reference.len() + 12);
+        assert_eq!(
+            planned.command_line(),
+            "msg-in-png -p image.png encode ruSt <MESSAGE> --max-chunk-size 1048576 \
+             --store payloads --store-url https://example.com/p --no-touch-time"
+        );
+
+        request.hash = HashAlgorithm::Sha512;
+        let planned = plan(&testing_png(b"pixels", &[]), &request);
+        let reference =
+            PayloadRef::new(request.hash.hash(b""), request.store_url.clone()).as_bytes();
+        assert_eq!(planned.file_growth, reference.len() + 12);
+        assert!(planned.command_line().contains("--hash sha512"));
+    }
+
+    #[test]
+    fn test_plan_counts_trailer_and_time() {
+        let mut png = testing_png(b"pixels", &[]);
+        png.remove_chunk("IEND").unwrap();
+        let mut request = request(10, 100);
+        request.touch_time = true;
+        let planned = plan(&png, &request);
+        assert_eq!(planned.file_growth, 22 + 12 + 19);
+        assert!(planned.command.contains(&"--repair-trailer".to_string()));
+        assert!(!planned.command.contains(&"--no-touch-time".to_string()));
+    }
+
+    #[test]
+    fn test_plan_that_does_not_fit() {
+        let mut no_split = request(1000, 100);
+        no_split.no_split = true;
+        let planned = plan(&testing_png(b"pixels", &[]), &no_split);
+        assert!(!planned.fits);
+        assert_eq!(planned.chunks, 0);
+        assert_eq!(planned.file_growth, 0);
+        assert!(planned
+            .to_json()
+            .contains("\"fits\":false,\"reason\":\"1000 bytes do not fit"));
+
+        let mut too_large = request(1000, 100);
+        too_large.max_file_size = Some(500);
+        let planned = plan(&testing_png(b"pixels", &[]), &too_large);
+        assert!(!planned.fits);
+        assert!(planned.reason.unwrap().contains("--max-file-size"));
+    }
+
+    #[test]
+    fn test_plan_json() {
+        let mut request = request(5, 100);
+        request.png_path = PathBuf::from("my \"image\".png");
+        let planned = plan(&testing_png(b"pixels", &[]), &request);
+        assert_eq!(
+            planned.to_json(),
+            format!(
+                "{{\"method\":\"chunk\",\"payload_bytes\":5,\"fits\":true,\"reason\":null,\
+                 \"chunks\":1,\"file_growth\":17,\"file_size\":{},\"new_file_size\":{},\
+                 \"command\":[\"msg-in-png\",\"-p\",\"my \\\"image\\\".png\",\"encode\",\
+                 \"ruSt\",\"<MESSAGE>\",\"--max-chunk-size\",\"100\",\"--no-touch-time\"]}}",
+                planned.file_size,
+                planned.file_size + 17
+            )
+        );
+        assert!(planned
+            .command_line()
+            .starts_with("msg-in-png -p 'my \"image\".png' encode"));
+    }
+}
@@ -2,19 +2,20 @@
 mod tests {
     use crate::chunk::Chunk;
     use crate::chunk_type::ChunkType;
-    use crate::png::PNG;
+    use crate::metadata::{touch_time, LastModified};
+    use crate::ordering::OrderingRules;
+    use crate::png::{Format, PNG};
+    use crate::verify::FieldError;
     use anyhow::Result;
     use std::convert::TryFrom;
     use std::str::FromStr;
 
     fn testing_chunks() -> Vec<Chunk> {
-        let mut chunks = Vec::new();
-
-        chunks.push(chunk_from_strings("FrSt", "I am the first chunk").unwrap());
-        chunks.push(chunk_from_strings("miDl", "I am another chunk").unwrap());
-        chunks.push(chunk_from_strings("LASt", "I am the last chunk").unwrap());
-
-        chunks
+        vec![
+            chunk_from_strings("FrSt", "I am the first chunk").unwrap(),
+            chunk_from_strings("miDl", "I am another chunk").unwrap(),
+            chunk_from_strings("LASt", "I am the last chunk").unwrap(),
+        ]
     }
 
     fn testing_png() -> PNG {
@@ -55,6 +56,37 @@ mod tests {
         assert!(png.is_ok());
     }
 
+    #[test]
+    fn test_snapshot_and_revert() {
+        let mut png = testing_png();
+        let original = png.snapshot();
+        png.remove_chunk_at(1).unwrap();
+        png.append_chunk(chunk_from_strings("neXt", "I was added later").unwrap());
+        let edited = png.snapshot();
+
+        png.revert(&original);
+        assert_eq!(png.as_bytes(), testing_png().as_bytes());
+        png.revert(&edited);
+        assert_eq!(png.chunks().len(), 3);
+        assert!(png.chunk_by_type("neXt").is_some());
+        assert!(png.chunk_by_type("miDl").is_none());
+    }
+
+    #[test]
+    fn test_snapshot_shares_chunk_data() {
+        let mut png = testing_png();
+        let snapshot = png.snapshot();
+        png.replace_chunk(0, chunk_from_strings("FrSt", "Replaced").unwrap())
+            .unwrap();
+        assert!(!png.chunks()[0].shares_data(&snapshot.chunks()[0]));
+        for index in 1..3 {
+            assert!(png.chunks()[index].shares_data(&snapshot.chunks()[index]));
+        }
+        png.revert(&snapshot);
+        assert!(png.chunks()[0].shares_data(&snapshot.chunks()[0]));
+        assert_eq!(png.chunks()[0].data(), b"I am the first chunk");
+    }
+
     #[test]
     fn test_invalid_header() {
         let chunk_bytes: Vec<u8> = testing_chunks()
@@ -110,6 +142,21 @@ mod tests {
         assert_eq!(&chunk.data_as_string().unwrap(), "I am the first chunk");
     }
 
+    #[test]
+    fn test_chunks_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "first").unwrap());
+        png.append_chunk(chunk_from_strings("TeSt", "second").unwrap());
+        let data: Vec<String> = png
+            .chunks_by_type("TeSt")
+            .iter()
+            .map(|chunk| chunk.data_as_string().unwrap())
+            .collect();
+        assert_eq!(data, ["first", "second"]);
+        assert!(png.chunks_by_type("NoNe").is_empty());
+        assert!(png.chunks_by_type("T1St").is_empty());
+    }
+
     #[test]
     fn test_append_chunk() {
         let mut png = testing_png();
@@ -128,6 +175,298 @@ mod tests {
         assert!(chunk.is_none());
     }
 
+    #[test]
+    fn test_chunk_by_type_nth() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "first").unwrap());
+        png.append_chunk(chunk_from_strings("TeSt", "second").unwrap());
+        let chunk = png.chunk_by_type_nth("TeSt", 1).unwrap();
+        assert_eq!(&chunk.data_as_string().unwrap(), "second");
+        assert!(png.chunk_by_type_nth("TeSt", 2).is_none());
+        assert!(png.chunk_by_type_nth("T1St", 0).is_none());
+    }
+
+    #[test]
+    fn test_position_of() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "first").unwrap());
+        png.append_chunk(chunk_from_strings("TeSt", "second").unwrap());
+        assert_eq!(png.position_of("TeSt", 0).unwrap(), Some(2));
+        assert_eq!(png.position_of("TeSt", 1).unwrap(), Some(3));
+        assert_eq!(png.position_of("TeSt", 2).unwrap(), None);
+        assert!(png.position_of("T1St", 0).is_err());
+    }
+
+    #[test]
+    fn test_fragment_round_trip() {
+        let png = testing_png();
+        let fragment = png.fragment_bytes();
+        assert_eq!(
+            fragment.len() + PNG::STANDARD_HEADER.len(),
+            png.as_bytes().len()
+        );
+        let parsed = PNG::from_fragment(&fragment).unwrap();
+        assert_eq!(parsed.as_bytes(), png.as_bytes());
+    }
+
+    #[test]
+    fn test_fragment_rejects_signature() {
+        let bytes = testing_png().as_bytes();
+        assert!(PNG::from_fragment(&bytes).is_err());
+        assert!(PNG::from_fragment(&[]).unwrap().chunks().is_empty());
+    }
+
+    #[test]
+    fn test_insert_chunk() {
+        let mut png = testing_png();
+        png.insert_chunk(1, chunk_from_strings("TeSt", "Message").unwrap())
+            .unwrap();
+        assert_eq!(png.position_of("TeSt", 0).unwrap(), Some(1));
+        png.insert_chunk(4, chunk_from_strings("TeSt", "Tail").unwrap())
+            .unwrap();
+        assert!(png
+            .insert_chunk(6, chunk_from_strings("TeSt", "Far").unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_replace_chunk() {
+        let mut png = testing_png();
+        let old = png
+            .replace_chunk(1, chunk_from_strings("TeSt", "Message").unwrap())
+            .unwrap();
+        assert_eq!(&old.chunk_type().bytes(), b"miDl");
+        assert_eq!(png.position_of("TeSt", 0).unwrap(), Some(1));
+        assert_eq!(png.chunks().len(), 3);
+        assert!(png
+            .replace_chunk(3, chunk_from_strings("TeSt", "Far").unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_remove_chunk_at() {
+        let mut png = testing_png();
+        let removed = png.remove_chunk_at(1).unwrap();
+        assert_eq!(&removed.chunk_type().bytes(), b"miDl");
+        assert_eq!(png.chunks().len(), 2);
+        assert!(png.remove_chunk_at(2).is_err());
+    }
+
+    #[test]
+    fn test_set_chunk_before_idat() {
+        let mut png = PNG::from_chunks(vec![
+            chunk_from_strings("IHDR", "header").unwrap(),
+            chunk_from_strings("IDAT", "data").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+        png.set_chunk_before_idat(chunk_from_strings("pHYs", "first").unwrap())
+            .unwrap();
+        assert_eq!(png.position_of("pHYs", 0).unwrap(), Some(1));
+        png.set_chunk_before_idat(chunk_from_strings("pHYs", "second").unwrap())
+            .unwrap();
+        assert_eq!(png.chunks().len(), 4);
+        assert_eq!(
+            png.chunk_by_type("pHYs").unwrap().data_as_string().unwrap(),
+            "second"
+        );
+        let mut png = testing_png();
+        png.set_chunk_before_idat(chunk_from_strings("pHYs", "tail").unwrap())
+            .unwrap();
+        assert_eq!(png.position_of("pHYs", 0).unwrap(), Some(2));
+    }
+
+    #[test]
+    fn test_ends_with_iend() {
+        let mut png = testing_png();
+        assert!(!png.ends_with_iend());
+        png.repair_trailer();
+        assert!(png.ends_with_iend());
+        assert_eq!(png.chunks().len(), 4);
+        png.repair_trailer();
+        assert_eq!(png.chunks().len(), 4);
+    }
+
+    #[test]
+    fn test_append_chunk_after_repair_trailer() {
+        let mut png = testing_png();
+        png.repair_trailer();
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["FrSt", "miDl", "LASt", "TeSt", "IEND"]);
+    }
+
+    #[test]
+    fn test_remove_chunk_returns_index() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
+        let (index, chunk) = png.remove_chunk("TeSt").unwrap();
+        assert_eq!(index, 2);
+        assert_eq!(&chunk.data_as_string().unwrap(), "Message");
+        assert!(png.remove_chunk("TeSt").is_err());
+    }
+
+    #[test]
+    fn test_rename_chunk_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("olDt", "first").unwrap());
+        png.append_chunk(chunk_from_strings("olDt", "second").unwrap());
+        assert_eq!(png.rename_chunk_type("olDt", "neWt").unwrap(), vec![2, 3]);
+        assert!(png.chunk_by_type("olDt").is_none());
+        let renamed = png.chunk_by_type_nth("neWt", 1).unwrap();
+        assert_eq!(&renamed.data_as_string().unwrap(), "second");
+        let reparsed = PNG::try_from(png.as_bytes().as_slice()).unwrap();
+        assert_eq!(reparsed.chunks_by_type("neWt").len(), 2);
+        assert!(png.rename_chunk_type("olDt", "neWt").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rename_chunk_type_checks_types() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("olDt", "message").unwrap());
+        // Reserved bit set
+        assert!(png.rename_chunk_type("olDt", "newt").is_err());
+        // Critical types
+        assert!(png.rename_chunk_type("olDt", "NeWt").is_err());
+        assert!(png.rename_chunk_type("LASt", "laSt").is_err());
+        assert!(png.rename_chunk_type("olDt", "olDt").is_err());
+        assert_eq!(png.chunks()[2].chunk_type().to_string(), "olDt");
+    }
+
+    #[test]
+    fn test_rename_chunk_type_keeps_ordering() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("olDt", "message").unwrap());
+        let mut rules = OrderingRules::new();
+        rules.set_unique(ChunkType::from_str("neWt").unwrap());
+        png.set_ordering_rules(rules);
+        png.append_chunk(chunk_from_strings("neWt", "already there").unwrap());
+        assert!(png.rename_chunk_type("olDt", "neWt").is_err());
+        assert_eq!(png.chunks_by_type("olDt").len(), 1);
+    }
+
+    #[test]
+    fn test_remove_nth() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "first").unwrap());
+        png.append_chunk(chunk_from_strings("TeSt", "second").unwrap());
+        png.append_chunk(chunk_from_strings("TeSt", "third").unwrap());
+        let (index, chunk) = png.remove_nth("TeSt", 1).unwrap();
+        assert_eq!(index, 3);
+        assert_eq!(&chunk.data_as_string().unwrap(), "second");
+        assert_eq!(
+            &png.chunk_by_type_nth("TeSt", 1)
+                .unwrap()
+                .data_as_string()
+                .unwrap(),
+            "third"
+        );
+        assert!(png.remove_nth("TeSt", 2).is_err());
+        assert!(png.remove_nth("T1St", 0).is_err());
+    }
+
+    #[test]
+    fn test_remove_chunks_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "first").unwrap());
+        png.insert_chunk(1, chunk_from_strings("TeSt", "second").unwrap())
+            .unwrap();
+        let removed = png.remove_chunks_by_type("TeSt").unwrap();
+        let removed: Vec<(usize, String)> = removed
+            .into_iter()
+            .map(|(index, chunk)| (index, chunk.data_as_string().unwrap()))
+            .collect();
+        assert_eq!(
+            removed,
+            vec![(1, "second".to_string()), (3, "first".to_string())]
+        );
+        assert_eq!(png.chunks().len(), 3);
+        assert!(png.remove_chunks_by_type("TeSt").unwrap().is_empty());
+        assert!(png.remove_chunks_by_type("T1St").is_err());
+    }
+
+    #[test]
+    fn test_content_id_ignores_ancillary_chunks() {
+        let png = PNG::try_from(&PNG_FILE[..]).unwrap();
+        let mut tagged = PNG::try_from(&PNG_FILE[..]).unwrap();
+        tagged.append_chunk(chunk_from_strings("ruSt", "Message").unwrap());
+        assert_eq!(png.content_id(), tagged.content_id());
+        assert_ne!(png.content_id(), testing_png().content_id());
+    }
+
+    #[test]
+    fn test_structural_digest() {
+        let png = PNG::try_from(&PNG_FILE[..]).unwrap();
+        let digest = png.structural_digest();
+        assert_eq!(
+            PNG::try_from(&PNG_FILE[..]).unwrap().structural_digest(),
+            digest
+        );
+
+        let mut timed = PNG::try_from(&PNG_FILE[..]).unwrap();
+        touch_time(&mut timed, LastModified::from_unix_seconds(0)).unwrap();
+        let first = timed.structural_digest();
+        assert_ne!(first, digest);
+        touch_time(&mut timed, LastModified::from_unix_seconds(86400)).unwrap();
+        assert_eq!(timed.structural_digest(), first);
+
+        let mut tagged = PNG::try_from(&PNG_FILE[..]).unwrap();
+        tagged.append_chunk(chunk_from_strings("ruSt", "Message").unwrap());
+        assert_ne!(tagged.structural_digest(), digest);
+        // Same chunks under another signature
+        let mng = PNG::from_chunks_with_format(png.chunks().to_vec(), Format::Mng);
+        assert_ne!(mng.structural_digest(), digest);
+    }
+
+    #[test]
+    fn test_find_chunk_streaming() {
+        let mut png = PNG::try_from(&PNG_FILE[..]).unwrap();
+        png.append_chunk(chunk_from_strings("ruSt", "Message").unwrap());
+        let bytes = png.as_bytes();
+        let chunk = PNG::find_chunk_streaming(bytes.as_slice(), "ruSt")
+            .unwrap()
+            .unwrap();
+        assert_eq!(chunk.data_as_string().unwrap(), "Message");
+        assert!(PNG::find_chunk_streaming(bytes.as_slice(), "miSs")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_find_chunk_streaming_rejects_huge_lengths() {
+        let mut bytes = PNG::STANDARD_HEADER.to_vec();
+        bytes.extend_from_slice(&0xFFFF_FFF0u32.to_be_bytes());
+        bytes.extend_from_slice(b"ruSt");
+        assert!(PNG::find_chunk_streaming(bytes.as_slice(), "ruSt").is_err());
+        // A length within the limit but past the end of the file
+        bytes[8..12].copy_from_slice(&1000u32.to_be_bytes());
+        bytes.extend_from_slice(b"short");
+        assert!(PNG::find_chunk_streaming(bytes.as_slice(), "ruSt").is_err());
+    }
+
+    #[test]
+    fn test_find_chunk_streaming_stops_at_match() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("ruSt", "Message").unwrap());
+        let mut bytes = png.as_bytes();
+        // Anything after the matching chunk is never read, even if it is garbage
+        let end = bytes.len() - png.chunks().last().unwrap().as_bytes().len();
+        bytes.truncate(end);
+        bytes.extend_from_slice(b"garbage");
+        let chunk = PNG::find_chunk_streaming(bytes.as_slice(), "ruSt").unwrap();
+        assert!(chunk.is_some());
+    }
+
+    #[test]
+    fn test_find_chunk_streaming_truncated() {
+        let bytes = testing_png().as_bytes();
+        let truncated = &bytes[..bytes.len() - 3];
+        assert!(PNG::find_chunk_streaming(truncated, "ruSt").is_err());
+    }
+
     #[test]
     fn test_png_from_image_file() {
         let png = PNG::try_from(&PNG_FILE[..]);
@@ -138,10 +477,60 @@ mod tests {
     fn test_as_bytes() {
         let png = PNG::try_from(&PNG_FILE[..]).unwrap();
         let actual = png.as_bytes();
-        let expected: Vec<u8> = PNG_FILE.iter().copied().collect();
+        let expected: Vec<u8> = PNG_FILE.to_vec();
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_from_reader() {
+        /// Hands out a few bytes per read, like a pipe would
+        struct Trickle<'a>(&'a [u8]);
+        impl std::io::Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let n = buf.len().min(self.0.len()).min(3);
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+        let png = PNG::from_reader(Trickle(&PNG_FILE)).unwrap();
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+        assert!(PNG::from_reader(&PNG_FILE[..5]).is_err());
+        assert!(PNG::from_reader(&PNG_FILE[1..]).is_err());
+        assert!(PNG::from_reader(&PNG_FILE[..PNG_FILE.len() - 20]).is_err());
+    }
+
+    #[test]
+    fn test_from_reader_truncated_length() {
+        let mut bytes = testing_png().as_bytes();
+        bytes.extend_from_slice(&0x7fff_ffffu32.to_be_bytes());
+        bytes.extend_from_slice(b"laRgshort");
+        assert!(PNG::from_reader(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_write_to() {
+        let png = testing_png();
+        let mut written = Vec::new();
+        png.write_to(&mut written).unwrap();
+        assert_eq!(written, png.as_bytes());
+        assert_eq!(written.len(), png.byte_len());
+        let mut chunk_bytes = Vec::new();
+        png.chunks()[0].write_to(&mut chunk_bytes).unwrap();
+        assert_eq!(chunk_bytes, png.chunks()[0].as_bytes());
+    }
+
+    #[test]
+    fn test_chunk_offsets() {
+        let png = testing_png();
+        let offsets = png.chunk_offsets();
+        assert_eq!(offsets, vec![8, 8 + 12 + 20, 8 + 12 + 20 + 12 + 18]);
+        let bytes = png.as_bytes();
+        for (offset, chunk) in offsets.iter().zip(png.chunks()) {
+            assert_eq!(&bytes[offset + 4..offset + 8], &chunk.chunk_type().bytes());
+        }
+    }
+
     #[test]
     fn test_png_trait_impls() {
         let chunk_bytes: Vec<u8> = testing_chunks()
@@ -160,6 +549,126 @@ mod tests {
         let _png_string = format!("{}", png);
     }
 
+    fn testing_mng() -> PNG {
+        let chunks = ["MHDR", "IHDR", "IDAT", "IEND", "MEND"]
+            .iter()
+            .map(|chunk_type| chunk_from_strings(chunk_type, "").unwrap())
+            .collect();
+        PNG::from_chunks_with_format(chunks, Format::Mng)
+    }
+
+    #[test]
+    fn test_mng_round_trip() {
+        let mut png = testing_mng();
+        png.append_chunk(chunk_from_strings("ruSt", "message").unwrap());
+        let bytes = png.as_bytes();
+        assert!(bytes.starts_with(&PNG::MNG_HEADER));
+
+        let parsed = PNG::try_from(bytes.as_slice()).unwrap();
+        assert_eq!(parsed.format(), Format::Mng);
+        assert_eq!(parsed.chunks()[4].chunk_type().to_string(), "ruSt");
+        assert!(parsed.ends_with_iend());
+        assert_eq!(parsed.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_jng_signature() {
+        let chunks = vec![
+            chunk_from_strings("JHDR", "").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ];
+        let bytes = PNG::from_chunks_with_format(chunks, Format::Jng).as_bytes();
+        assert_eq!(Format::from_signature(&bytes), Some(Format::Jng));
+        assert_eq!(
+            PNG::try_from(bytes.as_slice()).unwrap().format(),
+            Format::Jng
+        );
+        assert_eq!(Format::from_signature(b"\x8cJNG\r\n\x1a\n"), None);
+    }
+
+    #[test]
+    fn test_mng_repair_trailer() {
+        let mut png = testing_mng();
+        png.remove_chunk("MEND").unwrap();
+        // IEND of the embedded image does not end an MNG
+        assert!(!png.ends_with_iend());
+        png.repair_trailer();
+        assert_eq!(
+            png.chunks().last().unwrap().chunk_type().to_string(),
+            "MEND"
+        );
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        let png = testing_png();
+        let mut bytes = png.as_bytes();
+        // Chunks start at 8, 40 and 70
+        bytes[36] ^= 0xff;
+        bytes[40..44].copy_from_slice(&500u32.to_be_bytes());
+        bytes[75] = b'a';
+        assert!(PNG::try_from(bytes.as_slice()).is_err());
+        let (parsed, failures) = PNG::parse_lenient(&bytes).unwrap();
+        let types: Vec<String> = parsed
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["FrSt", "miDl", "LaSt"]);
+        assert_eq!(parsed.chunks()[1].data(), b"I am another chunk");
+        let errors: Vec<(usize, usize, FieldError)> = failures
+            .iter()
+            .map(|failure| (failure.chunk_index, failure.offset, failure.error))
+            .collect();
+        let crc = |index: usize| png.chunks()[index].crc();
+        assert_eq!(
+            errors,
+            [
+                (
+                    0,
+                    8,
+                    FieldError::Crc {
+                        stored: crc(0) ^ 0xff00_0000,
+                        computed: crc(0)
+                    }
+                ),
+                (
+                    1,
+                    40,
+                    FieldError::Length {
+                        declared: 500,
+                        actual: Some(18)
+                    }
+                ),
+                (
+                    2,
+                    70,
+                    FieldError::Crc {
+                        stored: crc(2),
+                        computed: parsed.chunks()[2].crc()
+                    }
+                ),
+            ]
+        );
+        assert_eq!(
+            failures[1].to_string(),
+            "chunk 1 miDl at byte 40: length is 500, should be 18"
+        );
+
+        let (parsed, failures) = PNG::parse_lenient(&png.as_bytes()[..90]).unwrap();
+        assert_eq!(parsed.chunks().len(), 2);
+        assert_eq!(
+            failures[0].error,
+            FieldError::Length {
+                declared: 19,
+                actual: None
+            }
+        );
+        let (_, failures) = PNG::parse_lenient(&png.as_bytes()[..75]).unwrap();
+        assert_eq!(failures[0].error, FieldError::Truncated(5));
+        assert!(PNG::parse_lenient(b"not a png").is_err());
+    }
+
     // This is the raw bytes for a shrunken version of the `dice.png` image on Wikipedia
     const PNG_FILE: [u8; 4803] = [
         137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 50, 0, 0, 0, 50, 8,
@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod tests {
+    use crate::digest::HashAlgorithm;
+    use crate::pointer::FilePointer;
+    use crate::sha256::{sha256, to_hex};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn testing_bundle(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("msg-in-png-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("data")).unwrap();
+        fs::write(root.join("data/sidecar.bin"), b"large sidecar data").unwrap();
+        root
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let pointer = FilePointer {
+            hash: HashAlgorithm::Sha512.hash(b"content"),
+            path: "data/sidecar.bin".to_string(),
+        };
+        let bytes = pointer.as_bytes();
+        assert!(FilePointer::is_pointer(&bytes));
+        assert_eq!(FilePointer::try_from(bytes.as_slice()).unwrap(), pointer);
+        assert!(FilePointer::try_from(&b"msgptr:sha256:00"[..]).is_err());
+    }
+
+    #[test]
+    fn test_sha256_pointer_still_parses() {
+        let bytes = format!(
+            "msgptr:sha256:{}\ndata/sidecar.bin",
+            to_hex(&sha256(b"content"))
+        );
+        let pointer = FilePointer::try_from(bytes.as_bytes()).unwrap();
+        assert_eq!(pointer.hash, HashAlgorithm::Sha256.hash(b"content"));
+        assert_eq!(pointer.as_bytes(), bytes.as_bytes());
+    }
+
+    #[test]
+    fn test_follow_checks_hash() {
+        for algorithm in HashAlgorithm::ALL {
+            let root = testing_bundle("pointer-follow");
+            let target = root.join("data/sidecar.bin");
+            let pointer = FilePointer::to_file(&root, &target, algorithm).unwrap();
+            assert_eq!(pointer.path, "data/sidecar.bin");
+            assert_eq!(pointer.hash.algorithm, algorithm);
+            assert_eq!(pointer.follow(&root).unwrap(), b"large sidecar data");
+
+            fs::write(&target, b"tampered").unwrap();
+            assert!(pointer
+                .follow(&root)
+                .unwrap_err()
+                .to_string()
+                .contains("was modified"));
+            fs::remove_dir_all(root).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_pointer_stays_in_bundle() {
+        let root = testing_bundle("pointer-bundle");
+        assert!(FilePointer::to_file(
+            &root.join("data"),
+            &root.join("data/../data/sidecar.bin"),
+            HashAlgorithm::default()
+        )
+        .is_ok());
+        fs::write(root.join("outside.bin"), b"outside").unwrap();
+        assert!(FilePointer::to_file(
+            &root.join("data"),
+            &root.join("outside.bin"),
+            HashAlgorithm::default()
+        )
+        .is_err());
+
+        for path in ["../outside.bin", "/etc/passwd", "data/./../outside.bin"] {
+            let pointer = FilePointer {
+                hash: HashAlgorithm::default().hash(b"outside"),
+                path: path.to_string(),
+            };
+            assert!(pointer.follow(&root.join("data")).is_err(), "{}", path);
+        }
+        fs::remove_dir_all(root).unwrap();
+    }
+}
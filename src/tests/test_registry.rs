@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunk_type::ChunkType;
+    use crate::ordering::Position;
+    use crate::png::PNG;
+    use crate::registry::{DataFormat, Registry};
+    use crate::tests::chunk;
+    use std::str::FromStr;
+
+    const REGISTRY: &str = r#"
+# Chunks written by the asset pipeline
+[ruSt]
+description = "Build metadata"
+format = "utf8"
+min_length = 2
+max_length = 8
+unique = true
+
+[biNa]
+position = "before_idat"
+"#;
+
+    #[test]
+    fn test_parse_registry() {
+        let registry = Registry::from_toml(REGISTRY).unwrap();
+        let rust = ChunkType::from_str("ruSt").unwrap();
+        let spec = registry.get(&rust).unwrap();
+        assert_eq!(spec.format, DataFormat::Utf8);
+        assert_eq!(spec.min_length, Some(2));
+        assert_eq!(spec.max_length, Some(8));
+        assert!(spec.unique);
+        assert_eq!(registry.describe(&rust), Some("Build metadata"));
+        let binary = ChunkType::from_str("biNa").unwrap();
+        assert_eq!(registry.get(&binary).unwrap().format, DataFormat::Binary);
+        assert_eq!(registry.describe(&binary), None);
+    }
+
+    #[test]
+    fn test_invalid_registry() {
+        assert!(Registry::from_toml("description = \"outside\"").is_err());
+        assert!(Registry::from_toml("[ruSt]\n[ruSt]").is_err());
+        assert!(Registry::from_toml("[ru5t]").is_err());
+        assert!(Registry::from_toml("[ruSt]\nmin_length = \"2\"").is_err());
+        assert!(Registry::from_toml("[ruSt]\nmax_length = -1").is_err());
+        assert!(Registry::from_toml("[ruSt]\nformat = \"json\"").is_err());
+        assert!(Registry::from_toml("[ruSt]\ncolour = \"red\"").is_err());
+        assert!(Registry::from_toml("[ruSt]\nposition = \"last\"").is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let registry = Registry::from_toml(REGISTRY).unwrap();
+        let png = PNG::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("ruSt", b"fine"),
+            chunk("ruSt", b"x"),
+            chunk("ruSt", b"\xff\xfe"),
+            chunk("biNa", b"\xff"),
+            chunk("IEND", b""),
+        ]);
+        let issues = registry.validate(&png);
+        let indices = issues
+            .iter()
+            .map(|issue| issue.chunk_index.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(indices, vec![2, 2, 3, 3]);
+        assert!(issues[0]
+            .message
+            .starts_with("ruSt (Build metadata) is 1 bytes long"));
+        assert!(issues.iter().all(|issue| issue.is_error()));
+    }
+
+    #[test]
+    fn test_ordering_rules() {
+        let registry = Registry::from_toml(REGISTRY).unwrap();
+        let rules = registry.ordering_rules();
+        let rust = rules.get(&ChunkType::from_str("ruSt").unwrap()).unwrap();
+        assert!(rust.unique);
+        assert_eq!(rust.position, None);
+        let binary = rules.get(&ChunkType::from_str("biNa").unwrap()).unwrap();
+        assert!(!binary.unique);
+        assert_eq!(binary.position, Some(Position::BeforeIdat));
+
+        let png = PNG::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("IDAT", b""),
+            chunk("biNa", b""),
+            chunk("IEND", b""),
+        ]);
+        let issues = registry.validate(&png);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].chunk_index, Some(2));
+        assert_eq!(issues[0].message, "biNa must come before IDAT");
+    }
+}
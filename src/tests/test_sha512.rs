@@ -0,0 +1,44 @@
+#[cfg(test)]
+mod tests {
+    use crate::sha256::to_hex;
+    use crate::sha512::{sha512, Sha512};
+
+    #[test]
+    fn test_empty_digest() {
+        assert_eq!(
+            to_hex(&sha512(b"")),
+            "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce\
+             47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e"
+        );
+    }
+
+    #[test]
+    fn test_abc_digest() {
+        assert_eq!(
+            to_hex(&sha512(b"abc")),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+    }
+
+    #[test]
+    fn test_padding_boundaries() {
+        // 112 bytes leave no room for the length in the last block
+        let data: Vec<u8> = (0..112u32).map(|i| (i % 251) as u8).collect();
+        assert_eq!(
+            to_hex(&sha512(&data)),
+            "c5fbd731d19d2ae1180f001be72c2c1aaba1d7b094b3748880e24593b8e117a7\
+             50e11c1bd867cc2f96dace8c8b74abd2d5c4f236be444e77d30d1916174070b9"
+        );
+    }
+
+    #[test]
+    fn test_incremental_update() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let mut hasher = Sha512::new();
+        for piece in data.chunks(37) {
+            hasher.update(piece);
+        }
+        assert_eq!(hasher.finalize(), sha512(&data));
+    }
+}
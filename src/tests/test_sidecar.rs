@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use crate::png::PNG;
+    use crate::sidecar::RemovedChunks;
+    use crate::tests::chunk;
+
+    #[test]
+    fn test_fragment_round_trip() {
+        let mut removed = RemovedChunks::new();
+        removed.push(2, chunk("ruSt", b"first"));
+        removed.push(5, chunk("tEXt", b"Comment\0second"));
+        let bytes = removed.to_fragment().unwrap();
+        let parsed = RemovedChunks::from_fragment(&bytes).unwrap();
+        assert_eq!(parsed.entries().len(), 2);
+        assert_eq!(parsed.entries()[0].0, 2);
+        assert_eq!(parsed.entries()[0].1.data(), b"first");
+        assert_eq!(parsed.entries()[1].0, 5);
+        assert_eq!(parsed.entries()[1].1.chunk_type().to_string(), "tEXt");
+    }
+
+    #[test]
+    fn test_fragment_without_index_chunk() {
+        let fragment = PNG::from_chunks(vec![chunk("ruSt", b"data")]).fragment_bytes();
+        assert!(RemovedChunks::from_fragment(&fragment).is_err());
+        assert!(RemovedChunks::from_fragment(&[]).is_err());
+    }
+
+    #[test]
+    fn test_fragment_index_count_mismatch() {
+        let fragment = PNG::from_chunks(vec![
+            chunk("rmIx", &[0, 0, 0, 1, 0, 0, 0, 2]),
+            chunk("ruSt", b"data"),
+        ])
+        .fragment_bytes();
+        assert!(RemovedChunks::from_fragment(&fragment).is_err());
+    }
+
+    fn chunk_types(png: &PNG) -> Vec<String> {
+        png.chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_restore_original_positions() {
+        let original = vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("ruSt", b"a"),
+            chunk("IDAT", b""),
+            chunk("ruSt", b"b"),
+            chunk("IEND", b""),
+        ];
+        let mut png = PNG::from_chunks(original.clone());
+        let mut removed = RemovedChunks::new();
+        // Removed from the back, the sidecar keeps indices of the original png
+        removed.push(3, png.remove_chunk_at(3).unwrap());
+        removed.push(1, png.remove_chunk_at(1).unwrap());
+        assert_eq!(removed.restore(&mut png).unwrap(), vec![1, 3]);
+        assert_eq!(chunk_types(&png), chunk_types(&PNG::from_chunks(original)));
+        assert_eq!(png.chunks()[3].data(), b"b");
+    }
+
+    #[test]
+    fn test_restore_out_of_range() {
+        let mut png = PNG::from_chunks(vec![chunk("IHDR", &[0; 13]), chunk("IEND", b"")]);
+        let mut removed = RemovedChunks::new();
+        removed.push(2, chunk("ruSt", b"a"));
+        removed.push(9, chunk("ruSt", b"b"));
+        assert!(removed.restore(&mut png).is_err());
+        assert_eq!(png.chunks().len(), 2);
+    }
+}
@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::png::PNG;
+    use crate::signature::{diagnose, repair, Corruption};
+    use std::str::FromStr;
+
+    fn testing_png_bytes() -> Vec<u8> {
+        PNG::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0x0A; 13]),
+            Chunk::new(
+                ChunkType::from_str("ruSt").unwrap(),
+                b"a\r\nb\r\r\nc".to_vec(),
+            ),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![]),
+        ])
+        .as_bytes()
+    }
+
+    fn unix_to_dos(bytes: &[u8]) -> Vec<u8> {
+        bytes
+            .iter()
+            .flat_map(|byte| match byte {
+                0x0A => vec![0x0D, 0x0A],
+                other => vec![*other],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_signature() {
+        assert_eq!(diagnose(&testing_png_bytes()), None);
+    }
+
+    #[test]
+    fn test_diagnose_bom() {
+        let mut bytes = vec![0xEF, 0xBB, 0xBF];
+        bytes.extend(testing_png_bytes());
+        assert_eq!(diagnose(&bytes), Some(Corruption::Utf8Bom));
+        assert_eq!(repair(&bytes).unwrap(), testing_png_bytes());
+    }
+
+    #[test]
+    fn test_repair_expanded_line_endings() {
+        let original = testing_png_bytes();
+        let mangled = unix_to_dos(&original);
+        assert_eq!(diagnose(&mangled), Some(Corruption::LineEndingsExpanded));
+        assert_eq!(repair(&mangled).unwrap(), original);
+        assert_eq!(
+            diagnose(&mangled[..8]),
+            Some(Corruption::LineEndingsExpanded)
+        );
+        assert!(PNG::try_from(repair(&mangled).unwrap().as_slice()).is_ok());
+    }
+
+    #[test]
+    fn test_diagnose_unrepairable() {
+        let mut collapsed = vec![0x89, 0x50, 0x4E, 0x47, 0x0A, 0x1A, 0x0A];
+        collapsed.extend_from_slice(&testing_png_bytes()[8..]);
+        assert_eq!(diagnose(&collapsed), Some(Corruption::LineEndingsCollapsed));
+        assert!(repair(&collapsed).is_err());
+
+        let mut stripped = testing_png_bytes();
+        stripped[0] = 0x09;
+        assert_eq!(diagnose(&stripped), Some(Corruption::HighBitStripped));
+        assert!(!Corruption::HighBitStripped.is_repairable());
+    }
+
+    #[test]
+    fn test_diagnose_containers() {
+        assert_eq!(diagnose(&[0x1F, 0x8B, 8, 0]), Some(Corruption::Gzip));
+        assert_eq!(diagnose(b"PK\x03\x04rest"), Some(Corruption::Zip));
+        assert_eq!(diagnose(&[0x89, 0x50]), Some(Corruption::Truncated));
+        assert_eq!(diagnose(b"GIF89a"), Some(Corruption::Unknown));
+    }
+
+    #[test]
+    fn test_parse_error_explains_corruption() {
+        let mut bytes = vec![0xEF, 0xBB, 0xBF];
+        bytes.extend(testing_png_bytes());
+        let err = PNG::try_from(bytes.as_slice()).err().unwrap();
+        assert!(err.to_string().contains("byte order mark"));
+    }
+}
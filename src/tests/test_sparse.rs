@@ -0,0 +1,100 @@
+#[cfg(test)]
+mod tests {
+    use crate::png::{Format, PNG};
+    use crate::sparse::{scan_file, trailer_chunk, Found};
+    use crate::tests::chunk;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn png_bytes(pixels: usize) -> Vec<u8> {
+        PNG::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("IDAT", &vec![7; pixels]),
+            chunk("IEND", b""),
+        ])
+        .as_bytes()
+    }
+
+    fn testing_file(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "msg-in-png-sparse-{}-{}.png",
+            name,
+            std::process::id()
+        ));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_trailer_chunk_crc() {
+        assert_eq!(
+            trailer_chunk(Format::Png),
+            [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]
+        );
+        assert_eq!(&trailer_chunk(Format::Mng)[4..8], b"MEND");
+    }
+
+    #[test]
+    fn test_clean_png() {
+        let path = testing_file("clean", &png_bytes(100));
+        let report = scan_file(&path, 64).unwrap();
+        assert_eq!(report.format, Some(Format::Png));
+        assert_eq!(report.trailing_bytes(), Some(0));
+        assert!(!report.is_suspicious());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_trailing_zip_in_sparse_window() {
+        let mut bytes = png_bytes(10_000);
+        let iend = bytes.len() - 12;
+        bytes.extend_from_slice(b"PK\x03\x04 archive PK\x05\x06 end");
+        let path = testing_file("zip", &bytes);
+        let report = scan_file(&path, 64).unwrap();
+        assert_eq!(report.iend_offset, Some(iend as u64));
+        assert_eq!(report.trailing_bytes(), Some(21));
+        assert_eq!(
+            report.signatures,
+            vec![
+                Found {
+                    kind: "zip",
+                    offset: iend as u64 + 12,
+                },
+                Found {
+                    kind: "zip-end",
+                    offset: iend as u64 + 25,
+                },
+            ]
+        );
+        assert!(report.is_suspicious());
+        assert!(report.to_json().contains("\"trailing_bytes\":21"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_iend_out_of_window() {
+        let mut bytes = png_bytes(100);
+        bytes.extend_from_slice(&[0x7F, b'E', b'L', b'F']);
+        bytes.extend(vec![0; 1000]);
+        let path = testing_file("elf", &bytes);
+        let report = scan_file(&path, 64).unwrap();
+        assert_eq!(report.iend_offset, None);
+        assert_eq!(report.trailing_bytes(), None);
+        assert!(report.is_suspicious());
+        // The signature is before the tail window, only the full file scan sees it
+        assert!(report.signatures.is_empty());
+        let report = scan_file(&path, 4096).unwrap();
+        assert_eq!(report.signatures[0].kind, "elf");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_not_a_png() {
+        let path = testing_file("text", b"just some text");
+        let report = scan_file(&path, 64).unwrap();
+        assert_eq!(report.format, None);
+        assert!(report.is_suspicious());
+        assert!(report.to_json().contains("\"format\":null"));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,260 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::png::PNG;
+    use crate::split::{
+        copy_payload, is_part, join_all, join_parts, join_payload, parse_part, read_part,
+        split_payload, split_size, Token,
+    };
+    use std::str::FromStr;
+
+    fn png_with(chunks: Vec<Chunk>) -> PNG {
+        let mut all = vec![Chunk::new(
+            ChunkType::from_str("IHDR").unwrap(),
+            vec![0; 13],
+        )];
+        all.extend(chunks);
+        all.push(Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![]));
+        PNG::from_chunks(all)
+    }
+
+    #[test]
+    fn test_small_payload_is_not_split() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let chunks = split_payload(&chunk_type, b"hello", 16, None).unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].data(), b"hello");
+    }
+
+    #[test]
+    fn test_split_and_join() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let payload: Vec<u8> = (0..200u8).collect();
+        let chunks = split_payload(&chunk_type, &payload, 48, None).unwrap();
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| chunk.data().len() <= 48));
+        assert!(chunks.iter().all(|chunk| is_part(chunk.data())));
+        let png = png_with(chunks);
+        assert_eq!(
+            join_payload(&png, &chunk_type, None).unwrap(),
+            Some(payload)
+        );
+    }
+
+    #[test]
+    fn test_payload_looking_like_a_part_is_framed() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let chunks = split_payload(&chunk_type, b"msgsplit:0/1\nfake", 1024, None).unwrap();
+        assert_eq!(chunks.len(), 1);
+        let png = png_with(chunks);
+        assert_eq!(
+            join_payload(&png, &chunk_type, None).unwrap().unwrap(),
+            b"msgsplit:0/1\nfake"
+        );
+    }
+
+    #[test]
+    fn test_limit_too_small() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        assert!(split_payload(&chunk_type, b"hello world", 8, None).is_err());
+    }
+
+    #[test]
+    fn test_missing_part() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let mut chunks = split_payload(&chunk_type, &[7; 100], 50, None).unwrap();
+        chunks.remove(1);
+        let png = png_with(chunks);
+        assert!(join_payload(&png, &chunk_type, None).is_err());
+    }
+
+    #[test]
+    fn test_parse_part() {
+        assert_eq!(parse_part(b"plain").unwrap(), None);
+        assert_eq!(
+            parse_part(b"msgsplit:1/3\nabc").unwrap(),
+            Some((1, 3, b"abc".as_slice()))
+        );
+        assert!(parse_part(b"msgsplit:3/3\nabc").is_err());
+        assert!(parse_part(b"msgsplit:1/3").is_err());
+    }
+
+    #[test]
+    fn test_join_all() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let payload: Vec<u8> = (0..100u8).collect();
+        let mut chunks = vec![Chunk::new(chunk_type.clone(), b"first".to_vec())];
+        chunks.extend(split_payload(&chunk_type, &payload, 48, None).unwrap());
+        chunks.push(Chunk::new(chunk_type.clone(), b"last".to_vec()));
+        let png = png_with(chunks);
+        assert_eq!(
+            join_all(&png, &chunk_type, None).unwrap(),
+            vec![b"first".to_vec(), payload, b"last".to_vec()]
+        );
+        let other = ChunkType::from_str("teSt").unwrap();
+        assert!(join_all(&png, &other, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_join_without_chunk() {
+        let png = png_with(vec![]);
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        assert_eq!(join_payload(&png, &chunk_type, None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_copy_payload_split() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let payload: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        let mut chunks = split_payload(&chunk_type, &payload, 100, None).unwrap();
+        chunks.insert(
+            1,
+            Chunk::new(ChunkType::from_str("tEXt").unwrap(), vec![1; 7]),
+        );
+        let bytes = png_with(chunks).as_bytes();
+        let mut output = Vec::new();
+        let copied = copy_payload(bytes.as_slice(), &chunk_type, &mut output, None).unwrap();
+        assert_eq!(copied, Some(1000));
+        assert_eq!(output, payload);
+    }
+
+    #[test]
+    fn test_copy_payload_single_and_missing() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let bytes = png_with(vec![Chunk::new(chunk_type.clone(), vec![])]).as_bytes();
+        let mut output = Vec::new();
+        assert_eq!(
+            copy_payload(bytes.as_slice(), &chunk_type, &mut output, None).unwrap(),
+            Some(0)
+        );
+        let other = ChunkType::from_str("raRe").unwrap();
+        assert_eq!(
+            copy_payload(bytes.as_slice(), &other, &mut output, None).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_copy_payload_bad_crc() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let mut bytes =
+            png_with(vec![Chunk::new(chunk_type.clone(), b"hello".to_vec())]).as_bytes();
+        // Last CRC byte of the ruSt chunk, right after IHDR
+        let crc_end = 8 + 25 + 12 + 5;
+        bytes[crc_end - 1] ^= 1;
+        let mut output = Vec::new();
+        assert!(copy_payload(bytes.as_slice(), &chunk_type, &mut output, None).is_err());
+    }
+
+    #[test]
+    fn test_split_size_matches_split_payload() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        for (length, max) in [(0, 16), (16, 16), (17, 40), (200, 40), (5000, 60)] {
+            let chunks = split_payload(&chunk_type, &vec![b'x'; length], max, None).unwrap();
+            let bytes = chunks.iter().map(|chunk| chunk.data().len()).sum();
+            assert_eq!(split_size(length, max).unwrap(), (chunks.len(), bytes));
+        }
+        assert!(split_size(100, 30).is_err());
+    }
+
+    /// Swap the pieces of parts at `a` and `b`, keeping their headers
+    fn swap_pieces(chunks: &mut [Chunk], a: usize, b: usize) {
+        let piece = |chunk: &Chunk| read_part(chunk.data()).unwrap().unwrap().piece.to_vec();
+        let header = |chunk: &Chunk| {
+            let data = chunk.data();
+            data[..data.len() - piece(chunk).len()].to_vec()
+        };
+        let (piece_a, piece_b) = (piece(&chunks[a]), piece(&chunks[b]));
+        let (header_a, header_b) = (header(&chunks[a]), header(&chunks[b]));
+        chunks[a].set_data([header_a, piece_b].concat());
+        chunks[b].set_data([header_b, piece_a].concat());
+    }
+
+    #[test]
+    fn test_parts_carry_tokens() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let chunks = split_payload(&chunk_type, &[1; 100], 50, None).unwrap();
+        let part = read_part(chunks[1].data()).unwrap().unwrap();
+        assert_eq!((part.index, part.count), (1, chunks.len()));
+        assert!(matches!(part.token, Some(Token::Chained(_))));
+        let signed = split_payload(&chunk_type, &[1; 100], 50, Some(b"key")).unwrap();
+        let part = read_part(signed[1].data()).unwrap().unwrap();
+        assert!(matches!(part.token, Some(Token::Signed(_))));
+        assert_eq!(
+            read_part(b"msgsplit:1/3\nabc").unwrap().unwrap().token,
+            None
+        );
+        assert!(read_part(b"msgsplit:1/3:x0011223344556677\nabc").is_err());
+        assert!(read_part(b"msgsplit:1/3:c00\nabc").is_err());
+        assert!(read_part(b"msgsplit:1/3:c+011223344556677\nabc").is_err());
+    }
+
+    #[test]
+    fn test_swapped_pieces_are_detected() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let payload: Vec<u8> = (0..100u8).collect();
+        let mut chunks = split_payload(&chunk_type, &payload, 50, None).unwrap();
+        swap_pieces(&mut chunks, 0, 1);
+        let parts: Vec<&[u8]> = chunks.iter().map(|chunk| chunk.data()).collect();
+        assert!(join_parts(parts.iter().copied(), None).is_err());
+        let bytes = png_with(chunks.clone()).as_bytes();
+        assert!(copy_payload(bytes.as_slice(), &chunk_type, &mut Vec::new(), None).is_err());
+    }
+
+    #[test]
+    fn test_parts_of_another_payload_are_detected() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let first = split_payload(&chunk_type, &[1; 100], 50, None).unwrap();
+        let second = split_payload(&chunk_type, &[2; 100], 50, None).unwrap();
+        assert_eq!(first.len(), second.len());
+        let mixed = [first[0].data(), second[1].data(), first[2].data()];
+        let error = join_parts(mixed, None).unwrap_err();
+        assert!(error.to_string().contains("ordering token"));
+    }
+
+    #[test]
+    fn test_signed_parts() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let payload: Vec<u8> = (0..100u8).collect();
+        let chunks = split_payload(&chunk_type, &payload, 50, Some(b"key")).unwrap();
+        let parts: Vec<&[u8]> = chunks.iter().map(|chunk| chunk.data()).collect();
+        assert_eq!(
+            join_parts(parts.iter().copied(), Some(b"key")).unwrap(),
+            Some(payload.clone())
+        );
+        assert!(join_parts(parts.iter().copied(), Some(b"other")).is_err());
+        // Signatures can't be checked without the key
+        assert_eq!(
+            join_parts(parts.iter().copied(), None).unwrap(),
+            Some(payload.clone())
+        );
+        let bytes = png_with(chunks.clone()).as_bytes();
+        let mut output = Vec::new();
+        copy_payload(bytes.as_slice(), &chunk_type, &mut output, Some(b"key")).unwrap();
+        assert_eq!(output, payload);
+        assert!(copy_payload(
+            bytes.as_slice(),
+            &chunk_type,
+            &mut Vec::new(),
+            Some(b"other")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_key_requires_signed_parts() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let chunked = split_payload(&chunk_type, &[1; 100], 50, None).unwrap();
+        let parts: Vec<&[u8]> = chunked.iter().map(|chunk| chunk.data()).collect();
+        assert!(join_parts(parts.iter().copied(), Some(b"key")).is_err());
+        let legacy = [
+            b"msgsplit:0/2\nab".as_slice(),
+            b"msgsplit:1/2\ncd".as_slice(),
+        ];
+        assert_eq!(join_parts(legacy, None).unwrap(), Some(b"abcd".to_vec()));
+        assert!(join_parts(legacy, Some(b"key")).is_err());
+        let mixed = [parts[0], b"msgsplit:1/3\ncd".as_slice(), parts[2]];
+        assert!(join_parts(mixed, None).is_err());
+    }
+}
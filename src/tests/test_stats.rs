@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod tests {
+    use crate::png::PNG;
+    use crate::stats::{breakdown, by_type, by_type_to_text, to_json, to_text, SortKey};
+    use crate::tests::chunk;
+
+    fn testing_png() -> PNG {
+        let zeroed = |chunk_type: &str, length: usize| chunk(chunk_type, &vec![0; length]);
+        PNG::from_chunks(vec![
+            zeroed("IHDR", 13),
+            zeroed("tEXt", 20),
+            zeroed("IDAT", 100),
+            zeroed("IDAT", 50),
+            zeroed("ruSt", 300),
+            zeroed("IEND", 0),
+        ])
+    }
+
+    #[test]
+    fn test_by_type_sorted_by_size() {
+        let stats = by_type(&testing_png(), SortKey::Size);
+        let types: Vec<String> = stats
+            .iter()
+            .map(|stat| stat.chunk_type.to_string())
+            .collect();
+        assert_eq!(types, ["ruSt", "IDAT", "tEXt", "IHDR", "IEND"]);
+        assert_eq!(stats[1].count, 2);
+        assert_eq!(stats[1].bytes, 150 + 24);
+    }
+
+    #[test]
+    fn test_by_type_other_orders() {
+        let png = testing_png();
+        assert_eq!(
+            by_type(&png, SortKey::Count)[0].chunk_type.to_string(),
+            "IDAT"
+        );
+        assert_eq!(
+            by_type(&png, SortKey::Order)[0].chunk_type.to_string(),
+            "IHDR"
+        );
+        assert_eq!(
+            by_type(&png, SortKey::Type)[0].chunk_type.to_string(),
+            "IDAT"
+        );
+    }
+
+    #[test]
+    fn test_breakdown_adds_up_to_file_size() {
+        let png = testing_png();
+        let breakdown = breakdown(&png);
+        assert_eq!(breakdown.total(), png.byte_len() as u64);
+        assert_eq!(breakdown.messages, 312);
+        assert_eq!(breakdown.metadata, 32);
+    }
+
+    #[test]
+    fn test_significant_share_is_pointed_out() {
+        let png = testing_png();
+        assert!(to_text(&png).contains("Metadata and messages take"));
+        assert!(by_type_to_text(&png, SortKey::Size).contains("! ruSt"));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let json = to_json(&testing_png(), SortKey::Order);
+        assert!(json.starts_with(
+            "{\"signature\":8,\"image\":211,\"metadata\":32,\"messages\":312,\"total\":563,"
+        ));
+        assert!(json.ends_with(
+            "\"types\":[{\"type\":\"IHDR\",\"count\":1,\"bytes\":25},\
+             {\"type\":\"tEXt\",\"count\":1,\"bytes\":32},\
+             {\"type\":\"IDAT\",\"count\":2,\"bytes\":174},\
+             {\"type\":\"ruSt\",\"count\":1,\"bytes\":312},\
+             {\"type\":\"IEND\",\"count\":1,\"bytes\":12}]}"
+        ));
+    }
+}
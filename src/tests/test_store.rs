@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use crate::digest::HashAlgorithm;
+    use crate::sha256::{sha256, to_hex};
+    use crate::store::{PayloadRef, PayloadStore};
+    use std::path::PathBuf;
+
+    fn testing_store_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("msg-in-png-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_payload_ref_round_trip() {
+        let payload_ref = PayloadRef::new(
+            HashAlgorithm::default().hash(b"payload"),
+            Some("https://example.com/p".into()),
+        );
+        let bytes = payload_ref.as_bytes();
+        assert!(PayloadRef::is_ref(&bytes));
+        assert_eq!(PayloadRef::try_from(bytes.as_slice()).unwrap(), payload_ref);
+    }
+
+    #[test]
+    fn test_payload_ref_without_url() {
+        let payload_ref = PayloadRef::new(HashAlgorithm::Sha512.hash(b"payload"), None);
+        let bytes = payload_ref.as_bytes();
+        assert_eq!(
+            bytes.len(),
+            PayloadRef::len_without_url(HashAlgorithm::Sha512)
+        );
+        let parsed = PayloadRef::try_from(bytes.as_slice()).unwrap();
+        assert_eq!(parsed.url, None);
+    }
+
+    #[test]
+    fn test_sha256_payload_ref_still_parses() {
+        let bytes = format!("msgref:sha256:{}", to_hex(&sha256(b"payload")));
+        let payload_ref = PayloadRef::try_from(bytes.as_bytes()).unwrap();
+        assert_eq!(payload_ref.hash, HashAlgorithm::Sha256.hash(b"payload"));
+        assert_eq!(payload_ref.as_bytes(), bytes.as_bytes());
+    }
+
+    #[test]
+    fn test_invalid_payload_ref() {
+        assert!(!PayloadRef::is_ref(b"plain message"));
+        assert!(PayloadRef::try_from(&b"msgref:sha256:1234"[..]).is_err());
+    }
+
+    #[test]
+    fn test_store_put_get() {
+        let dir = testing_store_dir("store-put-get");
+        let store = PayloadStore::open(&dir).unwrap();
+        for algorithm in HashAlgorithm::ALL {
+            let hash = store.put(b"a large payload", algorithm).unwrap();
+            assert_eq!(hash.algorithm, algorithm);
+            assert_eq!(store.put(b"a large payload", algorithm).unwrap(), hash);
+            assert_eq!(store.get(&hash).unwrap(), b"a large payload");
+        }
+        // SHA-256 payloads keep the bare hex names stores had before other algorithms
+        let hash = HashAlgorithm::Sha256.hash(b"a large payload");
+        assert_eq!(store.path_of(&hash), dir.join(hash.hex()));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_store_resolve_missing() {
+        let dir = testing_store_dir("store-missing");
+        let store = PayloadStore::open(&dir).unwrap();
+        let payload_ref = PayloadRef::new(
+            HashAlgorithm::default().hash(b"never stored"),
+            Some("https://x".into()),
+        );
+        let err = store.resolve(&payload_ref).unwrap_err();
+        assert!(err.to_string().contains("https://x"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_store_detects_corruption() {
+        let dir = testing_store_dir("store-corrupt");
+        let store = PayloadStore::open(&dir).unwrap();
+        let hash = store.put(b"original", HashAlgorithm::default()).unwrap();
+        std::fs::write(store.path_of(&hash), b"tampered").unwrap();
+        assert!(store.get(&hash).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
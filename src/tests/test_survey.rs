@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod tests {
+    use crate::confine::PathPolicy;
+    use crate::corpus::{header, image, with_corrupt_crc};
+    use crate::survey::{FileOutcome, Health, Survey, UNREADABLE_KIND};
+    use crate::verify::StreamOptions;
+    use crate::{Chunk, ChunkType};
+    use std::fs;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    fn testing_tree(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("msg-in-png-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("nested")).unwrap();
+        let png = image(header(4, 4, 2, 8, false)).unwrap();
+        fs::write(root.join("good.png"), png.as_bytes()).unwrap();
+        fs::write(
+            root.join("nested/corrupt.png"),
+            with_corrupt_crc(&png, 1).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            root.join("nested/also-corrupt.png"),
+            with_corrupt_crc(&png, 1).unwrap(),
+        )
+        .unwrap();
+        let mut warned = image(header(4, 4, 2, 8, false)).unwrap();
+        let iend = warned.chunks().len() - 1;
+        warned.remove_chunk("IEND").unwrap();
+        warned
+            .insert_chunk(
+                iend,
+                Chunk::new(ChunkType::from_str("IEND").unwrap(), b"x".to_vec()),
+            )
+            .unwrap();
+        fs::write(root.join("warned.png"), warned.as_bytes()).unwrap();
+        fs::write(root.join("notes.txt"), b"not a png").unwrap();
+        root
+    }
+
+    #[test]
+    fn test_summary() {
+        let root = testing_tree("survey-summary");
+        let survey = Survey::run(&root, &PathPolicy::default(), StreamOptions::default()).unwrap();
+        assert_eq!(survey.files.len(), 4);
+        assert_eq!(survey.count(Health::Ok), 1);
+        assert_eq!(survey.count(Health::Warnings), 1);
+        assert_eq!(survey.count(Health::Errors), 2);
+        let total: u64 = [
+            "good.png",
+            "warned.png",
+            "nested/corrupt.png",
+            "nested/also-corrupt.png",
+        ]
+        .iter()
+        .map(|name| fs::metadata(root.join(name)).unwrap().len())
+        .sum();
+        assert_eq!(survey.bytes_scanned(), total);
+        assert_eq!(
+            survey.top_error_kinds(5),
+            vec![("IDAT CRC check failed".to_string(), 2)]
+        );
+        assert!(survey.top_error_kinds(0).is_empty());
+        let json = survey.to_json(5);
+        assert!(json.contains(&format!(
+            ",\"ok\":1,\"warnings\":1,\"errors\":2,\"bytes\":{},\"top_errors\":\
+             [{{\"kind\":\"IDAT CRC check failed\",\"files\":2}}]}}",
+            total
+        )));
+        assert_eq!(json.matches("\"health\":\"errors\"").count(), 2);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_unreadable_file() {
+        let outcome = FileOutcome::check(
+            &std::env::temp_dir().join("msg-in-png-survey-missing.png"),
+            StreamOptions::default(),
+        );
+        assert_eq!(outcome.health(), Health::Errors);
+        assert_eq!(outcome.bytes(), 0);
+        let survey = Survey {
+            root: PathBuf::from("."),
+            files: vec![outcome],
+        };
+        assert_eq!(
+            survey.top_error_kinds(5),
+            vec![(UNREADABLE_KIND.to_string(), 1)]
+        );
+        assert!(survey
+            .to_junit()
+            .contains("<error type=\"unreadable\" message=\""));
+    }
+
+    #[test]
+    fn test_junit_report() {
+        let root = testing_tree("survey-junit");
+        let survey = Survey::run(&root, &PathPolicy::default(), StreamOptions::default()).unwrap();
+        let xml = survey.to_junit();
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites "));
+        assert!(xml.contains("tests=\"4\" failures=\"2\" errors=\"0\""));
+        assert!(xml.contains("<testcase classname=\"png\" name=\"good.png\"/>"));
+        assert!(xml.contains("name=\"nested/corrupt.png\">"));
+        assert_eq!(xml.matches("<failure ").count(), 2);
+        assert!(xml.contains("<system-out>warning: chunk 2: IEND should be empty</system-out>"));
+        assert!(xml.ends_with("</testsuite>\n</testsuites>\n"));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_junit_escapes_names() {
+        let survey = Survey {
+            root: PathBuf::from("a&b"),
+            files: vec![FileOutcome {
+                path: PathBuf::from("a&b/<odd>\u{1}.png"),
+                report: Err("\"gone\"".to_string()),
+            }],
+        };
+        let xml = survey.to_junit();
+        assert!(xml.contains("name=\"a&amp;b\""));
+        assert!(xml.contains("name=\"&lt;odd&gt;\u{fffd}.png\""));
+        assert!(xml.contains("message=\"&quot;gone&quot;\""));
+    }
+}
@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use crate::confine::PathPolicy;
+    use crate::png::PNG;
+    use crate::sync::{sync_meta, SyncOutcome};
+    use crate::tests::{chunk, testing_png};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn testing_trees(name: &str) -> (PathBuf, PathBuf) {
+        let root = std::env::temp_dir().join(format!("msg-in-png-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let (source, destination) = (root.join("src"), root.join("dst"));
+        fs::create_dir_all(source.join("nested")).unwrap();
+        fs::create_dir_all(destination.join("nested")).unwrap();
+        (source, destination)
+    }
+
+    fn write(path: &Path, png: &PNG) {
+        fs::write(path, png.as_bytes()).unwrap();
+    }
+
+    fn outcome_of<'a>(report: &'a crate::sync::SyncReport, path: &str) -> &'a SyncOutcome {
+        &report
+            .entries
+            .iter()
+            .find(|(entry, _)| entry == Path::new(path))
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn test_sync_meta() {
+        let (source, destination) = testing_trees("sync-meta");
+        write(
+            &source.join("a.png"),
+            &testing_png(b"px", &[chunk("ruSt", b"hello")]),
+        );
+        write(&destination.join("a.png"), &testing_png(b"px", &[]));
+        write(
+            &source.join("nested/b.png"),
+            &testing_png(b"px", &[chunk("ruSt", b"hi")]),
+        );
+        write(
+            &destination.join("nested/b.png"),
+            &testing_png(b"other", &[]),
+        );
+        write(
+            &source.join("c.png"),
+            &testing_png(b"px", &[chunk("ruSt", b"mine")]),
+        );
+        write(
+            &destination.join("c.png"),
+            &testing_png(b"px", &[chunk("ruSt", b"theirs")]),
+        );
+        write(
+            &source.join("d.png"),
+            &testing_png(b"px", &[chunk("ruSt", b"lost")]),
+        );
+
+        let report = sync_meta(&source, &destination, false, &PathPolicy::default()).unwrap();
+        assert_eq!(outcome_of(&report, "a.png"), &SyncOutcome::Synced(1));
+        assert_eq!(
+            outcome_of(&report, "nested/b.png"),
+            &SyncOutcome::ContentMismatch
+        );
+        assert_eq!(
+            outcome_of(&report, "c.png"),
+            &SyncOutcome::Conflict(vec!["ruSt".to_string()])
+        );
+        assert_eq!(
+            outcome_of(&report, "d.png"),
+            &SyncOutcome::MissingDestination
+        );
+
+        let synced =
+            PNG::try_from(fs::read(destination.join("a.png")).unwrap().as_slice()).unwrap();
+        assert_eq!(synced.chunk_by_type("ruSt").unwrap().data(), b"hello");
+        assert_eq!(
+            synced.chunks().last().unwrap().chunk_type().to_string(),
+            "IEND"
+        );
+
+        let again = sync_meta(&source, &destination, false, &PathPolicy::default()).unwrap();
+        assert_eq!(outcome_of(&again, "a.png"), &SyncOutcome::UpToDate);
+        assert!(again
+            .to_json()
+            .contains("{\"path\":\"a.png\",\"outcome\":\"up_to_date\"}"));
+        fs::remove_dir_all(source.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_sync_meta_dry_run() {
+        let (source, destination) = testing_trees("sync-meta-dry");
+        write(
+            &source.join("a.png"),
+            &testing_png(b"px", &[chunk("ruSt", b"hello")]),
+        );
+        write(&destination.join("a.png"), &testing_png(b"px", &[]));
+
+        let report = sync_meta(&source, &destination, true, &PathPolicy::default()).unwrap();
+        assert_eq!(outcome_of(&report, "a.png"), &SyncOutcome::Synced(1));
+        let untouched =
+            PNG::try_from(fs::read(destination.join("a.png")).unwrap().as_slice()).unwrap();
+        assert!(untouched.chunk_by_type("ruSt").is_none());
+        fs::remove_dir_all(source.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,135 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::mime::{wrap, MimeType};
+    use crate::png::{Format, PNG};
+    use crate::split::split_payload;
+    use crate::tee::{tee, TeeMessage};
+    use crate::tests::chunk;
+    use std::str::FromStr;
+
+    fn chunk_type(name: &str) -> ChunkType {
+        ChunkType::from_str(name).unwrap()
+    }
+
+    fn png_bytes(messages: Vec<Chunk>) -> Vec<u8> {
+        let mut chunks = vec![chunk("IHDR", &[0; 13]), chunk("IDAT", b"pixels")];
+        chunks.extend(messages);
+        chunks.push(chunk("IEND", b""));
+        PNG::from_chunks(chunks).as_bytes()
+    }
+
+    fn run(
+        input: &[u8],
+        chunk_types: &[&str],
+    ) -> (Vec<u8>, Vec<TeeMessage>, anyhow::Result<usize>) {
+        let chunk_types: Vec<ChunkType> = chunk_types.iter().map(|name| chunk_type(name)).collect();
+        let mut output = Vec::new();
+        let mut messages = Vec::new();
+        let result = tee(input, &mut output, &chunk_types, |message| {
+            messages.push(message);
+            Ok(())
+        });
+        (output, messages, result)
+    }
+
+    #[test]
+    fn test_tee_copies_and_reports() {
+        let mut input = png_bytes(vec![chunk("ruSt", b"hello"), chunk("abCd", b"other")]);
+        input.extend_from_slice(b"trailing bytes");
+        let (output, messages, result) = run(&input, &["ruSt"]);
+        assert_eq!(output, input);
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(
+            messages,
+            vec![TeeMessage {
+                chunk_type: chunk_type("ruSt"),
+                index: 2,
+                mime: None,
+                message: b"hello".to_vec(),
+            }]
+        );
+        assert_eq!(messages[0].to_text(), "ruSt[2]: hello");
+        assert_eq!(
+            messages[0].to_json(),
+            "{\"chunk_type\":\"ruSt\",\"index\":2,\"mime\":null,\"bytes\":5,\"message\":\"hello\"}"
+        );
+    }
+
+    #[test]
+    fn test_tee_every_message_chunk() {
+        let input = png_bytes(vec![
+            chunk("tEXt", b"Title\0x"),
+            chunk("ruSt", b"one"),
+            chunk("abCd", b"two"),
+        ]);
+        let (_, messages, result) = run(&input, &[]);
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(messages[0].message, b"one");
+        assert_eq!(messages[1].message, b"two");
+    }
+
+    #[test]
+    fn test_tee_split_and_typed() {
+        let payload = wrap(&MimeType::from_str("text/plain").unwrap(), &[b'x'; 100]);
+        let parts = split_payload(&chunk_type("ruSt"), &payload, 40, None).unwrap();
+        let input = png_bytes(parts);
+        let (output, messages, result) = run(&input, &["ruSt"]);
+        assert_eq!(output, input);
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(messages[0].index, 2);
+        assert_eq!(messages[0].mime.as_ref().unwrap().as_str(), "text/plain");
+        assert_eq!(messages[0].message, vec![b'x'; 100]);
+        assert!(messages[0]
+            .to_text()
+            .starts_with("ruSt[2] (text/plain): xxx"));
+    }
+
+    #[test]
+    fn test_tee_binary_message() {
+        let input = png_bytes(vec![chunk("ruSt", &[0xff, 0xfe])]);
+        let (_, messages, _) = run(&input, &["ruSt"]);
+        assert_eq!(messages[0].to_text(), "ruSt[2]: <2 bytes of binary data>");
+        assert!(messages[0]
+            .to_json()
+            .ends_with("\"bytes\":2,\"message\":null}"));
+    }
+
+    #[test]
+    fn test_tee_copies_invalid_input() {
+        let (output, messages, result) = run(b"not a png at all", &["ruSt"]);
+        assert_eq!(output, b"not a png at all");
+        assert!(messages.is_empty());
+        assert!(result.is_err());
+
+        let mut input = png_bytes(vec![chunk("ruSt", b"hello")]);
+        let crc_of_message = 8 + 25 + 18 + 12 + 5 - 1;
+        input[crc_of_message] ^= 1;
+        let (output, _, result) = run(&input, &["ruSt"]);
+        assert_eq!(output, input);
+        assert!(result.is_err());
+
+        let truncated = &input[..40];
+        let (output, _, result) = run(truncated, &["ruSt"]);
+        assert_eq!(output, truncated);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_tee_reads_past_embedded_iend_of_mng() {
+        let chunks = vec![
+            chunk("MHDR", b""),
+            chunk("IHDR", &[0; 13]),
+            chunk("IDAT", b"pixels"),
+            chunk("IEND", b""),
+            chunk("ruSt", b"after"),
+            chunk("MEND", b""),
+        ];
+        let input = PNG::from_chunks_with_format(chunks, Format::Mng).as_bytes();
+        let (output, messages, result) = run(&input, &[]);
+        assert_eq!(output, input);
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(messages[0].index, 4);
+    }
+}
@@ -0,0 +1,287 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::png::PNG;
+    use crate::text::{
+        convert_chunks, find, keyword_of, positions, CompressedText, InternationalText, Keyword,
+        Text, TextForm, TextualChunk, COPYRIGHT, REGISTERED_KEYWORDS,
+    };
+    use std::str::FromStr;
+
+    fn keyword(keyword: &str) -> Keyword {
+        Keyword::from_str(keyword).unwrap()
+    }
+
+    #[test]
+    fn test_keyword_normalization() {
+        assert_eq!(keyword("  My   Title ").as_str(), "My Title");
+        assert_eq!(keyword("Café").latin1_bytes(), b"Caf\xe9");
+    }
+
+    #[test]
+    fn test_invalid_keywords() {
+        assert!(Keyword::from_str("").is_err());
+        assert!(Keyword::from_str("   ").is_err());
+        assert!(Keyword::from_str(&"k".repeat(80)).is_err());
+        assert!(Keyword::from_str(&"k".repeat(79)).is_ok());
+        assert!(Keyword::from_str("Tab\there").is_err());
+        assert!(Keyword::from_str("你好").is_err());
+        assert!(Keyword::from_str("Nul\0").is_err());
+    }
+
+    #[test]
+    fn test_registered_keywords() {
+        assert!(keyword(COPYRIGHT).is_registered());
+        assert!(keyword(" Creation  Time").is_registered());
+        assert!(!keyword("Message").is_registered());
+        assert!(REGISTERED_KEYWORDS
+            .iter()
+            .all(|registered| Keyword::from_str(registered).is_ok()));
+    }
+
+    #[test]
+    fn test_international_text_latin1_keyword() {
+        let text = InternationalText::new(&keyword("Café"), "fr", "Bonjour");
+        let chunk = text.to_chunk();
+        assert!(chunk.data().starts_with(b"Caf\xe9\0"));
+        assert_eq!(InternationalText::try_from(&chunk).unwrap().keyword, "Café");
+    }
+
+    #[test]
+    fn test_international_text_round_trip() {
+        let mut text = InternationalText::new(&keyword("Title"), "zh-CN", "你好");
+        text.translated_keyword = "标题".to_string();
+        let chunk = text.to_chunk();
+        assert_eq!(chunk.chunk_type().to_string(), "iTXt");
+        assert_eq!(InternationalText::try_from(&chunk).unwrap(), text);
+    }
+
+    #[test]
+    fn test_international_text_layout() {
+        let chunk = InternationalText::new(&keyword("Title"), "en", "Hi").to_chunk();
+        assert_eq!(chunk.data(), b"Title\0\0\0en\0\0Hi");
+    }
+
+    #[test]
+    fn test_international_text_rejects_other_types() {
+        let chunk = Chunk::new(ChunkType::from_str("tEXt").unwrap(), b"Title\0Hi".to_vec());
+        assert!(InternationalText::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_international_text_malformed() {
+        let chunk = Chunk::new(ChunkType::from_str("iTXt").unwrap(), b"Title".to_vec());
+        assert!(InternationalText::try_from(&chunk).is_err());
+        let chunk = Chunk::new(
+            ChunkType::from_str("iTXt").unwrap(),
+            b"Title\0\x01\0en\0\0zz".to_vec(),
+        );
+        assert!(InternationalText::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_international_text_compressed() {
+        let mut text =
+            InternationalText::new(&keyword("Comment"), "en", &"compress me ".repeat(50));
+        text.compressed = true;
+        let chunk = text.to_chunk();
+        assert!(chunk.data().starts_with(b"Comment\0\x01\0en\0\0\x78"));
+        assert!(chunk.data().len() < 100);
+        assert_eq!(InternationalText::try_from(&chunk).unwrap(), text);
+    }
+
+    #[test]
+    fn test_international_text_try_new() {
+        let text =
+            InternationalText::try_new(&keyword("Title"), "de-DE", "Titel", "Grüße", true).unwrap();
+        let chunk = text.to_chunk();
+        assert!(chunk.data().starts_with(b"Title\0\x01\0de-DE\0Titel\0"));
+        assert_eq!(InternationalText::try_from(&chunk).unwrap(), text);
+        assert!(InternationalText::try_new(&keyword("Title"), "de DE", "", "", false).is_err());
+        assert!(InternationalText::try_new(&keyword("Title"), "de\0", "", "", false).is_err());
+        assert!(InternationalText::try_new(&keyword("Title"), "de", "Ti\0tel", "", false).is_err());
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let text = Text::new(&keyword("Author"), "Zoë").unwrap();
+        let chunk = text.to_chunk();
+        assert_eq!(chunk.data(), b"Author\0Zo\xeb");
+        assert_eq!(Text::try_from(&chunk).unwrap(), text);
+        assert!(Text::new(&keyword("Author"), "你好").is_err());
+    }
+
+    #[test]
+    fn test_compressed_text_round_trip() {
+        let text = CompressedText::new(&keyword("Comment"), &"Zoë ".repeat(100)).unwrap();
+        let chunk = text.to_chunk();
+        assert_eq!(chunk.chunk_type().to_string(), "zTXt");
+        assert!(chunk.data().starts_with(b"Comment\0\0"));
+        assert!(chunk.data().len() < 100);
+        assert_eq!(CompressedText::try_from(&chunk).unwrap(), text);
+        let chunk = Chunk::new(
+            ChunkType::from_str("zTXt").unwrap(),
+            b"Comment\0\x01".to_vec(),
+        );
+        assert!(CompressedText::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_text_form_from_str() {
+        assert_eq!(TextForm::from_str("tEXt").unwrap(), TextForm::Text);
+        assert_eq!(
+            TextForm::from_str("ZTXT").unwrap(),
+            TextForm::CompressedText
+        );
+        assert_eq!(
+            TextForm::from_str("itxt").unwrap(),
+            TextForm::InternationalText
+        );
+        assert!(TextForm::from_str("ruSt").is_err());
+    }
+
+    #[test]
+    fn test_convert() {
+        let text = TextualChunk::Text(Text::new(&keyword("Title"), "Sunset").unwrap());
+        let converted = text
+            .convert(TextForm::InternationalText, false, Some("en"))
+            .unwrap();
+        assert_eq!(converted.form(), TextForm::InternationalText);
+        assert_eq!(converted.keyword(), "Title");
+        assert_eq!(converted.text(), "Sunset");
+        let back = converted.convert(TextForm::Text, false, None).unwrap();
+        assert_eq!(back, text);
+        let unicode = TextualChunk::InternationalText(InternationalText::new(
+            &keyword("Title"),
+            "zh",
+            "日落",
+        ));
+        assert!(unicode
+            .convert(TextForm::CompressedText, false, None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_convert_chunks() {
+        let mut png = PNG::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Text::new(&keyword("Title"), "Sunset").unwrap().to_chunk(),
+            InternationalText::new(&keyword("Author"), "", "Zoë").to_chunk(),
+            InternationalText::new(&keyword("Title"), "ja", "日没").to_chunk(),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![]),
+        ]);
+        let outcomes = convert_chunks(
+            &mut png,
+            None,
+            TextForm::CompressedText,
+            false,
+            None,
+            usize::MAX,
+        );
+        assert_eq!(outcomes.len(), 3);
+        assert_eq!(outcomes[0].0, 1);
+        assert_eq!(outcomes[0].1.as_ref().unwrap(), &TextForm::Text);
+        assert_eq!(
+            outcomes[1].1.as_ref().unwrap(),
+            &TextForm::InternationalText
+        );
+        assert!(outcomes[2].1.is_err());
+        assert_eq!(png.chunks()[1].chunk_type().to_string(), "zTXt");
+        assert_eq!(png.chunks()[3].chunk_type().to_string(), "iTXt");
+
+        let title = keyword("Title");
+        let outcomes = convert_chunks(
+            &mut png,
+            Some(&title),
+            TextForm::InternationalText,
+            false,
+            Some("en"),
+            usize::MAX,
+        );
+        assert_eq!(outcomes.len(), 1);
+        let converted = InternationalText::try_from(&png.chunks()[1]).unwrap();
+        assert_eq!(converted.language_tag, "en");
+        assert_eq!(png.chunks()[2].chunk_type().to_string(), "zTXt");
+    }
+
+    #[test]
+    fn test_convert_chunks_limits_text() {
+        let long = "a".repeat(4096);
+        let mut png = PNG::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            CompressedText::new(&keyword("Comment"), &long)
+                .unwrap()
+                .to_chunk(),
+            CompressedText::new(&keyword("Title"), "short")
+                .unwrap()
+                .to_chunk(),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![]),
+        ]);
+        let outcomes = convert_chunks(&mut png, None, TextForm::Text, false, None, 1024);
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(outcomes[0].0, 1);
+        assert!(outcomes[0].1.is_err());
+        assert_eq!(outcomes[1].1.as_ref().unwrap(), &TextForm::CompressedText);
+        assert_eq!(png.chunks()[1].chunk_type().to_string(), "zTXt");
+        assert_eq!(png.chunks()[2].chunk_type().to_string(), "tEXt");
+        let compressed = CompressedText::from_chunk_limited(&png.chunks()[1], 4096).unwrap();
+        assert_eq!(compressed.text(), long);
+    }
+
+    #[test]
+    fn test_find() {
+        let png = PNG::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("tEXt").unwrap(), b"Author".to_vec()),
+            CompressedText::new(&keyword("Author"), "compressed")
+                .unwrap()
+                .to_chunk(),
+            Text::new(&keyword("Author"), "Jörg").unwrap().to_chunk(),
+            Text::new(&keyword("Author"), "second").unwrap().to_chunk(),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        ]);
+        // The unterminated keyword is skipped, zTXt does not count as tEXt
+        let found = find(&png, &keyword("Author"), TextForm::Text).unwrap();
+        assert_eq!(found.text(), "Jörg");
+        assert_eq!(png.chunks()[3].data(), b"Author\0J\xf6rg");
+        let found = find(&png, &keyword("Author"), TextForm::CompressedText).unwrap();
+        assert_eq!(found.text(), "compressed");
+        assert!(find(&png, &keyword("Title"), TextForm::Text).is_none());
+    }
+
+    #[test]
+    fn test_positions() {
+        let png = PNG::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            InternationalText::new(&keyword("Title"), "en", "one").to_chunk(),
+            Text::new(&keyword("Title"), "tEXt").unwrap().to_chunk(),
+            InternationalText::new(&keyword("Author"), "en", "me").to_chunk(),
+            InternationalText::new(&keyword("Title"), "de", "eins").to_chunk(),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        ]);
+        let title = keyword("Title");
+        assert_eq!(positions(&png, &title, TextForm::InternationalText), [1, 4]);
+        assert_eq!(positions(&png, &title, TextForm::Text), [2]);
+        assert!(positions(&png, &title, TextForm::CompressedText).is_empty());
+    }
+
+    #[test]
+    fn test_keyword_of() {
+        let text = Text::new(&keyword("Author"), "J\u{f6}rg")
+            .unwrap()
+            .to_chunk();
+        assert_eq!(
+            keyword_of(text.chunk_type(), text.data()).as_deref(),
+            Some("Author")
+        );
+        let text_type = ChunkType::from_str("tEXt").unwrap();
+        assert_eq!(
+            keyword_of(&text_type, b"Caf\xe9\0").as_deref(),
+            Some("Caf\u{e9}")
+        );
+        assert!(keyword_of(&text_type, b"no separator").is_none());
+        let other_type = ChunkType::from_str("ruSt").unwrap();
+        assert!(keyword_of(&other_type, b"Title\0text").is_none());
+    }
+}
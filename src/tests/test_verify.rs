@@ -0,0 +1,196 @@
+#[cfg(test)]
+mod tests {
+    use crate::png::{Format, PNG};
+    use crate::sha256::sha256;
+    use crate::tests::chunk;
+    use crate::verify::{
+        validate_stream, verify, verify_strict, Severity, StreamOptions,
+        STRICT_MAX_ANCILLARY_LENGTH,
+    };
+
+    fn testing_png(types: &[&str]) -> PNG {
+        PNG::from_chunks(
+            types
+                .iter()
+                .map(|chunk_type| match *chunk_type {
+                    "IHDR" => chunk("IHDR", &[0; 13]),
+                    other => chunk(other, b""),
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_valid_layout() {
+        let png = testing_png(&["IHDR", "PLTE", "IDAT", "IDAT", "ruSt", "IEND"]);
+        assert!(verify(&png).is_empty());
+    }
+
+    #[test]
+    fn test_mng_layout() {
+        let chunks = [
+            "MHDR", "ruSt", "IHDR", "IDAT", "IEND", "IHDR", "IDAT", "IEND", "MEND",
+        ]
+        .iter()
+        .map(|chunk_type| chunk(chunk_type, b""))
+        .collect();
+        let png = PNG::from_chunks_with_format(chunks, Format::Mng);
+        assert!(verify(&png).is_empty());
+
+        let report = validate_stream(png.as_bytes().as_slice(), StreamOptions::default()).unwrap();
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn test_jng_needs_header_and_trailer() {
+        let chunks = vec![chunk("JDAT", b""), chunk("MEND", b"")];
+        let issues = verify(&PNG::from_chunks_with_format(chunks, Format::Jng));
+        let messages: Vec<&str> = issues.iter().map(|issue| issue.message.as_str()).collect();
+        assert!(messages.contains(&"first chunk must be JHDR"));
+        assert!(messages.contains(&"last chunk must be IEND"));
+        assert!(messages.contains(&"missing JHDR"));
+    }
+
+    #[test]
+    fn test_missing_ihdr_and_iend() {
+        let png = testing_png(&["IDAT"]);
+        let issues = verify(&png);
+        assert!(issues
+            .iter()
+            .any(|issue| issue.message == "first chunk must be IHDR"));
+        assert!(issues
+            .iter()
+            .any(|issue| issue.message == "last chunk must be IEND"));
+        assert!(issues.iter().any(|issue| issue.message == "missing IHDR"));
+    }
+
+    #[test]
+    fn test_split_idat_run() {
+        let png = testing_png(&["IHDR", "IDAT", "ruSt", "IDAT", "IEND"]);
+        let issues = verify(&png);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].chunk_index, Some(3));
+    }
+
+    #[test]
+    fn test_chunk_after_iend() {
+        let png = testing_png(&["IHDR", "IDAT", "IEND", "ruSt", "IEND"]);
+        let issues = verify(&png);
+        assert_eq!(issues.len(), 2);
+        assert!(issues
+            .iter()
+            .all(|issue| issue.message.contains("after IEND")));
+    }
+
+    #[test]
+    fn test_unknown_critical_chunk_is_warning() {
+        let png = testing_png(&["IHDR", "IDAT", "RuSt", "IEND"]);
+        let issues = verify(&png);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Warning);
+        assert!(!issues[0].is_error());
+    }
+
+    #[test]
+    fn test_strict_accepts_valid_layout() {
+        let mut png = testing_png(&["IHDR", "IDAT", "IEND"]);
+        png.append_chunk(chunk("tEXt", b"Title\0Hello"));
+        assert!(verify_strict(&png, STRICT_MAX_ANCILLARY_LENGTH).is_empty());
+    }
+
+    #[test]
+    fn test_strict_text_keywords() {
+        let mut png = testing_png(&["IHDR", "IDAT", "IEND"]);
+        png.append_chunk(chunk("tEXt", b"\0Hello"));
+        png.append_chunk(chunk("iTXt", b"no separator"));
+        png.append_chunk(chunk("zTXt", &[b'k'; 80]));
+        let mut long_keyword = vec![b'k'; 80];
+        long_keyword.push(0);
+        png.append_chunk(chunk("tEXt", &long_keyword));
+        let issues = verify_strict(&png, STRICT_MAX_ANCILLARY_LENGTH);
+        let indices: Vec<_> = issues.iter().map(|issue| issue.chunk_index).collect();
+        assert_eq!(indices, [Some(2), Some(3), Some(4), Some(5)]);
+        assert!(issues[3].message.contains("80 bytes long"));
+    }
+
+    #[test]
+    fn test_strict_oversized_ancillary() {
+        let mut png = testing_png(&["IHDR", "IDAT", "IEND"]);
+        png.append_chunk(chunk("ruSt", &[0; 11]));
+        assert!(verify_strict(&png, 11).is_empty());
+        let issues = verify_strict(&png, 10);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].message.contains("above the strict limit"));
+    }
+
+    #[test]
+    fn test_strict_promotes_warnings() {
+        let png = testing_png(&["IHDR", "IDAT", "RuSt", "IEND"]);
+        let issues = verify_strict(&png, STRICT_MAX_ANCILLARY_LENGTH);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].is_error());
+    }
+
+    #[test]
+    fn test_validate_stream_matches_verify() {
+        let png = testing_png(&["IHDR", "IDAT", "ruSt", "IDAT", "IEND"]);
+        let bytes = png.as_bytes();
+        let report = validate_stream(bytes.as_slice(), StreamOptions::default()).unwrap();
+        assert_eq!(report.issues, verify(&png));
+        assert_eq!(report.chunks, 5);
+        assert_eq!(report.bytes, bytes.len() as u64);
+        assert_eq!(report.sha256, sha256(&bytes));
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn test_validate_stream_strict() {
+        let mut png = testing_png(&["IHDR", "IDAT", "RuSt", "IEND"]);
+        png.append_chunk(chunk("tEXt", b"no separator"));
+        let options = StreamOptions {
+            strict: true,
+            ..StreamOptions::default()
+        };
+        let report = validate_stream(png.as_bytes().as_slice(), options).unwrap();
+        assert_eq!(report.issues.len(), 2);
+        assert!(report.issues.iter().all(|issue| issue.is_error()));
+        assert!(report.issues[1].message.contains("not null terminated"));
+    }
+
+    #[test]
+    fn test_validate_stream_crc_and_truncation() {
+        let png = testing_png(&["IHDR", "IDAT", "IEND"]);
+        let mut bytes = png.as_bytes();
+        // Last byte of the IHDR CRC
+        bytes[8 + 8 + 13 + 3] ^= 1;
+        let report = validate_stream(bytes.as_slice(), StreamOptions::default()).unwrap();
+        assert_eq!(report.issues.len(), 1);
+        assert!(report.issues[0].message.contains("CRC"));
+
+        bytes.truncate(bytes.len() - 2);
+        let report = validate_stream(bytes.as_slice(), StreamOptions::default()).unwrap();
+        assert!(report
+            .issues
+            .iter()
+            .any(|issue| issue.message == "file ends inside IEND"));
+    }
+
+    #[test]
+    fn test_validate_stream_json() {
+        let png = testing_png(&["IHDR", "IDAT", "IEND"]);
+        let mut bytes = png.as_bytes();
+        bytes[8 + 8 + 13 + 3] ^= 1;
+        let json = validate_stream(bytes.as_slice(), StreamOptions::default())
+            .unwrap()
+            .to_json();
+        assert!(json.starts_with("{\"valid\":false,\"chunks\":3,\"bytes\":"));
+        assert!(json.contains("\"issues\":[{\"severity\":\"error\",\"chunk_index\":0,"));
+    }
+
+    #[test]
+    fn test_validate_stream_bad_signature() {
+        let report = validate_stream(b"GIF89a".as_slice(), StreamOptions::default()).unwrap();
+        assert_eq!(report.chunks, 0);
+        assert!(!report.is_valid());
+    }
+}
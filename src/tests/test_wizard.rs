@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunk_type::ChunkType;
+    use crate::png::PNG;
+    use crate::registry::Registry;
+    use crate::tests::chunk;
+    use crate::wizard::{chunk_type_problems, chunk_type_warnings, run};
+    use std::path::{Path, PathBuf};
+    use std::str::FromStr;
+
+    fn testing_png() -> PNG {
+        PNG::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("ruSt", b"earlier"),
+            chunk("IDAT", b"pixels"),
+            chunk("IEND", b""),
+        ])
+    }
+
+    fn chunk_type(name: &str) -> ChunkType {
+        ChunkType::from_str(name).unwrap()
+    }
+
+    /// Path that does not exist, so no overwrite question is asked
+    fn missing_path() -> &'static Path {
+        Path::new("/nonexistent/wizard/image.png")
+    }
+
+    fn answer(input: &str) -> anyhow::Result<crate::wizard::Answers> {
+        let mut prompts = Vec::new();
+        run(
+            &testing_png(),
+            None,
+            missing_path(),
+            input.as_bytes(),
+            &mut prompts,
+        )
+    }
+
+    #[test]
+    fn test_problems() {
+        assert!(chunk_type_problems(&chunk_type("ruSt")).is_empty());
+        assert_eq!(chunk_type_problems(&chunk_type("RuSt")).len(), 1);
+        assert_eq!(chunk_type_problems(&chunk_type("rUSt")).len(), 1);
+        assert_eq!(chunk_type_problems(&chunk_type("rust")).len(), 1);
+        assert_eq!(chunk_type_problems(&chunk_type("IDAT")).len(), 2);
+    }
+
+    #[test]
+    fn test_warnings() {
+        let png = testing_png();
+        assert!(chunk_type_warnings(&chunk_type("abCd"), &png, None).is_empty());
+        assert_eq!(
+            chunk_type_warnings(&chunk_type("abCD"), &png, None).len(),
+            1
+        );
+        assert_eq!(
+            chunk_type_warnings(&chunk_type("ruSt"), &png, None).len(),
+            1
+        );
+        let registry = Registry::from_toml("[abCd]\ndescription = \"Tool output\"\n").unwrap();
+        let warnings = chunk_type_warnings(&chunk_type("abCd"), &png, Some(&registry));
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("Tool output"));
+    }
+
+    #[test]
+    fn test_run() {
+        let answers = answer("abCd\nhello\nworld\n\nout.png\n\n").unwrap();
+        assert_eq!(answers.chunk_type, chunk_type("abCd"));
+        assert_eq!(answers.message, "hello\nworld");
+        assert_eq!(answers.output, Some(PathBuf::from("out.png")));
+    }
+
+    #[test]
+    fn test_run_overwrites_by_default() {
+        let answers = answer("abCd\nhello\n\n\ny\n").unwrap();
+        assert_eq!(answers.output, None);
+    }
+
+    #[test]
+    fn test_run_asks_again() {
+        // Invalid type, refused type, then a colliding type declined before a good one
+        let answers = answer("a\nIDAT\nruSt\nn\nabCd\n\nhello\n\n\n\n").unwrap();
+        assert_eq!(answers.chunk_type, chunk_type("abCd"));
+        assert_eq!(answers.message, "hello");
+    }
+
+    #[test]
+    fn test_run_suggests_chunk_type() {
+        let answers = answer("\ny\nhello\n\n\n\n").unwrap();
+        assert_eq!(answers.chunk_type, chunk_type("ruSt"));
+    }
+
+    #[test]
+    fn test_run_cancelled() {
+        assert!(answer("abCd\nhello\n\n\nn\n").is_err());
+    }
+
+    #[test]
+    fn test_run_input_ended() {
+        assert!(answer("abCd\n").is_err());
+        assert!(answer("abCd\nhello\n").is_err());
+    }
+
+    #[test]
+    fn test_run_confirms_overwrite() {
+        let existing = std::env::temp_dir().join("msg-in-png-wizard-existing.png");
+        std::fs::write(&existing, b"").unwrap();
+        let input = format!("abCd\nhello\n\n{}\nn\nnew.png\n\n", existing.display());
+        let mut prompts = Vec::new();
+        let answers = run(
+            &testing_png(),
+            None,
+            missing_path(),
+            input.as_bytes(),
+            &mut prompts,
+        )
+        .unwrap();
+        std::fs::remove_file(&existing).unwrap();
+        assert_eq!(answers.output, Some(PathBuf::from("new.png")));
+    }
+}
@@ -0,0 +1,109 @@
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::png::PNG;
+    use crate::xmp::{from_png, get_property, set_property, store, validate, EMPTY_PACKET};
+    use std::str::FromStr;
+
+    const PACKET: &str = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about="" xmlns:dc="http://purl.org/dc/elements/1.1/"
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmp:CreatorTool="Paint &amp; Co">
+   <dc:creator><rdf:Seq><rdf:li>Alice</rdf:li><rdf:li>Bob</rdf:li></rdf:Seq></dc:creator>
+   <dc:format>image/png</dc:format>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#;
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(PACKET).is_ok());
+        assert!(validate(EMPTY_PACKET).is_ok());
+    }
+
+    #[test]
+    fn test_validate_malformed() {
+        assert!(validate("<a><b></a></b>").is_err());
+        assert!(validate("<a>").is_err());
+        assert!(validate("<a/><b/>").is_err());
+        assert!(validate("<a x=1/>").is_err());
+        assert!(validate("<a x='1' x='2'/>").is_err());
+        assert!(validate("<a>&nbsp;</a>").is_err());
+        assert!(validate("text<a/>").is_err());
+        assert!(validate("").is_err());
+    }
+
+    #[test]
+    fn test_get_property() {
+        assert_eq!(
+            get_property(PACKET, "dc:creator").unwrap(),
+            Some("Alice\nBob".to_string())
+        );
+        assert_eq!(
+            get_property(PACKET, "dc:format").unwrap(),
+            Some("image/png".to_string())
+        );
+        assert_eq!(
+            get_property(PACKET, "xmp:CreatorTool").unwrap(),
+            Some("Paint & Co".to_string())
+        );
+        assert_eq!(get_property(PACKET, "dc:rights").unwrap(), None);
+        assert!(get_property(PACKET, "creator").is_err());
+    }
+
+    #[test]
+    fn test_set_existing_property() {
+        let xml = set_property(PACKET, "dc:creator", "Carol <c@example.org>").unwrap();
+        assert_eq!(
+            get_property(&xml, "dc:creator").unwrap(),
+            Some("Carol <c@example.org>".to_string())
+        );
+        assert!(xml.contains("<dc:creator><rdf:Seq><rdf:li>Carol &lt;c@example.org&gt;</rdf:li></rdf:Seq></dc:creator>"));
+        let xml = set_property(&xml, "xmp:CreatorTool", "\"Tool\"").unwrap();
+        assert_eq!(
+            get_property(&xml, "xmp:CreatorTool").unwrap(),
+            Some("\"Tool\"".to_string())
+        );
+        assert_eq!(
+            get_property(&xml, "dc:format").unwrap(),
+            Some("image/png".to_string())
+        );
+    }
+
+    #[test]
+    fn test_set_new_property() {
+        let xml = set_property(EMPTY_PACKET, "dc:title", "Sunset").unwrap();
+        assert!(xml.contains("xmlns:dc=\"http://purl.org/dc/elements/1.1/\""));
+        assert!(xml.contains("<rdf:Alt><rdf:li xml:lang=\"x-default\">Sunset</rdf:li></rdf:Alt>"));
+        assert_eq!(
+            get_property(&xml, "dc:title").unwrap(),
+            Some("Sunset".to_string())
+        );
+        let xml = set_property(&xml, "dc:source", "camera").unwrap();
+        assert_eq!(xml.matches("xmlns:dc=").count(), 1);
+        assert_eq!(
+            get_property(&xml, "dc:source").unwrap(),
+            Some("camera".to_string())
+        );
+        assert!(set_property(EMPTY_PACKET, "foo:bar", "x").is_err());
+    }
+
+    #[test]
+    fn test_store_in_png() {
+        let mut png = PNG::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("IDAT").unwrap(), vec![1, 2, 3]),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![]),
+        ]);
+        assert!(from_png(&png).is_none());
+        store(&mut png, EMPTY_PACKET).unwrap();
+        assert_eq!(from_png(&png), Some((1, EMPTY_PACKET.to_string())));
+        store(&mut png, PACKET).unwrap();
+        assert_eq!(png.chunks().len(), 4);
+        assert_eq!(from_png(&png), Some((1, PACKET.to_string())));
+        assert!(store(&mut png, "<broken>").is_err());
+    }
+}
@@ -0,0 +1,595 @@
+#![allow(dead_code)]
+//! # Text
+//! Typed representation of the textual chunks defined by the PNG spec.
+//!
+//! See [Textual information](http://www.libpng.org/pub/png/spec/1.2/PNG-Chunks.html#C.Anc-text)
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::deflate;
+use crate::png::PNG;
+use anyhow::{anyhow, Result};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+pub const TITLE: &str = "Title";
+pub const AUTHOR: &str = "Author";
+pub const DESCRIPTION: &str = "Description";
+pub const COPYRIGHT: &str = "Copyright";
+pub const CREATION_TIME: &str = "Creation Time";
+pub const SOFTWARE: &str = "Software";
+pub const DISCLAIMER: &str = "Disclaimer";
+pub const WARNING: &str = "Warning";
+pub const SOURCE: &str = "Source";
+pub const COMMENT: &str = "Comment";
+
+/// Longest keyword followed by its null separator
+pub const KEYWORD_PREFIX_LEN: usize = 80;
+
+/// Keywords predefined by the PNG spec
+pub const REGISTERED_KEYWORDS: [&str; 10] = [
+    TITLE,
+    AUTHOR,
+    DESCRIPTION,
+    COPYRIGHT,
+    CREATION_TIME,
+    SOFTWARE,
+    DISCLAIMER,
+    WARNING,
+    SOURCE,
+    COMMENT,
+];
+
+/// A validated keyword of a textual chunk.
+///
+/// Keywords are 1 to 79 printable Latin-1 characters, without leading, trailing or consecutive
+/// spaces. Parsing normalizes spaces instead of rejecting them, so ` My  Title ` becomes
+/// `My Title`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keyword(String);
+
+impl Keyword {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Latin-1 encoding of the keyword, as stored in chunk data
+    pub fn latin1_bytes(&self) -> Vec<u8> {
+        // Validation guarantees every char fits in a single Latin-1 byte
+        self.0.chars().map(|c| c as u8).collect()
+    }
+
+    /// Tells whether this is one of the [REGISTERED_KEYWORDS]
+    pub fn is_registered(&self) -> bool {
+        REGISTERED_KEYWORDS.contains(&self.0.as_str())
+    }
+}
+
+impl FromStr for Keyword {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s
+            .split(' ')
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>();
+        let normalized = normalized.join(" ");
+        if let Some(c) = normalized
+            .chars()
+            .find(|c| !matches!(*c as u32, 32..=126 | 161..=255))
+        {
+            return Err(anyhow!(
+                "Keyword {:?} contains {:?}, only printable Latin-1 characters are allowed",
+                s,
+                c
+            ));
+        }
+        let length = normalized.chars().count();
+        if !(1..=79).contains(&length) {
+            return Err(anyhow!(
+                "Keyword {:?} is {} characters long, it must be 1 to 79 characters",
+                s,
+                length
+            ));
+        }
+        Ok(Self(normalized))
+    }
+}
+
+impl Display for Keyword {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// An `iTXt` chunk, UTF-8 text with an optional language tag.
+///
+/// Its data is laid out as `keyword\0`, a compression flag byte, a compression method byte,
+/// `language tag\0`, `translated keyword\0` and the text itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InternationalText {
+    pub keyword: String,
+    /// RFC 3066 language tag like `en` or `zh-CN`, empty when the language is unknown
+    pub language_tag: String,
+    /// Keyword translated into the language of `text`
+    pub translated_keyword: String,
+    pub text: String,
+    /// Whether the text is stored zlib compressed
+    pub compressed: bool,
+}
+
+impl InternationalText {
+    pub const CHUNK_TYPE: [u8; 4] = *b"iTXt";
+
+    pub fn new(keyword: &Keyword, language_tag: &str, text: &str) -> Self {
+        Self {
+            keyword: keyword.to_string(),
+            language_tag: language_tag.to_string(),
+            translated_keyword: String::new(),
+            text: text.to_string(),
+            compressed: false,
+        }
+    }
+
+    /// Like [new](InternationalText::new) with every field given, checking the ones with a
+    /// restricted syntax: the language tag is made of ASCII letters, digits and hyphens, the
+    /// translated keyword has no null byte
+    pub fn try_new(
+        keyword: &Keyword,
+        language_tag: &str,
+        translated_keyword: &str,
+        text: &str,
+        compressed: bool,
+    ) -> Result<Self> {
+        if !language_tag
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-')
+        {
+            return Err(anyhow!(
+                "Language tag {:?} must only have ASCII letters, digits and hyphens",
+                language_tag
+            ));
+        }
+        if translated_keyword.contains('\0') {
+            return Err(anyhow!("Translated keyword must not contain a null byte"));
+        }
+        Ok(Self {
+            translated_keyword: translated_keyword.to_string(),
+            compressed,
+            ..Self::new(keyword, language_tag, text)
+        })
+    }
+
+    /// Build an `iTXt` chunk, compressing the text when [compressed](InternationalText::compressed)
+    /// is set
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data = Vec::new();
+        data.extend(
+            self.keyword
+                .chars()
+                .map(|c| u8::try_from(c).unwrap_or(b'?')),
+        );
+        data.push(0);
+        // Compression flag, then compression method 0 (zlib) which is the only one defined
+        data.extend_from_slice(&[u8::from(self.compressed), 0]);
+        data.extend_from_slice(self.language_tag.as_bytes());
+        data.push(0);
+        data.extend_from_slice(self.translated_keyword.as_bytes());
+        data.push(0);
+        if self.compressed {
+            data.extend(deflate::zlib_compress(self.text.as_bytes()));
+        } else {
+            data.extend_from_slice(self.text.as_bytes());
+        }
+        Chunk::new(
+            ChunkType {
+                inner: Self::CHUNK_TYPE,
+            },
+            data,
+        )
+    }
+}
+
+impl TryFrom<&Chunk> for InternationalText {
+    type Error = anyhow::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        Self::from_chunk_limited(chunk, usize::MAX)
+    }
+}
+
+impl InternationalText {
+    /// Like [try_from](InternationalText::try_from), failing when compressed text inflates to
+    /// more than `max_text` bytes
+    pub fn from_chunk_limited(chunk: &Chunk, max_text: usize) -> Result<Self> {
+        if chunk.chunk_type().bytes() != Self::CHUNK_TYPE {
+            return Err(anyhow!("{} is not an iTXt chunk", chunk.chunk_type()));
+        }
+        let data = chunk.data();
+        let (keyword, rest) = split_null(data)?;
+        if rest.len() < 2 {
+            return Err(anyhow!("iTXt chunk is missing its compression fields"));
+        }
+        let compressed = rest[0] != 0;
+        if compressed && rest[1] != 0 {
+            return Err(anyhow!("Unknown iTXt compression method {}", rest[1]));
+        }
+        let (language_tag, rest) = split_null(&rest[2..])?;
+        let (translated_keyword, text) = split_null(rest)?;
+        let text = if compressed {
+            deflate::zlib_decompress_limited(text, max_text)?
+        } else {
+            text.to_vec()
+        };
+        Ok(Self {
+            // Keywords are Latin-1, which maps one to one onto the first 256 code points
+            keyword: keyword.iter().map(|byte| *byte as char).collect(),
+            language_tag: String::from_utf8(language_tag.to_vec())?,
+            translated_keyword: String::from_utf8(translated_keyword.to_vec())?,
+            text: String::from_utf8(text)?,
+            compressed,
+        })
+    }
+}
+
+/// A `tEXt` chunk, uncompressed Latin-1 text.
+///
+/// Its data is laid out as `keyword\0` followed by the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Text {
+    pub keyword: String,
+    text: String,
+}
+
+impl Text {
+    pub const CHUNK_TYPE: [u8; 4] = *b"tEXt";
+
+    /// Fails when `text` has characters outside of Latin-1
+    pub fn new(keyword: &Keyword, text: &str) -> Result<Self> {
+        check_latin1(text)?;
+        Ok(Self {
+            keyword: keyword.to_string(),
+            text: text.to_string(),
+        })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data = latin1_encode(&self.keyword);
+        data.push(0);
+        data.extend(latin1_encode(&self.text));
+        Chunk::new(
+            ChunkType {
+                inner: Self::CHUNK_TYPE,
+            },
+            data,
+        )
+    }
+}
+
+impl TryFrom<&Chunk> for Text {
+    type Error = anyhow::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if chunk.chunk_type().bytes() != Self::CHUNK_TYPE {
+            return Err(anyhow!("{} is not a tEXt chunk", chunk.chunk_type()));
+        }
+        let (keyword, text) = split_null(chunk.data())?;
+        Ok(Self {
+            keyword: latin1_decode(keyword),
+            text: latin1_decode(text),
+        })
+    }
+}
+
+/// A `zTXt` chunk, zlib compressed Latin-1 text.
+///
+/// Its data is laid out as `keyword\0`, a compression method byte and the compressed text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedText {
+    pub keyword: String,
+    text: String,
+}
+
+impl CompressedText {
+    pub const CHUNK_TYPE: [u8; 4] = *b"zTXt";
+
+    /// Fails when `text` has characters outside of Latin-1
+    pub fn new(keyword: &Keyword, text: &str) -> Result<Self> {
+        check_latin1(text)?;
+        Ok(Self {
+            keyword: keyword.to_string(),
+            text: text.to_string(),
+        })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data = latin1_encode(&self.keyword);
+        // Null separator and compression method 0 (zlib)
+        data.extend_from_slice(&[0, 0]);
+        data.extend(deflate::zlib_compress(&latin1_encode(&self.text)));
+        Chunk::new(
+            ChunkType {
+                inner: Self::CHUNK_TYPE,
+            },
+            data,
+        )
+    }
+}
+
+impl TryFrom<&Chunk> for CompressedText {
+    type Error = anyhow::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        Self::from_chunk_limited(chunk, usize::MAX)
+    }
+}
+
+impl CompressedText {
+    /// Like [try_from](CompressedText::try_from), failing when the text inflates to more than
+    /// `max_text` bytes
+    pub fn from_chunk_limited(chunk: &Chunk, max_text: usize) -> Result<Self> {
+        if chunk.chunk_type().bytes() != Self::CHUNK_TYPE {
+            return Err(anyhow!("{} is not a zTXt chunk", chunk.chunk_type()));
+        }
+        let (keyword, rest) = split_null(chunk.data())?;
+        match rest.first() {
+            Some(0) => {}
+            Some(method) => return Err(anyhow!("Unknown zTXt compression method {}", method)),
+            None => return Err(anyhow!("zTXt chunk is missing its compression method")),
+        }
+        Ok(Self {
+            keyword: latin1_decode(keyword),
+            text: latin1_decode(&deflate::zlib_decompress_limited(&rest[1..], max_text)?),
+        })
+    }
+}
+
+/// The three textual chunk types of the spec
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextForm {
+    Text,
+    CompressedText,
+    InternationalText,
+}
+
+impl FromStr for TextForm {
+    type Err = anyhow::Error;
+
+    /// Parse a chunk type name, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(TextForm::Text),
+            "ztxt" => Ok(TextForm::CompressedText),
+            "itxt" => Ok(TextForm::InternationalText),
+            _ => Err(anyhow!(
+                "{} is not a textual chunk type, use tEXt, zTXt or iTXt",
+                s
+            )),
+        }
+    }
+}
+
+impl Display for TextForm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextForm::Text => write!(f, "tEXt"),
+            TextForm::CompressedText => write!(f, "zTXt"),
+            TextForm::InternationalText => write!(f, "iTXt"),
+        }
+    }
+}
+
+/// Any of the textual chunks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextualChunk {
+    Text(Text),
+    CompressedText(CompressedText),
+    InternationalText(InternationalText),
+}
+
+impl TextualChunk {
+    pub fn keyword(&self) -> &str {
+        match self {
+            TextualChunk::Text(text) => &text.keyword,
+            TextualChunk::CompressedText(text) => &text.keyword,
+            TextualChunk::InternationalText(text) => &text.keyword,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        match self {
+            TextualChunk::Text(text) => text.text(),
+            TextualChunk::CompressedText(text) => text.text(),
+            TextualChunk::InternationalText(text) => &text.text,
+        }
+    }
+
+    pub fn form(&self) -> TextForm {
+        match self {
+            TextualChunk::Text(_) => TextForm::Text,
+            TextualChunk::CompressedText(_) => TextForm::CompressedText,
+            TextualChunk::InternationalText(_) => TextForm::InternationalText,
+        }
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        match self {
+            TextualChunk::Text(text) => text.to_chunk(),
+            TextualChunk::CompressedText(text) => text.to_chunk(),
+            TextualChunk::InternationalText(text) => text.to_chunk(),
+        }
+    }
+
+    /// Rewrite this chunk in another form, keeping keyword and text.
+    /// `compress` only applies to `iTXt`, and `language_tag` is given to `iTXt` chunks that have
+    /// none. Converting to `tEXt` or `zTXt` drops language tag and translated keyword, and fails
+    /// when the text is not Latin-1.
+    pub fn convert(
+        &self,
+        to: TextForm,
+        compress: bool,
+        language_tag: Option<&str>,
+    ) -> Result<TextualChunk> {
+        let keyword = Keyword::from_str(self.keyword())?;
+        Ok(match to {
+            TextForm::Text => TextualChunk::Text(Text::new(&keyword, self.text())?),
+            TextForm::CompressedText => {
+                TextualChunk::CompressedText(CompressedText::new(&keyword, self.text())?)
+            }
+            TextForm::InternationalText => {
+                let mut converted = match self {
+                    TextualChunk::InternationalText(text) => text.clone(),
+                    _ => InternationalText::new(&keyword, "", self.text()),
+                };
+                if converted.language_tag.is_empty() {
+                    converted.language_tag = language_tag.unwrap_or_default().to_string();
+                }
+                converted.compressed = compress;
+                TextualChunk::InternationalText(converted)
+            }
+        })
+    }
+}
+
+impl TryFrom<&Chunk> for TextualChunk {
+    type Error = anyhow::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        Self::from_chunk_limited(chunk, usize::MAX)
+    }
+}
+
+impl TextualChunk {
+    /// Like [try_from](TextualChunk::try_from), failing when compressed text inflates to more
+    /// than `max_text` bytes
+    pub fn from_chunk_limited(chunk: &Chunk, max_text: usize) -> Result<Self> {
+        match chunk.chunk_type().bytes() {
+            Text::CHUNK_TYPE => Text::try_from(chunk).map(TextualChunk::Text),
+            CompressedText::CHUNK_TYPE => CompressedText::from_chunk_limited(chunk, max_text)
+                .map(TextualChunk::CompressedText),
+            InternationalText::CHUNK_TYPE => InternationalText::from_chunk_limited(chunk, max_text)
+                .map(TextualChunk::InternationalText),
+            _ => Err(anyhow!("{} is not a textual chunk", chunk.chunk_type())),
+        }
+    }
+}
+
+/// First textual chunk of given form and keyword, chunks that can't be parsed are skipped
+pub fn find(png: &PNG, keyword: &Keyword, form: TextForm) -> Option<TextualChunk> {
+    png.chunks()
+        .iter()
+        .filter_map(|chunk| TextualChunk::try_from(chunk).ok())
+        .find(|text| text.form() == form && text.keyword() == keyword.as_str())
+}
+
+/// Indices of every textual chunk of given form and keyword, in file order. Chunks that can't be
+/// parsed are skipped
+pub fn positions(png: &PNG, keyword: &Keyword, form: TextForm) -> Vec<usize> {
+    png.chunks()
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| has_keyword(chunk, keyword, form))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Tells whether the chunk is a textual chunk of given form and keyword, a chunk that can't be
+/// parsed has none
+pub fn has_keyword(chunk: &Chunk, keyword: &Keyword, form: TextForm) -> bool {
+    TextualChunk::try_from(chunk)
+        .is_ok_and(|text| text.form() == form && text.keyword() == keyword.as_str())
+}
+
+/// Keyword of a tEXt, zTXt or iTXt chunk, read from the start of its data without parsing the
+/// rest. `None` for any other chunk type, or when no null separator ends the keyword.
+/// [KEYWORD_PREFIX_LEN] bytes of data are enough for any valid keyword.
+pub fn keyword_of(chunk_type: &ChunkType, data: &[u8]) -> Option<String> {
+    match chunk_type.bytes() {
+        Text::CHUNK_TYPE | CompressedText::CHUNK_TYPE | InternationalText::CHUNK_TYPE => {
+            split_null(data)
+                .ok()
+                .map(|(keyword, _)| latin1_decode(keyword))
+        }
+        _ => None,
+    }
+}
+
+/// Convert every textual chunk of the png, or only those of given keyword, to another form.
+/// Chunks that would come out unchanged are left alone. Returns the index of every other chunk
+/// with its form before conversion, or the reason it could not be converted: text that can't be
+/// read, or compressed text inflating to more than `max_text` bytes, is left as it is.
+pub fn convert_chunks(
+    png: &mut PNG,
+    keyword: Option<&Keyword>,
+    to: TextForm,
+    compress: bool,
+    language_tag: Option<&str>,
+    max_text: usize,
+) -> Vec<(usize, Result<TextForm>)> {
+    let mut outcomes = Vec::new();
+    for index in 0..png.chunks().len() {
+        let chunk = &png.chunks()[index];
+        // Keywords are checked before inflating any text
+        let chunk_keyword = match keyword_of(chunk.chunk_type(), chunk.data()) {
+            Some(chunk_keyword) => chunk_keyword,
+            None => continue,
+        };
+        if keyword.is_some_and(|keyword| keyword.as_str() != chunk_keyword) {
+            continue;
+        }
+        let original = match TextualChunk::from_chunk_limited(chunk, max_text) {
+            Ok(original) => original,
+            Err(e) => {
+                outcomes.push((index, Err(e)));
+                continue;
+            }
+        };
+        match original.convert(to, compress, language_tag) {
+            Ok(converted) if converted == original => {}
+            Ok(converted) => {
+                // Index comes from iterating over the chunks, it is in range
+                png.replace_chunk(index, converted.to_chunk()).unwrap();
+                outcomes.push((index, Ok(original.form())));
+            }
+            Err(e) => outcomes.push((index, Err(e))),
+        }
+    }
+    outcomes
+}
+
+fn check_latin1(text: &str) -> Result<()> {
+    match text.chars().find(|c| *c as u32 > 255) {
+        Some(c) => Err(anyhow!(
+            "Text contains {:?}, only Latin-1 characters fit in tEXt and zTXt chunks",
+            c
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Latin-1 maps one to one onto the first 256 code points
+fn latin1_decode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| *byte as char).collect()
+}
+
+fn latin1_encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| u8::try_from(c).unwrap_or(b'?'))
+        .collect()
+}
+
+/// Split bytes at the first null separator, the separator itself is dropped.
+fn split_null(bytes: &[u8]) -> Result<(&[u8], &[u8])> {
+    let position = bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .ok_or_else(|| anyhow!("Missing null separator in text chunk"))?;
+    Ok((&bytes[..position], &bytes[position + 1..]))
+}
@@ -0,0 +1,126 @@
+#![allow(dead_code)]
+//! # TOML
+//! The small subset of TOML used by files this tool reads: comments, `[table]` headers and
+//! `key = value` pairs whose value is a basic string, an integer or a boolean.
+use anyhow::{anyhow, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line {
+    /// A `[table]` header, everything after it belongs to that table
+    Table(String),
+    KeyValue(String, Value),
+}
+
+/// Parse a single line, returning `None` for blank lines and comments
+pub fn parse_line(line: &str) -> Result<Option<Line>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    if let Some(header) = line.strip_prefix('[') {
+        let (name, rest) = parse_key(header.trim_start())?;
+        let rest = rest
+            .trim_start()
+            .strip_prefix(']')
+            .ok_or_else(|| anyhow!("expected `]` after table name {}", name))?;
+        check_end(rest, &name)?;
+        return Ok(Some(Line::Table(name)));
+    }
+    let (key, rest) = parse_key(line)?;
+    let rest = rest
+        .trim_start()
+        .strip_prefix('=')
+        .ok_or_else(|| anyhow!("expected `=` after {}", key))?
+        .trim_start();
+    let (value, rest) = parse_value(rest, &key)?;
+    check_end(rest, &key)?;
+    Ok(Some(Line::KeyValue(key, value)))
+}
+
+fn check_end(rest: &str, key: &str) -> Result<()> {
+    let rest = rest.trim_start();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err(anyhow!("unexpected `{}` after {}", rest, key));
+    }
+    Ok(())
+}
+
+/// A bare or quoted key, followed by the rest of the line
+fn parse_key(source: &str) -> Result<(String, &str)> {
+    if let Some(quoted) = source.strip_prefix('"') {
+        return parse_basic_string(quoted);
+    }
+    let end = source
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .unwrap_or(source.len());
+    if end == 0 {
+        return Err(anyhow!("missing key"));
+    }
+    Ok((source[..end].to_string(), &source[end..]))
+}
+
+fn parse_value<'a>(source: &'a str, key: &str) -> Result<(Value, &'a str)> {
+    if let Some(quoted) = source.strip_prefix('"') {
+        let (value, rest) = parse_basic_string(quoted)?;
+        return Ok((Value::String(value), rest));
+    }
+    let end = source
+        .find(|c: char| c.is_whitespace() || c == '#')
+        .unwrap_or(source.len());
+    let (token, rest) = source.split_at(end);
+    let value = match token {
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        _ => token
+            .replace('_', "")
+            .parse()
+            .map(Value::Integer)
+            .map_err(|_| {
+                anyhow!(
+                    "value of {} must be a quoted string, an integer or a boolean",
+                    key
+                )
+            })?,
+    };
+    Ok((value, rest))
+}
+
+/// Parse a TOML basic string whose opening quote was already consumed.
+/// Returns the unescaped string and what follows its closing quote.
+fn parse_basic_string(source: &str) -> Result<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = source.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &source[index + 1..])),
+            '\\' => {
+                let (_, escaped) = chars.next().ok_or_else(|| anyhow!("unterminated escape"))?;
+                match escaped {
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    'r' => value.push('\r'),
+                    '"' => value.push('"'),
+                    '\\' => value.push('\\'),
+                    'u' => {
+                        let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                        let code = u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| anyhow!("invalid unicode escape \\u{}", hex))?;
+                        value.push(code);
+                    }
+                    other => return Err(anyhow!("unsupported escape \\{}", other)),
+                }
+            }
+            c => value.push(c),
+        }
+    }
+    Err(anyhow!("unterminated string"))
+}
@@ -0,0 +1,50 @@
+#![allow(dead_code)]
+//! # Tree
+//! Helpers for commands working on whole directory trees instead of a single png file.
+use crate::confine::PathPolicy;
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Tells whether given path has a `.png` extension, ignoring case
+pub fn is_png_path(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case("png"))
+        .unwrap_or(false)
+}
+
+/// Recursively collect every png file under `root`, sorted so output is stable between runs.
+/// Symbolic links to directories are not followed, files `policy` refuses are left out.
+pub fn png_files(root: &Path, policy: &PathPolicy) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for_each_png_file(root, policy, |path| {
+        files.push(path.to_path_buf());
+        Ok(())
+    })?;
+    files.sort();
+    Ok(files)
+}
+
+/// Call `visit` on every png file under `root` as directories are read, in no particular
+/// order and without holding the list in memory. Symbolic links to directories are not
+/// followed, files `policy` refuses are skipped.
+pub fn for_each_png_file<F: FnMut(&Path) -> Result<()>>(
+    root: &Path,
+    policy: &PathPolicy,
+    mut visit: F,
+) -> Result<()> {
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            let path = entry.path();
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if is_png_path(&path) && policy.allows(&path) {
+                visit(&path)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,675 @@
+#![allow(dead_code)]
+//! # Verify
+//! Structural checks on a parsed png, on top of the length and CRC checks done while parsing.
+//!
+//! Only the chunk sequence is inspected, image data is never decoded. [validate_stream] runs the
+//! same checks over a reader without keeping chunk data in memory.
+use crate::cgbi;
+use crate::chunk_type::ChunkType;
+use crate::plan::json_string;
+use crate::png::{Format, PNG};
+use crate::sha256::{to_hex, Sha256};
+use anyhow::Result;
+use std::fmt::{Display, Formatter};
+use std::io::{ErrorKind, Read};
+
+/// Critical chunk types defined by the PNG spec, any other critical chunk can't be understood
+/// by a decoder.
+pub const KNOWN_CRITICAL_TYPES: [&[u8; 4]; 4] = [b"IHDR", b"PLTE", b"IDAT", b"IEND"];
+
+/// Reported on Apple CgBI pngs, which standard decoders can't display
+pub const CGBI_NOTE: &str =
+    "Apple CgBI png, only Apple decoders display it. Use convert-cgbi to get a standard png";
+
+/// Largest chunk length allowed by the PNG spec, 2^31 - 1
+pub const MAX_CHUNK_LENGTH: u32 = crate::chunk::MAX_LENGTH as u32;
+
+/// Default limit for ancillary chunks in strict mode. Metadata this large is almost always a
+/// mistake or an attempt to smuggle data past size checks.
+pub const STRICT_MAX_ANCILLARY_LENGTH: u32 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A single problem found in a png
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub severity: Severity,
+    /// Index of the offending chunk, `None` when the problem concerns the whole file
+    pub chunk_index: Option<usize>,
+    pub message: String,
+}
+
+impl Issue {
+    pub fn error(chunk_index: Option<usize>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            chunk_index,
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(chunk_index: Option<usize>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            chunk_index,
+            message: message.into(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"severity\":\"{}\",\"chunk_index\":{},\"message\":{}}}",
+            self.severity,
+            self.chunk_index
+                .map_or("null".to_string(), |index| index.to_string()),
+            json_string(&self.message)
+        )
+    }
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.chunk_index {
+            Some(index) => write!(f, "{}: chunk {}: {}", self.severity, index, self.message),
+            None => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+/// What is wrong with a chunk found by [PNG::parse_lenient]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldError {
+    /// The stored CRC is not the one computed over chunk type and data
+    Crc { stored: u32, computed: u32 },
+    /// The length field does not end the chunk where it really ends. `actual` is the length
+    /// that does, `None` when the file ends inside the chunk
+    Length { declared: u32, actual: Option<u32> },
+    /// The file ends with this many bytes, too few for a chunk
+    Truncated(usize),
+}
+
+/// A chunk with a wrong CRC or length field, or the bytes left where a chunk should be
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkFailure {
+    pub chunk_index: usize,
+    /// Offset of the chunk in the file, where its length field starts
+    pub offset: usize,
+    /// `None` when the file ends before the chunk type
+    pub chunk_type: Option<ChunkType>,
+    pub error: FieldError,
+}
+
+impl ChunkFailure {
+    /// `error` is `crc`, `length` or `truncated`, `message` the same line as [Display] gives
+    pub fn to_json(&self) -> String {
+        let error = match self.error {
+            FieldError::Crc { .. } => "crc",
+            FieldError::Length { .. } => "length",
+            FieldError::Truncated(_) => "truncated",
+        };
+        format!(
+            "{{\"chunk_index\":{},\"offset\":{},\"chunk_type\":{},\"error\":\"{}\",\"message\":{}}}",
+            self.chunk_index,
+            self.offset,
+            self.chunk_type
+                .as_ref()
+                .map_or("null".to_string(), |chunk_type| json_string(
+                    &chunk_type.to_string()
+                )),
+            error,
+            json_string(&self.to_string())
+        )
+    }
+}
+
+impl Display for ChunkFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "chunk {} ", self.chunk_index)?;
+        if let Some(chunk_type) = &self.chunk_type {
+            write!(f, "{} ", chunk_type)?;
+        }
+        write!(f, "at byte {}: ", self.offset)?;
+        match self.error {
+            FieldError::Crc { stored, computed } => {
+                write!(f, "CRC is {:08x}, should be {:08x}", stored, computed)
+            }
+            FieldError::Length {
+                declared,
+                actual: Some(actual),
+            } => write!(f, "length is {}, should be {}", declared, actual),
+            FieldError::Length {
+                declared,
+                actual: None,
+            } => write!(f, "length is {}, the file ends inside the chunk", declared),
+            FieldError::Truncated(bytes) => {
+                write!(f, "file ends {} bytes later, too early for a chunk", bytes)
+            }
+        }
+    }
+}
+
+/// Ordering rules of the PNG spec, fed one chunk at a time so whole files and streams share
+/// them. Checks needing the last chunk are left to the caller.
+///
+/// MNG and JNG only get their header and trailer checked, the chunks in between follow rules
+/// of their own that are not implemented.
+#[derive(Debug)]
+struct SequenceCheck {
+    format: Format,
+    seen_header: bool,
+    seen_ihdr: bool,
+    seen_plte: bool,
+    /// IEND, or MEND for MNG
+    seen_iend: bool,
+    first_idat: Option<usize>,
+    idat_run_ended: bool,
+}
+
+impl SequenceCheck {
+    fn new(format: Format) -> Self {
+        SequenceCheck {
+            format,
+            seen_header: false,
+            seen_ihdr: false,
+            seen_plte: false,
+            seen_iend: false,
+            first_idat: None,
+            idat_run_ended: false,
+        }
+    }
+
+    fn chunk(
+        &mut self,
+        index: usize,
+        chunk_type: &ChunkType,
+        length: u32,
+        issues: &mut Vec<Issue>,
+    ) {
+        let bytes = chunk_type.bytes();
+        if !chunk_type.is_valid() {
+            issues.push(Issue::error(
+                Some(index),
+                format!("{} has its reserved bit set", chunk_type),
+            ));
+        }
+        let trailer = ChunkType {
+            inner: self.format.trailer_type(),
+        };
+        if self.seen_iend {
+            issues.push(Issue::error(
+                Some(index),
+                format!("{} appears after {}", chunk_type, trailer),
+            ));
+        }
+        if self.format != Format::Png {
+            if bytes == self.format.header_type() {
+                if self.seen_header {
+                    issues.push(Issue::error(
+                        Some(index),
+                        format!("duplicate {}", chunk_type),
+                    ));
+                }
+                self.seen_header = true;
+            } else if bytes == trailer.bytes() {
+                self.seen_iend = true;
+            }
+            return;
+        }
+        match &bytes {
+            b"IHDR" => {
+                if self.seen_ihdr {
+                    issues.push(Issue::error(Some(index), "duplicate IHDR"));
+                }
+                if length != 13 {
+                    issues.push(Issue::error(
+                        Some(index),
+                        format!("IHDR must be 13 bytes long, found {}", length),
+                    ));
+                }
+                self.seen_ihdr = true;
+            }
+            b"PLTE" => {
+                if self.seen_plte {
+                    issues.push(Issue::error(Some(index), "duplicate PLTE"));
+                }
+                if self.first_idat.is_some() {
+                    issues.push(Issue::error(Some(index), "PLTE must precede IDAT"));
+                }
+                self.seen_plte = true;
+            }
+            b"IDAT" => {
+                if self.first_idat.is_none() {
+                    self.first_idat = Some(index);
+                } else if self.idat_run_ended {
+                    issues.push(Issue::error(Some(index), "IDAT chunks must be consecutive"));
+                }
+            }
+            b"IEND" => {
+                if length != 0 {
+                    issues.push(Issue::warning(Some(index), "IEND should be empty"));
+                }
+                self.seen_iend = true;
+            }
+            &cgbi::CHUNK_TYPE if index == 0 => {}
+            _ => {
+                if chunk_type.is_critical() {
+                    issues.push(Issue::warning(
+                        Some(index),
+                        format!("unknown critical chunk {}", chunk_type),
+                    ));
+                }
+            }
+        }
+        if self.first_idat.is_some() && &bytes != b"IDAT" {
+            self.idat_run_ended = true;
+        }
+    }
+
+    fn finish(self, issues: &mut Vec<Issue>) {
+        if self.format != Format::Png {
+            if !self.seen_header {
+                let header = ChunkType {
+                    inner: self.format.header_type(),
+                };
+                issues.push(Issue::error(None, format!("missing {}", header)));
+            }
+            return;
+        }
+        if !self.seen_ihdr {
+            issues.push(Issue::error(None, "missing IHDR"));
+        }
+        if self.first_idat.is_none() {
+            issues.push(Issue::error(None, "missing IDAT"));
+        }
+    }
+}
+
+/// Check the chunk sequence of given png against the ordering rules of the PNG spec, and the
+/// [ordering rules](PNG::set_ordering_rules) set on it.
+pub fn verify(png: &PNG) -> Vec<Issue> {
+    let chunks = png.chunks();
+    let mut issues = Vec::new();
+    if chunks.is_empty() {
+        issues.push(Issue::error(None, "png has no chunks"));
+        return issues;
+    }
+    let format = png.format();
+    let first = if cgbi::is_cgbi(png) {
+        issues.push(Issue::warning(Some(0), CGBI_NOTE));
+        1
+    } else {
+        0
+    };
+    let header = ChunkType {
+        inner: format.header_type(),
+    };
+    match chunks.get(first) {
+        Some(chunk) if chunk.chunk_type() == &header => {}
+        _ => issues.push(Issue::error(
+            Some(first),
+            format!("first chunk must be {}", header),
+        )),
+    }
+    let last_index = chunks.len() - 1;
+    if !png.ends_with_iend() {
+        issues.push(Issue::error(
+            Some(last_index),
+            format!(
+                "last chunk must be {}",
+                ChunkType {
+                    inner: format.trailer_type()
+                }
+            ),
+        ));
+    }
+    let mut sequence = SequenceCheck::new(format);
+    for (index, chunk) in chunks.iter().enumerate() {
+        sequence.chunk(index, chunk.chunk_type(), chunk.length(), &mut issues);
+    }
+    sequence.finish(&mut issues);
+    issues.extend(png.ordering_rules().validate(chunks));
+    issues
+}
+
+/// Strict checks on a single chunk. `keyword_length` is the offset of the first null byte in
+/// data, only looked at for textual chunks.
+fn check_strict_chunk(
+    index: usize,
+    chunk_type: &ChunkType,
+    length: u32,
+    keyword_length: Option<usize>,
+    max_ancillary_length: u32,
+    issues: &mut Vec<Issue>,
+) {
+    if length > MAX_CHUNK_LENGTH {
+        issues.push(Issue::error(
+            Some(index),
+            format!(
+                "{} is {} bytes long, above the spec limit of {}",
+                chunk_type, length, MAX_CHUNK_LENGTH
+            ),
+        ));
+    } else if !chunk_type.is_critical() && length > max_ancillary_length {
+        issues.push(Issue::error(
+            Some(index),
+            format!(
+                "ancillary {} is {} bytes long, above the strict limit of {}",
+                chunk_type, length, max_ancillary_length
+            ),
+        ));
+    }
+    if is_textual(chunk_type) {
+        match keyword_length {
+            None => issues.push(Issue::error(
+                Some(index),
+                format!("{} keyword is not null terminated", chunk_type),
+            )),
+            Some(length) if !(1..=79).contains(&length) => issues.push(Issue::error(
+                Some(index),
+                format!(
+                    "{} keyword is {} bytes long, it must be 1 to 79 bytes",
+                    chunk_type, length
+                ),
+            )),
+            Some(_) => {}
+        }
+    }
+}
+
+fn is_textual(chunk_type: &ChunkType) -> bool {
+    matches!(&chunk_type.bytes(), b"tEXt" | b"zTXt" | b"iTXt")
+}
+
+/// [verify] plus the recommendations of the PNG spec that decoders usually tolerate:
+/// - no chunk longer than [MAX_CHUNK_LENGTH], no ancillary chunk longer than
+///   `max_ancillary_length`
+/// - tEXt, zTXt and iTXt keywords of 1 to 79 bytes followed by a null separator
+/// - unknown critical chunks are errors instead of warnings
+pub fn verify_strict(png: &PNG, max_ancillary_length: u32) -> Vec<Issue> {
+    let mut issues: Vec<Issue> = verify(png)
+        .into_iter()
+        .map(|mut issue| {
+            issue.severity = Severity::Error;
+            issue
+        })
+        .collect();
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        let keyword_length = chunk.data().iter().position(|byte| *byte == 0);
+        check_strict_chunk(
+            index,
+            chunk.chunk_type(),
+            chunk.length(),
+            keyword_length,
+            max_ancillary_length,
+            &mut issues,
+        );
+    }
+    issues
+}
+
+/// What [validate_stream] checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamOptions {
+    /// Apply the [verify_strict] rules, every issue is an error
+    pub strict: bool,
+    pub max_ancillary_length: u32,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            strict: false,
+            max_ancillary_length: STRICT_MAX_ANCILLARY_LENGTH,
+        }
+    }
+}
+
+/// Outcome of [validate_stream]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamReport {
+    pub issues: Vec<Issue>,
+    /// Chunks read, the truncated one included
+    pub chunks: usize,
+    /// Bytes read from the stream
+    pub bytes: u64,
+    /// SHA-256 over every byte read
+    pub sha256: [u8; 32],
+}
+
+impl StreamReport {
+    pub fn is_valid(&self) -> bool {
+        !self.issues.iter().any(Issue::is_error)
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"valid\":{},\"chunks\":{},\"bytes\":{},\"sha256\":\"{}\",\"issues\":[{}]}}",
+            self.is_valid(),
+            self.chunks,
+            self.bytes,
+            to_hex(&self.sha256),
+            self.issues
+                .iter()
+                .map(Issue::to_json)
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+}
+
+/// Size of the buffer chunk data is streamed through
+const STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Reader that hashes everything going through it
+struct HashingReader<R> {
+    inner: R,
+    sha256: Sha256,
+    bytes: u64,
+}
+
+impl<R: Read> HashingReader<R> {
+    /// Fill `buffer` completely. Returns false when the stream ends before anything was read,
+    /// an error when it ends part way.
+    fn fill(&mut self, buffer: &mut [u8]) -> std::io::Result<bool> {
+        let mut filled = 0;
+        while filled < buffer.len() {
+            match self.inner.read(&mut buffer[filled..]) {
+                Ok(0) if filled == 0 => return Ok(false),
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(read) => {
+                    self.sha256.update(&buffer[filled..filled + read]);
+                    self.bytes += read as u64;
+                    filled += read;
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Check a png read from `reader` without keeping chunk data in memory: chunk data goes
+/// through a fixed size buffer, CRCs and the file hash are computed on the fly. Runs the same
+/// checks as [verify], or [verify_strict] when asked, plus CRC and truncation checks.
+///
+/// Problems with the file are reported as issues, only read errors fail the call.
+pub fn validate_stream<R: Read>(reader: R, options: StreamOptions) -> Result<StreamReport> {
+    let mut reader = HashingReader {
+        inner: reader,
+        sha256: Sha256::new(),
+        bytes: 0,
+    };
+    let mut issues = Vec::new();
+    let mut chunks = 0;
+    let mut signature = [0u8; 8];
+    let format = match reader.fill(&mut signature) {
+        Ok(true) => Format::from_signature(&signature),
+        Ok(false) => None,
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => None,
+        Err(e) => return Err(e.into()),
+    };
+    if let Some(format) = format {
+        let header_type = ChunkType {
+            inner: format.header_type(),
+        };
+        let trailer_type = ChunkType {
+            inner: format.trailer_type(),
+        };
+        let mut sequence = SequenceCheck::new(format);
+        let mut starts_with_cgbi = false;
+        let mut last_type: Option<ChunkType> = None;
+        let mut buffer = vec![0u8; STREAM_BUFFER_SIZE];
+        loop {
+            let mut header = [0u8; 8];
+            match reader.fill(&mut header) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                    issues.push(Issue::error(None, "file ends inside a chunk header"));
+                    break;
+                }
+                Err(e) => return Err(e.into()),
+            }
+            let index = chunks;
+            chunks += 1;
+            let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+            let chunk_type = match ChunkType::try_from([header[4], header[5], header[6], header[7]])
+            {
+                Ok(chunk_type) => chunk_type,
+                Err(e) => {
+                    issues.push(Issue::error(Some(index), e.to_string()));
+                    break;
+                }
+            };
+            if length > MAX_CHUNK_LENGTH {
+                issues.push(Issue::error(
+                    Some(index),
+                    format!(
+                        "{} is {} bytes long, above the spec limit of {}",
+                        chunk_type, length, MAX_CHUNK_LENGTH
+                    ),
+                ));
+                break;
+            }
+            if index == 0 && chunk_type.bytes() == cgbi::CHUNK_TYPE {
+                issues.push(Issue::warning(Some(0), CGBI_NOTE));
+                starts_with_cgbi = true;
+            } else if index == usize::from(starts_with_cgbi) && chunk_type != header_type {
+                issues.push(Issue::error(
+                    Some(index),
+                    format!("first chunk must be {}", header_type),
+                ));
+            }
+            sequence.chunk(index, &chunk_type, length, &mut issues);
+
+            let mut crc_hasher = crc32fast::Hasher::new();
+            crc_hasher.update(&chunk_type.bytes());
+            let mut keyword_length = None;
+            let mut remaining = length as usize;
+            let mut truncated = false;
+            while remaining > 0 {
+                let piece = &mut buffer[..remaining.min(STREAM_BUFFER_SIZE)];
+                match reader.fill(piece) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        truncated = true;
+                        break;
+                    }
+                    Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                        truncated = true;
+                        break;
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+                if keyword_length.is_none() {
+                    let offset = length as usize - remaining;
+                    keyword_length = piece
+                        .iter()
+                        .position(|byte| *byte == 0)
+                        .map(|position| offset + position);
+                }
+                crc_hasher.update(piece);
+                remaining -= piece.len();
+            }
+            let mut crc = [0u8; 4];
+            if !truncated {
+                match reader.fill(&mut crc) {
+                    Ok(true) => {}
+                    Ok(false) => truncated = true,
+                    Err(e) if e.kind() == ErrorKind::UnexpectedEof => truncated = true,
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            if truncated {
+                issues.push(Issue::error(
+                    Some(index),
+                    format!("file ends inside {}", chunk_type),
+                ));
+                break;
+            }
+            if u32::from_be_bytes(crc) != crc_hasher.finalize() {
+                issues.push(Issue::error(
+                    Some(index),
+                    format!("{} CRC check failed", chunk_type),
+                ));
+            }
+            if options.strict {
+                check_strict_chunk(
+                    index,
+                    &chunk_type,
+                    length,
+                    keyword_length,
+                    options.max_ancillary_length,
+                    &mut issues,
+                );
+            }
+            last_type = Some(chunk_type);
+        }
+        match last_type {
+            None => issues.push(Issue::error(None, "png has no chunks")),
+            Some(last_type) => {
+                if last_type != trailer_type {
+                    issues.push(Issue::error(
+                        Some(chunks - 1),
+                        format!("last chunk must be {}", trailer_type),
+                    ));
+                }
+                sequence.finish(&mut issues);
+            }
+        }
+    } else {
+        issues.push(Issue::error(
+            None,
+            "not a png, the signature does not match",
+        ));
+    }
+    if options.strict {
+        for issue in issues.iter_mut() {
+            issue.severity = Severity::Error;
+        }
+    }
+    Ok(StreamReport {
+        issues,
+        chunks,
+        bytes: reader.bytes,
+        sha256: reader.sha256.finalize(),
+    })
+}
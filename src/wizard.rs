@@ -0,0 +1,234 @@
+#![allow(dead_code)]
+//! # Wizard
+//! Guided `encode --interactive`: asks for a chunk type, the message and where to write the
+//! result, explaining the choices along the way.
+//!
+//! The wizard only collects [Answers], encoding itself goes through the same path as a plain
+//! `encode`. Prompts are written to `output` so the report on stdout stays usable with
+//! `--format json`.
+use crate::chunk_type::ChunkType;
+use crate::i18n::tr;
+use crate::png::PNG;
+use crate::registry::Registry;
+use anyhow::{anyhow, Result};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Suggested when the user has no chunk type in mind. Ancillary, private and safe to copy.
+pub const SUGGESTED_CHUNK_TYPE: &str = "ruSt";
+
+/// What the user chose
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Answers {
+    pub chunk_type: ChunkType,
+    pub message: String,
+    /// `None` to overwrite the png
+    pub output: Option<PathBuf>,
+}
+
+struct Prompter<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Prompter<R, W> {
+    fn say(&mut self, text: &str) -> Result<()> {
+        writeln!(self.output, "{}", text)?;
+        Ok(())
+    }
+
+    /// Next input line without its line ending, `None` at the end of input
+    fn line(&mut self) -> Result<Option<String>> {
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+    }
+
+    fn ask(&mut self, question: &str) -> Result<String> {
+        write!(self.output, "{} ", question)?;
+        self.output.flush()?;
+        self.line()?
+            .map(|answer| answer.trim().to_string())
+            .ok_or_else(|| anyhow!(tr("wizard-input-ended", &[])))
+    }
+
+    fn confirm(&mut self, question: &str, default: bool) -> Result<bool> {
+        let choices = if default { "[Y/n]" } else { "[y/N]" };
+        loop {
+            let answer = self.ask(&format!("{} {}", question, choices))?;
+            match answer.to_lowercase().as_str() {
+                "" => return Ok(default),
+                "y" | "yes" => return Ok(true),
+                "n" | "no" => return Ok(false),
+                _ => self.say(&tr("wizard-yes-or-no", &[]))?,
+            }
+        }
+    }
+}
+
+/// Reasons a chunk type is refused by the wizard. Plain `encode` accepts these.
+pub fn chunk_type_problems(chunk_type: &ChunkType) -> Vec<String> {
+    let mut problems = Vec::new();
+    if !chunk_type.is_reserved_bit_valid() {
+        problems.push(tr("wizard-type-reserved", &[]));
+    }
+    if chunk_type.is_critical() {
+        problems.push(tr("wizard-type-critical", &[]));
+    }
+    if chunk_type.is_public() {
+        problems.push(tr("wizard-type-public", &[]));
+    }
+    problems
+}
+
+/// Things worth knowing before using a chunk type, like chunks of that type already in the png
+pub fn chunk_type_warnings(
+    chunk_type: &ChunkType,
+    png: &PNG,
+    registry: Option<&Registry>,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if !chunk_type.is_safe_to_copy() {
+        warnings.push(tr("wizard-type-unsafe-to-copy", &[]));
+    }
+    let existing = png
+        .chunks()
+        .iter()
+        .filter(|chunk| chunk.chunk_type() == chunk_type)
+        .count();
+    if existing > 0 {
+        warnings.push(tr(
+            "wizard-type-collision",
+            &[("count", &existing), ("chunk_type", chunk_type)],
+        ));
+    }
+    if let Some(spec) = registry.and_then(|registry| registry.get(chunk_type)) {
+        let description = spec.description.as_deref().unwrap_or("-");
+        warnings.push(tr(
+            "wizard-type-registered",
+            &[("chunk_type", chunk_type), ("description", &description)],
+        ));
+    }
+    warnings
+}
+
+/// Walk the user through encoding a message into `png`, read from `png_path`
+pub fn run<R: BufRead, W: Write>(
+    png: &PNG,
+    registry: Option<&Registry>,
+    png_path: &Path,
+    input: R,
+    output: W,
+) -> Result<Answers> {
+    let mut prompter = Prompter { input, output };
+    let chunk_type = ask_chunk_type(&mut prompter, png, registry)?;
+    let message = ask_message(&mut prompter)?;
+    let output = ask_output(&mut prompter, png_path)?;
+    let target = match &output {
+        Some(path) => path.display().to_string(),
+        None => png_path.display().to_string(),
+    };
+    prompter.say(&tr(
+        "wizard-summary",
+        &[
+            ("bytes", &message.len()),
+            ("chunk_type", &chunk_type),
+            ("path", &target),
+        ],
+    ))?;
+    if !prompter.confirm(&tr("wizard-continue", &[]), true)? {
+        return Err(anyhow!(tr("wizard-cancelled", &[])));
+    }
+    Ok(Answers {
+        chunk_type,
+        message,
+        output,
+    })
+}
+
+fn ask_chunk_type<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    png: &PNG,
+    registry: Option<&Registry>,
+) -> Result<ChunkType> {
+    prompter.say(&tr("wizard-type-intro", &[]))?;
+    loop {
+        let answer = prompter.ask(&tr(
+            "wizard-type-prompt",
+            &[("suggested", &SUGGESTED_CHUNK_TYPE)],
+        ))?;
+        let answer = if answer.is_empty() {
+            SUGGESTED_CHUNK_TYPE
+        } else {
+            answer.as_str()
+        };
+        let chunk_type = match ChunkType::from_str(answer) {
+            Ok(chunk_type) => chunk_type,
+            Err(e) => {
+                prompter.say(&e.to_string())?;
+                continue;
+            }
+        };
+        let problems = chunk_type_problems(&chunk_type);
+        if !problems.is_empty() {
+            for problem in problems {
+                prompter.say(&problem)?;
+            }
+            continue;
+        }
+        let warnings = chunk_type_warnings(&chunk_type, png, registry);
+        for warning in warnings.iter() {
+            prompter.say(warning)?;
+        }
+        if warnings.is_empty() || prompter.confirm(&tr("wizard-use-anyway", &[]), false)? {
+            return Ok(chunk_type);
+        }
+    }
+}
+
+fn ask_message<R: BufRead, W: Write>(prompter: &mut Prompter<R, W>) -> Result<String> {
+    loop {
+        prompter.say(&tr("wizard-message-prompt", &[]))?;
+        let mut lines: Vec<String> = Vec::new();
+        while let Some(line) = prompter.line()? {
+            if line.is_empty() {
+                break;
+            }
+            lines.push(line);
+        }
+        if !lines.is_empty() {
+            return Ok(lines.join("\n"));
+        }
+        prompter.say(&tr("wizard-message-empty", &[]))?;
+        // Nothing more will come, asking again would loop forever
+        if prompter.input.fill_buf()?.is_empty() {
+            return Err(anyhow!(tr("wizard-input-ended", &[])));
+        }
+    }
+}
+
+fn ask_output<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    png_path: &Path,
+) -> Result<Option<PathBuf>> {
+    loop {
+        let answer = prompter.ask(&tr(
+            "wizard-output-prompt",
+            &[("path", &png_path.display())],
+        ))?;
+        let (output, path) = if answer.is_empty() {
+            (None, png_path.to_path_buf())
+        } else {
+            let path = PathBuf::from(answer);
+            (Some(path.clone()), path)
+        };
+        if !path.exists()
+            || prompter.confirm(&tr("wizard-overwrite", &[("path", &path.display())]), false)?
+        {
+            return Ok(output);
+        }
+    }
+}
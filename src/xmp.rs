@@ -0,0 +1,675 @@
+#![allow(dead_code)]
+//! # XMP
+//! Adobe XMP metadata, stored as an uncompressed `iTXt` chunk with keyword `XML:com.adobe.xmp`.
+//!
+//! Only the part of XML needed to check and edit an XMP packet is understood here: elements,
+//! attributes, character references, comments, CDATA sections and processing instructions.
+//! Properties are addressed by their qualified name (`dc:creator`) and looked up on every
+//! `rdf:Description`, both in attribute form and in element form.
+use crate::chunk::Chunk;
+use crate::png::PNG;
+use crate::text::{InternationalText, Keyword};
+use anyhow::{anyhow, Result};
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Keyword of the `iTXt` chunk holding the XMP packet
+pub const KEYWORD: &str = "XML:com.adobe.xmp";
+
+/// Namespaces declared automatically when a property using their prefix is added
+const KNOWN_NAMESPACES: [(&str, &str); 7] = [
+    ("dc", "http://purl.org/dc/elements/1.1/"),
+    ("xmp", "http://ns.adobe.com/xap/1.0/"),
+    ("xmpRights", "http://ns.adobe.com/xap/1.0/rights/"),
+    ("xmpMM", "http://ns.adobe.com/xap/1.0/mm/"),
+    ("photoshop", "http://ns.adobe.com/photoshop/1.0/"),
+    ("tiff", "http://ns.adobe.com/tiff/1.0/"),
+    ("exif", "http://ns.adobe.com/exif/1.0/"),
+];
+
+/// Dublin Core properties the XMP spec defines as arrays, with the array type they use
+const ARRAY_PROPERTIES: [(&str, &str); 10] = [
+    ("dc:contributor", "rdf:Bag"),
+    ("dc:creator", "rdf:Seq"),
+    ("dc:date", "rdf:Seq"),
+    ("dc:description", "rdf:Alt"),
+    ("dc:language", "rdf:Bag"),
+    ("dc:publisher", "rdf:Bag"),
+    ("dc:rights", "rdf:Alt"),
+    ("dc:subject", "rdf:Bag"),
+    ("dc:title", "rdf:Alt"),
+    ("dc:type", "rdf:Bag"),
+];
+
+/// A packet with a single empty `rdf:Description`, used when a png has no XMP yet
+pub const EMPTY_PACKET: &str = "<?xpacket begin=\"\u{FEFF}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>
+<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">
+ <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">
+  <rdf:Description rdf:about=\"\"/>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end=\"w\"?>";
+
+#[derive(Debug)]
+enum Token<'a> {
+    Start {
+        name: &'a str,
+        attributes: Vec<Attribute<'a>>,
+        self_closing: bool,
+        span: Range<usize>,
+    },
+    End {
+        name: &'a str,
+        span: Range<usize>,
+    },
+    /// Character data, entity references are still escaped
+    Text(Range<usize>),
+    /// Content of a CDATA section, taken literally
+    CData(Range<usize>),
+}
+
+#[derive(Debug)]
+struct Attribute<'a> {
+    name: &'a str,
+    /// Position of the value, without its quotes
+    value: Range<usize>,
+}
+
+/// Check that `xml` is a well formed document with a single root element
+pub fn validate(xml: &str) -> Result<()> {
+    tokenize(xml).map(|_| ())
+}
+
+/// Value of a property, array items are returned one per line
+pub fn get_property(xml: &str, property: &str) -> Result<Option<String>> {
+    check_qualified_name(property)?;
+    let tokens = tokenize(xml)?;
+    for (index, token) in tokens.iter().enumerate() {
+        if let Token::Start {
+            name,
+            attributes,
+            self_closing,
+            ..
+        } = token
+        {
+            if *name == "rdf:Description" {
+                if let Some(attribute) = attributes.iter().find(|a| a.name == property) {
+                    return Ok(Some(unescape(&xml[attribute.value.clone()])));
+                }
+            }
+            if *name == property {
+                if *self_closing {
+                    return Ok(Some(String::new()));
+                }
+                let end = matching_end(&tokens, index);
+                return Ok(Some(element_value(xml, &tokens[index + 1..end])));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Return a copy of `xml` with given property set to `value`.
+/// An array property keeps its array type and ends up with `value` as its only item.
+pub fn set_property(xml: &str, property: &str, value: &str) -> Result<String> {
+    check_qualified_name(property)?;
+    let tokens = tokenize(xml)?;
+    let mut edited = None;
+    for (index, token) in tokens.iter().enumerate() {
+        if let Token::Start {
+            name,
+            attributes,
+            self_closing,
+            span,
+        } = token
+        {
+            if *name == "rdf:Description" {
+                if let Some(attribute) = attributes.iter().find(|a| a.name == property) {
+                    edited = Some(splice(xml, attribute.value.clone(), &escape(value, true)));
+                    break;
+                }
+            }
+            if *name == property {
+                let replacement = if *self_closing {
+                    (span.clone(), element(property, value))
+                } else {
+                    let end = matching_end(&tokens, index);
+                    let content = span.end..token_span(&tokens[end]).start;
+                    (content, array_content(xml, &tokens[index + 1..end], value))
+                };
+                edited = Some(splice(xml, replacement.0, &replacement.1));
+                break;
+            }
+        }
+    }
+    let edited = match edited {
+        Some(edited) => edited,
+        None => add_property(xml, &tokens, property, value)?,
+    };
+    validate(&edited)?;
+    Ok(edited)
+}
+
+/// The XMP packet of a png along with the index of its chunk
+pub fn from_png(png: &PNG) -> Option<(usize, String)> {
+    png.chunks().iter().enumerate().find_map(|(index, chunk)| {
+        InternationalText::try_from(chunk)
+            .ok()
+            .filter(|text| text.keyword == KEYWORD)
+            .map(|text| (index, text.text))
+    })
+}
+
+/// Build the `iTXt` chunk holding given packet. The XMP spec asks for no language tag and no
+/// compression, so that scanners looking for the packet in raw bytes can find it.
+pub fn to_chunk(packet: &str) -> Chunk {
+    // The keyword is a valid constant
+    let keyword = Keyword::from_str(KEYWORD).unwrap();
+    InternationalText::new(&keyword, "", packet).to_chunk()
+}
+
+/// Store a packet in the png, replacing the existing XMP chunk or, when there is none, adding one
+/// before the image data so readers find it without scanning the whole file.
+pub fn store(png: &mut PNG, packet: &str) -> Result<()> {
+    validate(packet)?;
+    let chunk = to_chunk(packet);
+    match from_png(png) {
+        Some((index, _)) => {
+            png.replace_chunk(index, chunk)?;
+        }
+        None => match png.position_of("IDAT", 0)? {
+            Some(index) => png.insert_chunk(index, chunk)?,
+            None => png.append_chunk(chunk),
+        },
+    }
+    Ok(())
+}
+
+fn check_qualified_name(name: &str) -> Result<()> {
+    match name.split_once(':') {
+        Some((prefix, local)) if is_name(prefix) && is_name(local) => Ok(()),
+        _ => Err(anyhow!(
+            "XMP property {:?} must be a prefixed name like dc:creator",
+            name
+        )),
+    }
+}
+
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+fn token_span<'a>(token: &'a Token) -> &'a Range<usize> {
+    match token {
+        Token::Start { span, .. } | Token::End { span, .. } => span,
+        Token::Text(span) | Token::CData(span) => span,
+    }
+}
+
+/// Index of the end tag closing the start tag at `start`. Tokens come from a validated
+/// document, so it always exists.
+fn matching_end(tokens: &[Token], start: usize) -> usize {
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().skip(start) {
+        match token {
+            Token::Start {
+                self_closing: false,
+                ..
+            } => depth += 1,
+            Token::End { .. } => {
+                depth -= 1;
+                if depth == 0 {
+                    return index;
+                }
+            }
+            _ => {}
+        }
+    }
+    unreachable!("Tags are balanced in a validated document")
+}
+
+fn text_of(xml: &str, tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|token| match token {
+            Token::Text(span) => unescape(&xml[span.clone()]),
+            Token::CData(span) => xml[span.clone()].to_string(),
+            _ => String::new(),
+        })
+        .collect()
+}
+
+/// Text of an element, or its `rdf:li` items one per line when it holds an array
+fn element_value(xml: &str, content: &[Token]) -> String {
+    let items = content
+        .iter()
+        .enumerate()
+        .filter(|(_, token)| matches!(token, Token::Start { name: "rdf:li", .. }))
+        .map(|(index, token)| match token {
+            Token::Start {
+                self_closing: true, ..
+            } => String::new(),
+            _ => text_of(xml, &content[index + 1..matching_end(content, index)]),
+        })
+        .collect::<Vec<_>>();
+    if items.is_empty() {
+        text_of(xml, content)
+    } else {
+        items.join("\n")
+    }
+}
+
+/// New content of an existing element: the same array with a single item when it held an array,
+/// the escaped value otherwise.
+fn array_content(xml: &str, content: &[Token], value: &str) -> String {
+    let container = content.iter().find_map(|token| match token {
+        Token::Start {
+            name: name @ ("rdf:Seq" | "rdf:Bag" | "rdf:Alt"),
+            self_closing: false,
+            span,
+            ..
+        } => Some((*name, span.clone())),
+        _ => None,
+    });
+    let item = content.iter().find_map(|token| match token {
+        Token::Start {
+            name: "rdf:li",
+            self_closing: false,
+            span,
+            ..
+        } => Some(span.clone()),
+        _ => None,
+    });
+    match (container, item) {
+        (Some((name, container)), Some(item)) => format!(
+            "{}{}{}</rdf:li></{}>",
+            &xml[container],
+            &xml[item],
+            escape(value, false),
+            name
+        ),
+        (Some((name, _)), None) => array(name, value),
+        _ => escape(value, false),
+    }
+}
+
+fn array(array_type: &str, value: &str) -> String {
+    let language = if array_type == "rdf:Alt" {
+        " xml:lang=\"x-default\""
+    } else {
+        ""
+    };
+    format!(
+        "<{}><rdf:li{}>{}</rdf:li></{}>",
+        array_type,
+        language,
+        escape(value, false),
+        array_type
+    )
+}
+
+/// A property element, as an array when the property is known to be one
+fn element(property: &str, value: &str) -> String {
+    let array_type = ARRAY_PROPERTIES
+        .iter()
+        .find(|(name, _)| *name == property)
+        .map(|(_, array_type)| *array_type);
+    let content = match array_type {
+        Some(array_type) => array(array_type, value),
+        None => escape(value, false),
+    };
+    format!("<{}>{}</{}>", property, content, property)
+}
+
+/// Add a property missing from the packet to its first `rdf:Description`, declaring its
+/// namespace when the prefix is a well known one.
+fn add_property(xml: &str, tokens: &[Token], property: &str, value: &str) -> Result<String> {
+    let (prefix, _) = property.split_once(':').unwrap_or_default();
+    let declaration = format!("xmlns:{}", prefix);
+    let declared = tokens.iter().any(|token| match token {
+        Token::Start { attributes, .. } => attributes.iter().any(|a| a.name == declaration),
+        _ => false,
+    });
+    let namespace = if declared {
+        String::new()
+    } else {
+        let uri = KNOWN_NAMESPACES
+            .iter()
+            .find(|(known, _)| *known == prefix)
+            .map(|(_, uri)| *uri)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Namespace prefix {} is not declared in the packet and not a known one",
+                    prefix
+                )
+            })?;
+        format!(" {}=\"{}\"", declaration, uri)
+    };
+    let (span, self_closing) = tokens
+        .iter()
+        .find_map(|token| match token {
+            Token::Start {
+                name: "rdf:Description",
+                span,
+                self_closing,
+                ..
+            } => Some((span.clone(), *self_closing)),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("XMP packet has no rdf:Description to add {} to", property))?;
+    let tag = &xml[span.clone()];
+    let open = tag.trim_end_matches('>').trim_end_matches('/').trim_end();
+    let element = element(property, value);
+    let replacement = if self_closing {
+        format!("{}{}>{}</rdf:Description>", open, namespace, element)
+    } else {
+        format!("{}{}>{}", open, namespace, element)
+    };
+    Ok(splice(xml, span, &replacement))
+}
+
+fn splice(xml: &str, range: Range<usize>, replacement: &str) -> String {
+    format!(
+        "{}{}{}",
+        &xml[..range.start],
+        replacement,
+        &xml[range.end..]
+    )
+}
+
+pub(crate) fn escape(text: &str, attribute: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' if attribute => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Replace entity and character references, they have been checked by the tokenizer
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find(';')
+            .map(|end| start + end)
+            .unwrap_or(start);
+        unescaped.push(reference_char(&rest[start + 1..end]).unwrap_or('\u{FFFD}'));
+        rest = &rest[end + 1..];
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+fn reference_char(reference: &str) -> Option<char> {
+    match reference {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => {
+            let number = reference.strip_prefix('#')?;
+            let code = match number.strip_prefix('x') {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+/// Check every `&` in text starts a known reference
+fn check_references(text: &str, offset: usize) -> Result<()> {
+    let mut position = 0;
+    while let Some(start) = text[position..].find('&') {
+        let start = position + start;
+        let end = text[start..]
+            .find(';')
+            .map(|end| start + end)
+            .ok_or_else(|| anyhow!("Unterminated reference at byte {}", offset + start))?;
+        if reference_char(&text[start + 1..end]).is_none() {
+            return Err(anyhow!(
+                "Unknown reference &{}; at byte {}",
+                &text[start + 1..end],
+                offset + start
+            ));
+        }
+        position = end + 1;
+    }
+    Ok(())
+}
+
+fn tokenize(xml: &str) -> Result<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    let mut open: Vec<&str> = Vec::new();
+    let mut roots = 0;
+    let mut position = 0;
+    let find = |from: usize, pattern: &str, what: &str| -> Result<usize> {
+        xml[from..]
+            .find(pattern)
+            .map(|found| from + found)
+            .ok_or_else(|| anyhow!("Unterminated {} at byte {}", what, from))
+    };
+    while position < xml.len() {
+        let rest = &xml[position..];
+        if rest.starts_with("<!--") {
+            position = find(position + 4, "-->", "comment")? + 3;
+        } else if rest.starts_with("<![CDATA[") {
+            if open.is_empty() {
+                return Err(anyhow!(
+                    "CDATA outside of root element at byte {}",
+                    position
+                ));
+            }
+            let end = find(position + 9, "]]>", "CDATA section")?;
+            tokens.push(Token::CData(position + 9..end));
+            position = end + 3;
+        } else if rest.starts_with("<!") {
+            return Err(anyhow!(
+                "Document type declarations are not allowed in XMP, found at byte {}",
+                position
+            ));
+        } else if rest.starts_with("<?") {
+            position = find(position + 2, "?>", "processing instruction")? + 2;
+        } else if rest.starts_with("</") {
+            let end = find(position, ">", "end tag")?;
+            let name = xml[position + 2..end].trim_end();
+            match open.pop() {
+                Some(expected) if expected == name => {}
+                Some(expected) => {
+                    return Err(anyhow!(
+                        "End tag </{}> at byte {} does not close <{}>",
+                        name,
+                        position,
+                        expected
+                    ))
+                }
+                None => {
+                    return Err(anyhow!(
+                        "Unexpected end tag </{}> at byte {}",
+                        name,
+                        position
+                    ))
+                }
+            }
+            tokens.push(Token::End {
+                name,
+                span: position..end + 1,
+            });
+            position = end + 1;
+        } else if rest.starts_with('<') {
+            let token = start_tag(xml, position)?;
+            if let Token::Start {
+                name,
+                self_closing,
+                span,
+                ..
+            } = &token
+            {
+                if open.is_empty() {
+                    roots += 1;
+                    if roots > 1 {
+                        return Err(anyhow!(
+                            "Second root element <{}> at byte {}",
+                            name,
+                            position
+                        ));
+                    }
+                }
+                if !self_closing {
+                    open.push(name);
+                }
+                position = span.end;
+            }
+            tokens.push(token);
+        } else {
+            let end = rest
+                .find('<')
+                .map(|end| position + end)
+                .unwrap_or(xml.len());
+            let text = &xml[position..end];
+            if open.is_empty() {
+                if !text.trim().is_empty() {
+                    return Err(anyhow!("Text outside of root element at byte {}", position));
+                }
+            } else {
+                check_references(text, position)?;
+                tokens.push(Token::Text(position..end));
+            }
+            position = end;
+        }
+    }
+    if let Some(name) = open.pop() {
+        return Err(anyhow!("Element <{}> is never closed", name));
+    }
+    if roots == 0 {
+        return Err(anyhow!("Document has no root element"));
+    }
+    Ok(tokens)
+}
+
+/// Parse the start tag beginning at `start`
+fn start_tag(xml: &str, start: usize) -> Result<Token<'_>> {
+    let bytes = xml.as_bytes();
+    let name_end = |from: usize| {
+        xml[from..]
+            .find(|c: char| c.is_whitespace() || matches!(c, '=' | '>' | '/'))
+            .map(|end| from + end)
+            .unwrap_or(xml.len())
+    };
+    let skip_whitespace = |from: usize| {
+        xml[from..]
+            .find(|c: char| !c.is_whitespace())
+            .map(|end| from + end)
+            .unwrap_or(xml.len())
+    };
+    let end_of_name = name_end(start + 1);
+    let name = &xml[start + 1..end_of_name];
+    if !is_tag_name(name) {
+        return Err(anyhow!("Invalid element name {:?} at byte {}", name, start));
+    }
+    let mut attributes: Vec<Attribute> = Vec::new();
+    let mut position = end_of_name;
+    loop {
+        let after_whitespace = skip_whitespace(position);
+        match bytes.get(after_whitespace) {
+            Some(b'>') => {
+                return Ok(Token::Start {
+                    name,
+                    attributes,
+                    self_closing: false,
+                    span: start..after_whitespace + 1,
+                })
+            }
+            Some(b'/') if bytes.get(after_whitespace + 1) == Some(&b'>') => {
+                return Ok(Token::Start {
+                    name,
+                    attributes,
+                    self_closing: true,
+                    span: start..after_whitespace + 2,
+                })
+            }
+            None => {
+                return Err(anyhow!(
+                    "Unterminated start tag <{}> at byte {}",
+                    name,
+                    start
+                ))
+            }
+            _ => {}
+        }
+        if after_whitespace == position {
+            return Err(anyhow!(
+                "Expected whitespace before attribute in <{}> at byte {}",
+                name,
+                position
+            ));
+        }
+        let attribute_end = name_end(after_whitespace);
+        let attribute_name = &xml[after_whitespace..attribute_end];
+        if !is_tag_name(attribute_name) {
+            return Err(anyhow!(
+                "Invalid attribute name {:?} at byte {}",
+                attribute_name,
+                after_whitespace
+            ));
+        }
+        if attributes.iter().any(|a| a.name == attribute_name) {
+            return Err(anyhow!(
+                "Duplicate attribute {} in <{}> at byte {}",
+                attribute_name,
+                name,
+                after_whitespace
+            ));
+        }
+        let equals = skip_whitespace(attribute_end);
+        if bytes.get(equals) != Some(&b'=') {
+            return Err(anyhow!(
+                "Attribute {} has no value at byte {}",
+                attribute_name,
+                after_whitespace
+            ));
+        }
+        let quote_at = skip_whitespace(equals + 1);
+        let quote = match bytes.get(quote_at) {
+            Some(quote @ (b'"' | b'\'')) => *quote as char,
+            _ => {
+                return Err(anyhow!(
+                    "Value of attribute {} must be quoted at byte {}",
+                    attribute_name,
+                    quote_at
+                ))
+            }
+        };
+        let value_end = xml[quote_at + 1..]
+            .find(quote)
+            .map(|end| quote_at + 1 + end)
+            .ok_or_else(|| anyhow!("Unterminated attribute value at byte {}", quote_at))?;
+        let value = &xml[quote_at + 1..value_end];
+        if value.contains('<') {
+            return Err(anyhow!("Attribute value contains '<' at byte {}", quote_at));
+        }
+        check_references(value, quote_at + 1)?;
+        attributes.push(Attribute {
+            name: attribute_name,
+            value: quote_at + 1..value_end,
+        });
+        position = value_end + 1;
+    }
+}
+
+fn is_tag_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | ':' | '-' | '.'))
+}